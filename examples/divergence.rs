@@ -0,0 +1,41 @@
+//! Finds the first step where two simulation setups disagree, run with `cargo run --example
+//! divergence [max steps]`.
+//!
+//! Compares the synchronous and checkerboard steppers on the same random world, edit the
+//! `left` and `right` configurations to compare other setups.
+
+use bevy::prelude::*;
+use bevy_voxel_engine::{AutomataState, AutomataStepper, ChunkBundle, DivergenceFinder};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+fn main() {
+    let max_steps = std::env::args()
+        .nth(1)
+        .map_or(256, |arg| arg.parse().unwrap());
+
+    let finder = DivergenceFinder::new(max_steps, |app| {
+        let mut rng = StdRng::seed_from_u64(0);
+        for x in 0..2 {
+            for z in 0..2 {
+                app.world.spawn(ChunkBundle::from_generator(
+                    IVec3::new(x, 0, z),
+                    |_| match rng.gen_bool(0.3) {
+                        true => AutomataState::new(1, 0),
+                        false => AutomataState::EMPTY,
+                    },
+                ));
+            }
+        }
+    })
+    .left("synchronous", |app| {
+        app.insert_resource(AutomataStepper::Synchronous);
+    })
+    .right("checkerboard", |app| {
+        app.insert_resource(AutomataStepper::Checkerboard);
+    });
+
+    match finder.run() {
+        Some(divergence) => print!("{divergence}"),
+        None => println!("No divergence within {max_steps} steps"),
+    }
+}
@@ -0,0 +1,63 @@
+//! Steps a random world without a window or a GPU and prints the statistics of every step as
+//! CSV, run with `cargo run --release --no-default-features --example headless [generations]
+//! [chunks per axis]`.
+//!
+//! Works the same way for dedicated servers and batch experiments: `MinimalPlugins` and the
+//! `CellularAutomataPlugin` are all the simulation needs.
+
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use bevy_voxel_engine::{
+    AutomataState, CellularAutomataPlugin, ChunkBundle, SimulationControl, SimulationStats,
+    StepStatistics, VoxelEngineInfo,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::io::{self, Write};
+
+fn main() -> io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let generations: u64 = args.next().map_or(100, |arg| arg.parse().unwrap());
+    let extent: i32 = args.next().map_or(2, |arg| arg.parse().unwrap());
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(CellularAutomataPlugin)
+        .insert_resource(SimulationStats {
+            enabled: true,
+            ..default()
+        });
+
+    let mut rng = StdRng::seed_from_u64(0);
+    for x in 0..extent {
+        for y in 0..extent {
+            for z in 0..extent {
+                app.world.spawn(ChunkBundle::from_generator(
+                    IVec3::new(x, y, z),
+                    |_| match rng.gen_bool(0.2) {
+                        true => AutomataState::new(1, 0),
+                        false => AutomataState::EMPTY,
+                    },
+                ));
+            }
+        }
+    }
+
+    // Steps only run when asked for, as fast as the machine allows instead of at the fixed rate.
+    app.world.resource_mut::<SimulationControl>().paused = true;
+    app.update();
+
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", StepStatistics::CSV_HEADER)?;
+    for _ in 0..generations {
+        app.world.resource_mut::<SimulationControl>().step_once();
+        app.update();
+        if let Some(statistics) = app.world.resource::<SimulationStats>().latest() {
+            writeln!(stdout, "{}", statistics.to_csv())?;
+        }
+    }
+
+    let report = app
+        .world
+        .run_system_once(|info: VoxelEngineInfo| info.report());
+    eprintln!("{report}");
+    Ok(())
+}
@@ -0,0 +1,92 @@
+//! Times simulation steps on a headless app, run with `cargo run --release --example
+//! step_benchmark [chunks per axis] [steps]`.
+//!
+//! It then steps every chunk on a single thread twice, once reading neighbors through the border
+//! cache of each chunk and once looking up the chunk of every neighbor, which the cache avoids.
+
+use bevy::prelude::*;
+use bevy_voxel_engine::{
+    step_chunk_alone, AutomataRule, AutomataState, CellularAutomataPlugin, ChunkBundle,
+    ChunkSnapshots, NeighborSampling, SimulationControl, CHUNK_EDGE, CHUNK_VOLUME,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::time::{Duration, Instant};
+
+/// Steps of each single thread sampling.
+const SINGLE_THREAD_STEPS: u64 = 10;
+
+fn main() {
+    let mut args = std::env::args()
+        .skip(1)
+        .map(|arg| arg.parse::<i32>().unwrap());
+    let extent = args.next().unwrap_or(4);
+    let steps = args.next().unwrap_or(100);
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(CellularAutomataPlugin);
+
+    // A quarter of the cells alive keeps every chunk busy and every border populated.
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut coords = Vec::new();
+    for x in 0..extent {
+        for y in 0..extent {
+            for z in 0..extent {
+                let cells = (0..CHUNK_EDGE.pow(3))
+                    .map(|_| match rng.gen_bool(0.25) {
+                        true => AutomataState::new(1, 0),
+                        false => AutomataState::EMPTY,
+                    })
+                    .collect::<Vec<_>>();
+                let chunk = IVec3::new(x, y, z);
+                app.world.spawn(ChunkBundle::from_generator(chunk, |local| {
+                    cells[cell_index(local)]
+                }));
+                coords.push(chunk);
+            }
+        }
+    }
+
+    app.world.resource_mut::<SimulationControl>().paused = true;
+    // Lets the index and snapshots settle before measuring.
+    app.update();
+
+    let mut total = Duration::ZERO;
+    let mut slowest = Duration::ZERO;
+    for _ in 0..steps {
+        app.world.resource_mut::<SimulationControl>().step_once();
+        let start = Instant::now();
+        app.update();
+        let elapsed = start.elapsed();
+        total += elapsed;
+        slowest = slowest.max(elapsed);
+    }
+
+    let chunk_count = extent.pow(3);
+    println!(
+        "{chunk_count} chunks, {steps} steps: {:.3} ms per step, {:.3} ms slowest",
+        total.as_secs_f64() * 1000.0 / steps as f64,
+        slowest.as_secs_f64() * 1000.0,
+    );
+
+    // Both samplings step the same snapshots on this thread, so only the neighbor lookups differ.
+    let snapshots = app.world.resource::<ChunkSnapshots>();
+    let rule = app.world.resource::<AutomataRule>();
+    let mut output = vec![AutomataState::EMPTY; CHUNK_VOLUME];
+    for sampling in [NeighborSampling::BorderCache, NeighborSampling::PerCell] {
+        let start = Instant::now();
+        for step in 0..SINGLE_THREAD_STEPS {
+            for coords in &coords {
+                step_chunk_alone(snapshots, *coords, rule, sampling, step, &mut output);
+            }
+        }
+        println!(
+            "{sampling:?} on one thread, {SINGLE_THREAD_STEPS} steps: {:.3} ms per step",
+            start.elapsed().as_secs_f64() * 1000.0 / SINGLE_THREAD_STEPS as f64,
+        );
+    }
+}
+
+fn cell_index(local: IVec3) -> usize {
+    ((local.x * CHUNK_EDGE + local.y) * CHUNK_EDGE + local.z) as usize
+}
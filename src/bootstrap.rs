@@ -0,0 +1,312 @@
+use crate::{
+    persistence::META_FILE, ChunkBundle, ChunkGenerator, ChunkIndex, ChunkSource, LoadWorld,
+    PersistenceOperation, PersistenceProgress, PersistenceStatus, SimulationClock,
+    SimulationControl, WorldSaveSettings, CHUNK_EDGE,
+};
+#[cfg(feature = "render")]
+use crate::{ChunkLod, ChunkMeshEvicted, ChunkMeshPalette, ChunkMeshed};
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+/// Drives the world startups spawned with [`VoxelWorldLoader::spawn`].
+///
+/// Each startup goes through the [`WorldLoadStage`]s in order, sending a [`WorldLoadProgress`]
+/// every frame until it is done and a [`WorldReady`] once. Stages with nothing to do are
+/// skipped within the same frame.
+pub struct WorldBootstrapPlugin;
+
+impl Plugin for WorldBootstrapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LoadWorld>()
+            .add_event::<PersistenceProgress>()
+            .add_event::<WorldLoadProgress>()
+            .add_event::<WorldReady>()
+            .add_systems(Update, drive_world_loads);
+    }
+}
+
+/// What a world startup does, see [`VoxelWorldLoader::spawn`].
+#[derive(Clone)]
+pub struct WorldBootstrapConfig {
+    /// Restore the save in [`WorldSaveSettings::directory`] first, if the `PersistencePlugin` is
+    /// added and a save exists.
+    pub load_save: bool,
+    /// Chunks within `min..=max` on every axis that have to exist, the ones still missing after
+    /// the save is loaded are generated.
+    pub region: Option<(IVec3, IVec3)>,
+    /// Fills the generated chunks, called with voxel positions. Empty chunks without one.
+    pub generator: Option<ChunkGenerator>,
+    /// Chunks generated per frame.
+    pub chunks_per_frame: usize,
+    /// Steps run before the world is ready, so it does not start from its seeded state.
+    pub warmup_steps: u32,
+    /// Wait until every chunk that should be meshed went through the mesher. Ignored without
+    /// the `ChunkMeshPlugin` or the `render` feature.
+    pub wait_for_meshes: bool,
+}
+
+impl Default for WorldBootstrapConfig {
+    fn default() -> Self {
+        Self {
+            load_save: true,
+            region: None,
+            generator: None,
+            chunks_per_frame: 64,
+            warmup_steps: 0,
+            wait_for_meshes: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WorldLoadStage {
+    LoadingSave,
+    Generating,
+    WarmingUp,
+    Meshing,
+    Ready,
+}
+
+/// Tracks a world startup, the entity holding its state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WorldLoadHandle(pub Entity);
+
+/// Sent every frame while a world startup runs.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct WorldLoadProgress {
+    pub handle: WorldLoadHandle,
+    pub stage: WorldLoadStage,
+    /// Fraction of the current stage done, in `0.0..=1.0`.
+    pub fraction: f32,
+}
+
+/// Sent once when a world startup went through every stage.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldReady {
+    pub handle: WorldLoadHandle,
+}
+
+/// State of a world startup, kept on its entity after it is ready.
+#[derive(Component)]
+struct WorldLoad {
+    config: WorldBootstrapConfig,
+    stage: WorldLoadStage,
+    fraction: f32,
+    /// Whether a [`LoadWorld`] was sent and has not completed yet.
+    loading: bool,
+    /// Next chunk of the region to generate, in x, y, z order.
+    next_chunk: usize,
+    /// Clock step the warm-up started at.
+    warmup_start: Option<u64>,
+}
+
+/// Starts multi-stage world startups and reads their progress.
+#[derive(SystemParam)]
+pub struct VoxelWorldLoader<'w, 's> {
+    commands: Commands<'w, 's>,
+    loads: Query<'w, 's, &'static WorldLoad>,
+}
+
+impl<'w, 's> VoxelWorldLoader<'w, 's> {
+    /// Starts loading the world with `config`, beginning on the next `Update`. Requires the
+    /// [`WorldBootstrapPlugin`].
+    pub fn spawn(&mut self, config: WorldBootstrapConfig) -> WorldLoadHandle {
+        let entity = self
+            .commands
+            .spawn(WorldLoad {
+                config,
+                stage: WorldLoadStage::LoadingSave,
+                fraction: 0.0,
+                loading: false,
+                next_chunk: 0,
+                warmup_start: None,
+            })
+            .id();
+        WorldLoadHandle(entity)
+    }
+
+    /// Current stage of a startup, `None` before its first update or once its entity is
+    /// despawned.
+    pub fn stage(&self, handle: WorldLoadHandle) -> Option<WorldLoadStage> {
+        self.loads.get(handle.0).ok().map(|load| load.stage)
+    }
+
+    /// Fraction of the current stage done.
+    pub fn progress(&self, handle: WorldLoadHandle) -> Option<f32> {
+        self.loads.get(handle.0).ok().map(|load| load.fraction)
+    }
+
+    pub fn is_ready(&self, handle: WorldLoadHandle) -> bool {
+        self.stage(handle) == Some(WorldLoadStage::Ready)
+    }
+}
+
+/// Number of chunks in the box from `min` to `max` inclusive.
+fn region_len(min: IVec3, max: IVec3) -> usize {
+    let size = (max - min + IVec3::ONE).max(IVec3::ZERO);
+    (size.x * size.y * size.z) as usize
+}
+
+/// Coordinates of the `n`th chunk of the box, in x, y, z order.
+fn region_chunk(min: IVec3, max: IVec3, n: usize) -> IVec3 {
+    let size = max - min + IVec3::ONE;
+    let n = n as i32;
+    min + IVec3::new(n / (size.y * size.z), n / size.z % size.y, n % size.z)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn drive_world_loads(
+    mut commands: Commands,
+    mut index: ResMut<ChunkIndex>,
+    mut control: ResMut<SimulationControl>,
+    clock: Res<SimulationClock>,
+    save_settings: Option<Res<WorldSaveSettings>>,
+    #[cfg(feature = "render")] mesh_palette: Option<Res<ChunkMeshPalette>>,
+    mut load_world: EventWriter<LoadWorld>,
+    mut persistence: EventReader<PersistenceProgress>,
+    mut progress: EventWriter<WorldLoadProgress>,
+    mut ready: EventWriter<WorldReady>,
+    mut loads: Query<(Entity, &mut WorldLoad)>,
+    #[cfg(feature = "render")] chunks: Query<(
+        Option<&ChunkLod>,
+        Has<ChunkMeshed>,
+        Has<ChunkMeshEvicted>,
+    )>,
+) {
+    let save_load = persistence
+        .read()
+        .filter(|event| event.operation == PersistenceOperation::Load)
+        .last()
+        .copied();
+
+    for (entity, mut load) in loads.iter_mut() {
+        if load.stage == WorldLoadStage::Ready {
+            continue;
+        }
+        let handle = WorldLoadHandle(entity);
+
+        if load.stage == WorldLoadStage::LoadingSave {
+            if load.loading {
+                if let Some(event) = save_load {
+                    load.loading = event.status == PersistenceStatus::Running;
+                    load.fraction = event.fraction();
+                }
+            } else if load.config.load_save
+                && save_settings
+                    .as_ref()
+                    .is_some_and(|settings| settings.directory.join(META_FILE).exists())
+            {
+                load_world.send(LoadWorld);
+                load.loading = true;
+            }
+            if !load.loading {
+                load.stage = WorldLoadStage::Generating;
+                load.fraction = 0.0;
+            }
+        }
+
+        if load.stage == WorldLoadStage::Generating {
+            let (min, max) = load.config.region.unwrap_or((IVec3::ZERO, IVec3::NEG_ONE));
+            let total = region_len(min, max);
+            let mut spawned = 0;
+            while load.next_chunk < total && spawned < load.config.chunks_per_frame {
+                let coords = region_chunk(min, max, load.next_chunk);
+                load.next_chunk += 1;
+                if index.entity(coords).is_some() {
+                    continue;
+                }
+                let bundle = match &load.config.generator {
+                    Some(generator) => ChunkBundle::from_generator(coords, |local| {
+                        generator(coords * CHUNK_EDGE + local)
+                    }),
+                    None => ChunkBundle::new(coords),
+                };
+                let chunk = commands.spawn((bundle, ChunkSource::Generated)).id();
+                index.insert(coords, chunk);
+                spawned += 1;
+            }
+            load.fraction = if total == 0 {
+                1.0
+            } else {
+                load.next_chunk as f32 / total as f32
+            };
+            if load.next_chunk >= total {
+                load.stage = WorldLoadStage::WarmingUp;
+                load.fraction = 0.0;
+            }
+        }
+
+        if load.stage == WorldLoadStage::WarmingUp {
+            let start = *load.warmup_start.get_or_insert(clock.step);
+            let steps = load.config.warmup_steps as u64;
+            let done = clock.step.saturating_sub(start).min(steps);
+            // Steps already requested count as running, so several frames do not pile them up.
+            let remaining = (steps - done) as u32;
+            if remaining > control.pending_steps() {
+                for _ in control.pending_steps()..remaining.min(clock.max_steps_per_frame) {
+                    control.step_once();
+                }
+            }
+            load.fraction = if steps == 0 {
+                1.0
+            } else {
+                done as f32 / steps as f32
+            };
+            if done == steps {
+                load.stage = WorldLoadStage::Meshing;
+                load.fraction = 0.0;
+            }
+        }
+
+        if load.stage == WorldLoadStage::Meshing {
+            #[cfg(feature = "render")]
+            let (wanted, meshed) = match load.config.wait_for_meshes && mesh_palette.is_some() {
+                true => chunks
+                    .iter()
+                    .filter(|(lod, _, evicted)| !evicted && lod.map_or(true, |lod| lod.meshed))
+                    .fold((0, 0), |(wanted, meshed), (_, is_meshed, _)| {
+                        (wanted + 1, meshed + is_meshed as usize)
+                    }),
+                false => (0, 0),
+            };
+            #[cfg(not(feature = "render"))]
+            let (wanted, meshed) = (0, 0);
+            load.fraction = if wanted == 0 {
+                1.0
+            } else {
+                meshed as f32 / wanted as f32
+            };
+            if meshed == wanted {
+                load.stage = WorldLoadStage::Ready;
+                ready.send(WorldReady { handle });
+            }
+        }
+
+        progress.send(WorldLoadProgress {
+            handle,
+            stage: load.stage,
+            fraction: load.fraction,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_chunks_cover_the_box_once() {
+        let (min, max) = (IVec3::new(-1, 0, 2), IVec3::new(1, 1, 3));
+        assert_eq!(region_len(min, max), 12);
+        let chunks: Vec<_> = (0..12).map(|n| region_chunk(min, max, n)).collect();
+        assert_eq!(chunks[0], min);
+        assert_eq!(chunks[11], max);
+        for x in -1..=1 {
+            for y in 0..=1 {
+                for z in 2..=3 {
+                    assert!(chunks.contains(&IVec3::new(x, y, z)));
+                }
+            }
+        }
+        assert_eq!(region_len(IVec3::ZERO, IVec3::NEG_ONE), 0);
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{simulation::linear_index, CHUNK_EDGE, CHUNK_VOLUME};
+use bevy::prelude::*;
+#[cfg(feature = "rapier")]
+use {
+    crate::{
+        meshing::chunk_transform, ChunkCells, ChunkKey, MaterialClass, MaterialRegistry,
+        SimulationSet, VoxelWorldTransform,
+    },
+    bevy_rapier3d::prelude::{Collider, RigidBody},
+};
+
+/// Builds a fixed `bevy_rapier3d` collider for every chunk from its static solid voxels, so
+/// characters and rigid bodies can stand on the automata world.
+///
+/// Voxels are solid when they are alive and their material is [`MaterialClass::Solid`] in the
+/// [`MaterialRegistry`], powders, liquids and gases move too often to collide with. The
+/// collider of a chunk is rebuilt in `PostUpdate` after the steps whenever its cells changed,
+/// and removed when it has no solid voxel left. The rapier plugin itself is added by the game.
+#[cfg(feature = "rapier")]
+pub struct VoxelColliderPlugin;
+
+#[cfg(feature = "rapier")]
+impl Plugin for VoxelColliderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VoxelColliderSettings>()
+            .init_resource::<MaterialRegistry>()
+            .add_systems(PostUpdate, build_chunk_colliders.after(SimulationSet::Run));
+    }
+}
+
+/// Shapes chunk colliders are made of.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ChunkColliderShape {
+    /// One cube per solid voxel with an exposed face, exact but heavy for the broad phase.
+    Voxels,
+    /// Solid voxels greedily merged into boxes, a few large boxes for flat terrain.
+    #[default]
+    MergedBoxes,
+}
+
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VoxelColliderSettings {
+    pub shape: ChunkColliderShape,
+}
+
+/// Hash of the boxes the collider of a chunk was last built from, see [`boxes_hash`].
+#[cfg(feature = "rapier")]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ChunkColliderHash(pub u64);
+
+/// Hash identifying a set of [`solid_boxes`], so a collider can be checked against the voxels
+/// without comparing shapes.
+#[cfg(feature = "rapier")]
+pub(crate) fn boxes_hash(boxes: &[(IVec3, IVec3)]) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for (min, size) in boxes {
+        min.to_array().hash(&mut hasher);
+        size.to_array().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Solid voxels of a chunk as [`solid_boxes`], the voxels colliders are built from.
+#[cfg(feature = "rapier")]
+pub(crate) fn chunk_boxes(
+    cells: &ChunkCells,
+    registry: &MaterialRegistry,
+    shape: ChunkColliderShape,
+) -> Vec<(IVec3, IVec3)> {
+    let solid = |local: IVec3| {
+        let cell = cells.get(local);
+        cell.is_alive() && registry.get(cell.material).class == MaterialClass::Solid
+    };
+    match cells.occupancy().is_empty() {
+        true => Vec::new(),
+        false => solid_boxes(solid, shape),
+    }
+}
+
+/// Boxes covering the solid voxels of a chunk as `(min, size)` in chunk local voxels, `solid`
+/// being called with local positions inside the chunk.
+///
+/// With [`ChunkColliderShape::Voxels`] voxels enclosed by solid voxels on all six sides are
+/// left out, faces on the chunk border count as exposed.
+pub fn solid_boxes(
+    solid: impl Fn(IVec3) -> bool,
+    shape: ChunkColliderShape,
+) -> Vec<(IVec3, IVec3)> {
+    let mut grid = vec![false; CHUNK_VOLUME];
+    for (index, cell) in grid.iter_mut().enumerate() {
+        *cell = solid(local_position(index));
+    }
+    let is_solid = |local: IVec3| {
+        local.cmpge(IVec3::ZERO).all()
+            && local.cmplt(IVec3::splat(CHUNK_EDGE)).all()
+            && grid[linear_index(local)]
+    };
+
+    match shape {
+        ChunkColliderShape::Voxels => (0..CHUNK_VOLUME)
+            .map(local_position)
+            .filter(|&local| is_solid(local))
+            .filter(|&local| {
+                [IVec3::X, IVec3::Y, IVec3::Z]
+                    .into_iter()
+                    .any(|axis| !is_solid(local + axis) || !is_solid(local - axis))
+            })
+            .map(|local| (local, IVec3::ONE))
+            .collect(),
+        ChunkColliderShape::MergedBoxes => merge_boxes(&grid),
+    }
+}
+
+#[inline]
+fn local_position(index: usize) -> IVec3 {
+    let edge = CHUNK_EDGE as usize;
+    IVec3::new(
+        (index / (edge * edge)) as i32,
+        ((index / edge) % edge) as i32,
+        (index % edge) as i32,
+    )
+}
+
+/// Grows a box from every solid voxel not covered yet, first along z, then y, then x.
+fn merge_boxes(grid: &[bool]) -> Vec<(IVec3, IVec3)> {
+    let mut free = grid.to_vec();
+    let mut boxes = Vec::new();
+    let edge = CHUNK_EDGE;
+    let all_free = |free: &[bool], min: IVec3, max: IVec3| {
+        (min.x..max.x).all(|x| {
+            (min.y..max.y).all(|y| (min.z..max.z).all(|z| free[linear_index(IVec3::new(x, y, z))]))
+        })
+    };
+
+    for index in 0..CHUNK_VOLUME {
+        if !free[index] {
+            continue;
+        }
+        let min = local_position(index);
+        let mut max = min + IVec3::ONE;
+        while max.z < edge && free[linear_index(IVec3::new(min.x, min.y, max.z))] {
+            max.z += 1;
+        }
+        while max.y < edge
+            && all_free(
+                &free,
+                IVec3::new(min.x, max.y, min.z),
+                IVec3::new(max.x, max.y + 1, max.z),
+            )
+        {
+            max.y += 1;
+        }
+        while max.x < edge
+            && all_free(
+                &free,
+                IVec3::new(max.x, min.y, min.z),
+                IVec3::new(max.x + 1, max.y, max.z),
+            )
+        {
+            max.x += 1;
+        }
+
+        for x in min.x..max.x {
+            for y in min.y..max.y {
+                for z in min.z..max.z {
+                    free[linear_index(IVec3::new(x, y, z))] = false;
+                }
+            }
+        }
+        boxes.push((min, max - min));
+    }
+    boxes
+}
+
+#[cfg(feature = "rapier")]
+fn build_chunk_colliders(
+    mut commands: Commands,
+    settings: Res<VoxelColliderSettings>,
+    registry: Res<MaterialRegistry>,
+    world_transform: Res<VoxelWorldTransform>,
+    chunks: Query<(Entity, &ChunkKey, &ChunkCells, Has<Transform>), Changed<ChunkCells>>,
+) {
+    for (entity, key, cells, has_transform) in chunks.iter() {
+        let boxes = chunk_boxes(cells, &registry, settings.shape);
+
+        let mut entity_commands = commands.entity(entity);
+        if boxes.is_empty() {
+            entity_commands.remove::<(Collider, RigidBody, ChunkColliderHash)>();
+            continue;
+        }
+        entity_commands.insert(ChunkColliderHash(boxes_hash(&boxes)));
+        let shapes = boxes
+            .into_iter()
+            .map(|(min, size)| {
+                let half = size.as_vec3() / 2.0;
+                let cuboid = Collider::cuboid(half.x, half.y, half.z);
+                (min.as_vec3() + half, Quat::IDENTITY, cuboid)
+            })
+            .collect();
+        entity_commands.insert((RigidBody::Fixed, Collider::compound(shapes)));
+        if !has_transform {
+            entity_commands.insert(SpatialBundle::from_transform(chunk_transform(
+                &world_transform,
+                key.coords,
+            )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merged_boxes_cover_solid_voxels_once() {
+        // A 4x2x3 slab and a single voxel apart from it.
+        let solid = |local: IVec3| {
+            local.cmplt(IVec3::new(4, 2, 3)).all() || local == IVec3::new(10, 10, 10)
+        };
+        let boxes = solid_boxes(solid, ChunkColliderShape::MergedBoxes);
+        assert_eq!(
+            boxes,
+            vec![
+                (IVec3::ZERO, IVec3::new(4, 2, 3)),
+                (IVec3::splat(10), IVec3::ONE)
+            ]
+        );
+
+        // The slab has no enclosed voxel, every one of its 24 voxels is exposed.
+        let voxels = solid_boxes(solid, ChunkColliderShape::Voxels);
+        assert_eq!(voxels.len(), 25);
+        let cube = |local: IVec3| local.cmplt(IVec3::splat(3)).all();
+        assert_eq!(solid_boxes(cube, ChunkColliderShape::Voxels).len(), 26);
+    }
+}
@@ -0,0 +1,266 @@
+use crate::{
+    brick_origin, encode_rle, ChunkCells, ChunkKey, ChunkStorage, BRICKS_PER_AXIS, BRICK_EDGE,
+    CHUNK_VOLUME,
+};
+use bevy::{prelude::*, utils::HashSet};
+use std::{fmt, mem};
+
+/// Fraction of uniform bricks in mixed chunks past which smaller chunks are recommended.
+const SPARSE_BRICKS: f32 = 0.5;
+/// Fraction of dense chunks past which the content is reported as noisy.
+const DENSE_CHUNKS: f32 = 0.25;
+
+/// Sizes of a single chunk under each encoding, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkCompression {
+    pub coords: IVec3,
+    /// Number of distinct states in the chunk.
+    pub distinct_states: usize,
+    /// Current [`ChunkStorage`] of the chunk.
+    pub storage_bytes: usize,
+    /// Most compact [`ChunkStorage`] of the cells, zero for uniform chunks.
+    pub compact_bytes: usize,
+    /// Run length encoding used by save files.
+    pub rle_bytes: usize,
+    /// Bricks of [`BRICK_EDGE`]³ cells holding a single state.
+    pub uniform_bricks: u32,
+}
+
+impl ChunkCompression {
+    /// Bytes of the uncompressed cells.
+    pub const DENSE_BYTES: usize = CHUNK_VOLUME * mem::size_of::<u16>();
+
+    pub fn measure(coords: IVec3, cells: &ChunkCells) -> Self {
+        let data = cells.as_slice();
+        let distinct_states = data.iter().collect::<HashSet<_>>().len();
+
+        let mut rle = Vec::new();
+        encode_rle(&data, &mut rle);
+
+        let bricks = BRICKS_PER_AXIS * BRICKS_PER_AXIS * BRICKS_PER_AXIS;
+        let uniform_bricks = (0..bricks as usize)
+            .filter(|&brick| {
+                let origin = brick_origin(brick);
+                let first = cells.get(origin);
+                (0..BRICK_EDGE).all(|x| {
+                    (0..BRICK_EDGE).all(|y| {
+                        (0..BRICK_EDGE).all(|z| cells.get(origin + IVec3::new(x, y, z)) == first)
+                    })
+                })
+            })
+            .count() as u32;
+
+        Self {
+            coords,
+            distinct_states,
+            storage_bytes: cells.storage().heap_size(),
+            compact_bytes: ChunkStorage::from_slice(&data).heap_size(),
+            rle_bytes: rle.len(),
+            uniform_bricks,
+        }
+    }
+
+    #[inline]
+    pub fn is_uniform(&self) -> bool {
+        self.distinct_states == 1
+    }
+
+    /// Whether the cells need a dense storage.
+    #[inline]
+    pub fn is_dense(&self) -> bool {
+        self.compact_bytes >= Self::DENSE_BYTES
+    }
+}
+
+/// Suggestion drawn from a [`CompressionReport`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompressionAdvice {
+    /// Edited chunks kept a larger storage than their cells need, see [`ChunkCells::compact`].
+    Compact { chunks: usize, saved_bytes: usize },
+    /// Most bricks of the non-uniform chunks are uniform, so a smaller `CHUNK_EDGE` would turn
+    /// them into cheap uniform chunks.
+    SmallerChunks { uniform_bricks: f32 },
+    /// Many chunks have too many states for a palette. Fewer materials or flag combinations
+    /// would let them compress.
+    NoisyContent { dense_chunks: usize },
+}
+
+impl fmt::Display for CompressionAdvice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompressionAdvice::Compact {
+                chunks,
+                saved_bytes,
+            } => write!(
+                f,
+                "Compacting {} chunks would save {} bytes",
+                chunks, saved_bytes
+            ),
+            CompressionAdvice::SmallerChunks { uniform_bricks } => write!(
+                f,
+                "{:.0}% of the bricks in mixed chunks are uniform, smaller chunks would compress \
+                 better",
+                uniform_bricks * 100.0
+            ),
+            CompressionAdvice::NoisyContent { dense_chunks } => write!(
+                f,
+                "{} chunks have too many distinct states for a palette",
+                dense_chunks
+            ),
+        }
+    }
+}
+
+/// Compressibility of the loaded chunks, to tune storage and chunk settings for some content.
+#[derive(Debug, Clone, Default)]
+pub struct CompressionReport {
+    pub chunks: Vec<ChunkCompression>,
+}
+
+impl CompressionReport {
+    pub fn measure<'a>(chunks: impl IntoIterator<Item = (IVec3, &'a ChunkCells)>) -> Self {
+        let mut chunks: Vec<_> = chunks
+            .into_iter()
+            .map(|(coords, cells)| ChunkCompression::measure(coords, cells))
+            .collect();
+        chunks.sort_by_key(|chunk| chunk.coords.to_array());
+        Self { chunks }
+    }
+
+    pub fn uniform_chunks(&self) -> usize {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.is_uniform())
+            .count()
+    }
+
+    pub fn dense_chunks(&self) -> usize {
+        self.chunks.iter().filter(|chunk| chunk.is_dense()).count()
+    }
+
+    pub fn storage_bytes(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.storage_bytes).sum()
+    }
+
+    pub fn compact_bytes(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.compact_bytes).sum()
+    }
+
+    pub fn rle_bytes(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.rle_bytes).sum()
+    }
+
+    pub fn dense_bytes(&self) -> usize {
+        self.chunks.len() * ChunkCompression::DENSE_BYTES
+    }
+
+    pub fn recommendations(&self) -> Vec<CompressionAdvice> {
+        let mut advice = Vec::new();
+
+        let oversized: Vec<_> = self
+            .chunks
+            .iter()
+            .filter(|chunk| chunk.storage_bytes > chunk.compact_bytes)
+            .collect();
+        if !oversized.is_empty() {
+            advice.push(CompressionAdvice::Compact {
+                chunks: oversized.len(),
+                saved_bytes: oversized
+                    .iter()
+                    .map(|chunk| chunk.storage_bytes - chunk.compact_bytes)
+                    .sum(),
+            });
+        }
+
+        let mixed: Vec<_> = self.chunks.iter().filter(|c| !c.is_uniform()).collect();
+        if !mixed.is_empty() {
+            let bricks = (BRICKS_PER_AXIS * BRICKS_PER_AXIS * BRICKS_PER_AXIS) as usize;
+            let uniform: u32 = mixed.iter().map(|chunk| chunk.uniform_bricks).sum();
+            let uniform_bricks = uniform as f32 / (mixed.len() * bricks) as f32;
+            if uniform_bricks > SPARSE_BRICKS {
+                advice.push(CompressionAdvice::SmallerChunks { uniform_bricks });
+            }
+        }
+
+        let dense_chunks = self.dense_chunks();
+        if dense_chunks as f32 > self.chunks.len() as f32 * DENSE_CHUNKS {
+            advice.push(CompressionAdvice::NoisyContent { dense_chunks });
+        }
+
+        advice
+    }
+}
+
+impl fmt::Display for CompressionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} chunks, {} uniform, {} dense",
+            self.chunks.len(),
+            self.uniform_chunks(),
+            self.dense_chunks()
+        )?;
+        writeln!(
+            f,
+            "dense {} B, stored {} B, compact {} B, rle {} B",
+            self.dense_bytes(),
+            self.storage_bytes(),
+            self.compact_bytes(),
+            self.rle_bytes()
+        )?;
+        for advice in self.recommendations() {
+            writeln!(f, "- {}", advice)?;
+        }
+        Ok(())
+    }
+}
+
+/// Measures every loaded chunk of the world.
+pub fn compression_report(world: &mut World) -> CompressionReport {
+    let mut query = world.query::<(&ChunkKey, &ChunkCells)>();
+    CompressionReport::measure(query.iter(world).map(|(key, cells)| (key.coords, cells)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AutomataState, CHUNK_EDGE};
+
+    #[test]
+    fn report_measures_uniform_and_sparse_chunks() {
+        let empty = ChunkCells::default();
+        let floor = ChunkCells::from_generator(|pos| {
+            if pos.y == 0 {
+                AutomataState::new(1, 0)
+            } else {
+                AutomataState::EMPTY
+            }
+        });
+        let mut edited = ChunkCells::default();
+        for x in 0..CHUNK_EDGE {
+            edited.set(IVec3::new(x, 0, 0), AutomataState::new((x % 20) as u8, 0));
+        }
+        for x in 0..CHUNK_EDGE {
+            edited.set(IVec3::new(x, 0, 0), AutomataState::EMPTY);
+        }
+
+        let report = CompressionReport::measure([
+            (IVec3::ZERO, &empty),
+            (IVec3::X, &floor),
+            (IVec3::new(2, 0, 0), &edited),
+        ]);
+        assert_eq!(report.uniform_chunks(), 2);
+        assert_eq!(report.chunks[0].compact_bytes, 0);
+        assert!(report.chunks[1].compact_bytes < ChunkCompression::DENSE_BYTES / 8);
+        assert!(report.chunks[1].rle_bytes < report.chunks[1].compact_bytes);
+
+        let advice = report.recommendations();
+        assert!(advice.contains(&CompressionAdvice::Compact {
+            chunks: 1,
+            saved_bytes: ChunkCompression::DENSE_BYTES,
+        }));
+        assert!(advice
+            .iter()
+            .any(|advice| matches!(advice, CompressionAdvice::SmallerChunks { .. })));
+    }
+}
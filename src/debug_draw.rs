@@ -0,0 +1,211 @@
+use crate::{
+    ChunkCells, ChunkKey, ChunkLod, ChunkSleeping, SimulationRate, SimulationSet,
+    VoxelWorldTransform, CHUNK_EDGE,
+};
+use bevy::prelude::*;
+
+/// Draws the bounds of every chunk with gizmos, colored by what the simulation does with it,
+/// for seeing at a glance which parts of the world cost time.
+///
+/// Chunks can also be labeled with their Morton key, and the live voxels of one chosen chunk
+/// marked one by one. Everything is toggled from the [`DebugDrawConfig`] resource and drawn in
+/// `PostUpdate` after the steps of the frame.
+pub struct DebugDrawPlugin;
+
+impl Plugin for DebugDrawPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugDrawConfig>()
+            .init_resource::<VoxelWorldTransform>()
+            .add_systems(
+                PostUpdate,
+                (draw_chunk_bounds, draw_voxel_markers, update_chunk_labels)
+                    .after(SimulationSet::Run),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct DebugDrawConfig {
+    pub chunk_bounds: bool,
+    /// Labels chunks with their Morton key, drawn as UI text over the first active camera.
+    pub chunk_labels: bool,
+    /// Labels only the chunks nearest to the camera past this many.
+    pub max_labels: usize,
+    /// Chunk whose live voxels are marked, by chunk coordinates.
+    pub selected_chunk: Option<IVec3>,
+    pub active_color: Color,
+    pub sleeping_color: Color,
+    pub dirty_color: Color,
+    pub voxel_color: Color,
+}
+
+impl Default for DebugDrawConfig {
+    fn default() -> Self {
+        Self {
+            chunk_bounds: true,
+            chunk_labels: false,
+            max_labels: 64,
+            selected_chunk: None,
+            active_color: Color::GREEN,
+            sleeping_color: Color::GRAY,
+            dirty_color: Color::ORANGE,
+            voxel_color: Color::YELLOW,
+        }
+    }
+}
+
+impl DebugDrawConfig {
+    pub fn color(&self, activity: ChunkDebugActivity) -> Color {
+        match activity {
+            ChunkDebugActivity::Active => self.active_color,
+            ChunkDebugActivity::Sleeping => self.sleeping_color,
+            ChunkDebugActivity::Dirty => self.dirty_color,
+        }
+    }
+}
+
+/// What the simulation does with a chunk, as colored by the [`DebugDrawPlugin`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChunkDebugActivity {
+    /// Stepped by the simulation.
+    Active,
+    /// Left as is by the steps, because it is empty, frozen by its [`ChunkLod`] or
+    /// [`ChunkSleeping`].
+    Sleeping,
+    /// Cells changed this frame, by a step or an edit, and consumers such as meshing still have
+    /// to catch up.
+    Dirty,
+}
+
+impl ChunkDebugActivity {
+    fn of(cells: Ref<ChunkCells>, lod: Option<&ChunkLod>, sleeping: bool) -> Self {
+        if cells.is_changed() {
+            Self::Dirty
+        } else if sleeping
+            || cells.occupancy().count() == 0
+            || lod.is_some_and(|lod| lod.rate == SimulationRate::Frozen)
+        {
+            Self::Sleeping
+        } else {
+            Self::Active
+        }
+    }
+}
+
+/// UI text labeling a chunk, reused from frame to frame.
+#[derive(Component)]
+struct ChunkLabel;
+
+/// Transform drawing a box over `size` voxels from `min` in voxel space.
+fn box_transform(world_transform: &VoxelWorldTransform, min: IVec3, size: f32) -> Transform {
+    let center = min.as_vec3() + Vec3::splat(size * 0.5);
+    Transform {
+        translation: world_transform.voxel_space_to_world(center),
+        rotation: world_transform.rotation,
+        scale: Vec3::splat(size * world_transform.voxel_size),
+    }
+}
+
+fn draw_chunk_bounds(
+    mut gizmos: Gizmos,
+    config: Res<DebugDrawConfig>,
+    world_transform: Res<VoxelWorldTransform>,
+    chunks: Query<(
+        &ChunkKey,
+        Ref<ChunkCells>,
+        Option<&ChunkLod>,
+        Has<ChunkSleeping>,
+    )>,
+) {
+    if !config.chunk_bounds {
+        return;
+    }
+    for (key, cells, lod, sleeping) in chunks.iter() {
+        let color = config.color(ChunkDebugActivity::of(cells, lod, sleeping));
+        let transform = box_transform(&world_transform, key.coords * CHUNK_EDGE, CHUNK_EDGE as f32);
+        gizmos.cuboid(transform, color);
+    }
+}
+
+fn draw_voxel_markers(
+    mut gizmos: Gizmos,
+    config: Res<DebugDrawConfig>,
+    world_transform: Res<VoxelWorldTransform>,
+    chunks: Query<(&ChunkKey, &ChunkCells)>,
+) {
+    let Some(selected) = config.selected_chunk else {
+        return;
+    };
+    let Some((_, cells)) = chunks.iter().find(|(key, _)| key.coords == selected) else {
+        return;
+    };
+    let origin = selected * CHUNK_EDGE;
+    for x in 0..CHUNK_EDGE {
+        for y in 0..CHUNK_EDGE {
+            for z in 0..CHUNK_EDGE {
+                let local = IVec3::new(x, y, z);
+                if cells.is_alive(local) {
+                    // Slightly smaller than the voxel so neighboring markers stay apart.
+                    let mut transform = box_transform(&world_transform, origin + local, 1.0);
+                    transform.scale *= 0.8;
+                    gizmos.cuboid(transform, config.voxel_color);
+                }
+            }
+        }
+    }
+}
+
+fn update_chunk_labels(
+    mut commands: Commands,
+    config: Res<DebugDrawConfig>,
+    world_transform: Res<VoxelWorldTransform>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    chunks: Query<&ChunkKey>,
+    mut labels: Query<(Entity, &mut Text, &mut Style), With<ChunkLabel>>,
+) {
+    let camera = cameras.iter().find(|(camera, _)| camera.is_active);
+    let mut placed = Vec::new();
+    if let Some((camera, camera_transform)) = camera.filter(|_| config.chunk_labels) {
+        let eye = camera_transform.translation();
+        for key in chunks.iter() {
+            let center = (key.coords * CHUNK_EDGE).as_vec3() + Vec3::splat(CHUNK_EDGE as f32 * 0.5);
+            let center = world_transform.voxel_space_to_world(center);
+            if let Some(position) = camera.world_to_viewport(camera_transform, center) {
+                placed.push((eye.distance_squared(center), position, key.morton));
+            }
+        }
+        placed.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+        placed.truncate(config.max_labels);
+    }
+
+    let mut placed = placed.into_iter();
+    for (entity, mut text, mut style) in labels.iter_mut() {
+        match placed.next() {
+            Some((_, position, morton)) => {
+                text.sections[0].value = format!("{morton:#x}");
+                style.left = Val::Px(position.x);
+                style.top = Val::Px(position.y);
+            }
+            None => commands.entity(entity).despawn(),
+        }
+    }
+    for (_, position, morton) in placed {
+        commands.spawn((
+            ChunkLabel,
+            TextBundle::from_section(
+                format!("{morton:#x}"),
+                TextStyle {
+                    font_size: 14.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(position.x),
+                top: Val::Px(position.y),
+                ..default()
+            }),
+        ));
+    }
+}
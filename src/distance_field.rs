@@ -0,0 +1,289 @@
+use crate::{
+    brick_origin, voxel_to_chunk, ChunkCells, ChunkChanges, ChunkKey, SimulationSet, BRICK_EDGE,
+    CHUNK_EDGE,
+};
+use bevy::{prelude::*, utils::HashMap};
+
+/// Distance in voxels past which [`ChunkDistanceField`] values are clamped.
+pub const DISTANCE_FIELD_RANGE: i32 = 8;
+
+/// Chamfer weights for face, edge and corner steps, a voxel being 3 units long.
+const FACE: i8 = 3;
+const EDGE: i8 = 4;
+const CORNER: i8 = 5;
+const UNITS: f32 = FACE as f32;
+const MAX_UNITS: i8 = (DISTANCE_FIELD_RANGE * FACE as i32) as i8;
+
+/// Keeps a [`ChunkDistanceField`] on every chunk, for steering, soft shadows or smooth normals.
+///
+/// Only the part of a field within [`DISTANCE_FIELD_RANGE`] of the bricks marked in
+/// [`ChunkChanges`] is recomputed, including across chunk borders. Cells of unloaded chunks
+/// count as empty.
+pub struct DistanceFieldPlugin;
+
+impl Plugin for DistanceFieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, update_distance_fields.after(SimulationSet::Run));
+    }
+}
+
+/// Signed distance from every cell of a chunk to the nearest solid cell, computed with a 3-4-5
+/// chamfer metric.
+///
+/// Empty cells hold the distance to the closest solid cell, solid cells the negated distance to
+/// the closest empty cell, both clamped to [`DISTANCE_FIELD_RANGE`].
+#[derive(Component, Clone)]
+pub struct ChunkDistanceField {
+    data: Box<[i8]>,
+}
+
+impl ChunkDistanceField {
+    /// Signed distance in voxels at a local position.
+    #[inline]
+    pub fn distance(&self, local: IVec3) -> f32 {
+        self.data[index(local, IVec3::splat(CHUNK_EDGE))] as f32 / UNITS
+    }
+
+    /// Central difference gradient, pointing away from solid cells. One-sided on the chunk
+    /// border.
+    pub fn gradient(&self, local: IVec3) -> Vec3 {
+        let mut gradient = Vec3::ZERO;
+        for axis in 0..3 {
+            let mut step = IVec3::ZERO;
+            step[axis] = 1;
+            let low = (local - step).max(IVec3::ZERO);
+            let high = (local + step).min(IVec3::splat(CHUNK_EDGE - 1));
+            let span = (high - low)[axis].max(1) as f32;
+            gradient[axis] = (self.distance(high) - self.distance(low)) / span;
+        }
+        gradient
+    }
+
+    /// Raw values in chamfer units (a third of a voxel), in chunk order.
+    #[inline]
+    pub fn as_slice(&self) -> &[i8] {
+        &self.data
+    }
+
+    /// Field of the whole chunk at `coords`.
+    pub(crate) fn compute(coords: IVec3, solid: impl Fn(IVec3) -> bool) -> Self {
+        let data = signed_distances(coords * CHUNK_EDGE, IVec3::splat(CHUNK_EDGE), solid);
+        Self {
+            data: data.into_boxed_slice(),
+        }
+    }
+}
+
+/// Computes the signed chamfer distances of the `size` cells starting at voxel `min`.
+///
+/// Solidity is sampled up to [`DISTANCE_FIELD_RANGE`] voxels around the box, the result is in
+/// chamfer units and in x, y, z (innermost) order.
+pub fn signed_distances<F>(min: IVec3, size: IVec3, solid: F) -> Vec<i8>
+where
+    F: Fn(IVec3) -> bool,
+{
+    let margin = IVec3::splat(DISTANCE_FIELD_RANGE);
+    let grid = size + margin * 2;
+    let origin = min - margin;
+    let len = (grid.x * grid.y * grid.z) as usize;
+
+    let mut solids = vec![false; len];
+    let mut to_solid = vec![MAX_UNITS; len];
+    let mut to_empty = vec![MAX_UNITS; len];
+    for x in 0..grid.x {
+        for y in 0..grid.y {
+            for z in 0..grid.z {
+                let cell = IVec3::new(x, y, z);
+                let i = index(cell, grid);
+                solids[i] = solid(origin + cell);
+                if solids[i] {
+                    to_solid[i] = 0;
+                } else {
+                    to_empty[i] = 0;
+                }
+            }
+        }
+    }
+
+    chamfer(&mut to_solid, grid);
+    chamfer(&mut to_empty, grid);
+
+    let mut distances = Vec::with_capacity((size.x * size.y * size.z) as usize);
+    for x in 0..size.x {
+        for y in 0..size.y {
+            for z in 0..size.z {
+                let i = index(IVec3::new(x, y, z) + margin, grid);
+                distances.push(if solids[i] { -to_empty[i] } else { to_solid[i] });
+            }
+        }
+    }
+    distances
+}
+
+/// Two pass chamfer transform, clamped to [`MAX_UNITS`].
+fn chamfer(distances: &mut [i8], grid: IVec3) {
+    let mut forward = Vec::new();
+    for x in -1..=1 {
+        for y in -1..=1 {
+            for z in -1..=1 {
+                let offset = IVec3::new(x, y, z);
+                let weight = match x.abs() + y.abs() + z.abs() {
+                    1 => FACE,
+                    2 => EDGE,
+                    _ => CORNER,
+                };
+                // Offsets visited before the cell in x, y, z order.
+                if (x, y, z) < (0, 0, 0) {
+                    forward.push((offset, weight));
+                }
+            }
+        }
+    }
+
+    let mut relax = |cell: IVec3, offsets: &[(IVec3, i8)], sign: i32| {
+        let i = index(cell, grid);
+        for &(offset, weight) in offsets {
+            let neighbor = cell + offset * sign;
+            if neighbor.cmpge(IVec3::ZERO).all() && neighbor.cmplt(grid).all() {
+                let candidate = distances[index(neighbor, grid)].saturating_add(weight);
+                distances[i] = distances[i].min(candidate).min(MAX_UNITS);
+            }
+        }
+    };
+
+    for x in 0..grid.x {
+        for y in 0..grid.y {
+            for z in 0..grid.z {
+                relax(IVec3::new(x, y, z), &forward, 1);
+            }
+        }
+    }
+    for x in (0..grid.x).rev() {
+        for y in (0..grid.y).rev() {
+            for z in (0..grid.z).rev() {
+                relax(IVec3::new(x, y, z), &forward, -1);
+            }
+        }
+    }
+}
+
+#[inline]
+fn index(cell: IVec3, grid: IVec3) -> usize {
+    (cell.x * grid.y * grid.z + cell.y * grid.z + cell.z) as usize
+}
+
+/// Voxel box `(min, max)`, max exclusive, covering the bricks set in a mask.
+fn brick_bounds(coords: IVec3, bricks: u64) -> (IVec3, IVec3) {
+    let mut min = IVec3::splat(CHUNK_EDGE);
+    let mut max = IVec3::ZERO;
+    for index in (0..64).filter(|index| bricks & (1 << index) != 0) {
+        let origin = brick_origin(index);
+        min = min.min(origin);
+        max = max.max(origin + BRICK_EDGE);
+    }
+    let origin = coords * CHUNK_EDGE;
+    (origin + min, origin + max)
+}
+
+#[allow(clippy::type_complexity)]
+fn update_distance_fields(
+    mut commands: Commands,
+    mut tracked: Local<HashMap<Entity, IVec3>>,
+    mut removed: RemovedComponents<ChunkKey>,
+    chunks: Query<(Entity, &ChunkKey, &ChunkCells, Ref<ChunkChanges>)>,
+    mut fields: Query<&mut ChunkDistanceField>,
+) {
+    // Voxel boxes whose cells changed, grown by the range of the field.
+    let mut dirty = Vec::new();
+    let margin = IVec3::splat(DISTANCE_FIELD_RANGE);
+    for (entity, key, _, changes) in chunks.iter() {
+        let bricks = if changes.is_added() || !fields.contains(entity) {
+            u64::MAX
+        } else if changes.is_changed() {
+            changes.bricks
+        } else {
+            0
+        };
+        if bricks != 0 {
+            tracked.insert(entity, key.coords);
+            let (min, max) = brick_bounds(key.coords, bricks);
+            dirty.push((min - margin, max + margin));
+        }
+    }
+    for entity in removed.read() {
+        if let Some(coords) = tracked.remove(&entity) {
+            let min = coords * CHUNK_EDGE;
+            dirty.push((min - margin, min + CHUNK_EDGE + margin));
+        }
+    }
+    if dirty.is_empty() {
+        return;
+    }
+
+    let by_coords: HashMap<IVec3, Entity> = chunks
+        .iter()
+        .map(|(entity, key, ..)| (key.coords, entity))
+        .collect();
+    let solid = |voxel: IVec3| {
+        let (chunk, local) = voxel_to_chunk(voxel);
+        by_coords
+            .get(&chunk)
+            .and_then(|entity| chunks.get(*entity).ok())
+            .is_some_and(|(_, _, cells, _)| cells.is_alive(local))
+    };
+
+    for (entity, key, ..) in chunks.iter() {
+        let chunk_min = key.coords * CHUNK_EDGE;
+        let chunk_max = chunk_min + CHUNK_EDGE;
+
+        // Part of the chunk covered by the dirty boxes.
+        let mut min = chunk_max;
+        let mut max = chunk_min;
+        for &(dirty_min, dirty_max) in &dirty {
+            let overlap_min = dirty_min.max(chunk_min);
+            let overlap_max = dirty_max.min(chunk_max);
+            if overlap_min.cmplt(overlap_max).all() {
+                min = min.min(overlap_min);
+                max = max.max(overlap_max);
+            }
+        }
+        if !min.cmplt(max).all() {
+            continue;
+        }
+
+        let Ok(mut field) = fields.get_mut(entity) else {
+            let field = ChunkDistanceField::compute(key.coords, &solid);
+            commands.entity(entity).insert(field);
+            continue;
+        };
+
+        let size = max - min;
+        let distances = signed_distances(min, size, &solid);
+        for x in 0..size.x {
+            for y in 0..size.y {
+                for z in 0..size.z {
+                    let cell = IVec3::new(x, y, z);
+                    let local = min - chunk_min + cell;
+                    field.data[index(local, IVec3::splat(CHUNK_EDGE))] =
+                        distances[index(cell, size)];
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances_are_signed_around_a_voxel() {
+        let size = IVec3::splat(5);
+        let distances = signed_distances(IVec3::ZERO, size, |voxel| voxel == IVec3::splat(2));
+
+        assert_eq!(distances[index(IVec3::splat(2), size)], -FACE);
+        assert_eq!(distances[index(IVec3::new(4, 2, 2), size)], 2 * FACE);
+        assert_eq!(distances[index(IVec3::new(3, 3, 3), size)], CORNER);
+        assert_eq!(distances[index(IVec3::ZERO, size)], 2 * CORNER);
+    }
+}
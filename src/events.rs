@@ -0,0 +1,129 @@
+use crate::{ChunkInvariant, TooManyMaterials};
+use bevy::prelude::*;
+use std::{fmt, path::PathBuf};
+
+/// Non-fatal problems reported by the engine, so host apps can surface them in their UI or
+/// logs. Every event is also logged when it is sent.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub enum EngineEvent {
+    /// Chunks could not be read from disk.
+    ChunkLoadFailed { path: PathBuf, reason: String },
+    /// Chunks could not be written to disk.
+    ChunkSaveFailed { path: PathBuf, reason: String },
+    /// The [`StatisticsExport`](crate::StatisticsExport) could not write its file and stopped.
+    StatisticsWriteFailed { path: PathBuf, reason: String },
+    /// A background task panicked, its result was dropped.
+    TaskPanicked { task: String, message: String },
+    /// Simulation steps started taking longer than [`SimulationBudget::target_ms`].
+    ///
+    /// [`SimulationBudget::target_ms`]: crate::SimulationBudget::target_ms
+    BudgetOverloaded { rolling_ms: f32, target_ms: f32 },
+    /// Data expected back from the GPU was not available.
+    GpuReadbackFailed { source: &'static str },
+    /// Voxel edits were dropped because they fell in a region locked by another operation.
+    EditsRejected { voxels: usize },
+    /// The [`WorldValidation`](crate::WorldValidation) sweep found data of a chunk out of sync.
+    ChunkInvariantBroken {
+        coords: IVec3,
+        invariant: ChunkInvariant,
+        repaired: bool,
+    },
+    /// The material rules of the [`AutomataRule`](crate::AutomataRule) reference more materials
+    /// than [`MAX_TRACKED_MATERIALS`](crate::MAX_TRACKED_MATERIALS), conditions on the ones
+    /// past the limit count no neighbors.
+    MaterialsUntracked { materials: usize },
+}
+
+impl EngineEvent {
+    /// Whether the condition lost data, rather than only degrading the simulation.
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            EngineEvent::ChunkLoadFailed { .. }
+                | EngineEvent::ChunkSaveFailed { .. }
+                | EngineEvent::StatisticsWriteFailed { .. }
+                | EngineEvent::TaskPanicked { .. }
+        )
+    }
+
+    /// Logs the event and sends it through `events`.
+    pub fn report(self, events: &mut EventWriter<EngineEvent>) {
+        self.log();
+        events.send(self);
+    }
+
+    /// Logs the event and sends it through the world's event queue.
+    pub fn report_to_world(self, world: &mut World) {
+        self.log();
+        world.send_event(self);
+    }
+
+    fn log(&self) {
+        if self.is_error() {
+            error!("{}", self);
+        } else {
+            warn!("{}", self);
+        }
+    }
+}
+
+impl fmt::Display for EngineEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineEvent::ChunkLoadFailed { path, reason } => {
+                write!(
+                    f,
+                    "Failed to load chunks from {}: {}",
+                    path.display(),
+                    reason
+                )
+            }
+            EngineEvent::ChunkSaveFailed { path, reason } => {
+                write!(f, "Failed to save chunks to {}: {}", path.display(), reason)
+            }
+            EngineEvent::StatisticsWriteFailed { path, reason } => {
+                write!(
+                    f,
+                    "Failed to write statistics to {}: {}",
+                    path.display(),
+                    reason
+                )
+            }
+            EngineEvent::TaskPanicked { task, message } => {
+                write!(f, "Task {} panicked: {}", task, message)
+            }
+            EngineEvent::BudgetOverloaded {
+                rolling_ms,
+                target_ms,
+            } => write!(
+                f,
+                "Simulation steps take {:.2}ms, over the {:.2}ms budget",
+                rolling_ms, target_ms
+            ),
+            EngineEvent::GpuReadbackFailed { source } => {
+                write!(f, "No {} data returned from the gpu", source)
+            }
+            EngineEvent::EditsRejected { voxels } => {
+                write!(f, "Dropped {} voxel edits inside locked regions", voxels)
+            }
+            EngineEvent::ChunkInvariantBroken {
+                coords,
+                invariant,
+                repaired,
+            } => write!(
+                f,
+                "Chunk {} failed the {:?} check{}",
+                coords,
+                invariant,
+                if *repaired { ", repaired" } else { "" }
+            ),
+            EngineEvent::MaterialsUntracked { materials } => write!(
+                f,
+                "{}",
+                TooManyMaterials {
+                    materials: *materials
+                }
+            ),
+        }
+    }
+}
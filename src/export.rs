@@ -0,0 +1,268 @@
+use crate::{
+    greedy_mesh, meshing::in_chunk, voxel_to_chunk, AutomataState, ChunkCells, ChunkKey,
+    ChunkMeshData, ChunkMeshPalette, VoxelWorldTransform, CHUNK_EDGE,
+};
+use bevy::{prelude::*, utils::HashMap};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_JSON: u32 = 0x4E4F_534A;
+const GLB_BIN: u32 = 0x004E_4942;
+
+/// File format written by [`export_mesh`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshExportFormat {
+    /// Wavefront OBJ with per vertex colors, one object per chunk.
+    Obj,
+    /// Binary glTF 2.0, a single mesh with a `COLOR_0` attribute.
+    Glb,
+}
+
+impl MeshExportFormat {
+    /// Format matching the extension of `path`, `obj` or `glb`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "obj" => Some(Self::Obj),
+            "glb" => Some(Self::Glb),
+            _ => None,
+        }
+    }
+}
+
+/// Greedy meshes every loaded chunk and writes the result to `path`, for inspecting a world in
+/// a modeling tool or baking it into a static asset.
+///
+/// Vertices are in world space following the [`VoxelWorldTransform`] and colored per material
+/// with the [`ChunkMeshPalette`]. Faces between two chunks are culled as in the rendered
+/// meshes, faces against unloaded chunks are kept.
+pub fn export_mesh(
+    world: &mut World,
+    path: impl AsRef<Path>,
+    format: MeshExportFormat,
+) -> io::Result<()> {
+    let chunks = mesh_world(world);
+    let palette = world
+        .get_resource::<ChunkMeshPalette>()
+        .cloned()
+        .unwrap_or_default();
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    match format {
+        MeshExportFormat::Obj => write_obj(&mut writer, &chunks, &palette)?,
+        MeshExportFormat::Glb => write_glb(&mut writer, &chunks, &palette)?,
+    }
+    writer.flush()
+}
+
+/// Meshes of the loaded chunks in coordinate order, positions and normals in world space.
+fn mesh_world(world: &mut World) -> Vec<(IVec3, ChunkMeshData)> {
+    let world_transform = world
+        .get_resource::<VoxelWorldTransform>()
+        .copied()
+        .unwrap_or_default();
+    let mut query = world.query::<(&ChunkKey, &ChunkCells)>();
+    let by_coords: HashMap<IVec3, &ChunkCells> = query
+        .iter(world)
+        .map(|(key, cells)| (key.coords, cells))
+        .collect();
+
+    let mut coords: Vec<IVec3> = by_coords.keys().copied().collect();
+    coords.sort_by_key(|coords| coords.to_array());
+    coords
+        .into_iter()
+        .filter(|coords| !by_coords[coords].occupancy().is_empty())
+        .map(|coords| {
+            let cells = by_coords[&coords];
+            let origin = coords * CHUNK_EDGE;
+            let mut data = greedy_mesh(|local| {
+                if in_chunk(local) {
+                    return cells.get(local);
+                }
+                let (neighbor, local) = voxel_to_chunk(origin + local);
+                by_coords
+                    .get(&neighbor)
+                    .map_or(AutomataState::EMPTY, |cells| cells.get(local))
+            });
+
+            let offset = origin.as_vec3();
+            for position in &mut data.positions {
+                let voxel_space = offset + Vec3::from_array(*position);
+                *position = world_transform.voxel_space_to_world(voxel_space).to_array();
+            }
+            for normal in &mut data.normals {
+                *normal = (world_transform.rotation * Vec3::from_array(*normal)).to_array();
+            }
+            (coords, data)
+        })
+        .collect()
+}
+
+fn write_obj(
+    writer: &mut impl Write,
+    chunks: &[(IVec3, ChunkMeshData)],
+    palette: &ChunkMeshPalette,
+) -> io::Result<()> {
+    // OBJ indices are 1-based and global to the file.
+    let mut first_vertex = 1;
+    for (coords, data) in chunks {
+        writeln!(writer, "o chunk_{}_{}_{}", coords.x, coords.y, coords.z)?;
+        for (position, material) in data.positions.iter().zip(&data.materials) {
+            let [r, g, b, _] = palette.colors[*material as usize].as_rgba_f32();
+            writeln!(
+                writer,
+                "v {} {} {} {} {} {}",
+                position[0], position[1], position[2], r, g, b
+            )?;
+        }
+        for normal in &data.normals {
+            writeln!(writer, "vn {} {} {}", normal[0], normal[1], normal[2])?;
+        }
+        for triangle in data.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|corner| triangle[corner] + first_vertex);
+            writeln!(writer, "f {a}//{a} {b}//{b} {c}//{c}")?;
+        }
+        first_vertex += data.positions.len() as u32;
+    }
+    Ok(())
+}
+
+fn write_glb(
+    writer: &mut impl Write,
+    chunks: &[(IVec3, ChunkMeshData)],
+    palette: &ChunkMeshPalette,
+) -> io::Result<()> {
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut colors = Vec::new();
+    let mut indices = Vec::new();
+    let mut min = Vec3::INFINITY;
+    let mut max = Vec3::NEG_INFINITY;
+    let mut vertex_count = 0;
+    let mut index_count = 0;
+    for (_, data) in chunks {
+        for position in &data.positions {
+            min = min.min(Vec3::from_array(*position));
+            max = max.max(Vec3::from_array(*position));
+            positions.extend(position.iter().flat_map(|value| value.to_le_bytes()));
+        }
+        for normal in &data.normals {
+            normals.extend(normal.iter().flat_map(|value| value.to_le_bytes()));
+        }
+        for material in &data.materials {
+            let color = palette.colors[*material as usize].as_linear_rgba_f32();
+            colors.extend(color.iter().flat_map(|value| value.to_le_bytes()));
+        }
+        for index in &data.indices {
+            indices.extend((index + vertex_count).to_le_bytes());
+        }
+        vertex_count += data.positions.len() as u32;
+        index_count += data.indices.len();
+    }
+
+    // glTF forbids empty buffers and accessors, an empty world exports a scene without mesh.
+    let json = if vertex_count == 0 {
+        r#"{"asset":{"version":"2.0"},"scene":0,"scenes":[{"nodes":[]}]}"#.to_string()
+    } else {
+        let views = [&positions, &normals, &colors, &indices];
+        let mut view_json = Vec::new();
+        let mut offset = 0;
+        for (view, target) in views.iter().zip([34962, 34962, 34962, 34963]) {
+            view_json.push(format!(
+                r#"{{"buffer":0,"byteOffset":{offset},"byteLength":{},"target":{target}}}"#,
+                view.len()
+            ));
+            offset += view.len();
+        }
+        format!(
+            concat!(
+                r#"{{"asset":{{"version":"2.0","generator":"bevy_voxel_engine"}},"#,
+                r#""scene":0,"scenes":[{{"nodes":[0]}}],"nodes":[{{"mesh":0}}],"#,
+                r#""meshes":[{{"primitives":[{{"attributes":"#,
+                r#"{{"POSITION":0,"NORMAL":1,"COLOR_0":2}},"indices":3}}]}}],"#,
+                r#""accessors":["#,
+                r#"{{"bufferView":0,"componentType":5126,"count":{vertices},"type":"VEC3","#,
+                r#""min":[{},{},{}],"max":[{},{},{}]}},"#,
+                r#"{{"bufferView":1,"componentType":5126,"count":{vertices},"type":"VEC3"}},"#,
+                r#"{{"bufferView":2,"componentType":5126,"count":{vertices},"type":"VEC4"}},"#,
+                r#"{{"bufferView":3,"componentType":5125,"count":{indices},"type":"SCALAR"}}],"#,
+                r#""bufferViews":[{views}],"buffers":[{{"byteLength":{length}}}]}}"#,
+            ),
+            min.x,
+            min.y,
+            min.z,
+            max.x,
+            max.y,
+            max.z,
+            vertices = vertex_count,
+            indices = index_count,
+            views = view_json.join(","),
+            length = offset,
+        )
+    };
+
+    let mut json = json.into_bytes();
+    json.resize(json.len().next_multiple_of(4), b' ');
+    let mut bin = [positions, normals, colors, indices].concat();
+    bin.resize(bin.len().next_multiple_of(4), 0);
+
+    let bin_chunk = if bin.is_empty() { 0 } else { 8 + bin.len() };
+    let length = 12 + 8 + json.len() + bin_chunk;
+    writer.write_all(&GLB_MAGIC.to_le_bytes())?;
+    writer.write_all(&2u32.to_le_bytes())?;
+    writer.write_all(&(length as u32).to_le_bytes())?;
+    writer.write_all(&(json.len() as u32).to_le_bytes())?;
+    writer.write_all(&GLB_JSON.to_le_bytes())?;
+    writer.write_all(&json)?;
+    if !bin.is_empty() {
+        writer.write_all(&(bin.len() as u32).to_le_bytes())?;
+        writer.write_all(&GLB_BIN.to_le_bytes())?;
+        writer.write_all(&bin)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_voxel_exports() {
+        let data = greedy_mesh(|local| match local == IVec3::ZERO {
+            true => AutomataState::new(3, 0),
+            false => AutomataState::EMPTY,
+        });
+        let chunks = [(IVec3::ZERO, data)];
+        let palette = ChunkMeshPalette::default();
+
+        let mut obj = Vec::new();
+        write_obj(&mut obj, &chunks, &palette).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        assert_eq!(
+            obj.lines().filter(|line| line.starts_with("v ")).count(),
+            24
+        );
+        assert_eq!(
+            obj.lines().filter(|line| line.starts_with("f ")).count(),
+            12
+        );
+        assert!(obj.contains("//24") && !obj.contains("//25"));
+
+        let mut glb = Vec::new();
+        write_glb(&mut glb, &chunks, &palette).unwrap();
+        assert_eq!(glb.len() % 4, 0);
+        assert_eq!(&glb[0..4], b"glTF");
+        let length = u32::from_le_bytes(glb[8..12].try_into().unwrap());
+        assert_eq!(length as usize, glb.len());
+        let json_length = u32::from_le_bytes(glb[12..16].try_into().unwrap()) as usize;
+        let json = std::str::from_utf8(&glb[20..20 + json_length]).unwrap();
+        assert!(json.contains(r#""count":24"#) && json.contains(r#""count":36"#));
+        // 24 positions, normals and colors plus 36 indices.
+        let bin_length = u32::from_le_bytes(glb[20 + json_length..][..4].try_into().unwrap());
+        assert_eq!(bin_length, 24 * (12 + 12 + 16) + 36 * 4);
+    }
+}
@@ -0,0 +1,154 @@
+use crate::{ChunkCells, ChunkKey};
+use bevy::prelude::*;
+
+/// How a chunk entered the world, inserted on the chunk by whatever spawned it. Chunks spawned
+/// without one are reported as [`ChunkSource::Spawned`].
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ChunkSource {
+    /// Filled by the streaming generator.
+    Generated,
+    /// Brought back by streaming from the [`HibernatedChunks`](crate::HibernatedChunks).
+    Restored,
+    /// Read from a save or imported from a file.
+    Loaded,
+    /// Spawned directly by the app.
+    #[default]
+    Spawned,
+}
+
+/// Sent once for every new chunk, right after the [`ChunkHookAppExt::on_chunk_ready`] hooks ran
+/// for it. Only sent once a hook is registered.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkReady {
+    pub entity: Entity,
+    pub coords: IVec3,
+    pub source: ChunkSource,
+}
+
+/// Callback run for every new chunk, with its cells as they were generated or loaded.
+pub type ChunkHook = Box<dyn Fn(&ChunkReady, &ChunkCells, &mut Commands) + Send + Sync>;
+
+#[derive(Resource, Default)]
+struct ChunkHooks(Vec<ChunkHook>);
+
+/// Registers [`ChunkHook`]s on an [`App`].
+pub trait ChunkHookAppExt {
+    /// Runs `hook` for every chunk spawned from now on, in the `PreUpdate` following its spawn,
+    /// so gameplay code can spawn the entities tied to the generated content. Hooks run in
+    /// registration order, and before the first step of streamed chunks.
+    fn on_chunk_ready(
+        &mut self,
+        hook: impl Fn(&ChunkReady, &ChunkCells, &mut Commands) + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+
+impl ChunkHookAppExt for App {
+    fn on_chunk_ready(
+        &mut self,
+        hook: impl Fn(&ChunkReady, &ChunkCells, &mut Commands) + Send + Sync + 'static,
+    ) -> &mut Self {
+        if !self.world.contains_resource::<ChunkHooks>() {
+            self.init_resource::<ChunkHooks>()
+                .add_event::<ChunkReady>()
+                .add_systems(PreUpdate, run_chunk_hooks);
+        }
+        self.world
+            .resource_mut::<ChunkHooks>()
+            .0
+            .push(Box::new(hook));
+        self
+    }
+}
+
+fn run_chunk_hooks(
+    mut commands: Commands,
+    hooks: Res<ChunkHooks>,
+    mut ready: EventWriter<ChunkReady>,
+    chunks: Query<(Entity, &ChunkKey, &ChunkCells, Option<&ChunkSource>), Added<ChunkKey>>,
+) {
+    for (entity, key, cells, source) in chunks.iter() {
+        let event = ChunkReady {
+            entity,
+            coords: key.coords,
+            source: source.copied().unwrap_or_default(),
+        };
+        for hook in &hooks.0 {
+            hook(&event, cells, &mut commands);
+        }
+        ready.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AutomataState, ChunkBundle};
+
+    #[derive(Component, Debug, PartialEq, Eq)]
+    struct Tied {
+        coords: IVec3,
+        hook: u8,
+    }
+
+    #[test]
+    fn hooks_run_once_per_new_chunk_in_order() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        for hook in [1, 2] {
+            app.on_chunk_ready(move |ready, cells, commands| {
+                if cells.get(IVec3::ZERO).is_alive() {
+                    let coords = ready.coords;
+                    commands.spawn(Tied { coords, hook });
+                }
+            });
+        }
+        let loaded = app
+            .world
+            .spawn((
+                ChunkBundle::from_generator(IVec3::X, |_| AutomataState::new(1, 0)),
+                ChunkSource::Loaded,
+            ))
+            .id();
+        let spawned = app.world.spawn(ChunkBundle::new(IVec3::Y)).id();
+        app.update();
+
+        let ready = app.world.resource::<Events<ChunkReady>>();
+        let mut ready: Vec<_> = ready.iter_current_update_events().copied().collect();
+        ready.sort_by_key(|ready| ready.coords.to_array());
+        assert_eq!(
+            ready,
+            [
+                ChunkReady {
+                    entity: spawned,
+                    coords: IVec3::Y,
+                    source: ChunkSource::Spawned,
+                },
+                ChunkReady {
+                    entity: loaded,
+                    coords: IVec3::X,
+                    source: ChunkSource::Loaded,
+                },
+            ]
+        );
+        let mut tied = app.world.query::<&Tied>();
+        let tied: Vec<_> = tied.iter(&app.world).collect();
+        assert_eq!(
+            tied,
+            [
+                &Tied {
+                    coords: IVec3::X,
+                    hook: 1
+                },
+                &Tied {
+                    coords: IVec3::X,
+                    hook: 2
+                },
+            ]
+        );
+
+        app.update();
+        let ready = app.world.resource::<Events<ChunkReady>>();
+        assert_eq!(ready.iter_current_update_events().count(), 0);
+        assert_eq!(app.world.query::<&Tied>().iter(&app.world).count(), 2);
+    }
+}
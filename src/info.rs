@@ -0,0 +1,220 @@
+#[cfg(feature = "render")]
+use crate::GpuAutomata;
+use crate::{
+    AutomataRule, AutomataStepper, AuxRule, BoxedRule, BufferPool, ChunkCells, DeterministicCore,
+    PassControl, SimulationBackend, SimulationClock, SimulationPasses, CHUNK_EDGE,
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use std::fmt;
+
+/// Reads the configuration the engine runs with, see [`VoxelEngineInfo::report`].
+#[derive(SystemParam)]
+pub struct VoxelEngineInfo<'w, 's> {
+    rule: Option<Res<'w, AutomataRule>>,
+    boxed_rule: Option<Res<'w, BoxedRule>>,
+    aux_rule: Option<Res<'w, AuxRule>>,
+    stepper: Option<Res<'w, AutomataStepper>>,
+    backend: Option<Res<'w, SimulationBackend>>,
+    #[cfg(feature = "render")]
+    gpu: Option<Res<'w, GpuAutomata>>,
+    passes: Option<Res<'w, SimulationPasses>>,
+    pass_control: Option<Res<'w, PassControl>>,
+    deterministic: Option<Res<'w, DeterministicCore>>,
+    clock: Option<Res<'w, SimulationClock>>,
+    pool: Option<Res<'w, BufferPool>>,
+    chunks: Query<'w, 's, &'static ChunkCells>,
+}
+
+impl<'w, 's> VoxelEngineInfo<'w, 's> {
+    /// Snapshot of the active configuration, for bug reports, telemetry or checking that
+    /// networked peers simulate the same way with [`EngineReport::mismatches`].
+    pub fn report(&self) -> EngineReport {
+        let rule = match (&self.boxed_rule, &self.rule) {
+            (Some(boxed), _) => boxed.0.name(),
+            (None, Some(rule)) => rule.to_string(),
+            (None, None) => "none".to_string(),
+        };
+        let passes = self
+            .passes
+            .as_ref()
+            .and_then(|passes| passes.build_schedule().ok())
+            .map(|schedule| schedule.stages.concat())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|pass| {
+                self.pass_control
+                    .as_ref()
+                    .map_or(true, |control| control.is_enabled(pass))
+            });
+        let determinism = match self.deterministic.as_deref() {
+            Some(core) if core.enabled => DeterminismLevel::Lockstep {
+                steps_per_second: core.steps_per_second,
+            },
+            _ => DeterminismLevel::PerStep,
+        };
+
+        #[cfg(feature = "render")]
+        let gpu_available = self.gpu.is_some();
+        #[cfg(not(feature = "render"))]
+        let gpu_available = false;
+
+        EngineReport {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            chunk_edge: CHUNK_EDGE,
+            features: enabled_features(),
+            backend: format!("{:?}", self.backend.as_deref().copied().unwrap_or_default()),
+            gpu_available,
+            stepper: format!("{:?}", self.stepper.as_deref().copied().unwrap_or_default()),
+            rule,
+            aux_rule: format!(
+                "{:?}",
+                self.aux_rule.as_deref().copied().unwrap_or_default()
+            ),
+            passes: passes.map(str::to_string).collect(),
+            determinism,
+            step: self.clock.as_ref().map_or(0, |clock| clock.step),
+            chunks: self.chunks.iter().len(),
+            cell_bytes: self
+                .chunks
+                .iter()
+                .map(|cells| cells.storage().heap_size())
+                .sum(),
+            pooled_buffers: self.pool.as_ref().map_or(0, |pool| pool.stats().pooled),
+        }
+    }
+}
+
+/// How reproducible the steps of the simulation are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeterminismLevel {
+    /// The [`DeterministicCore`] derives the steps from integer time, peers fed the same
+    /// inputs run the same steps.
+    Lockstep { steps_per_second: u32 },
+    /// Every step gives the same result from the same cells, but how many steps run depends
+    /// on the frame times.
+    PerStep,
+}
+
+/// Configuration of the engine returned by [`VoxelEngineInfo::report`]. Serializable with the
+/// `serde` feature, and printed as one `key: value` line per field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EngineReport {
+    pub version: String,
+    pub chunk_edge: i32,
+    /// Cargo features the engine was built with.
+    pub features: Vec<String>,
+    pub backend: String,
+    /// Whether the GPU backend could be set up.
+    pub gpu_available: bool,
+    pub stepper: String,
+    /// The [`AutomataRule`] in rule notation, or the
+    /// [`AutomataRuleSet::name`](crate::AutomataRuleSet::name) of the [`BoxedRule`] replacing it.
+    pub rule: String,
+    pub aux_rule: String,
+    /// Enabled simulation passes in execution order.
+    pub passes: Vec<String>,
+    pub determinism: DeterminismLevel,
+    pub step: u64,
+    pub chunks: usize,
+    /// Heap memory held by the cells of the loaded chunks.
+    pub cell_bytes: usize,
+    pub pooled_buffers: usize,
+}
+
+impl EngineReport {
+    /// Names of the fields that differ from `other` and would make the two engines step the
+    /// same cells differently. Empty when they are compatible.
+    pub fn mismatches(&self, other: &EngineReport) -> Vec<&'static str> {
+        let checks = [
+            ("version", self.version == other.version),
+            ("chunk_edge", self.chunk_edge == other.chunk_edge),
+            ("stepper", self.stepper == other.stepper),
+            ("rule", self.rule == other.rule),
+            ("aux_rule", self.aux_rule == other.aux_rule),
+            ("passes", self.passes == other.passes),
+            ("determinism", self.determinism == other.determinism),
+        ];
+        checks
+            .into_iter()
+            .filter(|(_, equal)| !equal)
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+impl fmt::Display for EngineReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "version: {}", self.version)?;
+        writeln!(f, "chunk_edge: {}", self.chunk_edge)?;
+        writeln!(f, "features: {}", self.features.join(", "))?;
+        writeln!(f, "backend: {}", self.backend)?;
+        writeln!(f, "gpu_available: {}", self.gpu_available)?;
+        writeln!(f, "stepper: {}", self.stepper)?;
+        writeln!(f, "rule: {}", self.rule)?;
+        writeln!(f, "aux_rule: {}", self.aux_rule)?;
+        writeln!(f, "passes: {}", self.passes.join(", "))?;
+        writeln!(f, "determinism: {:?}", self.determinism)?;
+        writeln!(f, "step: {}", self.step)?;
+        writeln!(f, "chunks: {}", self.chunks)?;
+        writeln!(f, "cell_bytes: {}", self.cell_bytes)?;
+        write!(f, "pooled_buffers: {}", self.pooled_buffers)
+    }
+}
+
+fn enabled_features() -> Vec<String> {
+    let features = [
+        ("render", cfg!(feature = "render")),
+        ("parallel", cfg!(feature = "parallel")),
+        ("dot_vox", cfg!(feature = "dot_vox")),
+        ("egui", cfg!(feature = "egui")),
+        ("debug_controls", cfg!(feature = "debug_controls")),
+        ("simd", cfg!(feature = "simd")),
+        ("voxel_history", cfg!(feature = "voxel_history")),
+        ("serde", cfg!(feature = "serde")),
+        ("ron", cfg!(feature = "ron")),
+        ("rapier", cfg!(feature = "rapier")),
+        ("debug_draw", cfg!(feature = "debug_draw")),
+    ];
+    features
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AutomataState, CellularAutomataPlugin};
+    use bevy::ecs::system::SystemState;
+
+    fn report(app: &mut App) -> EngineReport {
+        SystemState::<VoxelEngineInfo>::new(&mut app.world)
+            .get(&app.world)
+            .report()
+    }
+
+    #[test]
+    fn boxed_rules_are_told_apart_by_name() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(CellularAutomataPlugin);
+        let plain = report(&mut app);
+        assert_eq!(plain.rule, AutomataRule::default().to_string());
+
+        app.insert_resource(BoxedRule::named("still", |ctx: &crate::CellContext| {
+            ctx.current
+        }));
+        let still = report(&mut app);
+        app.insert_resource(BoxedRule::named("empty", |_: &crate::CellContext| {
+            AutomataState::EMPTY
+        }));
+        let empty = report(&mut app);
+
+        assert_eq!(still.rule, "still");
+        assert_eq!(still.mismatches(&empty), vec!["rule"]);
+        assert!(still.mismatches(&still.clone()).is_empty());
+    }
+}
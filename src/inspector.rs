@@ -0,0 +1,97 @@
+use crate::{AutomataRule, ChunkCells, SimulationBudget, SimulationControl, SimulationSpeed};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+/// Neighbor counts offered by the rule editor, a cell has at most 26 neighbors.
+const MAX_NEIGHBORS: u8 = 26;
+
+/// Egui window showing the state of the simulation, for tuning rules while it runs.
+///
+/// Lists the loaded chunks, the live voxel population, the rolling step time against the
+/// [`SimulationBudget`] and the [`SimulationSpeed`] factor. The birth and survive counts of
+/// the [`AutomataRule`] can be toggled and apply from the next step. Adds the [`EguiPlugin`]
+/// unless the app already has it.
+pub struct SimulationInspectorPlugin;
+
+impl Plugin for SimulationInspectorPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.add_systems(Update, inspector_window);
+    }
+}
+
+fn inspector_window(
+    mut contexts: EguiContexts,
+    chunks: Query<&ChunkCells>,
+    budget: Res<SimulationBudget>,
+    speed: Res<SimulationSpeed>,
+    mut control: ResMut<SimulationControl>,
+    mut rule: ResMut<AutomataRule>,
+) {
+    let population: u64 = chunks
+        .iter()
+        .map(|cells| cells.occupancy().count() as u64)
+        .sum();
+
+    egui::Window::new("Simulation").show(contexts.ctx_mut(), |ui| {
+        ui.label(format!("Chunks: {}", chunks.iter().len()));
+        ui.label(format!("Live voxels: {population}"));
+        ui.label(format!(
+            "Step time: {:.2} / {:.2} ms",
+            budget.rolling_ms, budget.target_ms
+        ));
+        ui.label(format!(
+            "Speed factor: {:.2} ({:.2}..{:.2})",
+            speed.factor, speed.min_factor, speed.max_factor
+        ));
+
+        let mut paused = control.paused;
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut paused, "Paused");
+            if ui.button("Step").clicked() {
+                control.step_once();
+            }
+        });
+        if paused != control.paused {
+            control.paused = paused;
+        }
+
+        ui.separator();
+        // Edit copies so the rule is only marked changed when a count is toggled.
+        let mut birth = rule.birth.clone();
+        let mut survive = rule.survive.clone();
+        ui.label("Birth");
+        let birth_changed = count_toggles(ui, "birth", &mut birth);
+        ui.label("Survive");
+        let survive_changed = count_toggles(ui, "survive", &mut survive);
+        if birth_changed {
+            rule.birth = birth;
+        }
+        if survive_changed {
+            rule.survive = survive;
+        }
+    });
+}
+
+/// One checkbox per neighbor count, returns whether `counts` changed.
+fn count_toggles(ui: &mut egui::Ui, id: &str, counts: &mut Vec<u8>) -> bool {
+    let mut changed = false;
+    ui.push_id(id, |ui| {
+        ui.horizontal_wrapped(|ui| {
+            for count in 0..=MAX_NEIGHBORS {
+                let mut enabled = counts.contains(&count);
+                if ui.checkbox(&mut enabled, count.to_string()).changed() {
+                    counts.retain(|other| *other != count);
+                    if enabled {
+                        counts.push(count);
+                        counts.sort_unstable();
+                    }
+                    changed = true;
+                }
+            }
+        });
+    });
+    changed
+}
@@ -1,24 +1,144 @@
-use bevy::{
-    prelude::*,
-    render::{camera::CameraRenderGraph, primitives::Frustum, view::VisibleEntities},
+use bevy::prelude::*;
+#[cfg(feature = "render")]
+use bevy::render::{camera::CameraRenderGraph, primitives::Frustum, view::VisibleEntities};
+pub use bootstrap::{
+    VoxelWorldLoader, WorldBootstrapConfig, WorldBootstrapPlugin, WorldLoadHandle,
+    WorldLoadProgress, WorldLoadStage, WorldReady,
 };
+#[cfg(feature = "rapier")]
+pub use colliders::VoxelColliderPlugin;
+pub use colliders::{solid_boxes, ChunkColliderShape, VoxelColliderSettings};
+pub use compression::{compression_report, ChunkCompression, CompressionAdvice, CompressionReport};
+#[cfg(feature = "debug_draw")]
+pub use debug_draw::{ChunkDebugActivity, DebugDrawConfig, DebugDrawPlugin};
+pub use distance_field::{
+    signed_distances, ChunkDistanceField, DistanceFieldPlugin, DISTANCE_FIELD_RANGE,
+};
+pub use events::EngineEvent;
+#[cfg(feature = "render")]
+pub use export::{export_mesh, MeshExportFormat};
+pub use hooks::{ChunkHook, ChunkHookAppExt, ChunkReady, ChunkSource};
+pub use info::{DeterminismLevel, EngineReport, VoxelEngineInfo};
+#[cfg(feature = "egui")]
+pub use inspector::SimulationInspectorPlugin;
+#[cfg(feature = "render")]
+pub use meshing::{
+    greedy_mesh, greedy_mesh_with_activity, ChunkLayerMesh, ChunkMeshData, ChunkMeshMaterial,
+    ChunkMeshPalette, ChunkMeshPlugin, ChunkMeshSettings, ChunkMeshed, MaterialRenderLayers,
+    ATTRIBUTE_VOXEL_ACTIVITY,
+};
+#[cfg(feature = "render")]
+pub use palette::{PaletteChanged, VoxelPalette};
+pub use persistence::{
+    decode_rle, encode_rle, load_world, read_saved_chunks, save_world, CancelPersistence,
+    LoadWorld, PersistenceOperation, PersistencePlugin, PersistenceProgress, PersistenceStatus,
+    SaveWorld, WorldSaveSettings,
+};
+#[cfg(feature = "render")]
 use physics::PhysicsPlugin;
+#[cfg(feature = "render")]
 pub use physics::VOXELS_PER_METER;
+#[cfg(feature = "render")]
+pub use residency::{
+    chunk_aabb, ChunkMeshEvicted, ChunkNeedsMesh, ChunkVisibility, MeshResidency,
+    MeshResidencyPlugin,
+};
 pub use simulation::{
-    AutomataRule, AutomataState, CellularAutomataPlugin, ChunkBundle, ChunkCells, ChunkCellsNext,
-    ChunkIndex, ChunkKey, SimulationBudget, SimulationClock, SimulationSet, SimulationSpeed,
-    CHUNK_EDGE, CHUNK_VOLUME, FIXED_STEP_SECONDS,
+    brick_origin, changed_bricks, copy_chunks, first_mismatch, move_chunks, pass_enabled,
+    raycast_voxels, step_chunk_alone, voxel_to_chunk, AutomataEffect, AutomataEffectExpired,
+    AutomataRule, AutomataRuleSet, AutomataState, AutomataStepper, AuxRule, BackendChanged,
+    BoundaryMode, BoxedRule, BufferPool, BufferPoolStats, BuiltinPattern, CellContext,
+    CellularAutomataPlugin, ChunkActivity, ChunkAux, ChunkBundle, ChunkCells, ChunkCellsLod,
+    ChunkCellsNext, ChunkChanges, ChunkDataError, ChunkEntities, ChunkIndex, ChunkKey, ChunkLod,
+    ChunkSleep, ChunkSleeping, ChunkSnapshots, ChunkStillness, ChunkStorage, ChunkTemperature,
+    ChunkTracked, ChunkUpdated, ConsistencyCheck, ConsistencyMismatch, Debris, DebrisSpawned,
+    DeterministicCore, Divergence, DivergenceFinder, EditBudget, EffectExpiry, Endianness,
+    FluidRule, GranularRule, InterpolatedVoxels, LockedRegion, MargolusRule, MaterialClass,
+    MaterialCondition, MaterialParseError, MaterialProperties, MaterialRegistry,
+    MaterialRegistryAppExt, MaterialRegistryPlugin, MaterialRule, MaterialTable, MaterialTracker,
+    NeighborCounts, NeighborSampling, Neighborhood, NotableVoxel, NotableVoxelDestroyed,
+    OccupancyMask, PaletteCells, PassChannel, PassControl, PassGraphError, PassSchedule,
+    PatternParseError, PendingRule, RegionLockConflict, RegionLockId, RegionLocks, RegionRecorded,
+    RegionRecorder, RegionRecording, RegionReplay, ReplayFinished, RuleChanged, RuleDriver,
+    RuleKeyframe, RuleParseError, RulePreset, RuleTimeline, SeedPattern, SetPassEnabled,
+    SimulationAnchor, SimulationBackend, SimulationBudget, SimulationClock, SimulationControl,
+    SimulationPass, SimulationPassAppExt, SimulationPassSet, SimulationPasses, SimulationProfile,
+    SimulationRate, SimulationSchedule, SimulationSet, SimulationSpeed, SimulationStats,
+    SimulationTimings, SortedChunks, SplitEditFinished, Stamp, StampLoader, StasisBounds,
+    StasisEntered, StasisLeft, StasisVolume, StatisticsExport, StatisticsFormat, StepStatistics,
+    TerraformBrush, ThermalPlugin, ThermalSettings, ThrottleTiers, TooManyMaterials,
+    VoxelChangeEvents, VoxelChanged, VoxelCommands, VoxelHit, VoxelOccupancy, VoxelRaycast,
+    VoxelWorld, VoxelWorldTransform, AUX_PASS, BRICKS_PER_AXIS, BRICK_EDGE, CHUNK_EDGE,
+    CHUNK_VOLUME, FIXED_STEP_SECONDS, LIFE_PASS, LOD_EDGE, MAX_PALETTE_LEN, MAX_PATTERN_CELLS,
+    MAX_STATES, MAX_TRACKED_MATERIALS, THERMAL_PASS,
 };
+#[cfg(feature = "ron")]
+pub use simulation::{
+    ActiveSimulationConfig, BoundsEdge, MaterialConfig, NeighborhoodConfig, SeedRegion,
+    SimulationConfig, SimulationConfigError, SimulationConfigPlugin, WorldBounds,
+};
+#[cfg(feature = "render")]
+pub use simulation::{GpuAutomata, GpuAutomataPlugin};
+#[cfg(feature = "voxel_history")]
+pub use simulation::{TransitionCause, VoxelHistory, VoxelTransition};
+pub use streaming::{
+    ChunkGenerator, ChunkStreaming, ChunkStreamingPlugin, ChunkUnloadMode, ContextChunkGenerator,
+    GenerationContext, HibernatedChunks, PendingChunk,
+};
+pub use validation::{ChunkInvariant, WorldValidation, WorldValidationPlugin};
+#[cfg(feature = "dot_vox")]
+pub use vox::{load_vox_into_world, VoxChunks, VoxLoadError};
+#[cfg(feature = "render")]
 use voxel_pipeline::RenderPlugin;
+#[cfg(feature = "render")]
 pub use voxel_pipeline::{
-    trace::TraceSettings, voxelization::VoxelizationMaterial,
-    voxelization::VoxelizationMaterialType, RenderGraphSettings,
+    atlas::{AtlasChunk, ChunkGpuAtlas, ChunkGpuAtlasPlugin, ChunkGpuAtlasSource},
+    aux_textures::{AuxChannel, AuxFormat, AuxTextureLayout, AuxTextures},
+    raymarch::ChunkRaymarchPlugin,
+    render_chunks::{RenderChunk, RenderChunkPlugin},
+    trace::TraceSettings,
+    voxelization::VoxelizationMaterial,
+    voxelization::VoxelizationMaterialType,
+    RenderGraphSettings,
 };
+pub use world_diff::{
+    diff_save_with_world, diff_saves, world_chunks, ChunkDiff, ChunkDiffKind, VoxelDiff, WorldDiff,
+};
+pub use worldgen::{NoiseTerrain, TerrainGenerator, WorldGen, WorldGenPlugin};
 
+mod bootstrap;
+mod colliders;
+mod compression;
+#[cfg(feature = "debug_draw")]
+mod debug_draw;
+mod distance_field;
+mod events;
+#[cfg(feature = "render")]
+mod export;
+mod hooks;
+mod info;
+#[cfg(feature = "egui")]
+mod inspector;
+#[cfg(feature = "render")]
 mod load;
+#[cfg(feature = "render")]
+mod meshing;
+#[cfg(feature = "render")]
+mod palette;
+mod persistence;
+#[cfg(feature = "render")]
 mod physics;
+#[cfg(feature = "render")]
+mod residency;
 mod simulation;
+mod streaming;
+mod validation;
+#[cfg(feature = "dot_vox")]
+mod vox;
+#[cfg(feature = "render")]
 mod voxel_pipeline;
+mod world_diff;
+mod worldgen;
 
 #[derive(Component)]
 pub struct Particle {
@@ -113,6 +233,7 @@ pub struct BoxCollider {
     pub half_size: IVec3,
 }
 
+#[cfg(feature = "render")]
 #[derive(Bundle)]
 pub struct VoxelCameraBundle {
     pub camera: Camera,
@@ -126,6 +247,7 @@ pub struct VoxelCameraBundle {
     pub trace_settings: TraceSettings,
 }
 
+#[cfg(feature = "render")]
 impl Default for VoxelCameraBundle {
     fn default() -> Self {
         Self {
@@ -145,6 +267,7 @@ impl Default for VoxelCameraBundle {
     }
 }
 
+#[cfg(feature = "render")]
 #[derive(Bundle, Default)]
 pub struct VoxelizationBundle {
     pub mesh_handle: Handle<Mesh>,
@@ -160,9 +283,16 @@ pub struct BevyVoxelEnginePlugin;
 
 impl Plugin for BevyVoxelEnginePlugin {
     fn build(&self, app: &mut App) {
+        app.add_plugins(CellularAutomataPlugin)
+            .add_plugins(MaterialRegistryPlugin)
+            .init_asset::<Stamp>()
+            .register_asset_loader(StampLoader);
+
+        #[cfg(feature = "render")]
         app.insert_resource(Msaa::Off)
             .add_plugins(PhysicsPlugin)
-            .add_plugins(CellularAutomataPlugin)
+            .add_plugins(GpuAutomataPlugin)
+            .add_plugins(MeshResidencyPlugin)
             .add_plugins(RenderPlugin);
     }
 }
@@ -51,6 +51,7 @@ impl GH {
         Self::get_buffer_size_from_levels(&self.levels)
     }
 
+    #[cfg(feature = "dot_vox")]
     pub fn from_vox(file: &[u8]) -> Result<GH, String> {
         let vox = dot_vox::load_bytes(file)?;
         let size = vox.models[0].size;
@@ -0,0 +1,780 @@
+use crate::{
+    brick_origin, voxel_to_chunk, AutomataState, ChunkActivity, ChunkCells, ChunkChanges, ChunkKey,
+    ChunkLod, ChunkMeshEvicted, ChunkNeedsMesh, PaletteChanged, SimulationSet, VoxelWorldTransform,
+    BRICKS_PER_AXIS, BRICK_EDGE, CHUNK_EDGE,
+};
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, MeshVertexAttribute},
+        render_resource::{PrimitiveTopology, VertexFormat},
+        view::RenderLayers,
+    },
+    utils::{HashMap, HashSet},
+};
+
+/// Step the live cell behind a face last changed, see [`ChunkActivity`]. Only present on chunk
+/// meshes when [`ChunkMeshSettings::activity`] is on, shaders compare it against the current
+/// step to animate recently active surfaces.
+pub const ATTRIBUTE_VOXEL_ACTIVITY: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_VoxelActivity", 988_540_917, VertexFormat::Uint32);
+
+/// Builds a greedy-meshed [`Mesh`] for every chunk so the cells can be drawn with regular PBR
+/// rendering.
+///
+/// Meshes are built in chunk local voxel space and placed with the [`VoxelWorldTransform`].
+/// A chunk is remeshed when a step changed its cells or the border of a neighbor, when one of its
+/// materials sent a [`PaletteChanged`], when
+/// [`MeshResidencyPlugin`] asks for it through [`ChunkNeedsMesh`], or when its [`ChunkLod`]
+/// starts asking for a mesh again. Faces between two chunks are only emitted by the chunk
+/// owning the solid cell, and never between two solid cells.
+///
+/// Faces of materials given other [`MaterialRenderLayers`] are split into [`ChunkLayerMesh`]
+/// children of the chunk, one per set of layers.
+///
+/// Dense worlds can leave this plugin out and draw chunks with the [`ChunkRaymarchPlugin`]
+/// instead, skipping meshing entirely.
+///
+/// [`MeshResidencyPlugin`]: crate::MeshResidencyPlugin
+/// [`ChunkRaymarchPlugin`]: crate::ChunkRaymarchPlugin
+pub struct ChunkMeshPlugin;
+
+impl Plugin for ChunkMeshPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkMeshPalette>()
+            .init_resource::<ChunkMeshSettings>()
+            .init_resource::<MaterialRenderLayers>()
+            .add_event::<PaletteChanged>()
+            .add_systems(
+                PostUpdate,
+                (drop_unwanted_meshes, mesh_chunks)
+                    .chain()
+                    .after(SimulationSet::Run),
+            )
+            .add_systems(
+                PostUpdate,
+                track_chunk_activity
+                    .run_if(activity_enabled)
+                    .before(SimulationSet::Run),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        app.init_resource::<ChunkMeshMaterial>();
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct ChunkMeshSettings {
+    /// Adds [`ATTRIBUTE_VOXEL_ACTIVITY`] to the meshes, tracking a [`ChunkActivity`] on every
+    /// chunk.
+    pub activity: bool,
+}
+
+/// Vertex colors and shading given to each material.
+#[derive(Resource, Clone)]
+pub struct ChunkMeshPalette {
+    pub colors: [Color; 256],
+    /// Materials shaded with smooth normals, see [`ChunkMeshData::smooth_normals`]. The others
+    /// keep hard voxel normals.
+    pub smooth: [bool; 256],
+}
+
+impl Default for ChunkMeshPalette {
+    fn default() -> Self {
+        let mut colors = [Color::NONE; 256];
+        for (material, color) in colors.iter_mut().enumerate().skip(1) {
+            // Golden angle hue steps keep neighboring materials distinct.
+            *color = Color::hsl((material as f32 * 137.5) % 360.0, 0.6, 0.55);
+        }
+        Self {
+            colors,
+            smooth: [false; 256],
+        }
+    }
+}
+
+/// Render layers the faces of each material are drawn on, for example to show a blueprint
+/// material only to an overlay camera or hide ores from the main camera.
+///
+/// Materials without an entry stay on the default layer, in the mesh of the chunk itself.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MaterialRenderLayers {
+    layers: HashMap<u8, RenderLayers>,
+}
+
+impl MaterialRenderLayers {
+    pub fn set(&mut self, material: u8, layers: RenderLayers) -> &mut Self {
+        if layers == RenderLayers::default() {
+            self.layers.remove(&material);
+        } else {
+            self.layers.insert(material, layers);
+        }
+        self
+    }
+
+    pub fn get(&self, material: u8) -> RenderLayers {
+        self.layers.get(&material).copied().unwrap_or_default()
+    }
+
+    /// Splits `data` into the faces on the default layer and the faces of every other set of
+    /// layers.
+    pub fn split(
+        &self,
+        data: ChunkMeshData,
+    ) -> (ChunkMeshData, Vec<(RenderLayers, ChunkMeshData)>) {
+        if self.layers.is_empty() {
+            return (data, Vec::new());
+        }
+
+        let mut groups: Vec<RenderLayers> = Vec::new();
+        for &material in &data.materials {
+            let layers = self.get(material);
+            if layers != RenderLayers::default() && !groups.contains(&layers) {
+                groups.push(layers);
+            }
+        }
+        if groups.is_empty() {
+            return (data, Vec::new());
+        }
+
+        let layered = groups
+            .into_iter()
+            .map(|layers| {
+                let faces = data.with_materials(|material| self.get(material) == layers);
+                (layers, faces)
+            })
+            .collect();
+        let default = data.with_materials(|material| self.get(material) == RenderLayers::default());
+        (default, layered)
+    }
+}
+
+/// Child of a chunk holding the faces drawn on a non-default set of [`MaterialRenderLayers`].
+#[derive(Component, Debug, Default)]
+pub struct ChunkLayerMesh;
+
+/// Material shared by every chunk mesh, colored through the vertex colors.
+#[derive(Resource, Clone)]
+pub struct ChunkMeshMaterial(pub Handle<StandardMaterial>);
+
+impl FromWorld for ChunkMeshMaterial {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        Self(materials.add(StandardMaterial {
+            base_color: Color::WHITE,
+            perceptual_roughness: 0.9,
+            ..default()
+        }))
+    }
+}
+
+/// Marks a chunk that went through the mesher, even if it produced no faces.
+#[derive(Component, Debug, Default)]
+pub struct ChunkMeshed;
+
+/// Faces of a chunk merged into quads, in chunk local voxel space.
+#[derive(Debug, Default, Clone)]
+pub struct ChunkMeshData {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub materials: Vec<u8>,
+    /// Per vertex [`ATTRIBUTE_VOXEL_ACTIVITY`] values, empty unless built with
+    /// [`greedy_mesh_with_activity`].
+    pub activity: Vec<u32>,
+    pub indices: Vec<u32>,
+}
+
+impl ChunkMeshData {
+    #[inline]
+    pub fn quad_count(&self) -> usize {
+        self.positions.len() / 4
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn to_mesh(&self, palette: &ChunkMeshPalette) -> Mesh {
+        let colors: Vec<[f32; 4]> = self
+            .materials
+            .iter()
+            .map(|&material| palette.colors[material as usize].as_linear_rgba_f32())
+            .collect();
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions.clone());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals.clone());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        if !self.activity.is_empty() {
+            mesh.insert_attribute(ATTRIBUTE_VOXEL_ACTIVITY, self.activity.clone());
+        }
+        mesh.set_indices(Some(Indices::U32(self.indices.clone())));
+        mesh
+    }
+
+    /// Copy holding only the quads of the materials `keep` accepts.
+    pub fn with_materials(&self, keep: impl Fn(u8) -> bool) -> Self {
+        let mut data = Self::default();
+        for quad in 0..self.quad_count() {
+            let vertices = quad * 4..quad * 4 + 4;
+            if !keep(self.materials[vertices.start]) {
+                continue;
+            }
+
+            let start = data.positions.len() as u32;
+            data.positions
+                .extend_from_slice(&self.positions[vertices.clone()]);
+            data.normals
+                .extend_from_slice(&self.normals[vertices.clone()]);
+            data.materials
+                .extend_from_slice(&self.materials[vertices.clone()]);
+            if !self.activity.is_empty() {
+                data.activity.extend_from_slice(&self.activity[vertices]);
+            }
+            data.indices.extend_from_slice(&[
+                start,
+                start + 1,
+                start + 2,
+                start,
+                start + 2,
+                start + 3,
+            ]);
+        }
+        data
+    }
+
+    /// Copy where the quads of the materials `smooth` accepts are split into single voxel
+    /// faces with normals averaged from the live cells around each vertex, so they shade like
+    /// rolling terrain instead of blocks. `sample` reads the cells as in [`greedy_mesh`].
+    ///
+    /// A vertex normal points away from the live cells among the eight around the vertex,
+    /// falling back to the face normal where they are balanced or would face backwards. Other
+    /// quads are copied unchanged.
+    pub fn smooth_normals<F>(&self, sample: F, smooth: impl Fn(u8) -> bool) -> Self
+    where
+        F: Fn(IVec3) -> AutomataState,
+    {
+        let mut data = Self::default();
+        for quad in 0..self.quad_count() {
+            let first = quad * 4;
+            let material = self.materials[first];
+            let activity = self.activity.get(first).copied();
+            let corners: [Vec3; 4] = std::array::from_fn(|k| Vec3::from(self.positions[first + k]));
+            let normal = Vec3::from(self.normals[first]);
+            if !smooth(material) {
+                data.push_quad(corners, [normal; 4], material, activity);
+                continue;
+            }
+
+            let (a, b) = (corners[1] - corners[0], corners[3] - corners[0]);
+            let (width, height) = (a.length().round() as i32, b.length().round() as i32);
+            let (step_a, step_b) = (a / width as f32, b / height as f32);
+            for s in 0..width {
+                for t in 0..height {
+                    let base = corners[0] + step_a * s as f32 + step_b * t as f32;
+                    let corners = [base, base + step_a, base + step_a + step_b, base + step_b];
+                    let normals = corners.map(|corner| vertex_normal(&sample, corner, normal));
+                    data.push_quad(corners, normals, material, activity);
+                }
+            }
+        }
+        data
+    }
+
+    fn push_quad(
+        &mut self,
+        corners: [Vec3; 4],
+        normals: [Vec3; 4],
+        material: u8,
+        activity: Option<u32>,
+    ) {
+        let start = self.positions.len() as u32;
+        for (corner, normal) in corners.into_iter().zip(normals) {
+            self.positions.push(corner.to_array());
+            self.normals.push(normal.to_array());
+            self.materials.push(material);
+        }
+        if let Some(activity) = activity {
+            self.activity.extend_from_slice(&[activity; 4]);
+        }
+        self.indices
+            .extend_from_slice(&[start, start + 1, start + 2, start, start + 2, start + 3]);
+    }
+}
+
+/// Normal at a vertex of a smooth face, pointing away from the live cells touching it.
+fn vertex_normal(sample: &impl Fn(IVec3) -> AutomataState, corner: Vec3, face: Vec3) -> Vec3 {
+    let corner_voxel = corner.round().as_ivec3();
+    let mut normal = Vec3::ZERO;
+    for x in -1..1 {
+        for y in -1..1 {
+            for z in -1..1 {
+                let offset = IVec3::new(x, y, z);
+                if sample(corner_voxel + offset).is_alive() {
+                    normal -= offset.as_vec3() + 0.5;
+                }
+            }
+        }
+    }
+    match normal.normalize_or_zero() {
+        normal if normal.dot(face) > 0.0 => normal,
+        _ => face,
+    }
+}
+
+/// Greedy meshes a chunk, `sample` returning the cells at local positions (including one cell
+/// outside the chunk on every side).
+///
+/// Faces are emitted between a live cell and an empty one, and coplanar faces of the same
+/// material and direction are merged into rectangles.
+pub fn greedy_mesh<F>(sample: F) -> ChunkMeshData
+where
+    F: Fn(IVec3) -> AutomataState,
+{
+    mesh_faces(&sample, None)
+}
+
+/// Same as [`greedy_mesh`], also filling [`ChunkMeshData::activity`] with the value `activity`
+/// returns for the live cell behind each face. Faces only merge when their activity matches.
+pub fn greedy_mesh_with_activity<F, G>(sample: F, activity: G) -> ChunkMeshData
+where
+    F: Fn(IVec3) -> AutomataState,
+    G: Fn(IVec3) -> u32,
+{
+    mesh_faces(&sample, Some(&activity))
+}
+
+fn mesh_faces(
+    sample: &dyn Fn(IVec3) -> AutomataState,
+    activity: Option<&dyn Fn(IVec3) -> u32>,
+) -> ChunkMeshData {
+    let edge = CHUNK_EDGE as usize;
+    let mut data = ChunkMeshData::default();
+    // Positive entries face along the axis, negative ones against it.
+    let mut mask = vec![0i16; edge * edge];
+    let mut activity_mask = vec![0u32; edge * edge];
+
+    for d in 0..3 {
+        let u = (d + 1) % 3;
+        let v = (d + 2) % 3;
+
+        for slice in 0..=CHUNK_EDGE {
+            for j in 0..CHUNK_EDGE {
+                for i in 0..CHUNK_EDGE {
+                    let mut position = IVec3::ZERO;
+                    position[d] = slice;
+                    position[u] = i;
+                    position[v] = j;
+                    let mut behind = position;
+                    behind[d] -= 1;
+
+                    let back = sample(behind);
+                    let front = sample(position);
+                    let index = i as usize + j as usize * edge;
+                    // Only the chunk owning the live cell emits the face.
+                    let (face, owner) = match (back.is_alive(), front.is_alive()) {
+                        (true, false) if slice > 0 => (back.material as i16, behind),
+                        (false, true) if slice < CHUNK_EDGE => (-(front.material as i16), position),
+                        _ => (0, position),
+                    };
+                    mask[index] = face;
+                    match activity {
+                        Some(activity) if face != 0 => activity_mask[index] = activity(owner),
+                        _ => {}
+                    }
+                }
+            }
+
+            for j in 0..edge {
+                let mut i = 0;
+                while i < edge {
+                    let face = mask[i + j * edge];
+                    if face == 0 {
+                        i += 1;
+                        continue;
+                    }
+
+                    let key = (face, activity_mask[i + j * edge]);
+                    let matches = |index: usize| (mask[index], activity_mask[index]) == key;
+                    let mut width = 1;
+                    while i + width < edge && matches(i + width + j * edge) {
+                        width += 1;
+                    }
+
+                    let mut height = 1;
+                    'grow: while j + height < edge {
+                        for k in 0..width {
+                            if !matches(i + k + (j + height) * edge) {
+                                break 'grow;
+                            }
+                        }
+                        height += 1;
+                    }
+
+                    let mut base = Vec3::ZERO;
+                    base[d] = slice as f32;
+                    base[u] = i as f32;
+                    base[v] = j as f32;
+                    let mut du = Vec3::ZERO;
+                    du[u] = width as f32;
+                    let mut dv = Vec3::ZERO;
+                    dv[v] = height as f32;
+                    let mut normal = Vec3::ZERO;
+                    normal[d] = face.signum() as f32;
+
+                    let corners = if face > 0 {
+                        [base, base + du, base + du + dv, base + dv]
+                    } else {
+                        [base, base + dv, base + du + dv, base + du]
+                    };
+                    data.push_quad(
+                        corners,
+                        [normal; 4],
+                        face.unsigned_abs() as u8,
+                        activity.map(|_| key.1),
+                    );
+
+                    for row in 0..height {
+                        for k in 0..width {
+                            mask[i + k + (j + row) * edge] = 0;
+                        }
+                    }
+                    i += width;
+                }
+            }
+        }
+    }
+
+    data
+}
+
+pub(crate) fn in_chunk(local: IVec3) -> bool {
+    local.cmpge(IVec3::ZERO).all() && local.cmplt(IVec3::splat(CHUNK_EDGE)).all()
+}
+
+pub(crate) fn chunk_transform(world_transform: &VoxelWorldTransform, coords: IVec3) -> Transform {
+    let mut transform = world_transform.transform();
+    transform.translation = world_transform.voxel_space_to_world((coords * CHUNK_EDGE).as_vec3());
+    transform
+}
+
+#[allow(clippy::type_complexity)]
+fn drop_unwanted_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    chunks: Query<
+        (Entity, &ChunkLod, Option<&Handle<Mesh>>, Option<&Children>),
+        (With<ChunkMeshed>, Changed<ChunkLod>),
+    >,
+    layer_meshes: Query<&Handle<Mesh>, With<ChunkLayerMesh>>,
+) {
+    for (entity, lod, mesh, children) in chunks.iter() {
+        if lod.meshed {
+            continue;
+        }
+
+        if let Some(mesh) = mesh {
+            meshes.remove(mesh.id());
+        }
+        despawn_layer_meshes(&mut commands, &mut meshes, children, &layer_meshes);
+        commands
+            .entity(entity)
+            .remove::<(Handle<Mesh>, ChunkMeshed)>();
+    }
+}
+
+/// Despawns the [`ChunkLayerMesh`] children of a chunk along with their mesh assets.
+pub(crate) fn despawn_layer_meshes(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    children: Option<&Children>,
+    layer_meshes: &Query<&Handle<Mesh>, With<ChunkLayerMesh>>,
+) {
+    for &child in children.into_iter().flatten() {
+        if let Ok(mesh) = layer_meshes.get(child) {
+            meshes.remove(mesh.id());
+            commands.entity(child).despawn_recursive();
+        }
+    }
+}
+
+fn activity_enabled(settings: Res<ChunkMeshSettings>) -> bool {
+    settings.activity
+}
+
+fn track_chunk_activity(
+    mut commands: Commands,
+    chunks: Query<Entity, (With<ChunkKey>, Without<ChunkActivity>)>,
+) {
+    for entity in chunks.iter() {
+        commands.entity(entity).insert(ChunkActivity::default());
+    }
+}
+
+/// Offsets of the neighbors sharing a face with the changed bricks of a chunk.
+fn border_neighbors(bricks: u64) -> impl Iterator<Item = IVec3> {
+    let last = (BRICKS_PER_AXIS - 1) * BRICK_EDGE;
+    let mut offsets = Vec::new();
+    for index in (0..64).filter(|index| bricks & (1 << index) != 0) {
+        let origin = brick_origin(index);
+        for axis in 0..3 {
+            let mut offset = IVec3::ZERO;
+            if origin[axis] == 0 {
+                offset[axis] = -1;
+                offsets.push(offset);
+            }
+            if origin[axis] == last {
+                offset[axis] = 1;
+                offsets.push(offset);
+            }
+        }
+    }
+    offsets.sort_unstable_by_key(|offset| offset.to_array());
+    offsets.dedup();
+    offsets.into_iter()
+}
+
+const FACE_NEIGHBORS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn mesh_chunks(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut spawned: Local<HashMap<Entity, IVec3>>,
+    mut removed: RemovedComponents<ChunkKey>,
+    palette: Res<ChunkMeshPalette>,
+    settings: Res<ChunkMeshSettings>,
+    render_layers: Res<MaterialRenderLayers>,
+    mut palette_changed: EventReader<PaletteChanged>,
+    material: Res<ChunkMeshMaterial>,
+    world_transform: Res<VoxelWorldTransform>,
+    chunks: Query<(
+        Entity,
+        &ChunkKey,
+        &ChunkCells,
+        Ref<ChunkChanges>,
+        Option<&ChunkLod>,
+        Option<&Handle<Mesh>>,
+        Option<&Handle<StandardMaterial>>,
+        Has<ChunkMeshed>,
+        Has<ChunkNeedsMesh>,
+        Has<ChunkMeshEvicted>,
+    )>,
+    spatial: Query<(Has<GlobalTransform>, Option<&Children>)>,
+    layer_meshes: Query<&Handle<Mesh>, With<ChunkLayerMesh>>,
+    activity: Query<&ChunkActivity>,
+) {
+    // Chunks whose border faces may have changed because a neighbor changed.
+    let mut dirty = HashSet::new();
+    for (entity, key, _, changes, ..) in chunks.iter() {
+        if changes.is_added() {
+            spawned.insert(entity, key.coords);
+            dirty.extend(FACE_NEIGHBORS.iter().map(|offset| key.coords + *offset));
+        } else if changes.is_changed() && changes.bricks != 0 {
+            dirty.extend(border_neighbors(changes.bricks).map(|offset| key.coords + offset));
+        }
+    }
+    for entity in removed.read() {
+        if let Some(coords) = spawned.remove(&entity) {
+            dirty.extend(FACE_NEIGHBORS.iter().map(|offset| coords + *offset));
+        }
+    }
+
+    let recolored: Vec<u8> = palette_changed
+        .read()
+        .map(|changed| changed.material)
+        .collect();
+
+    let mut by_coords = None;
+    for (entity, key, cells, changes, lod, mesh, chunk_material, meshed, needs_mesh, evicted) in
+        chunks.iter()
+    {
+        if evicted || !lod.is_none_or(|lod| lod.meshed) {
+            continue;
+        }
+
+        let transform = chunk_transform(&world_transform, key.coords);
+        let changed = changes.is_changed() && changes.bricks != 0;
+        if meshed
+            && !needs_mesh
+            && !changed
+            && !render_layers.is_changed()
+            && !dirty.contains(&key.coords)
+            && !recolored
+                .iter()
+                .any(|&material| cells.contains_material(material))
+        {
+            if world_transform.is_changed() {
+                commands.entity(entity).insert(transform);
+            }
+            continue;
+        }
+
+        // Faces on the chunk border depend on the cells of the neighboring chunks.
+        let by_coords: &HashMap<IVec3, Entity> = by_coords.get_or_insert_with(|| {
+            chunks
+                .iter()
+                .map(|(entity, key, ..)| (key.coords, entity))
+                .collect()
+        });
+        let origin = key.coords * CHUNK_EDGE;
+        let sample = |local: IVec3| {
+            if in_chunk(local) {
+                return cells.get(local);
+            }
+
+            let (neighbor, local) = voxel_to_chunk(origin + local);
+            by_coords
+                .get(&neighbor)
+                .and_then(|entity| chunks.get(*entity).ok())
+                .map_or(AutomataState::EMPTY, |(_, _, cells, ..)| cells.get(local))
+        };
+        let data = match activity.get(entity) {
+            // Faces belong to the chunk of their live cell, an empty chunk has none.
+            _ if cells.occupancy().is_empty() => ChunkMeshData::default(),
+            // The live cell behind a face is always inside the chunk.
+            Ok(activity) if settings.activity => {
+                greedy_mesh_with_activity(sample, |local| activity.get(local))
+            }
+            _ => greedy_mesh(sample),
+        };
+        let smooth = |material: u8| palette.smooth[material as usize];
+        let data = match data.materials.iter().any(|&material| smooth(material)) {
+            true => data.smooth_normals(sample, smooth),
+            false => data,
+        };
+
+        let (data, layered) = render_layers.split(data);
+
+        if let Some(mesh) = mesh {
+            meshes.remove(mesh.id());
+        }
+        let (has_transform, children) = spatial.get(entity).unwrap_or((false, None));
+        despawn_layer_meshes(&mut commands, &mut meshes, children, &layer_meshes);
+
+        let mut entity_commands = commands.entity(entity);
+        entity_commands
+            .remove::<ChunkNeedsMesh>()
+            .insert(ChunkMeshed);
+        if !layered.is_empty() {
+            if !has_transform {
+                entity_commands.insert(SpatialBundle::from_transform(transform));
+            }
+            let material = chunk_material.unwrap_or(&material.0);
+            entity_commands.with_children(|parent| {
+                for (layers, data) in layered {
+                    parent.spawn((
+                        PbrBundle {
+                            mesh: meshes.add(data.to_mesh(&palette)),
+                            material: material.clone(),
+                            ..default()
+                        },
+                        layers,
+                        ChunkLayerMesh,
+                    ));
+                }
+            });
+        }
+        if data.is_empty() {
+            entity_commands.remove::<Handle<Mesh>>();
+            continue;
+        }
+
+        let mesh = data.to_mesh(&palette);
+        if let Some(aabb) = mesh.compute_aabb() {
+            entity_commands.insert(aabb);
+        }
+        if chunk_material.is_some() {
+            entity_commands.insert((meshes.add(mesh), transform));
+        } else {
+            entity_commands.insert(PbrBundle {
+                mesh: meshes.add(mesh),
+                material: material.0.clone(),
+                transform,
+                ..default()
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn greedy_mesh_merges_faces() {
+        let single = greedy_mesh(|local| {
+            if local == IVec3::new(3, 4, 5) {
+                AutomataState::new(1, 0)
+            } else {
+                AutomataState::EMPTY
+            }
+        });
+        assert_eq!(single.quad_count(), 6);
+
+        let solid = greedy_mesh(|local| {
+            if in_chunk(local) {
+                AutomataState::new(2, 0)
+            } else {
+                AutomataState::EMPTY
+            }
+        });
+        assert_eq!(solid.quad_count(), 6);
+        assert!(solid.materials.iter().all(|&material| material == 2));
+    }
+
+    #[test]
+    fn smooth_materials_average_vertex_normals() {
+        let slab = |local: IVec3| match in_chunk(local) && local.y < 2 {
+            true => AutomataState::new(1, 0),
+            false => AutomataState::EMPTY,
+        };
+        let data = greedy_mesh(slab);
+        assert_eq!(data.smooth_normals(slab, |_| false).quad_count(), 6);
+
+        let smooth = data.smooth_normals(slab, |material| material == 1);
+        let edge = CHUNK_EDGE as usize;
+        assert_eq!(smooth.quad_count(), 2 * edge * edge + 4 * edge * 2);
+        let normal_at = |position: [f32; 3]| {
+            let vertex = smooth
+                .positions
+                .iter()
+                .position(|p| *p == position)
+                .unwrap();
+            Vec3::from(smooth.normals[vertex])
+        };
+        assert_eq!(normal_at([16.0, 2.0, 16.0]), Vec3::Y);
+        let corner = normal_at([0.0, 2.0, 0.0]);
+        assert!(corner.y > 0.0 && corner.x < 0.0 && corner.z < 0.0);
+    }
+
+    #[test]
+    fn render_layers_split_faces_by_material() {
+        let data = greedy_mesh(|local| match local {
+            IVec3 { x: 1, y: 1, z: 1 } => AutomataState::new(1, 0),
+            IVec3 { x: 5, y: 5, z: 5 } => AutomataState::new(2, 0),
+            IVec3 { x: 9, y: 9, z: 9 } => AutomataState::new(3, 0),
+            _ => AutomataState::EMPTY,
+        });
+
+        let mut layers = MaterialRenderLayers::default();
+        layers
+            .set(2, RenderLayers::layer(1))
+            .set(3, RenderLayers::layer(1));
+        let (default, layered) = layers.split(data);
+        assert_eq!(default.quad_count(), 6);
+        assert!(default.materials.iter().all(|&material| material == 1));
+        assert_eq!(layered.len(), 1);
+        assert_eq!(layered[0].0, RenderLayers::layer(1));
+        assert_eq!(layered[0].1.quad_count(), 12);
+        assert_eq!(layered[0].1.indices.len(), 12 * 6);
+    }
+}
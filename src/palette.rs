@@ -0,0 +1,76 @@
+use crate::{voxel_pipeline::voxel_world::VoxelUniforms, ChunkMeshPalette};
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+/// Sent when a material of the palette was edited through [`VoxelPalette`].
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaletteChanged {
+    pub material: u8,
+}
+
+/// Edits the colors and shading of materials while the app runs.
+///
+/// Changes reach the GPU palette of the traced world on the next frame, and chunks containing an
+/// edited material are remeshed to update their vertex colors. Emission only applies to the
+/// traced world. Either side is skipped when its plugin is missing.
+#[derive(SystemParam)]
+pub struct VoxelPalette<'w> {
+    mesh_palette: Option<ResMut<'w, ChunkMeshPalette>>,
+    uniforms: Option<ResMut<'w, VoxelUniforms>>,
+    changed: EventWriter<'w, PaletteChanged>,
+}
+
+impl VoxelPalette<'_> {
+    /// Color of `material` on the chunk meshes, or in the traced world (including emission)
+    /// without meshing.
+    pub fn color(&self, material: u8) -> Color {
+        match (&self.mesh_palette, &self.uniforms) {
+            (Some(palette), _) => palette.colors[material as usize],
+            (None, Some(uniforms)) => {
+                let colour = uniforms.pallete[material as usize].colour;
+                Color::rgb_linear(colour.x, colour.y, colour.z)
+            }
+            (None, None) => Color::NONE,
+        }
+    }
+
+    /// Sets the color of `material`. An `emissive` strength above zero makes the traced material
+    /// emit light, scaling its color by `1 + emissive`.
+    pub fn set(&mut self, material: u8, color: Color, emissive: f32) {
+        if let Some(palette) = self.mesh_palette.as_mut() {
+            palette.colors[material as usize] = color;
+        }
+
+        if let Some(uniforms) = self.uniforms.as_mut() {
+            let linear = Vec4::from(color.as_linear_rgba_f32()).truncate();
+            let (scale, emits) = if emissive > 0.0 {
+                (1.0 + emissive, 1.0)
+            } else {
+                (1.0, 0.0)
+            };
+            uniforms.pallete[material as usize].colour = (linear * scale).extend(emits);
+        }
+
+        self.changed.send(PaletteChanged { material });
+    }
+
+    pub fn set_color(&mut self, material: u8, color: Color) {
+        self.set(material, color, 0.0);
+    }
+
+    /// Whether the chunk meshes shade `material` with smooth normals, see
+    /// [`ChunkMeshData::smooth_normals`](crate::ChunkMeshData::smooth_normals).
+    pub fn is_smooth(&self, material: u8) -> bool {
+        self.mesh_palette
+            .as_ref()
+            .is_some_and(|palette| palette.smooth[material as usize])
+    }
+
+    /// Shades `material` with smooth normals on the chunk meshes, or with hard voxel normals.
+    /// The traced world is not affected.
+    pub fn set_smooth(&mut self, material: u8, smooth: bool) {
+        if let Some(palette) = self.mesh_palette.as_mut() {
+            palette.smooth[material as usize] = smooth;
+        }
+        self.changed.send(PaletteChanged { material });
+    }
+}
@@ -0,0 +1,922 @@
+use crate::{
+    AutomataRule, AutomataState, ChunkBundle, ChunkCells, ChunkIndex, ChunkKey, ChunkSource,
+    EngineEvent, MaterialCondition, MaterialRule, Neighborhood, RuleKeyframe, RuleTimeline,
+    SimulationClock, CHUNK_VOLUME, MAX_STATES,
+};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+const REGION_MAGIC: &[u8; 4] = b"BVER";
+const META_MAGIC: &[u8; 4] = b"BVEM";
+/// Bumped whenever the layout changes, version 2 added rule states and neighborhoods, version 3
+/// the keyframes of the rule timeline.
+const VERSION: u16 = 3;
+/// Regions hold 8³ chunks, the low 9 bits of the Morton key.
+const REGION_SHIFT: u32 = 9;
+pub(crate) const META_FILE: &str = "world.meta";
+
+/// Saves and restores the automata world as region files.
+///
+/// Chunks are grouped into regions of 8³ by Morton key, each region file storing its chunks
+/// in Morton order as run-length encoded packed cells. A `world.meta` file next to them holds
+/// the [`AutomataRule`], the keyframes of the [`RuleTimeline`] and the [`SimulationClock`] so a
+/// saved simulation resumes deterministically. The driver of the timeline is code and is not
+/// saved, a loaded world keeps the driver already installed.
+///
+/// [`SaveWorld`] and [`LoadWorld`] run over several frames, reporting
+/// [`PersistenceProgress`] every frame until they complete, fail or are cancelled with
+/// [`CancelPersistence`].
+pub struct PersistencePlugin {
+    pub directory: PathBuf,
+    /// Restore the saved world on startup, if any.
+    pub load_on_startup: bool,
+}
+
+impl Plugin for PersistencePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WorldSaveSettings {
+            directory: self.directory.clone(),
+            chunks_per_frame: 256,
+        })
+        .add_event::<SaveWorld>()
+        .add_event::<LoadWorld>()
+        .add_event::<CancelPersistence>()
+        .add_event::<PersistenceProgress>()
+        .add_event::<EngineEvent>()
+        .add_systems(Last, run_persistence);
+
+        if self.load_on_startup {
+            app.add_systems(PostStartup, load_saved_world);
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct WorldSaveSettings {
+    pub directory: PathBuf,
+    /// Chunks encoded or decoded per frame by [`SaveWorld`] and [`LoadWorld`], at least one.
+    pub chunks_per_frame: usize,
+}
+
+/// Saves the world into [`WorldSaveSettings::directory`], starting at the end of the frame.
+///
+/// The chunks are captured when the save starts, so later changes do not end up in it. The
+/// previous save stays intact until the new one completes.
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct SaveWorld;
+
+/// Replaces the world with the save in [`WorldSaveSettings::directory`], starting at the end of
+/// the frame. The world is only touched once every chunk has been read.
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct LoadWorld;
+
+/// Stops the running save or load, leaving both the save and the world as they were.
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct CancelPersistence;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PersistenceOperation {
+    Save,
+    Load,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PersistenceStatus {
+    Running,
+    Completed,
+    Cancelled,
+    /// The error is reported as an [`EngineEvent`].
+    Failed,
+}
+
+/// Sent every frame while a [`SaveWorld`] or [`LoadWorld`] runs, and once when it ends.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PersistenceProgress {
+    pub operation: PersistenceOperation,
+    pub status: PersistenceStatus,
+    pub chunks_done: usize,
+    pub chunks_total: usize,
+    /// Bytes written or read so far.
+    pub bytes: u64,
+}
+
+impl PersistenceProgress {
+    /// Fraction of the chunks done, in `0.0..=1.0`.
+    pub fn fraction(&self) -> f32 {
+        if self.chunks_total == 0 {
+            1.0
+        } else {
+            self.chunks_done as f32 / self.chunks_total as f32
+        }
+    }
+}
+
+/// Save or load spread over several frames.
+enum PersistenceJob {
+    Save(SaveJob),
+    Load(LoadJob),
+}
+
+impl PersistenceJob {
+    fn progress(&self, status: PersistenceStatus) -> PersistenceProgress {
+        let (operation, chunks_done, chunks_total, bytes) = match self {
+            PersistenceJob::Save(job) => (
+                PersistenceOperation::Save,
+                job.chunks_done,
+                job.chunks_total,
+                job.bytes,
+            ),
+            PersistenceJob::Load(job) => (
+                PersistenceOperation::Load,
+                job.chunks.len(),
+                job.chunks_total,
+                job.bytes,
+            ),
+        };
+        PersistenceProgress {
+            operation,
+            status,
+            chunks_done,
+            chunks_total,
+            bytes,
+        }
+    }
+
+    fn failure(&self, error: io::Error) -> EngineEvent {
+        match self {
+            PersistenceJob::Save(job) => EngineEvent::ChunkSaveFailed {
+                path: job.directory.clone(),
+                reason: error.to_string(),
+            },
+            PersistenceJob::Load(job) => EngineEvent::ChunkLoadFailed {
+                path: job.directory.clone(),
+                reason: error.to_string(),
+            },
+        }
+    }
+}
+
+fn run_persistence(world: &mut World, mut job: Local<Option<PersistenceJob>>) {
+    let cancelled = world
+        .resource_mut::<Events<CancelPersistence>>()
+        .drain()
+        .count()
+        > 0;
+    let save = world.resource_mut::<Events<SaveWorld>>().drain().count() > 0;
+    let load = world.resource_mut::<Events<LoadWorld>>().drain().count() > 0;
+
+    if cancelled {
+        if let Some(mut cancelled_job) = job.take() {
+            let progress = cancelled_job.progress(PersistenceStatus::Cancelled);
+            if let PersistenceJob::Save(save_job) = &mut cancelled_job {
+                save_job.discard();
+            }
+            world.send_event(progress);
+        }
+    }
+
+    if job.is_none() && (save || load) {
+        let directory = world.resource::<WorldSaveSettings>().directory.clone();
+        let started = if save {
+            SaveJob::new(world, &directory).map(PersistenceJob::Save)
+        } else {
+            LoadJob::new(&directory).map(PersistenceJob::Load)
+        };
+        match started {
+            Ok(started) => *job = Some(started),
+            Err(error) => {
+                let (event, operation) = if save {
+                    let event = EngineEvent::ChunkSaveFailed {
+                        path: directory,
+                        reason: error.to_string(),
+                    };
+                    (event, PersistenceOperation::Save)
+                } else {
+                    let event = EngineEvent::ChunkLoadFailed {
+                        path: directory,
+                        reason: error.to_string(),
+                    };
+                    (event, PersistenceOperation::Load)
+                };
+                event.report_to_world(world);
+                world.send_event(PersistenceProgress {
+                    operation,
+                    status: PersistenceStatus::Failed,
+                    chunks_done: 0,
+                    chunks_total: 0,
+                    bytes: 0,
+                });
+            }
+        }
+    } else if save || load {
+        warn!("A save or load is already running, the new request was ignored");
+    }
+
+    let Some(running) = job.as_mut() else {
+        return;
+    };
+    // A budget of zero would never finish.
+    let budget = world
+        .resource::<WorldSaveSettings>()
+        .chunks_per_frame
+        .max(1);
+    let result = match running {
+        PersistenceJob::Save(save_job) => save_job.step(budget),
+        PersistenceJob::Load(load_job) => load_job.step(budget),
+    };
+
+    match result {
+        Ok(false) => {
+            let progress = running.progress(PersistenceStatus::Running);
+            world.send_event(progress);
+        }
+        Ok(true) => {
+            let finished = job.take().unwrap();
+            let progress = finished.progress(PersistenceStatus::Completed);
+            if let PersistenceJob::Load(load_job) = finished {
+                let directory = load_job.directory.clone();
+                let chunks = load_job.finish(world);
+                info!("Loaded {} chunks from {}", chunks, directory.display());
+            }
+            world.send_event(progress);
+        }
+        Err(error) => {
+            let mut failed = job.take().unwrap();
+            let progress = failed.progress(PersistenceStatus::Failed);
+            if let PersistenceJob::Save(save_job) = &mut failed {
+                save_job.discard();
+            }
+            failed.failure(error).report_to_world(world);
+            world.send_event(progress);
+        }
+    }
+}
+
+fn load_saved_world(settings: Res<WorldSaveSettings>, mut load: EventWriter<LoadWorld>) {
+    if settings.directory.join(META_FILE).exists() {
+        load.send(LoadWorld);
+    }
+}
+
+/// Save in progress, holding a copy of the chunks taken when it started.
+struct SaveJob {
+    directory: PathBuf,
+    /// Chunks grouped by region, each region in Morton order.
+    regions: Vec<(u64, Vec<(IVec3, ChunkCells)>)>,
+    next_region: usize,
+    next_chunk: usize,
+    /// Region file being encoded.
+    buffer: Vec<u8>,
+    meta: Vec<u8>,
+    /// Region files written under a temporary name until the save completes.
+    written: Vec<PathBuf>,
+    chunks_done: usize,
+    chunks_total: usize,
+    bytes: u64,
+}
+
+impl SaveJob {
+    fn new(world: &mut World, directory: &Path) -> io::Result<Self> {
+        fs::create_dir_all(directory)?;
+
+        let mut regions: HashMap<u64, Vec<(u64, IVec3, ChunkCells)>> = HashMap::new();
+        let mut query = world.query::<(&ChunkKey, &ChunkCells)>();
+        for (key, cells) in query.iter(world) {
+            regions
+                .entry(key.morton >> REGION_SHIFT)
+                .or_default()
+                .push((key.morton, key.coords, cells.clone()));
+        }
+        let mut regions: Vec<_> = regions
+            .into_iter()
+            .map(|(region, mut chunks)| {
+                chunks.sort_unstable_by_key(|(morton, ..)| *morton);
+                let chunks = chunks
+                    .into_iter()
+                    .map(|(_, coords, cells)| (coords, cells))
+                    .collect::<Vec<_>>();
+                (region, chunks)
+            })
+            .collect();
+        regions.sort_unstable_by_key(|(region, _)| *region);
+
+        let mut meta = Vec::new();
+        meta.extend_from_slice(META_MAGIC);
+        meta.extend_from_slice(&VERSION.to_le_bytes());
+        let clock = world.resource::<SimulationClock>();
+        meta.extend_from_slice(&clock.step.to_le_bytes());
+        meta.extend_from_slice(&clock.accumulator().to_le_bytes());
+        encode_rule(world.resource::<AutomataRule>(), &mut meta);
+        let keyframes = world
+            .get_resource::<RuleTimeline>()
+            .map_or(&[][..], |timeline| timeline.keyframes());
+        meta.extend_from_slice(&(keyframes.len() as u32).to_le_bytes());
+        for keyframe in keyframes {
+            meta.extend_from_slice(&keyframe.step.to_le_bytes());
+            encode_rule(&keyframe.rule, &mut meta);
+        }
+
+        let chunks_total = regions.iter().map(|(_, chunks)| chunks.len()).sum();
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            regions,
+            next_region: 0,
+            next_chunk: 0,
+            buffer: Vec::new(),
+            meta,
+            written: Vec::new(),
+            chunks_done: 0,
+            chunks_total,
+            bytes: 0,
+        })
+    }
+
+    /// Encodes up to `budget` chunks, returning whether the save completed.
+    fn step(&mut self, budget: usize) -> io::Result<bool> {
+        let mut encoded = 0;
+        while encoded < budget {
+            let Some((region, chunks)) = self.regions.get(self.next_region) else {
+                self.complete()?;
+                return Ok(true);
+            };
+
+            if self.next_chunk == 0 {
+                self.buffer.clear();
+                self.buffer.extend_from_slice(REGION_MAGIC);
+                self.buffer.extend_from_slice(&VERSION.to_le_bytes());
+                self.buffer
+                    .extend_from_slice(&(chunks.len() as u32).to_le_bytes());
+            }
+
+            if let Some((coords, cells)) = chunks.get(self.next_chunk) {
+                let mut rle = Vec::new();
+                encode_rle(&cells.as_slice(), &mut rle);
+                for axis in coords.to_array() {
+                    self.buffer.extend_from_slice(&axis.to_le_bytes());
+                }
+                self.buffer
+                    .extend_from_slice(&(rle.len() as u32).to_le_bytes());
+                self.buffer.extend_from_slice(&rle);
+                self.next_chunk += 1;
+                self.chunks_done += 1;
+                encoded += 1;
+            }
+
+            if self.next_chunk == chunks.len() {
+                let path = self.directory.join(format!("{:016x}.region.tmp", region));
+                fs::write(&path, &self.buffer)?;
+                self.bytes += self.buffer.len() as u64;
+                self.written.push(path);
+                self.next_region += 1;
+                self.next_chunk = 0;
+            }
+        }
+        Ok(false)
+    }
+
+    /// Replaces the previous save with the region files written so far, then the meta file.
+    ///
+    /// The new regions are renamed over the old ones before the regions missing from the new
+    /// save are removed, so a failure midway never leaves the directory without regions.
+    fn complete(&mut self) -> io::Result<()> {
+        let mut saved = HashSet::new();
+        for path in self.written.drain(..) {
+            let region = path.with_extension("");
+            fs::rename(&path, &region)?;
+            saved.insert(region);
+        }
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "region")
+                && !saved.contains(&path)
+            {
+                fs::remove_file(path)?;
+            }
+        }
+        fs::write(self.directory.join(META_FILE), &self.meta)?;
+        self.bytes += self.meta.len() as u64;
+        Ok(())
+    }
+
+    /// Removes the region files written so far.
+    fn discard(&mut self) {
+        for path in self.written.drain(..) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Load in progress. The chunks are kept aside until every region file has been read.
+struct LoadJob {
+    directory: PathBuf,
+    step: u64,
+    accumulator: f32,
+    rule: AutomataRule,
+    keyframes: Vec<RuleKeyframe>,
+    files: Vec<PathBuf>,
+    next_file: usize,
+    chunks: Vec<(IVec3, ChunkCells)>,
+    chunks_total: usize,
+    bytes: u64,
+}
+
+impl LoadJob {
+    fn new(directory: &Path) -> io::Result<Self> {
+        let meta = fs::read(directory.join(META_FILE))?;
+        let mut reader = meta.as_slice();
+        read_header(&mut reader, META_MAGIC)?;
+        let step = u64::from_le_bytes(read_array(&mut reader)?);
+        let accumulator = f32::from_le_bytes(read_array(&mut reader)?);
+        let rule = decode_rule(&mut reader)?;
+        let keyframes = (0..u32::from_le_bytes(read_array(&mut reader)?))
+            .map(|_| {
+                let step = u64::from_le_bytes(read_array(&mut reader)?);
+                Ok(RuleKeyframe {
+                    step,
+                    rule: decode_rule(&mut reader)?,
+                })
+            })
+            .collect::<io::Result<_>>()?;
+
+        // Only the headers are read here, to know the total for progress reports.
+        let files = region_files(directory)?;
+        let mut chunks_total = 0;
+        for path in &files {
+            let mut header = [0; 10];
+            fs::File::open(path)?.read_exact(&mut header)?;
+            let mut reader = header.as_slice();
+            read_header(&mut reader, REGION_MAGIC)?;
+            chunks_total += u32::from_le_bytes(read_array(&mut reader)?) as usize;
+        }
+
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            step,
+            accumulator,
+            rule,
+            keyframes,
+            files,
+            next_file: 0,
+            chunks: Vec::with_capacity(chunks_total),
+            chunks_total,
+            bytes: meta.len() as u64,
+        })
+    }
+
+    /// Reads region files until at least `budget` chunks were decoded, returning whether every
+    /// file has been read.
+    fn step(&mut self, budget: usize) -> io::Result<bool> {
+        let start = self.chunks.len();
+        while self.chunks.len() - start < budget {
+            let Some(path) = self.files.get(self.next_file) else {
+                return Ok(true);
+            };
+            let bytes = fs::read(path)?;
+            self.bytes += bytes.len() as u64;
+            read_region(&bytes, &mut self.chunks)?;
+            self.next_file += 1;
+        }
+        Ok(self.next_file == self.files.len())
+    }
+
+    /// Replaces the chunks, rule, timeline keyframes and clock of the world, returning the
+    /// number of chunks.
+    fn finish(self, world: &mut World) -> usize {
+        let existing: Vec<Entity> = world
+            .query_filtered::<Entity, With<ChunkKey>>()
+            .iter(world)
+            .collect();
+        for entity in existing {
+            world.despawn(entity);
+        }
+
+        let count = self.chunks.len();
+        let mut spawned = Vec::with_capacity(count);
+        for (coords, cells) in self.chunks {
+            let mut bundle = ChunkBundle::new(coords);
+            bundle.cells = cells;
+            spawned.push((coords, world.spawn((bundle, ChunkSource::Loaded)).id()));
+        }
+
+        let mut index = world.resource_mut::<ChunkIndex>();
+        for (coords, entity) in spawned {
+            index.insert(coords, entity);
+        }
+        world.insert_resource(self.rule);
+        world
+            .get_resource_or_insert_with(RuleTimeline::default)
+            .set_keyframes(self.keyframes);
+        world
+            .resource_mut::<SimulationClock>()
+            .restore(self.step, self.accumulator);
+
+        count
+    }
+}
+
+/// Writes every chunk, the rule, the timeline keyframes and the clock to `directory`, replacing
+/// a previous save.
+///
+/// Blocks until done, see [`SaveWorld`] to spread the save over several frames.
+pub fn save_world(world: &mut World, directory: &Path) -> io::Result<()> {
+    let mut job = SaveJob::new(world, directory)?;
+    match job.step(usize::MAX) {
+        Ok(_) => Ok(()),
+        Err(error) => {
+            job.discard();
+            Err(error)
+        }
+    }
+}
+
+/// Replaces every loaded chunk, the rule, the timeline keyframes and the clock with the save in
+/// `directory`.
+/// Returns the number of chunks loaded.
+///
+/// Blocks until done, see [`LoadWorld`] to spread the load over several frames.
+pub fn load_world(world: &mut World, directory: &Path) -> io::Result<usize> {
+    let mut job = LoadJob::new(directory)?;
+    job.step(usize::MAX)?;
+    Ok(job.finish(world))
+}
+
+/// Reads the chunks of the save in `directory` without touching a world, for example to
+/// compare saves with [`diff_saves`](crate::diff_saves).
+pub fn read_saved_chunks(directory: &Path) -> io::Result<Vec<(IVec3, ChunkCells)>> {
+    let mut chunks = Vec::new();
+    for path in region_files(directory)? {
+        read_region(&fs::read(path)?, &mut chunks)?;
+    }
+    Ok(chunks)
+}
+
+fn region_files(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "region")
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn read_region(bytes: &[u8], chunks: &mut Vec<(IVec3, ChunkCells)>) -> io::Result<()> {
+    let mut reader = bytes;
+    read_header(&mut reader, REGION_MAGIC)?;
+    let count = u32::from_le_bytes(read_array(&mut reader)?);
+    for _ in 0..count {
+        let coords = IVec3::new(
+            i32::from_le_bytes(read_array(&mut reader)?),
+            i32::from_le_bytes(read_array(&mut reader)?),
+            i32::from_le_bytes(read_array(&mut reader)?),
+        );
+        let len = u32::from_le_bytes(read_array(&mut reader)?) as usize;
+        if reader.len() < len {
+            return Err(invalid_data("truncated chunk"));
+        }
+        let (encoded, rest) = reader.split_at(len);
+        reader = rest;
+        chunks.push((coords, decode_rle(encoded)?));
+    }
+    Ok(())
+}
+
+/// Appends `(packed value, run length)` pairs of little endian `u16`s.
+pub fn encode_rle(cells: &[AutomataState], out: &mut Vec<u8>) {
+    let mut cells = cells.iter().map(|state| state.to_packed()).peekable();
+    while let Some(value) = cells.next() {
+        let mut length: u16 = 1;
+        while length < u16::MAX && cells.next_if_eq(&value).is_some() {
+            length += 1;
+        }
+        out.extend_from_slice(&value.to_le_bytes());
+        out.extend_from_slice(&length.to_le_bytes());
+    }
+}
+
+pub fn decode_rle(mut bytes: &[u8]) -> io::Result<ChunkCells> {
+    let mut packed = Vec::with_capacity(CHUNK_VOLUME);
+    while !bytes.is_empty() {
+        let value = u16::from_le_bytes(read_array(&mut bytes)?);
+        let length = u16::from_le_bytes(read_array(&mut bytes)?);
+        if packed.len() + length as usize > CHUNK_VOLUME {
+            return Err(invalid_data("chunk runs overflow the chunk"));
+        }
+        packed.extend(std::iter::repeat(value).take(length as usize));
+    }
+
+    ChunkCells::from_packed(&packed).map_err(|error| invalid_data(&error.to_string()))
+}
+
+fn encode_rule(rule: &AutomataRule, out: &mut Vec<u8>) {
+    let encode_counts = |counts: &[u8], out: &mut Vec<u8>| {
+        out.push(counts.len() as u8);
+        out.extend_from_slice(counts);
+    };
+    let encode_conditions = |conditions: &[MaterialCondition], out: &mut Vec<u8>| {
+        out.push(conditions.len() as u8);
+        for condition in conditions {
+            out.extend_from_slice(&[condition.material, condition.min, condition.max]);
+        }
+    };
+
+    encode_counts(&rule.birth, out);
+    encode_counts(&rule.survive, out);
+    out.push(rule.birth_material);
+    out.push(rule.material_rules.len() as u8);
+    for material_rule in &rule.material_rules {
+        out.push(material_rule.material);
+        encode_conditions(&material_rule.birth, out);
+        encode_conditions(&material_rule.survive, out);
+    }
+    out.push(rule.states);
+    match &rule.neighborhood {
+        Neighborhood::Moore => out.push(0),
+        Neighborhood::VonNeumann => out.push(1),
+        Neighborhood::Custom(offsets) => {
+            out.push(2);
+            out.push(offsets.len() as u8);
+            for offset in offsets {
+                out.extend(offset.to_array().map(|axis| axis as i8 as u8));
+            }
+        }
+    }
+}
+
+fn decode_rule(reader: &mut &[u8]) -> io::Result<AutomataRule> {
+    fn read_u8(reader: &mut &[u8]) -> io::Result<u8> {
+        Ok(read_array::<1>(reader)?[0])
+    }
+    fn read_counts(reader: &mut &[u8]) -> io::Result<Vec<u8>> {
+        let len = read_u8(reader)?;
+        (0..len).map(|_| read_u8(reader)).collect()
+    }
+    fn read_conditions(reader: &mut &[u8]) -> io::Result<Vec<MaterialCondition>> {
+        let len = read_u8(reader)?;
+        (0..len)
+            .map(|_| {
+                let [material, min, max] = read_array(reader)?;
+                Ok(MaterialCondition::between(material, min, max))
+            })
+            .collect()
+    }
+
+    let birth = read_counts(reader)?;
+    let survive = read_counts(reader)?;
+    let birth_material = read_u8(reader)?;
+    let material_rules = (0..read_u8(reader)?)
+        .map(|_| {
+            Ok(MaterialRule {
+                material: read_u8(reader)?,
+                birth: read_conditions(reader)?,
+                survive: read_conditions(reader)?,
+            })
+        })
+        .collect::<io::Result<_>>()?;
+    let rule = AutomataRule {
+        birth,
+        survive,
+        birth_material,
+        ..default()
+    }
+    .with_material_rules(material_rules)
+    .map_err(|error| invalid_data(&error.to_string()))?;
+    let states = read_u8(reader)?;
+    if !(2..=MAX_STATES).contains(&states) {
+        return Err(invalid_data(&format!(
+            "invalid number of states {}",
+            states
+        )));
+    }
+    let neighborhood = match read_u8(reader)? {
+        0 => Neighborhood::Moore,
+        1 => Neighborhood::VonNeumann,
+        2 => Neighborhood::Custom(
+            (0..read_u8(reader)?)
+                .map(|_| {
+                    let offset = read_array::<3>(reader)?.map(|axis| axis as i8 as i32);
+                    Ok(IVec3::from_array(offset))
+                })
+                .collect::<io::Result<_>>()?,
+        ),
+        other => return Err(invalid_data(&format!("unknown neighborhood {}", other))),
+    };
+    neighborhood
+        .validate()
+        .map_err(|error| invalid_data(&error.to_string()))?;
+
+    Ok(AutomataRule {
+        states,
+        neighborhood,
+        ..rule
+    })
+}
+
+fn read_header(reader: &mut &[u8], magic: &[u8; 4]) -> io::Result<()> {
+    if &read_array::<4>(reader)? != magic {
+        return Err(invalid_data("unrecognized file"));
+    }
+    let version = u16::from_le_bytes(read_array(reader)?);
+    if version != VERSION {
+        return Err(invalid_data(&format!("unsupported version {}", version)));
+    }
+    Ok(())
+}
+
+fn read_array<const N: usize>(reader: &mut &[u8]) -> io::Result<[u8; N]> {
+    let mut array = [0; N];
+    reader.read_exact(&mut array)?;
+    Ok(array)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rle_and_rule_round_trip() {
+        let cells = ChunkCells::from_generator(|pos| {
+            if pos.y < 4 {
+                AutomataState::new(3, 16)
+            } else {
+                AutomataState::EMPTY
+            }
+        });
+        let mut encoded = Vec::new();
+        encode_rle(&cells.as_slice(), &mut encoded);
+        assert!(encoded.len() < 4 * 32 * 32 * 2);
+        assert_eq!(decode_rle(&encoded).unwrap().as_slice(), cells.as_slice());
+
+        let rule = AutomataRule {
+            material_rules: vec![MaterialRule {
+                material: 3,
+                birth: vec![MaterialCondition::at_least(2, 3)],
+                survive: vec![],
+            }],
+            states: 5,
+            neighborhood: Neighborhood::Custom(vec![IVec3::X, IVec3::new(-1, 1, 0)]),
+            ..default()
+        };
+        let mut bytes = Vec::new();
+        encode_rule(&rule, &mut bytes);
+        assert_eq!(decode_rule(&mut bytes.as_slice()).unwrap(), rule);
+
+        let far = AutomataRule {
+            neighborhood: Neighborhood::Custom(vec![IVec3::new(0, -2, 0)]),
+            ..default()
+        };
+        let mut bytes = Vec::new();
+        encode_rule(&far, &mut bytes);
+        assert!(decode_rule(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn saves_of_other_versions_are_rejected() {
+        let mut header = META_MAGIC.to_vec();
+        header.extend_from_slice(&1u16.to_le_bytes());
+        assert!(read_header(&mut header.as_slice(), META_MAGIC).is_err());
+
+        let mut header = META_MAGIC.to_vec();
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        assert!(read_header(&mut header.as_slice(), META_MAGIC).is_ok());
+    }
+
+    fn save_app(name: &str) -> (App, PathBuf) {
+        let directory =
+            std::env::temp_dir().join(format!("persistence-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(crate::CellularAutomataPlugin)
+            .add_plugins(PersistencePlugin {
+                directory: directory.clone(),
+                load_on_startup: false,
+            });
+        // Clamped to one chunk per frame.
+        app.world
+            .resource_mut::<WorldSaveSettings>()
+            .chunks_per_frame = 0;
+        // Two chunks in different regions.
+        for coords in [IVec3::ZERO, IVec3::new(8, 0, 0)] {
+            app.world.spawn(ChunkBundle::new(coords));
+        }
+        app.update();
+        (app, directory)
+    }
+
+    fn progress(app: &App) -> Vec<PersistenceProgress> {
+        let events = app.world.resource::<Events<PersistenceProgress>>();
+        events.iter_current_update_events().copied().collect()
+    }
+
+    fn files(directory: &Path, extension: &str) -> usize {
+        fs::read_dir(directory)
+            .unwrap()
+            .filter(|entry| {
+                let path = entry.as_ref().unwrap().path();
+                path.extension().is_some_and(|found| found == extension)
+            })
+            .count()
+    }
+
+    #[test]
+    fn saves_report_progress_and_replace_stale_regions() {
+        let (mut app, directory) = save_app("progress");
+
+        app.world.send_event(SaveWorld);
+        app.update();
+        let running = progress(&app);
+        assert_eq!(running.len(), 1);
+        assert_eq!(running[0].status, PersistenceStatus::Running);
+        assert_eq!((running[0].chunks_done, running[0].chunks_total), (1, 2));
+        app.update();
+        app.update();
+        let completed = progress(&app);
+        assert_eq!(completed[0].status, PersistenceStatus::Completed);
+        assert_eq!(completed[0].chunks_done, 2);
+        assert_eq!(files(&directory, "region"), 2);
+
+        // The region of the despawned chunk goes away with the next save.
+        let mut query = app.world.query::<(Entity, &ChunkKey)>();
+        let (far, _) = query
+            .iter(&app.world)
+            .find(|(_, key)| key.coords.x == 8)
+            .unwrap();
+        app.world.despawn(far);
+        app.world.send_event(SaveWorld);
+        app.update();
+        app.update();
+        assert_eq!(progress(&app)[0].status, PersistenceStatus::Completed);
+        assert_eq!(files(&directory, "region"), 1);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn cancelled_saves_keep_the_previous_one() {
+        let (mut app, directory) = save_app("cancel");
+        app.world.send_event(SaveWorld);
+        for _ in 0..3 {
+            app.update();
+        }
+        let meta = fs::read(directory.join(META_FILE)).unwrap();
+
+        app.world.resource_mut::<SimulationClock>().step = 99;
+        app.world.send_event(SaveWorld);
+        app.update();
+        assert_eq!(files(&directory, "tmp"), 1);
+        app.world.send_event(CancelPersistence);
+        app.update();
+
+        let cancelled = progress(&app);
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(cancelled[0].status, PersistenceStatus::Cancelled);
+        assert_eq!(files(&directory, "tmp"), 0);
+        assert_eq!(files(&directory, "region"), 2);
+        assert_eq!(fs::read(directory.join(META_FILE)).unwrap(), meta);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn timeline_keyframes_are_saved_with_the_world() {
+        let (mut app, directory) = save_app("timeline");
+        let slow = AutomataRule {
+            birth: vec![4],
+            ..default()
+        };
+        let timeline = RuleTimeline::default()
+            .keyframe(10, slow.clone())
+            .keyframe(20, AutomataRule::default());
+        app.world.insert_resource(timeline.clone());
+        save_world(&mut app.world, &directory).unwrap();
+
+        let driven = RuleTimeline::default().driven_by(|_, rule| rule.birth_material = 2);
+        app.world.insert_resource(driven);
+        load_world(&mut app.world, &directory).unwrap();
+        let loaded = app.world.resource::<RuleTimeline>();
+        assert_eq!(loaded.keyframes(), timeline.keyframes());
+        // The installed driver is kept.
+        assert_eq!(loaded.rule_at(10, &default()).birth_material, 2);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -3,8 +3,8 @@ use crate::{
         compute::{AnimationData, PhysicsData},
         voxel_world::{ExtractedPortal, VoxelUniforms},
     },
-    Box, BoxCollider, Edges, Particle, Portal, RenderGraphSettings, VoxelPhysics,
-    VoxelizationMaterial, VoxelizationMaterialType,
+    Box, BoxCollider, Edges, EngineEvent, Particle, Portal, RenderGraphSettings, VoxelPhysics,
+    VoxelWorldTransform, VoxelizationMaterial, VoxelizationMaterialType,
 };
 use bevy::{
     prelude::*,
@@ -13,13 +13,18 @@ use bevy::{
     utils::HashMap,
 };
 
+/// Scale of the space the GPU physics works in: its positions times `VOXELS_PER_METER`, offset
+/// by half the voxel world texture, are texels. Positions are converted to and from it through
+/// the [`VoxelWorldTransform`], so physics collides with the simulated voxels wherever the grid
+/// is placed.
 pub const VOXELS_PER_METER: f32 = 4.0;
 
 pub struct PhysicsPlugin;
 
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(PreUpdate, insert_physics_data)
+        app.add_event::<EngineEvent>()
+            .add_systems(PreUpdate, insert_physics_data)
             .add_systems(PostUpdate, extract_physics_data)
             .add_systems(PostUpdate, extract_animation_data);
     }
@@ -28,9 +33,11 @@ impl Plugin for PhysicsPlugin {
 pub fn extract_physics_data(
     particle_query: Query<(&Transform, &VoxelPhysics, Entity), Without<BoxCollider>>,
     box_query: Query<(&Transform, &VoxelPhysics, &BoxCollider, Entity)>,
+    world_transform: Res<VoxelWorldTransform>,
     mut physics_data: ResMut<PhysicsData>,
     render_queue: Res<RenderQueue>,
 ) {
+    let physics = PhysicsSpace(*world_transform);
     let mut type_buffer = TypeBuffer::new();
     let mut entities = HashMap::new();
 
@@ -39,9 +46,9 @@ pub fn extract_physics_data(
         entities.insert(entity, type_buffer.header.len());
 
         type_buffer.push_object(0, |type_buffer| {
-            type_buffer.push_vec3(transform.translation);
-            type_buffer.push_vec3(voxel_physics.velocity);
-            type_buffer.push_vec3(voxel_physics.gravity);
+            type_buffer.push_vec3(physics.position(transform.translation));
+            type_buffer.push_vec3(physics.direction(voxel_physics.velocity));
+            type_buffer.push_vec3(physics.direction(voxel_physics.gravity));
             type_buffer.push_vec3(voxel_physics.collision_effect.to_vec3());
             type_buffer.push_vec3(Vec3::ZERO); // space to recieve hit data
             type_buffer.push_mat3(Mat3::IDENTITY); // space to recieve portal rotation
@@ -53,9 +60,9 @@ pub fn extract_physics_data(
         entities.insert(entity, type_buffer.header.len());
 
         type_buffer.push_object(1, |type_buffer| {
-            type_buffer.push_vec3(transform.translation);
-            type_buffer.push_vec3(voxel_physics.velocity);
-            type_buffer.push_vec3(voxel_physics.gravity);
+            type_buffer.push_vec3(physics.position(transform.translation));
+            type_buffer.push_vec3(physics.direction(voxel_physics.velocity));
+            type_buffer.push_vec3(physics.direction(voxel_physics.gravity));
             type_buffer.push_vec3(voxel_physics.collision_effect.to_vec3());
             type_buffer.push_vec3(Vec3::ZERO); // space to recieve hit data
             type_buffer.push_mat3(Mat3::IDENTITY); // space to recieve portal rotation
@@ -81,10 +88,13 @@ pub fn insert_physics_data(
     physics_data: Res<PhysicsData>,
     render_device: Res<RenderDevice>,
     render_graph_settings: Res<RenderGraphSettings>,
+    world_transform: Res<VoxelWorldTransform>,
+    mut engine_events: EventWriter<EngineEvent>,
 ) {
     if !render_graph_settings.physics {
         return;
     }
+    let physics = PhysicsSpace(*world_transform);
 
     // Process last frames physics data
     if physics_data.dispatch_size > 0 {
@@ -102,7 +112,7 @@ pub fn insert_physics_data(
         physics_data.physics_buffer_cpu.unmap();
 
         if result[0] == 0 {
-            warn!("No physics data returned from the gpu!");
+            EngineEvent::GpuReadbackFailed { source: "physics" }.report(&mut engine_events);
             return;
         }
 
@@ -110,22 +120,24 @@ pub fn insert_physics_data(
         for (mut transform, mut voxel_physics, entity) in voxel_physics_query.iter_mut() {
             if let Some(index) = physics_data.entities.get(&entity) {
                 let data_index = result[index + 1] as usize & 0xFFFFFF;
-                transform.translation = Vec3::new(
+                transform.translation = physics.world_position(Vec3::new(
                     bytemuck::cast(result[data_index + 0]),
                     bytemuck::cast(result[data_index + 1]),
                     bytemuck::cast(result[data_index + 2]),
-                );
-                voxel_physics.velocity = Vec3::new(
+                ));
+                voxel_physics.velocity = physics.world_direction(Vec3::new(
                     bytemuck::cast(result[data_index + 3]),
                     bytemuck::cast(result[data_index + 4]),
                     bytemuck::cast(result[data_index + 5]),
-                );
-                voxel_physics.hit_normal = Vec3::new(
-                    bytemuck::cast(result[data_index + 12]),
-                    bytemuck::cast(result[data_index + 13]),
-                    bytemuck::cast(result[data_index + 14]),
-                );
-                voxel_physics.portal_rotation = Mat3::from_cols(
+                ));
+                voxel_physics.hit_normal = world_transform.rotation
+                    * Vec3::new(
+                        bytemuck::cast(result[data_index + 12]),
+                        bytemuck::cast(result[data_index + 13]),
+                        bytemuck::cast(result[data_index + 14]),
+                    );
+                let rotation = Mat3::from_quat(world_transform.rotation);
+                let portal_rotation = Mat3::from_cols(
                     Vec3::new(
                         bytemuck::cast(result[data_index + 15]),
                         bytemuck::cast(result[data_index + 16]),
@@ -142,15 +154,40 @@ pub fn insert_physics_data(
                         bytemuck::cast(result[data_index + 23]),
                     ),
                 );
+                voxel_physics.portal_rotation = rotation * portal_rotation * rotation.transpose();
             }
         }
     }
 }
 
-#[allow(unused)]
-pub fn world_to_voxel(world_pos: Vec3, voxel_world_size: u32) -> IVec3 {
-    let world_pos = world_pos * VOXELS_PER_METER;
-    world_pos.as_ivec3() + IVec3::splat(voxel_world_size as i32 / 2)
+/// Texel of the voxel world texture holding the voxel at a world position.
+pub fn world_to_voxel(
+    world_pos: Vec3,
+    world_transform: &VoxelWorldTransform,
+    voxel_world_size: u32,
+) -> IVec3 {
+    world_transform.world_to_voxel(world_pos) + IVec3::splat(voxel_world_size as i32 / 2)
+}
+
+/// Converts between world space and the space of the GPU physics, see [`VOXELS_PER_METER`].
+struct PhysicsSpace(VoxelWorldTransform);
+
+impl PhysicsSpace {
+    fn position(&self, world: Vec3) -> Vec3 {
+        self.0.world_to_voxel_space(world) / VOXELS_PER_METER
+    }
+
+    fn direction(&self, world: Vec3) -> Vec3 {
+        self.0.world_to_voxel_direction(world) / VOXELS_PER_METER
+    }
+
+    fn world_position(&self, physics: Vec3) -> Vec3 {
+        self.0.voxel_space_to_world(physics * VOXELS_PER_METER)
+    }
+
+    fn world_direction(&self, physics: Vec3) -> Vec3 {
+        self.0.voxel_to_world_direction(physics * VOXELS_PER_METER)
+    }
 }
 
 #[allow(unused)]
@@ -226,6 +263,7 @@ impl TypeBuffer {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn extract_animation_data(
     mut animation_data: ResMut<AnimationData>,
     particle_query: Query<(&Transform, &Particle)>,
@@ -233,6 +271,7 @@ pub fn extract_animation_data(
     edges_query: Query<(&Transform, &Edges)>,
     boxes_query: Query<(&Transform, &Box)>,
     mut voxel_uniforms: ResMut<VoxelUniforms>,
+    world_transform: Res<VoxelWorldTransform>,
     render_queue: Res<RenderQueue>,
 ) {
     let mut type_buffer = TypeBuffer::new();
@@ -241,7 +280,7 @@ pub fn extract_animation_data(
 
     // Add particles
     for (transform, particle) in particle_query.iter() {
-        let pos = world_to_voxel(transform.translation, voxel_world_size);
+        let pos = world_to_voxel(transform.translation, &world_transform, voxel_world_size);
         type_buffer.push_object(0, |type_buffer| {
             type_buffer.push_ivec3(pos);
             type_buffer.push_u32(particle.material as u32);
@@ -251,7 +290,7 @@ pub fn extract_animation_data(
 
     // Add edges
     for (transform, edges) in edges_query.iter() {
-        let pos = world_to_voxel(transform.translation, voxel_world_size);
+        let pos = world_to_voxel(transform.translation, &world_transform, voxel_world_size);
         type_buffer.push_object(1, |type_buffer| {
             type_buffer.push_ivec3(pos);
             type_buffer.push_u32(edges.material as u32);
@@ -262,7 +301,7 @@ pub fn extract_animation_data(
 
     // Add boxes
     for (transform, boxes) in boxes_query.iter() {
-        let pos = world_to_voxel(transform.translation, voxel_world_size);
+        let pos = world_to_voxel(transform.translation, &world_transform, voxel_world_size);
         type_buffer.push_object(2, |type_buffer| {
             type_buffer.push_ivec3(pos);
             type_buffer.push_u32(boxes.material as u32);
@@ -314,3 +353,32 @@ pub fn extract_animation_data(
         bytemuck::cast_slice(&type_buffer.finish()),
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn physics_space_follows_the_voxel_world_transform() {
+        let world_transform = VoxelWorldTransform {
+            voxel_size: 0.5,
+            origin: Vec3::new(10.0, 0.0, -4.0),
+            rotation: Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+        };
+        let physics = PhysicsSpace(world_transform);
+
+        let voxel = IVec3::new(3, 7, -2);
+        let center = world_transform.voxel_center(voxel);
+        let texel = (physics.position(center) * VOXELS_PER_METER)
+            .floor()
+            .as_ivec3();
+        assert_eq!(texel, voxel);
+        assert_eq!(world_to_voxel(center, &world_transform, 64), voxel + 32);
+
+        let position = physics.world_position(physics.position(center));
+        assert!(position.abs_diff_eq(center, 1e-4));
+        let velocity = Vec3::new(1.0, -2.0, 3.0);
+        let round_trip = physics.world_direction(physics.direction(velocity));
+        assert!(round_trip.abs_diff_eq(velocity, 1e-4));
+    }
+}
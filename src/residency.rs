@@ -0,0 +1,203 @@
+use crate::{
+    meshing::despawn_layer_meshes, ChunkKey, ChunkLayerMesh, VoxelWorldTransform, CHUNK_EDGE,
+};
+use bevy::{
+    prelude::*,
+    render::primitives::{Aabb, Frustum},
+};
+
+/// Unloads the meshes of chunks that stayed out of every camera frustum for too long.
+///
+/// Only the `Handle<Mesh>`, its asset and the [`ChunkLayerMesh`] children are dropped, the cells
+/// stay loaded. When an evicted chunk becomes visible again it is tagged with [`ChunkNeedsMesh`]
+/// so it can be rebuilt.
+pub struct MeshResidencyPlugin;
+
+impl Plugin for MeshResidencyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MeshResidency>().add_systems(
+            PostUpdate,
+            (
+                update_chunk_visibility,
+                evict_invisible_meshes,
+                request_visible_meshes,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct MeshResidency {
+    /// Seconds a chunk has to stay invisible before its mesh is unloaded.
+    pub unload_after: f32,
+}
+
+impl Default for MeshResidency {
+    fn default() -> Self {
+        Self { unload_after: 10.0 }
+    }
+}
+
+/// Tracks when a chunk was last inside a camera frustum.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ChunkVisibility {
+    pub visible: bool,
+    /// Elapsed seconds at which the chunk was last visible.
+    pub last_visible: f32,
+}
+
+/// Marks a chunk whose mesh was unloaded while it was out of view.
+#[derive(Component, Debug, Default)]
+pub struct ChunkMeshEvicted;
+
+/// Marks a chunk that needs its mesh (re)built.
+#[derive(Component, Debug, Default)]
+pub struct ChunkNeedsMesh;
+
+/// Voxel space bounds of a chunk.
+pub fn chunk_aabb(coords: IVec3) -> Aabb {
+    let min = (coords * CHUNK_EDGE).as_vec3();
+    Aabb::from_min_max(min, min + Vec3::splat(CHUNK_EDGE as f32))
+}
+
+fn update_chunk_visibility(
+    mut commands: Commands,
+    time: Res<Time>,
+    world_transform: Res<VoxelWorldTransform>,
+    cameras: Query<(&Camera, &Frustum)>,
+    mut chunks: Query<(Entity, &ChunkKey, Option<&mut ChunkVisibility>)>,
+) {
+    let now = time.elapsed_seconds();
+    let voxel_to_world = world_transform.affine();
+
+    for (entity, key, visibility) in chunks.iter_mut() {
+        let aabb = chunk_aabb(key.coords);
+        let visible = cameras.iter().any(|(camera, frustum)| {
+            camera.is_active && frustum.intersects_obb(&aabb, &voxel_to_world, true, true)
+        });
+
+        match visibility {
+            Some(mut visibility) => {
+                if visibility.visible != visible {
+                    visibility.visible = visible;
+                }
+                if visible {
+                    visibility.last_visible = now;
+                }
+            }
+            None => {
+                commands.entity(entity).insert(ChunkVisibility {
+                    visible,
+                    last_visible: now,
+                });
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn evict_invisible_meshes(
+    mut commands: Commands,
+    time: Res<Time>,
+    residency: Res<MeshResidency>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    chunks: Query<
+        (
+            Entity,
+            &ChunkVisibility,
+            Option<&Handle<Mesh>>,
+            Option<&Children>,
+        ),
+        (With<ChunkKey>, Without<ChunkMeshEvicted>),
+    >,
+    layer_meshes: Query<&Handle<Mesh>, With<ChunkLayerMesh>>,
+) {
+    let now = time.elapsed_seconds();
+
+    for (entity, visibility, mesh, children) in chunks.iter() {
+        if visibility.visible || now - visibility.last_visible <= residency.unload_after {
+            continue;
+        }
+        // Chunks split by render layers only have meshes on their children.
+        let has_layers = children
+            .into_iter()
+            .flatten()
+            .any(|&child| layer_meshes.contains(child));
+        if mesh.is_none() && !has_layers {
+            continue;
+        }
+        if let Some(mesh) = mesh {
+            meshes.remove(mesh.id());
+        }
+        despawn_layer_meshes(&mut commands, &mut meshes, children, &layer_meshes);
+        commands
+            .entity(entity)
+            .remove::<Handle<Mesh>>()
+            .insert(ChunkMeshEvicted);
+    }
+}
+
+fn request_visible_meshes(
+    mut commands: Commands,
+    chunks: Query<(Entity, &ChunkVisibility), With<ChunkMeshEvicted>>,
+) {
+    for (entity, visibility) in chunks.iter() {
+        if visibility.visible {
+            commands
+                .entity(entity)
+                .remove::<ChunkMeshEvicted>()
+                .insert(ChunkNeedsMesh);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn chunks_with_only_layer_meshes_are_evicted() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<Assets<Mesh>>()
+            .insert_resource(MeshResidency { unload_after: 0.0 })
+            .add_systems(Update, evict_invisible_meshes);
+        let mesh = app
+            .world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Mesh::from(shape::Cube::default()));
+        let layer = app.world.spawn((ChunkLayerMesh, mesh.clone())).id();
+        let chunk = app
+            .world
+            .spawn((
+                ChunkKey::new(IVec3::ZERO),
+                ChunkVisibility {
+                    visible: false,
+                    last_visible: 0.0,
+                },
+            ))
+            .add_child(layer)
+            .id();
+        let bare = app
+            .world
+            .spawn((
+                ChunkKey::new(IVec3::X),
+                ChunkVisibility {
+                    visible: false,
+                    last_visible: 0.0,
+                },
+            ))
+            .id();
+        std::thread::sleep(Duration::from_millis(10));
+        app.update();
+        app.update();
+
+        assert!(app.world.get::<ChunkMeshEvicted>(chunk).is_some());
+        assert!(app.world.get_entity(layer).is_none());
+        assert!(app.world.resource::<Assets<Mesh>>().get(&mesh).is_none());
+        // Nothing to unload on a chunk that was never meshed.
+        assert!(app.world.get::<ChunkMeshEvicted>(bare).is_none());
+    }
+}
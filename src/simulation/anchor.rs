@@ -0,0 +1,308 @@
+use super::{ChunkKey, VoxelWorldTransform, CHUNK_EDGE};
+use bevy::prelude::*;
+#[cfg(feature = "render")]
+use bevy::render::primitives::{Aabb, Frustum};
+
+/// Distances, in chunks, controlling how much work an anchor asks for around itself.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimulationProfile {
+    /// Chunks within this radius step every simulation step.
+    pub full_rate_radius: f32,
+    /// Chunks within this radius step every `reduced_rate_interval` steps.
+    pub reduced_rate_radius: f32,
+    /// Chunks within this radius are kept but not stepped. Chunks past it are unloaded by the
+    /// [`ChunkStreamingPlugin`](crate::ChunkStreamingPlugin), which caps its radii around the
+    /// anchor by this one, so they hibernate with the default
+    /// [`ChunkUnloadMode`](crate::ChunkUnloadMode).
+    pub frozen_radius: f32,
+    /// Chunks within this radius should be meshed for rendering.
+    pub meshing_distance: f32,
+    pub reduced_rate_interval: u32,
+    /// Chunks beyond this radius are simulated on a grid downsampled 2:1, see
+    /// [`ChunkCellsLod`](super::ChunkCellsLod). Infinite by default, which keeps every chunk at
+    /// full resolution.
+    pub downsample_radius: f32,
+    /// Steps the chunks past `full_rate_radius` in tiers of decreasing rates instead of
+    /// `reduced_rate_radius` and `reduced_rate_interval`.
+    pub throttle: Option<ThrottleTiers>,
+}
+
+impl Default for SimulationProfile {
+    fn default() -> Self {
+        Self {
+            full_rate_radius: 4.0,
+            reduced_rate_radius: 8.0,
+            frozen_radius: 12.0,
+            meshing_distance: 8.0,
+            reduced_rate_interval: 4,
+            downsample_radius: f32::INFINITY,
+            throttle: None,
+        }
+    }
+}
+
+/// Distances, in chunks, of the tiers stepped at 1/2, 1/4 and 1/8 of the fixed rate, chunks
+/// beyond the last one being frozen.
+///
+/// Chunks outside the frustum of every active camera are throttled as if they were
+/// `offscreen_scale` times further away. Without the `render` feature every chunk counts as
+/// visible.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThrottleTiers {
+    pub half_rate_radius: f32,
+    pub quarter_rate_radius: f32,
+    pub eighth_rate_radius: f32,
+    pub offscreen_scale: f32,
+}
+
+impl Default for ThrottleTiers {
+    fn default() -> Self {
+        Self {
+            half_rate_radius: 6.0,
+            quarter_rate_radius: 8.0,
+            eighth_rate_radius: 12.0,
+            offscreen_scale: 2.0,
+        }
+    }
+}
+
+impl ThrottleTiers {
+    /// Steps between two steps of a chunk at `distance`, `None` past the last tier.
+    pub fn interval(&self, distance: f32) -> Option<u32> {
+        if distance <= self.half_rate_radius {
+            Some(2)
+        } else if distance <= self.quarter_rate_radius {
+            Some(4)
+        } else if distance <= self.eighth_rate_radius {
+            Some(8)
+        } else {
+            None
+        }
+    }
+}
+
+/// Marks an entity (usually a camera or player) the simulation is centered around.
+///
+/// When several anchors exist every chunk uses the most demanding profile that reaches it.
+/// Without any anchor all chunks simulate at full rate.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct SimulationAnchor {
+    pub profile: SimulationProfile,
+}
+
+/// How often a chunk is stepped, ordered from cheapest to most expensive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SimulationRate {
+    Frozen,
+    /// Stepped once every `interval` simulation steps, on the steps where
+    /// `(step + phase) % interval == 0`. Neighboring chunks get different phases, so the work
+    /// of a tier is spread over the steps instead of landing on one of them.
+    Reduced {
+        interval: u32,
+        phase: u32,
+    },
+    Full,
+}
+
+impl SimulationRate {
+    #[inline]
+    pub fn steps_on(&self, step: u64) -> bool {
+        match self {
+            SimulationRate::Frozen => false,
+            SimulationRate::Reduced { interval, phase } => {
+                (step + *phase as u64) % (*interval).max(1) as u64 == 0
+            }
+            SimulationRate::Full => true,
+        }
+    }
+}
+
+/// Level of detail a chunk is currently simulated and rendered at.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkLod {
+    pub rate: SimulationRate,
+    /// Whether any anchor wants this chunk meshed.
+    pub meshed: bool,
+    /// Whether the chunk is stepped on its [`ChunkCellsLod`](super::ChunkCellsLod) instead of
+    /// its full resolution cells.
+    pub downsampled: bool,
+}
+
+impl Default for ChunkLod {
+    fn default() -> Self {
+        Self {
+            rate: SimulationRate::Full,
+            meshed: true,
+            downsampled: false,
+        }
+    }
+}
+
+impl ChunkLod {
+    /// Whether the full resolution cells are stepped on `step`.
+    #[inline]
+    pub fn steps_cells_on(&self, step: u64) -> bool {
+        !self.downsampled && self.rate.steps_on(step)
+    }
+
+    /// Level of detail asked by `profile` for the chunk with Morton key `morton`, at `distance`
+    /// from the anchor.
+    fn from_profile(
+        profile: &SimulationProfile,
+        distance: f32,
+        visible: bool,
+        morton: u64,
+    ) -> Self {
+        let reduced = |interval: u32| SimulationRate::Reduced {
+            interval,
+            phase: (morton % interval.max(1) as u64) as u32,
+        };
+        let rate = if distance <= profile.full_rate_radius {
+            SimulationRate::Full
+        } else if let Some(tiers) = &profile.throttle {
+            let distance = if visible {
+                distance
+            } else {
+                distance * tiers.offscreen_scale
+            };
+            tiers
+                .interval(distance)
+                .map_or(SimulationRate::Frozen, reduced)
+        } else if distance <= profile.reduced_rate_radius {
+            reduced(profile.reduced_rate_interval)
+        } else {
+            SimulationRate::Frozen
+        };
+
+        Self {
+            rate,
+            meshed: distance <= profile.meshing_distance,
+            downsampled: distance > profile.downsample_radius,
+        }
+    }
+
+    fn merge(self, other: Self) -> Self {
+        let rate = match (self.rate, other.rate) {
+            (
+                a @ SimulationRate::Reduced { interval: x, .. },
+                b @ SimulationRate::Reduced { interval: y, .. },
+            ) => {
+                if x <= y {
+                    a
+                } else {
+                    b
+                }
+            }
+            (a, b) => a.max(b),
+        };
+
+        Self {
+            rate,
+            meshed: self.meshed || other.meshed,
+            downsampled: self.downsampled && other.downsampled,
+        }
+    }
+}
+
+pub(super) fn update_chunk_lod(
+    mut commands: Commands,
+    world_transform: Res<VoxelWorldTransform>,
+    anchors: Query<(&SimulationAnchor, &GlobalTransform)>,
+    #[cfg(feature = "render")] cameras: Query<(&Camera, &Frustum)>,
+    mut chunks: Query<(Entity, &ChunkKey, Option<&mut ChunkLod>)>,
+) {
+    let anchors: Vec<_> = anchors
+        .iter()
+        .map(|(anchor, transform)| {
+            let position =
+                world_transform.world_to_voxel_space(transform.translation()) / CHUNK_EDGE as f32;
+            (anchor.profile, position)
+        })
+        .collect();
+    #[cfg(feature = "render")]
+    let throttled = anchors
+        .iter()
+        .any(|(profile, _)| profile.throttle.is_some());
+    #[cfg(feature = "render")]
+    let affine = world_transform.affine();
+
+    for (entity, key, lod) in chunks.iter_mut() {
+        let center = key.coords.as_vec3() + Vec3::splat(0.5);
+        // Frustums are only tested when some anchor throttles, chunks count as visible otherwise.
+        #[cfg(feature = "render")]
+        let visible = !throttled || {
+            let min = (key.coords * CHUNK_EDGE).as_vec3();
+            let aabb = Aabb::from_min_max(min, min + CHUNK_EDGE as f32);
+            cameras
+                .iter()
+                .filter(|(camera, _)| camera.is_active)
+                .any(|(_, frustum)| frustum.intersects_obb(&aabb, &affine, true, false))
+        };
+        #[cfg(not(feature = "render"))]
+        let visible = true;
+        let target = if anchors.is_empty() {
+            ChunkLod::default()
+        } else {
+            anchors
+                .iter()
+                .map(|(profile, position)| {
+                    let distance = center.distance(*position);
+                    ChunkLod::from_profile(profile, distance, visible, key.morton)
+                })
+                .reduce(ChunkLod::merge)
+                .unwrap()
+        };
+
+        match lod {
+            Some(mut lod) => {
+                if *lod != target {
+                    *lod = target;
+                }
+            }
+            None => {
+                commands.entity(entity).insert(target);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttle_tiers_spread_distant_chunks_over_the_steps() {
+        let profile = SimulationProfile {
+            throttle: Some(ThrottleTiers::default()),
+            ..default()
+        };
+        let rate = |distance, visible| ChunkLod::from_profile(&profile, distance, visible, 5).rate;
+        assert_eq!(rate(3.0, true), SimulationRate::Full);
+        assert_eq!(
+            rate(5.0, true),
+            SimulationRate::Reduced {
+                interval: 2,
+                phase: 1
+            }
+        );
+        // Out of sight, the same chunk is throttled as if twice as far.
+        assert_eq!(
+            rate(5.0, false),
+            SimulationRate::Reduced {
+                interval: 8,
+                phase: 5
+            }
+        );
+        assert_eq!(rate(13.0, true), SimulationRate::Frozen);
+
+        // Eight neighboring chunks in the last tier each step on a different step.
+        let mut steps: Vec<_> = (0..8)
+            .map(|morton| {
+                let lod = ChunkLod::from_profile(&profile, 10.0, true, morton);
+                (0..8).find(|step| lod.rate.steps_on(*step)).unwrap()
+            })
+            .collect();
+        steps.sort_unstable();
+        assert_eq!(steps, (0..8).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,62 @@
+// Total-count birth/survival step of the cellular automata, see `AutomataRule`.
+//
+// Every chunk is uploaded with a one cell halo taken from its neighbors, cells are packed
+// as `material | flags << 8` like the voxel world texture.
+
+struct StepUniforms {
+    birth: u32,
+    survive: u32,
+    birth_material: u32,
+    chunk_count: u32,
+}
+
+@group(0) @binding(0)
+var<uniform> uniforms: StepUniforms;
+@group(0) @binding(1)
+var<storage, read> input: array<u32>;
+@group(0) @binding(2)
+var<storage, read_write> output: array<u32>;
+
+const EDGE: u32 = 32u;
+const PADDED: u32 = 34u;
+
+fn padded_index(chunk: u32, pos: vec3<u32>) -> u32 {
+    return chunk * PADDED * PADDED * PADDED + pos.x * PADDED * PADDED + pos.y * PADDED + pos.z;
+}
+
+@compute @workgroup_size(4, 4, 4)
+fn step(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let chunk = invocation_id.x / EDGE;
+    let local = vec3(invocation_id.x % EDGE, invocation_id.y, invocation_id.z);
+    if chunk >= uniforms.chunk_count {
+        return;
+    }
+
+    var total = 0u;
+    for (var dx = 0u; dx < 3u; dx += 1u) {
+        for (var dy = 0u; dy < 3u; dy += 1u) {
+            for (var dz = 0u; dz < 3u; dz += 1u) {
+                if dx == 1u && dy == 1u && dz == 1u {
+                    continue;
+                }
+
+                let neighbor = input[padded_index(chunk, local + vec3(dx, dy, dz))];
+                if (neighbor & 0xFFu) != 0u {
+                    total += 1u;
+                }
+            }
+        }
+    }
+
+    let current = input[padded_index(chunk, local + vec3(1u))];
+    var next = 0u;
+    if (current & 0xFFu) != 0u {
+        if ((uniforms.survive >> total) & 1u) != 0u {
+            next = current;
+        }
+    } else if ((uniforms.birth >> total) & 1u) != 0u {
+        next = uniforms.birth_material;
+    }
+
+    output[chunk * EDGE * EDGE * EDGE + local.x * EDGE * EDGE + local.y * EDGE + local.z] = next;
+}
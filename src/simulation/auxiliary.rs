@@ -0,0 +1,297 @@
+use super::{
+    fluid::step_fluid, linear_index, voxel_to_chunk, AutomataState, ChunkCells, ChunkCellsNext,
+    ChunkKey, ChunkLod, ChunkSnapshots, FluidRule, SimulationClock, CHUNK_EDGE, CHUNK_VOLUME,
+};
+use bevy::{prelude::*, utils::HashSet};
+
+/// Name of the pass advancing [`ChunkAux`] through the [`AuxRule`].
+pub const AUX_PASS: &str = "aux";
+
+/// Optional second `u16` per voxel for data such as age, temperature or energy.
+///
+/// Insert it on the chunks that need one, diffusion treats neighbors without aux data as
+/// matching the cell itself. The values of the previous step are available through
+/// [`ChunkSnapshots::aux`].
+#[derive(Component, Clone)]
+pub struct ChunkAux {
+    data: Box<[u16]>,
+    next: Box<[u16]>,
+}
+
+impl ChunkAux {
+    pub fn filled(value: u16) -> Self {
+        Self {
+            data: vec![value; CHUNK_VOLUME].into_boxed_slice(),
+            next: vec![value; CHUNK_VOLUME].into_boxed_slice(),
+        }
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &[u16] {
+        &self.data
+    }
+
+    #[inline]
+    pub fn get(&self, local: IVec3) -> u16 {
+        self.data[linear_index(local)]
+    }
+
+    /// Sets a value, also overriding the value computed for the step being applied.
+    #[inline]
+    pub fn set(&mut self, local: IVec3, value: u16) {
+        let index = linear_index(local);
+        self.data[index] = value;
+        self.next[index] = value;
+    }
+
+    #[inline]
+    pub fn clone_box(&self) -> Box<[u16]> {
+        self.data.clone()
+    }
+}
+
+impl Default for ChunkAux {
+    fn default() -> Self {
+        Self::filled(0)
+    }
+}
+
+/// How the [`ChunkAux`] channel evolves every step.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AuxRule {
+    /// The simulation leaves the channel untouched, only edits change it.
+    #[default]
+    Manual,
+    /// Counts the steps a cell has been alive, reset when it dies or changes material. Cells
+    /// reaching `max_age` die.
+    Age { max_age: Option<u16> },
+    /// Heat style diffusion, every value moves `rate / 255` of the way to the average of its
+    /// six face neighbors.
+    Diffuse { rate: u8 },
+    /// Values are fluid levels flowing down and spreading out, see [`FluidRule`].
+    Fluid(FluidRule),
+}
+
+pub(super) fn step_aux(
+    rule: Res<AuxRule>,
+    snapshots: Res<ChunkSnapshots>,
+    clock: Res<SimulationClock>,
+    mut chunks: Query<(
+        &ChunkKey,
+        &ChunkCells,
+        &mut ChunkCellsNext,
+        &mut ChunkAux,
+        Option<&ChunkLod>,
+    )>,
+) {
+    if *rule == AuxRule::Manual {
+        return;
+    }
+
+    // Values only cross borders between chunks stepped together, a chunk that is not stepped
+    // would neither give nor take its share.
+    let steps = |lod: Option<&ChunkLod>| lod.is_none_or(|lod| lod.rate.steps_on(clock.step));
+    let stepping: HashSet<IVec3> = chunks
+        .iter()
+        .filter(|(.., lod)| steps(*lod))
+        .map(|(key, ..)| key.coords)
+        .collect();
+
+    chunks
+        .par_iter_mut()
+        .for_each(|(key, cells, mut next, mut aux, lod)| {
+            if !steps(lod) {
+                let ChunkAux { data, next } = &mut *aux;
+                next.copy_from_slice(data);
+                return;
+            }
+
+            match *rule {
+                AuxRule::Manual => {}
+                AuxRule::Age { max_age } => {
+                    step_age(
+                        cells.storage().iter(),
+                        next.as_mut_slice(),
+                        &mut aux,
+                        max_age,
+                    );
+                }
+                AuxRule::Diffuse { rate } => {
+                    step_diffusion(key.coords, &snapshots, &stepping, &mut aux, rate)
+                }
+                AuxRule::Fluid(fluid) => step_fluid(
+                    &fluid,
+                    &snapshots,
+                    &stepping,
+                    key.coords,
+                    next.as_mut_slice(),
+                    &mut aux.next,
+                ),
+            }
+        });
+}
+
+fn step_age(
+    previous: impl Iterator<Item = AutomataState>,
+    next: &mut [AutomataState],
+    aux: &mut ChunkAux,
+    max_age: Option<u16>,
+) {
+    for (index, previous) in previous.enumerate() {
+        let state = next[index];
+        if !state.is_alive() {
+            aux.next[index] = 0;
+            continue;
+        }
+
+        let age = if previous == state {
+            aux.data[index].saturating_add(1)
+        } else {
+            0
+        };
+        if max_age.is_some_and(|max_age| age >= max_age) {
+            next[index] = AutomataState::EMPTY;
+            aux.next[index] = 0;
+        } else {
+            aux.next[index] = age;
+        }
+    }
+}
+
+fn step_diffusion(
+    coords: IVec3,
+    snapshots: &ChunkSnapshots,
+    stepping: &HashSet<IVec3>,
+    aux: &mut ChunkAux,
+    rate: u8,
+) {
+    const FACES: [IVec3; 6] = [
+        IVec3::X,
+        IVec3::NEG_X,
+        IVec3::Y,
+        IVec3::NEG_Y,
+        IVec3::Z,
+        IVec3::NEG_Z,
+    ];
+
+    for x in 0..CHUNK_EDGE {
+        for y in 0..CHUNK_EDGE {
+            for z in 0..CHUNK_EDGE {
+                let local = IVec3::new(x, y, z);
+                let index = linear_index(local);
+                let value = aux.data[index] as i32;
+
+                let mut sum = 0;
+                for face in FACES {
+                    sum += sample_aux(snapshots, stepping, coords, local + face)
+                        .map_or(value, i32::from);
+                }
+                let delta = (sum / 6 - value) * rate as i32 / 255;
+                aux.next[index] = (value + delta).clamp(0, u16::MAX as i32) as u16;
+            }
+        }
+    }
+}
+
+fn sample_aux(
+    snapshots: &ChunkSnapshots,
+    stepping: &HashSet<IVec3>,
+    coords: IVec3,
+    local: IVec3,
+) -> Option<u16> {
+    let (chunk, local) = voxel_to_chunk(coords * CHUNK_EDGE + local);
+    if !stepping.contains(&chunk) {
+        return None;
+    }
+    snapshots
+        .aux(chunk)
+        .map(|values| values[linear_index(local)])
+}
+
+/// Copies the values computed by the [`AUX_PASS`] into the chunks, before the cells are applied.
+pub(super) fn apply_aux(clock: Res<SimulationClock>, mut chunks: Query<&mut ChunkAux>) {
+    if !clock.executed_step {
+        return;
+    }
+
+    chunks.par_iter_mut().for_each(|mut aux| {
+        let ChunkAux { data, next } = &mut *aux;
+        data.copy_from_slice(next);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        CellularAutomataPlugin, ChunkBundle, SimulationAnchor, SimulationControl, SimulationProfile,
+    };
+
+    #[test]
+    fn cells_die_of_old_age() {
+        let alive = AutomataState::new(1, 0);
+        let mut previous = vec![AutomataState::EMPTY; CHUNK_VOLUME];
+        let mut next = vec![alive; CHUNK_VOLUME];
+        let mut aux = ChunkAux::default();
+
+        // Born this step, then surviving.
+        step_age(previous.iter().copied(), &mut next, &mut aux, Some(2));
+        assert_eq!(aux.next[0], 0);
+        aux.data.copy_from_slice(&aux.next);
+        previous.copy_from_slice(&next);
+
+        step_age(previous.iter().copied(), &mut next, &mut aux, Some(2));
+        assert_eq!(aux.next[0], 1);
+        aux.data.copy_from_slice(&aux.next);
+
+        step_age(previous.iter().copied(), &mut next, &mut aux, Some(2));
+        assert_eq!(next[0], AutomataState::EMPTY);
+        assert_eq!(aux.next[0], 0);
+    }
+
+    #[test]
+    fn fluid_does_not_flow_into_frozen_chunks() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(CellularAutomataPlugin)
+            .insert_resource(AuxRule::Fluid(FluidRule::default()));
+        // The anchor steps the first chunk and freezes its neighbor.
+        app.world.spawn((
+            SimulationAnchor {
+                profile: SimulationProfile {
+                    full_rate_radius: 1.0,
+                    reduced_rate_radius: 1.0,
+                    frozen_radius: 100.0,
+                    ..default()
+                },
+            },
+            GlobalTransform::IDENTITY,
+        ));
+        let mut pool = ChunkAux::default();
+        for y in 10..20 {
+            for z in 0..CHUNK_EDGE {
+                pool.set(IVec3::new(CHUNK_EDGE - 1, y, z), 1000);
+            }
+        }
+        let chunks = [
+            app.world.spawn((ChunkBundle::new(IVec3::ZERO), pool)).id(),
+            app.world
+                .spawn((ChunkBundle::new(IVec3::X), ChunkAux::default()))
+                .id(),
+        ];
+        let total = |app: &App, chunk: Entity| -> u64 {
+            let aux = app.world.get::<ChunkAux>(chunk).unwrap();
+            aux.as_slice().iter().map(|value| *value as u64).sum()
+        };
+
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        app.update();
+        let before = total(&app, chunks[0]);
+        for _ in 0..4 {
+            app.world.resource_mut::<SimulationControl>().step_once();
+            app.update();
+        }
+        assert_eq!(total(&app, chunks[0]), before);
+        assert_eq!(total(&app, chunks[1]), 0);
+    }
+}
@@ -0,0 +1,122 @@
+#[cfg(feature = "render")]
+pub(super) use super::gpu::cpu_step_active;
+use bevy::prelude::*;
+
+/// Which implementation advances the automata each step.
+///
+/// Can be changed at any time, the next step runs on the new backend and a [`BackendChanged`]
+/// is sent. Backends keep no cells between steps (the GPU one uploads and reads back every
+/// chunk within the step) so nothing has to be migrated, and the GPU buffers are released
+/// when switching away from [`Self::Gpu`].
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SimulationBackend {
+    /// Steps the chunks one after the other on the main thread.
+    #[cfg_attr(not(feature = "parallel"), default)]
+    Cpu,
+    /// Spreads the chunks over the rayon thread pool. Runs as [`Self::Cpu`] without the
+    /// `parallel` feature.
+    #[cfg_attr(feature = "parallel", default)]
+    CpuParallel,
+    /// Runs the step in a compute shader, see `GpuAutomataPlugin`. Falls back to
+    /// [`Self::CpuParallel`] when the plugin or the `render` feature is missing, or the
+    /// rule/stepper combination is not supported on the GPU.
+    Gpu,
+}
+
+impl SimulationBackend {
+    /// Whether steps on the CPU spread over threads with this backend.
+    pub fn parallel(self) -> bool {
+        cfg!(feature = "parallel") && self != SimulationBackend::Cpu
+    }
+}
+
+/// Sent before the first step run by a newly selected [`SimulationBackend`].
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackendChanged {
+    pub previous: SimulationBackend,
+    pub backend: SimulationBackend,
+}
+
+pub(super) fn report_backend_changes(
+    backend: Res<SimulationBackend>,
+    mut previous: Local<Option<SimulationBackend>>,
+    mut changes: EventWriter<BackendChanged>,
+) {
+    let previous = previous.get_or_insert(*backend);
+    if *previous != *backend {
+        changes.send(BackendChanged {
+            previous: *previous,
+            backend: *backend,
+        });
+        *previous = *backend;
+    }
+}
+
+/// Run condition of the CPU step, always running without the GPU backend.
+#[cfg(not(feature = "render"))]
+pub(super) fn cpu_step_active() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AutomataState, CellularAutomataPlugin, ChunkBundle, ChunkCells, SimulationControl,
+    };
+
+    #[test]
+    fn switching_backends_is_reported_and_keeps_the_results() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(CellularAutomataPlugin);
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        let chunk = app
+            .world
+            .spawn(ChunkBundle::from_generator(IVec3::ZERO, |local| {
+                match (local.x * 7 + local.y * 3 + local.z) % 4 == 0 {
+                    true => AutomataState::new(1, 0),
+                    false => AutomataState::EMPTY,
+                }
+            }))
+            .id();
+        app.update();
+
+        // Each backend steps its own copy of the same cells, the GPU one falling back to the
+        // CPU without its plugin.
+        let initial = app.world.get::<ChunkCells>(chunk).unwrap().clone();
+        let mut results = Vec::new();
+        let mut previous = *app.world.resource::<SimulationBackend>();
+        for backend in [
+            SimulationBackend::Cpu,
+            SimulationBackend::CpuParallel,
+            SimulationBackend::Gpu,
+        ] {
+            *app.world.get_mut::<ChunkCells>(chunk).unwrap() = initial.clone();
+            app.world.insert_resource(backend);
+            app.world.resource_mut::<SimulationControl>().step_once();
+            app.update();
+
+            let changes = app.world.resource::<Events<BackendChanged>>();
+            let changes: Vec<_> = changes.iter_current_update_events().copied().collect();
+            let expected = BackendChanged { previous, backend };
+            let expected: Vec<_> = (previous != backend)
+                .then_some(expected)
+                .into_iter()
+                .collect();
+            assert_eq!(changes, expected);
+            previous = backend;
+            results.push(
+                app.world
+                    .get::<ChunkCells>(chunk)
+                    .unwrap()
+                    .as_slice()
+                    .into_owned(),
+            );
+        }
+
+        assert_ne!(results[0], initial.as_slice().into_owned());
+        assert_eq!(results[0], results[1]);
+        assert_eq!(results[0], results[2]);
+    }
+}
@@ -0,0 +1,199 @@
+use super::{border::BorderCache, AutomataRule, AutomataState, OccupancyMask, CHUNK_EDGE};
+use bevy::prelude::*;
+
+const EDGE: usize = CHUNK_EDGE as usize;
+/// Rows of the chunk plus the border on both sides, along X and Y.
+const ROWS: usize = EDGE + 2;
+
+/// Steps a chunk under a rule that only depends on the live neighbor total, 32 cells at a time.
+///
+/// Aliveness is packed into one bit per cell along Z, bit `z + 1` of each row so the border
+/// cells fit in the same word. The 26 neighbor masks of a row are summed with a bit-sliced
+/// adder, then the birth and survive counts are selected from the resulting bit planes.
+/// Matches [`step_chunk`](super::step_chunk) cell for cell. The rows inside the chunk come from
+/// `occupancy` when the snapshot has one, and are decoded from `current` otherwise.
+pub(super) fn step_chunk(
+    current: &[AutomataState],
+    occupancy: Option<&OccupancyMask>,
+    border: &BorderCache,
+    rule: &AutomataRule,
+    output: &mut [AutomataState],
+    parity: Option<i32>,
+) {
+    let rows = alive_rows(current, occupancy, border);
+    let row = |x: usize, y: usize| rows[x * ROWS + y];
+    let birth = count_set(&rule.birth);
+    let survive = count_set(&rule.survive);
+    let born = AutomataState::new(rule.birth_material, 0);
+
+    for x in 0..EDGE {
+        for y in 0..EDGE {
+            let mut planes = [0u32; 5];
+            for dx in 0..3 {
+                for dy in 0..3 {
+                    let neighbors = row(x + dx, y + dy);
+                    add(&mut planes, neighbors as u32);
+                    if dx != 1 || dy != 1 {
+                        add(&mut planes, (neighbors >> 1) as u32);
+                    }
+                    add(&mut planes, (neighbors >> 2) as u32);
+                }
+            }
+
+            let alive = (row(x + 1, y + 1) >> 1) as u32;
+            let next = (alive & select(&planes, survive)) | (!alive & select(&planes, birth));
+            // CHUNK_EDGE is even, so local parity matches world parity.
+            let updated = match parity {
+                Some(parity) if (x as i32 + y as i32 + parity) & 1 == 0 => 0x5555_5555,
+                Some(_) => 0xAAAA_AAAA,
+                None => u32::MAX,
+            };
+
+            let base = x * EDGE * EDGE + y * EDGE;
+            for z in 0..EDGE {
+                let bit = 1 << z;
+                if updated & bit == 0 {
+                    continue;
+                }
+                output[base + z] = match (next & bit != 0, alive & bit != 0) {
+                    (true, true) => current[base + z],
+                    (true, false) => born,
+                    (false, _) => AutomataState::EMPTY,
+                };
+            }
+        }
+    }
+}
+
+/// Aliveness of the chunk and its border, one row along Z per `(x + 1, y + 1)`.
+fn alive_rows(
+    current: &[AutomataState],
+    occupancy: Option<&OccupancyMask>,
+    border: &BorderCache,
+) -> Vec<u64> {
+    let inside = |value: i32| (0..CHUNK_EDGE).contains(&value);
+    let mut rows = vec![0u64; ROWS * ROWS];
+    for x in -1..=CHUNK_EDGE {
+        for y in -1..=CHUNK_EDGE {
+            let alive = |z: i32| {
+                let state = border.sample(current, IVec3::new(x, y, z));
+                state.is_some_and(|state| state.is_alive()) as u64
+            };
+            let row = match occupancy.filter(|_| inside(x) && inside(y)) {
+                // Only the two border cells of the row need sampling.
+                Some(occupancy) => {
+                    let cells = (occupancy.row(x, y) as u64) << 1;
+                    alive(-1) | cells | (alive(CHUNK_EDGE) << (EDGE + 1))
+                }
+                None => (-1..=CHUNK_EDGE).fold(0, |row, z| row | (alive(z) << (z + 1))),
+            };
+            rows[(x + 1) as usize * ROWS + (y + 1) as usize] = row;
+        }
+    }
+    rows
+}
+
+/// Adds one to the counter of every cell set in `mask`, bit `i` of plane `p` holding bit `p`
+/// of the count of cell `i`.
+#[inline]
+fn add(planes: &mut [u32; 5], mask: u32) {
+    let mut carry = mask;
+    for plane in planes.iter_mut() {
+        let sum = *plane ^ carry;
+        carry &= *plane;
+        *plane = sum;
+    }
+}
+
+/// Cells whose count is in `counts`, a set of neighbor totals with bit `n` for `n` neighbors.
+#[inline]
+fn select(planes: &[u32; 5], counts: u32) -> u32 {
+    let mut selected = 0;
+    let mut remaining = counts;
+    while remaining != 0 {
+        let count = remaining.trailing_zeros();
+        remaining &= remaining - 1;
+        selected |= planes
+            .iter()
+            .enumerate()
+            .fold(u32::MAX, |equal, (bit, plane)| match (count >> bit) & 1 {
+                1 => equal & plane,
+                _ => equal & !plane,
+            });
+    }
+    selected
+}
+
+fn count_set(counts: &[u8]) -> u32 {
+    counts
+        .iter()
+        .filter(|count| **count < 32)
+        .fold(0, |set, count| set | 1 << count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AutomataRuleSet, BoundaryMode, CellContext, ChunkSnapshots, MaterialTracker};
+
+    #[test]
+    fn bitmask_kernel_matches_scalar_step() {
+        let mut snapshots = ChunkSnapshots {
+            boundary: BoundaryMode::Dead,
+            ..default()
+        };
+        let chunks = [IVec3::ZERO, IVec3::X, IVec3::NEG_Y, IVec3::new(1, 1, -1)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, coords)| {
+                let cells = (0..EDGE * EDGE * EDGE)
+                    .map(|cell| {
+                        let hash = (cell as u32 + i as u32 * 40_503).wrapping_mul(0x9E37_79B9);
+                        match hash >> 29 {
+                            0 | 1 => AutomataState::new(2, 3),
+                            _ => AutomataState::EMPTY,
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                (coords, cells.into())
+            });
+        snapshots.rebuild(chunks);
+
+        let rule = AutomataRule::default();
+        // A closure is not totalistic, so the scalar path steps it.
+        let scalar = {
+            let rule = rule.clone();
+            move |ctx: &CellContext| AutomataRuleSet::next_state(&rule, ctx)
+        };
+        let current = snapshots.get(IVec3::ZERO).unwrap();
+        let border = BorderCache::new(&snapshots, IVec3::ZERO);
+        for parity in [None, Some(0), Some(1)] {
+            let mut expected = current.to_vec();
+            super::super::step_chunk(
+                current,
+                IVec3::ZERO,
+                &snapshots,
+                &scalar,
+                &MaterialTracker::default(),
+                &mut expected,
+                parity,
+                0,
+            );
+            let mut output = current.to_vec();
+            step_chunk(current, None, &border, &rule, &mut output, parity);
+            assert_eq!(output, expected);
+
+            let occupancy = OccupancyMask::from_cells(current.iter().copied());
+            let mut output = current.to_vec();
+            step_chunk(
+                current,
+                Some(&occupancy),
+                &border,
+                &rule,
+                &mut output,
+                parity,
+            );
+            assert_eq!(output, expected);
+        }
+    }
+}
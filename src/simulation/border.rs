@@ -0,0 +1,146 @@
+use super::{linear_index, sample_cell, AutomataState, ChunkSnapshots, CHUNK_EDGE};
+use bevy::prelude::*;
+
+const EDGE: usize = CHUNK_EDGE as usize;
+
+/// Cells bordering a chunk, copied from the snapshots of its neighbors once per step.
+///
+/// Holds the 6 faces, 12 edges and 8 corners one cell outside the chunk, so stepping a chunk
+/// reads its neighbors without going through the snapshot map for every sample. `None` marks
+/// cells of unloaded chunks, after applying the [`BoundaryMode`](super::BoundaryMode).
+pub(super) struct BorderCache {
+    /// `-X, +X, -Y, +Y, -Z, +Z`, each indexed by the remaining axes in order.
+    faces: Box<[Option<AutomataState>]>,
+    /// Four edges along each axis, indexed by the sides of the two other axes.
+    edges: Box<[Option<AutomataState>]>,
+    /// Indexed by `x | y << 1 | z << 2` of the sides.
+    corners: [Option<AutomataState>; 8],
+}
+
+enum Slot {
+    Face(usize),
+    Edge(usize),
+    Corner(usize),
+}
+
+impl BorderCache {
+    pub fn new(snapshots: &ChunkSnapshots, coords: IVec3) -> Self {
+        let mut cache = Self {
+            faces: vec![None; 6 * EDGE * EDGE].into(),
+            edges: vec![None; 12 * EDGE].into(),
+            corners: [None; 8],
+        };
+
+        let outside = |value: i32| value == -1 || value == CHUNK_EDGE;
+        for x in -1..=CHUNK_EDGE {
+            for y in -1..=CHUNK_EDGE {
+                // Rows inside the chunk on both other axes only cross the two `z` faces.
+                let z_step = match outside(x) || outside(y) {
+                    true => 1,
+                    false => EDGE + 1,
+                };
+                for z in (-1..=CHUNK_EDGE).step_by(z_step) {
+                    let local = IVec3::new(x, y, z);
+                    let Some(slot) = Self::slot(local) else {
+                        continue;
+                    };
+                    let state = sample_cell(snapshots, coords, local);
+                    match slot {
+                        Slot::Face(index) => cache.faces[index] = state,
+                        Slot::Edge(index) => cache.edges[index] = state,
+                        Slot::Corner(index) => cache.corners[index] = state,
+                    }
+                }
+            }
+        }
+        cache
+    }
+
+    /// Cell at `local`, read from `chunk` inside of it and from the border one cell around it.
+    #[inline]
+    pub fn sample(&self, chunk: &[AutomataState], local: IVec3) -> Option<AutomataState> {
+        let inside = local.cmpge(IVec3::ZERO).all() && local.cmplt(IVec3::splat(CHUNK_EDGE)).all();
+        match inside {
+            true => Some(chunk[linear_index(local)]),
+            false => self.get(local),
+        }
+    }
+
+    /// Cell at `local`, which must lie one cell outside the chunk on at least one axis.
+    #[inline]
+    pub fn get(&self, local: IVec3) -> Option<AutomataState> {
+        match Self::slot(local) {
+            Some(Slot::Face(index)) => self.faces[index],
+            Some(Slot::Edge(index)) => self.edges[index],
+            Some(Slot::Corner(index)) => self.corners[index],
+            None => None,
+        }
+    }
+
+    #[inline]
+    fn slot(local: IVec3) -> Option<Slot> {
+        let side = |value: i32| match value {
+            -1 => Some(0),
+            CHUNK_EDGE => Some(1),
+            _ => None,
+        };
+        let sides = local.to_array().map(side);
+        let inner = local.to_array().map(|value| value as usize);
+
+        match sides {
+            [None, None, None] => None,
+            [Some(s), None, None] => Some(Slot::Face(s * EDGE * EDGE + inner[1] * EDGE + inner[2])),
+            [None, Some(s), None] => Some(Slot::Face(
+                (2 + s) * EDGE * EDGE + inner[0] * EDGE + inner[2],
+            )),
+            [None, None, Some(s)] => Some(Slot::Face(
+                (4 + s) * EDGE * EDGE + inner[0] * EDGE + inner[1],
+            )),
+            [None, Some(a), Some(b)] => Some(Slot::Edge((a * 2 + b) * EDGE + inner[0])),
+            [Some(a), None, Some(b)] => Some(Slot::Edge((4 + a * 2 + b) * EDGE + inner[1])),
+            [Some(a), Some(b), None] => Some(Slot::Edge((8 + a * 2 + b) * EDGE + inner[2])),
+            [Some(x), Some(y), Some(z)] => Some(Slot::Corner(x | y << 1 | z << 2)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoundaryMode;
+
+    #[test]
+    fn border_matches_sampled_neighbors() {
+        let mut snapshots = ChunkSnapshots {
+            boundary: BoundaryMode::Dead,
+            ..default()
+        };
+        let neighbors = (-1..=1)
+            .flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| IVec3::new(x, y, z))))
+            .filter(|offset| *offset != IVec3::ZERO && *offset != IVec3::X)
+            .enumerate()
+            .map(|(i, offset)| {
+                let cells = (0..EDGE * EDGE * EDGE)
+                    .map(|cell| AutomataState::new((i + cell % 7) as u8, 0))
+                    .collect::<Vec<_>>();
+                (offset, cells.into())
+            });
+        snapshots.rebuild(neighbors);
+
+        let cache = BorderCache::new(&snapshots, IVec3::ZERO);
+        for x in -1..=CHUNK_EDGE {
+            for y in -1..=CHUNK_EDGE {
+                for z in -1..=CHUNK_EDGE {
+                    let local = IVec3::new(x, y, z);
+                    if BorderCache::slot(local).is_some() {
+                        assert_eq!(
+                            cache.get(local),
+                            sample_cell(&snapshots, IVec3::ZERO, local)
+                        );
+                    }
+                }
+            }
+        }
+        assert_eq!(cache.get(IVec3::new(CHUNK_EDGE, 3, 4)), None);
+    }
+}
@@ -0,0 +1,81 @@
+use super::{AutomataState, CHUNK_EDGE};
+use bevy::prelude::*;
+
+/// How cells see neighbors beyond the edge of the world.
+///
+/// Consulted for every neighbor outside the cell's own chunk, by all steppers and the GPU halo.
+/// Bounds are given in chunks.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BoundaryMode {
+    /// Neighbors in unloaded chunks are missing, which rules count as dead.
+    #[default]
+    Dead,
+    /// Neighbors in unloaded chunks have the given state.
+    Alive(AutomataState),
+    /// Neighbors past the edge of the box wrap around to the opposite side, making a finite
+    /// toroidal world.
+    Wrap { min: IVec3, size: IVec3 },
+    /// Neighbors past the edge of the box are reflected back inside it.
+    Mirror { min: IVec3, size: IVec3 },
+}
+
+impl BoundaryMode {
+    /// Voxel actually read in place of `voxel`.
+    pub fn map_voxel(&self, voxel: IVec3) -> IVec3 {
+        match *self {
+            BoundaryMode::Wrap { min, size } => {
+                let min = min * CHUNK_EDGE;
+                let size = (size * CHUNK_EDGE).max(IVec3::ONE);
+                min + (voxel - min).rem_euclid(size)
+            }
+            BoundaryMode::Mirror { min, size } => {
+                let min = min * CHUNK_EDGE;
+                let max = min + size * CHUNK_EDGE;
+                let below = voxel.cmplt(min);
+                let above = voxel.cmpge(max);
+                let voxel = IVec3::select(below, min * 2 - voxel - IVec3::ONE, voxel);
+                IVec3::select(above, max * 2 - voxel - IVec3::ONE, voxel)
+            }
+            BoundaryMode::Dead | BoundaryMode::Alive(_) => voxel,
+        }
+    }
+
+    /// State of a neighbor whose chunk is not loaded.
+    #[inline]
+    pub fn unloaded_state(&self) -> Option<AutomataState> {
+        match *self {
+            BoundaryMode::Alive(state) => Some(state),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_and_mirror_stay_inside_the_box() {
+        let size = IVec3::splat(2);
+        let edge = CHUNK_EDGE * 2;
+
+        let wrap = BoundaryMode::Wrap {
+            min: IVec3::ZERO,
+            size,
+        };
+        assert_eq!(
+            wrap.map_voxel(IVec3::new(-1, edge, 3)),
+            IVec3::new(edge - 1, 0, 3)
+        );
+
+        let mirror = BoundaryMode::Mirror {
+            min: IVec3::ZERO,
+            size,
+        };
+        assert_eq!(
+            mirror.map_voxel(IVec3::new(-1, edge, 3)),
+            IVec3::new(0, edge - 1, 3)
+        );
+        assert_eq!(BoundaryMode::Dead.map_voxel(IVec3::NEG_ONE), IVec3::NEG_ONE);
+    }
+}
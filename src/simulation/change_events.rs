@@ -0,0 +1,142 @@
+use super::{AutomataState, ChunkCells, ChunkCellsNext, ChunkKey, CHUNK_EDGE};
+use bevy::prelude::*;
+use std::sync::Mutex;
+
+/// Enables [`VoxelChanged`] events, for effects such as particles, audio or colliders that need
+/// to know exactly which voxels a step changed.
+///
+/// A busy step can change many voxels, so this is disabled by default.
+/// [`ChunkChanges`](super::ChunkChanges) is a cheaper per brick alternative.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct VoxelChangeEvents {
+    pub enabled: bool,
+}
+
+/// Sent after a step is applied for every chunk whose cells changed since the previous step,
+/// by the step or by edits, so renderers and GPU uploads only touch those chunks. Chunks spawned
+/// since the previous step are included.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkUpdated(pub Entity, pub IVec3);
+
+/// Sent for every voxel a simulation step changed, before the step is applied.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoxelChanged {
+    pub chunk: IVec3,
+    pub local: IVec3,
+    pub prev: AutomataState,
+    pub next: AutomataState,
+}
+
+impl VoxelChanged {
+    #[inline]
+    pub fn voxel(&self) -> IVec3 {
+        self.chunk * CHUNK_EDGE + self.local
+    }
+
+    /// Whether the voxel went from empty to a material.
+    #[inline]
+    pub fn is_birth(&self) -> bool {
+        !self.prev.is_alive() && self.next.is_alive()
+    }
+
+    /// Whether the voxel went from a material to empty.
+    #[inline]
+    pub fn is_death(&self) -> bool {
+        self.prev.is_alive() && !self.next.is_alive()
+    }
+}
+
+pub(super) fn voxel_change_events_enabled(settings: Res<VoxelChangeEvents>) -> bool {
+    settings.enabled
+}
+
+/// Diffs the chunks across the compute task pool, then sends their changes in chunk coordinate
+/// order so the events do not depend on the scheduling.
+pub(super) fn send_voxel_changes(
+    mut changed: EventWriter<VoxelChanged>,
+    chunks: Query<(&ChunkKey, &ChunkCells, &ChunkCellsNext)>,
+) {
+    let batches = Mutex::new(Vec::new());
+    chunks.par_iter().for_each(|(key, cells, next)| {
+        let batch = chunk_changes(key.coords, cells, next);
+        if !batch.is_empty() {
+            batches.lock().unwrap().push(batch);
+        }
+    });
+
+    let mut batches = batches.into_inner().unwrap();
+    batches.sort_unstable_by_key(|batch| batch[0].chunk.to_array());
+    changed.send_batch(batches.into_iter().flatten());
+}
+
+fn chunk_changes(coords: IVec3, cells: &ChunkCells, next: &ChunkCellsNext) -> Vec<VoxelChanged> {
+    let edge = CHUNK_EDGE as usize;
+    let pairs = cells.storage().iter().zip(next.as_slice().iter().copied());
+    pairs
+        .enumerate()
+        .filter(|(_, (prev, next))| prev != next)
+        .map(|(index, (prev, next))| VoxelChanged {
+            chunk: coords,
+            local: IVec3::new(
+                (index / (edge * edge)) as i32,
+                ((index / edge) % edge) as i32,
+                (index % edge) as i32,
+            ),
+            prev,
+            next,
+        })
+        .collect()
+}
+
+pub(super) fn send_chunk_updates(
+    mut updated: EventWriter<ChunkUpdated>,
+    chunks: Query<(Entity, &ChunkKey), Changed<ChunkCells>>,
+) {
+    updated.send_batch(
+        chunks
+            .iter()
+            .map(|(entity, key)| ChunkUpdated(entity, key.coords)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AutomataRule, CellularAutomataPlugin, ChunkBundle, SimulationControl};
+
+    #[test]
+    fn voxel_changes_are_sent_in_chunk_order() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(CellularAutomataPlugin)
+            .insert_resource(VoxelChangeEvents { enabled: true })
+            // Every live cell dies.
+            .insert_resource(AutomataRule {
+                birth: Vec::new(),
+                survive: Vec::new(),
+                ..default()
+            });
+        let alive = AutomataState::new(1, 0);
+        for x in (-2..2).rev() {
+            app.world
+                .spawn(ChunkBundle::from_generator(IVec3::X * x, |local| {
+                    match local.y == 0 && local.z < 2 {
+                        true => alive,
+                        false => AutomataState::EMPTY,
+                    }
+                }));
+        }
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        app.update();
+        app.world.resource_mut::<SimulationControl>().step_once();
+        app.update();
+
+        let events = app.world.resource::<Events<VoxelChanged>>();
+        let changes: Vec<_> = events.iter_current_update_events().copied().collect();
+        assert_eq!(changes.len(), 4 * CHUNK_EDGE as usize * 2);
+        assert!(changes.iter().all(|change| change.is_death()));
+        assert!(changes
+            .windows(2)
+            .all(|pair| pair[0].chunk.x <= pair[1].chunk.x));
+    }
+}
@@ -0,0 +1,603 @@
+use super::{
+    edit::{apply_voxel_edits, write_chunk_edits},
+    AutomataRule, AutomataState, BoundaryMode, ChunkCells, ChunkIndex, MaterialClass,
+    MaterialProperties, MaterialRegistry, Neighborhood, PendingRule, RuleParseError, CHUNK_EDGE,
+};
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Loads `.casim.ron` files as [`SimulationConfig`] assets and applies the
+/// [`ActiveSimulationConfig`] to the running simulation.
+///
+/// The config is applied when it finishes loading, when the active handle changes and every
+/// time the file is reloaded, so with asset hot reloading editing the file re-rules and
+/// re-seeds the simulation live. A file that fails to parse is reported by the asset server
+/// and the simulation keeps the last config.
+pub struct SimulationConfigPlugin;
+
+impl Plugin for SimulationConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MaterialRegistry>()
+            .init_asset::<SimulationConfig>()
+            .register_asset_loader(SimulationConfigLoader)
+            .add_systems(Update, apply_simulation_config);
+    }
+}
+
+/// Handle of the [`SimulationConfig`] the [`SimulationConfigPlugin`] applies.
+#[derive(Resource, Debug, Clone)]
+pub struct ActiveSimulationConfig(pub Handle<SimulationConfig>);
+
+/// Rule, materials, seed patterns and bounds of a simulation, written in RON:
+///
+/// ```ron
+/// (
+///     rule: "B5/S45",
+///     neighborhood: Some(VonNeumann),
+///     materials: [(id: 1, name: "moss", color: Some("#4a7a32"))],
+///     seeds: [Random(min: (0, 0, 0), max: (32, 32, 32), material: 1, density: 0.3, seed: 7)],
+///     bounds: Some((min: (0, 0, 0), size: (2, 2, 2), edge: Wrap)),
+/// )
+/// ```
+///
+/// See [`SimulationConfig::apply`] for what applying it changes.
+#[derive(Asset, TypePath, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulationConfig {
+    /// Rule in the notation of [`AutomataRule::parse`].
+    pub rule: String,
+    /// Replaces the neighborhood written in the rule.
+    #[serde(default)]
+    pub neighborhood: Option<NeighborhoodConfig>,
+    /// Material of the cells the rule gives birth to.
+    #[serde(default)]
+    pub birth_material: Option<u8>,
+    #[serde(default)]
+    pub materials: Vec<MaterialConfig>,
+    /// Cells written when the config is applied, in order.
+    #[serde(default)]
+    pub seeds: Vec<SeedRegion>,
+    #[serde(default)]
+    pub bounds: Option<WorldBounds>,
+}
+
+impl SimulationConfig {
+    /// Parses a config written in RON, checking that its rule and colors are valid.
+    pub fn parse(text: &str) -> Result<Self, SimulationConfigError> {
+        let config: SimulationConfig = ron::de::from_str(text)?;
+        config.rule()?;
+        for material in &config.materials {
+            material.properties()?;
+        }
+        Ok(config)
+    }
+
+    /// Queues the rule as the [`PendingRule`], sets the listed materials in the
+    /// [`MaterialRegistry`] and writes the seeds as voxel edits. With bounds, the
+    /// [`BoundaryMode`] is replaced, every chunk inside them is loaded and their live cells are
+    /// cleared before seeding.
+    ///
+    /// Applied by the [`SimulationConfigPlugin`], and usable directly by headless runs that do
+    /// not go through the asset server. Nothing is changed when the rule or a color is invalid.
+    pub fn apply(&self, world: &mut World) -> Result<(), SimulationConfigError> {
+        let rule = self.rule()?;
+        let materials = self
+            .materials
+            .iter()
+            .map(|material| Ok((material.id, material.properties()?)))
+            .collect::<Result<Vec<_>, SimulationConfigError>>()?;
+
+        world.resource_mut::<PendingRule>().set(rule);
+        let mut registry = world.get_resource_or_insert_with(MaterialRegistry::default);
+        for (id, properties) in materials {
+            registry.set(id, properties);
+        }
+
+        let mut edits = HashMap::new();
+        if let Some(bounds) = &self.bounds {
+            world.insert_resource(bounds.boundary_mode());
+            let (min, size) = (IVec3::from(bounds.min), IVec3::from(bounds.size));
+            for x in 0..size.x {
+                for y in 0..size.y {
+                    for z in 0..size.z {
+                        let coords = min + IVec3::new(x, y, z);
+                        let cells = world
+                            .resource::<ChunkIndex>()
+                            .entity(coords)
+                            .and_then(|entity| world.get::<ChunkCells>(entity));
+                        let Some(cells) = cells else {
+                            write_chunk_edits(world, coords, Vec::new());
+                            continue;
+                        };
+                        for (cell, state) in cells.storage().iter().enumerate() {
+                            if state.is_alive() {
+                                let cell = cell as i32;
+                                let local = IVec3::new(
+                                    cell / (CHUNK_EDGE * CHUNK_EDGE),
+                                    cell / CHUNK_EDGE % CHUNK_EDGE,
+                                    cell % CHUNK_EDGE,
+                                );
+                                edits.insert(coords * CHUNK_EDGE + local, AutomataState::EMPTY);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        for seed in &self.seeds {
+            edits.extend(seed.cells());
+        }
+        apply_voxel_edits(world, edits.into_iter().collect());
+        Ok(())
+    }
+
+    /// The rule with the neighborhood and birth material of the config applied.
+    pub fn rule(&self) -> Result<AutomataRule, SimulationConfigError> {
+        let mut rule = AutomataRule::parse(&self.rule).map_err(SimulationConfigError::Rule)?;
+        if let Some(neighborhood) = &self.neighborhood {
+            rule.neighborhood = neighborhood.to_neighborhood();
+            rule.neighborhood
+                .validate()
+                .map_err(SimulationConfigError::Rule)?;
+        }
+        if let Some(material) = self.birth_material {
+            rule.birth_material = material;
+        }
+        Ok(rule)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NeighborhoodConfig {
+    Moore,
+    VonNeumann,
+    /// Offsets of the counted cells, see [`Neighborhood::Custom`].
+    Custom(Vec<[i32; 3]>),
+}
+
+impl NeighborhoodConfig {
+    pub fn to_neighborhood(&self) -> Neighborhood {
+        match self {
+            NeighborhoodConfig::Moore => Neighborhood::Moore,
+            NeighborhoodConfig::VonNeumann => Neighborhood::VonNeumann,
+            NeighborhoodConfig::Custom(offsets) => {
+                Neighborhood::Custom(offsets.iter().map(|offset| IVec3::from(*offset)).collect())
+            }
+        }
+    }
+}
+
+/// A material of the [`MaterialRegistry`], fields other than `id` missing from the file keeping
+/// the [`MaterialProperties`] defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaterialConfig {
+    /// Material set in the registry, `0` being the empty cell it is rejected.
+    pub id: u8,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub class: MaterialClass,
+    #[serde(default = "default_density")]
+    pub density: f32,
+    /// Hexadecimal color, `None` keeps the color of the palette.
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub emissive: f32,
+    #[serde(default)]
+    pub smooth: bool,
+    #[serde(default = "default_conductivity")]
+    pub conductivity: u8,
+    #[serde(default)]
+    pub ignition: Option<u16>,
+    #[serde(default)]
+    pub burn_heat: u16,
+    #[serde(default)]
+    pub burn_steps: u8,
+    /// Material left behind by a burned out voxel, `0` for empty.
+    #[serde(default)]
+    pub burns_into: u8,
+}
+
+fn default_density() -> f32 {
+    MaterialProperties::default().density
+}
+
+fn default_conductivity() -> u8 {
+    MaterialProperties::default().conductivity
+}
+
+impl Default for MaterialConfig {
+    fn default() -> Self {
+        let properties = MaterialProperties::default();
+        Self {
+            id: 0,
+            name: properties.name,
+            class: properties.class,
+            density: properties.density,
+            color: None,
+            emissive: properties.emissive,
+            smooth: properties.smooth,
+            conductivity: properties.conductivity,
+            ignition: properties.ignition,
+            burn_heat: properties.burn_heat,
+            burn_steps: properties.burn_steps,
+            burns_into: properties.burns_into.material,
+        }
+    }
+}
+
+impl MaterialConfig {
+    pub fn properties(&self) -> Result<MaterialProperties, SimulationConfigError> {
+        if self.id == 0 {
+            return Err(SimulationConfigError::EmptyMaterial);
+        }
+        #[cfg(feature = "render")]
+        let color = match &self.color {
+            Some(color) => {
+                Some(Color::hex(color).map_err(|_| SimulationConfigError::InvalidColor(self.id))?)
+            }
+            None => None,
+        };
+        Ok(MaterialProperties {
+            name: self.name.clone(),
+            class: self.class,
+            density: self.density,
+            #[cfg(feature = "render")]
+            color,
+            emissive: self.emissive,
+            smooth: self.smooth,
+            conductivity: self.conductivity,
+            ignition: self.ignition,
+            burn_heat: self.burn_heat,
+            burn_steps: match self.ignition {
+                Some(_) => self.burn_steps.max(1),
+                None => self.burn_steps,
+            },
+            burns_into: AutomataState::new(self.burns_into, 0),
+        })
+    }
+}
+
+/// Cells written when a [`SimulationConfig`] is applied. Boxes are given in voxels and span
+/// `min..max`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SeedRegion {
+    /// Fills the box with `material`.
+    Fill {
+        min: [i32; 3],
+        max: [i32; 3],
+        material: u8,
+    },
+    /// Gives each voxel of the box `material` with probability `density`, the same voxels for
+    /// the same `seed`.
+    Random {
+        min: [i32; 3],
+        max: [i32; 3],
+        material: u8,
+        density: f32,
+        seed: u64,
+    },
+    /// Gives the listed voxels `material`.
+    Voxels { material: u8, voxels: Vec<[i32; 3]> },
+}
+
+impl SeedRegion {
+    /// The voxels written by the pattern.
+    pub fn cells(&self) -> Vec<(IVec3, AutomataState)> {
+        match self {
+            SeedRegion::Fill { min, max, material } => {
+                let state = AutomataState::new(*material, 0);
+                box_voxels(*min, *max).map(|voxel| (voxel, state)).collect()
+            }
+            SeedRegion::Random {
+                min,
+                max,
+                material,
+                density,
+                seed,
+            } => {
+                let state = AutomataState::new(*material, 0);
+                let threshold = (density.clamp(0.0, 1.0) as f64 * u32::MAX as f64) as u32;
+                box_voxels(*min, *max)
+                    .filter(|voxel| seed_hash(*voxel, *seed) < threshold)
+                    .map(|voxel| (voxel, state))
+                    .collect()
+            }
+            SeedRegion::Voxels { material, voxels } => {
+                let state = AutomataState::new(*material, 0);
+                voxels
+                    .iter()
+                    .map(|voxel| (IVec3::from(*voxel), state))
+                    .collect()
+            }
+        }
+    }
+}
+
+fn box_voxels(min: [i32; 3], max: [i32; 3]) -> impl Iterator<Item = IVec3> {
+    let (min, max) = (IVec3::from(min), IVec3::from(max));
+    (min.x..max.x).flat_map(move |x| {
+        (min.y..max.y).flat_map(move |y| (min.z..max.z).map(move |z| IVec3::new(x, y, z)))
+    })
+}
+
+/// Integer hash of a voxel and a seed, so seeding does not depend on the platform.
+fn seed_hash(voxel: IVec3, seed: u64) -> u32 {
+    let mut hash = (voxel.x as u32).wrapping_mul(73_856_093)
+        ^ (voxel.y as u32).wrapping_mul(19_349_663)
+        ^ (voxel.z as u32).wrapping_mul(83_492_791)
+        ^ (seed as u32).wrapping_mul(0x9e37_79b9)
+        ^ ((seed >> 32) as u32).wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x7feb_352d);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x846c_a68b);
+    hash ^ (hash >> 16)
+}
+
+/// Box of chunks the simulation is set up in, and how cells see past its edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorldBounds {
+    /// First chunk of the box.
+    pub min: [i32; 3],
+    /// Size of the box in chunks.
+    pub size: [i32; 3],
+    #[serde(default)]
+    pub edge: BoundsEdge,
+}
+
+/// [`BoundaryMode`] set by [`WorldBounds`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BoundsEdge {
+    #[default]
+    Dead,
+    Wrap,
+    Mirror,
+}
+
+impl WorldBounds {
+    pub fn boundary_mode(&self) -> BoundaryMode {
+        let (min, size) = (IVec3::from(self.min), IVec3::from(self.size));
+        match self.edge {
+            BoundsEdge::Dead => BoundaryMode::Dead,
+            BoundsEdge::Wrap => BoundaryMode::Wrap { min, size },
+            BoundsEdge::Mirror => BoundaryMode::Mirror { min, size },
+        }
+    }
+}
+
+/// Why a `.casim.ron` file could not be loaded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulationConfigError {
+    /// The file could not be read.
+    Io(String),
+    /// The file is not a valid RON config.
+    Syntax(String),
+    Rule(RuleParseError),
+    /// The color of the material is not a hexadecimal color.
+    InvalidColor(u8),
+    /// A material has id `0`, which is the empty cell.
+    EmptyMaterial,
+}
+
+impl fmt::Display for SimulationConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulationConfigError::Io(error) => {
+                write!(f, "could not read simulation config: {}", error)
+            }
+            SimulationConfigError::Syntax(error) => {
+                write!(f, "invalid simulation config: {}", error)
+            }
+            SimulationConfigError::Rule(error) => write!(f, "invalid rule: {}", error),
+            SimulationConfigError::InvalidColor(material) => {
+                write!(f, "invalid color for material {}", material)
+            }
+            SimulationConfigError::EmptyMaterial => {
+                write!(f, "material 0 is the empty cell and cannot be configured")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SimulationConfigError {}
+
+impl From<std::io::Error> for SimulationConfigError {
+    fn from(error: std::io::Error) -> Self {
+        SimulationConfigError::Io(error.to_string())
+    }
+}
+
+impl From<ron::error::SpannedError> for SimulationConfigError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        SimulationConfigError::Syntax(error.to_string())
+    }
+}
+
+/// Loads `.casim.ron` files as [`SimulationConfig`] assets.
+struct SimulationConfigLoader;
+
+impl AssetLoader for SimulationConfigLoader {
+    type Asset = SimulationConfig;
+    type Settings = ();
+    type Error = SimulationConfigError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<SimulationConfig, SimulationConfigError>> {
+        Box::pin(async move {
+            let mut text = String::new();
+            reader.read_to_string(&mut text).await?;
+            SimulationConfig::parse(&text)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["casim.ron"]
+    }
+}
+
+fn apply_simulation_config(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<SimulationConfig>>,
+    configs: Res<Assets<SimulationConfig>>,
+    active: Option<Res<ActiveSimulationConfig>>,
+) {
+    let reloaded: Vec<_> = events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    let Some(active) = active else {
+        return;
+    };
+    if !active.is_changed() && !reloaded.contains(&active.0.id()) {
+        return;
+    }
+    let Some(config) = configs.get(&active.0).cloned() else {
+        return;
+    };
+    // Checked by the loader.
+    commands.add(move |world: &mut World| {
+        let _ = config.apply(world);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CellularAutomataPlugin, ChunkBundle, ChunkKey, SimulationControl};
+
+    #[test]
+    fn configs_apply_to_the_world() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(CellularAutomataPlugin)
+            // Every cell survives and none is born.
+            .insert_resource(AutomataRule {
+                birth: Vec::new(),
+                survive: (0..=26).collect(),
+                ..default()
+            });
+        let stale = app
+            .world
+            .spawn(ChunkBundle::from_generator(IVec3::ZERO, |_| {
+                AutomataState::new(2, 0)
+            }))
+            .id();
+        // Steps once so the chunk is indexed.
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        app.update();
+        app.world.resource_mut::<SimulationControl>().step_once();
+        app.update();
+
+        let config = SimulationConfig::parse(
+            r#"(
+                rule: "B4/S4",
+                materials: [(id: 3, name: "wood")],
+                seeds: [Fill(min: (0, 0, 0), max: (2, 2, 2), material: 3)],
+                bounds: Some((min: (0, 0, 0), size: (2, 1, 1), edge: Wrap)),
+            )"#,
+        )
+        .unwrap();
+        config.apply(&mut app.world).unwrap();
+
+        let pending = app.world.resource::<PendingRule>().get().cloned();
+        assert_eq!(pending, Some(config.rule().unwrap()));
+        assert_eq!(app.world.resource::<MaterialRegistry>().get(3).name, "wood");
+        assert_eq!(
+            *app.world.resource::<BoundaryMode>(),
+            config.bounds.as_ref().unwrap().boundary_mode()
+        );
+        // The stale cells are cleared, the seed written and the rest of the bounds loaded.
+        let cells = app.world.get::<ChunkCells>(stale).unwrap();
+        assert_eq!(cells.get(IVec3::ONE), AutomataState::new(3, 0));
+        assert!(!cells.get(IVec3::splat(2)).is_alive());
+        let chunks = app.world.query::<&ChunkKey>().iter(&app.world).count();
+        assert_eq!(chunks, 2);
+        assert!(app
+            .world
+            .resource::<ChunkIndex>()
+            .entity(IVec3::X)
+            .is_some());
+    }
+
+    #[test]
+    fn configs_parse_from_ron() {
+        let config = SimulationConfig::parse(
+            r##"(
+                rule: "B4/S4/5",
+                neighborhood: Some(Custom([(1, 0, 0), (-1, 0, 0)])),
+                materials: [(id: 3, name: "wood", color: Some("#8b5a2b"), ignition: Some(200))],
+                seeds: [
+                    Fill(min: (0, 0, 0), max: (2, 2, 2), material: 3),
+                    Random(min: (0, 0, 0), max: (16, 16, 16), material: 1, density: 0.5, seed: 9),
+                ],
+                bounds: Some((min: (0, 0, 0), size: (1, 1, 1), edge: Wrap)),
+            )"##,
+        )
+        .unwrap();
+
+        let rule = config.rule().unwrap();
+        assert_eq!(rule.states, 5);
+        assert_eq!(
+            rule.neighborhood,
+            Neighborhood::Custom(vec![IVec3::X, IVec3::NEG_X])
+        );
+        let wood = config.materials[0].properties().unwrap();
+        assert_eq!(wood.name, "wood");
+        assert_eq!(wood.burn_steps, 1);
+        assert_eq!(
+            wood.conductivity,
+            MaterialProperties::default().conductivity
+        );
+        assert_eq!(config.seeds[0].cells().len(), 8);
+        assert_eq!(
+            SimulationConfig::parse(r#"(rule: "B4/S4", neighborhood: Some(Custom([(2, 0, 0)])))"#),
+            Err(SimulationConfigError::Rule(
+                RuleParseError::OffsetOutOfRange(IVec3::new(2, 0, 0))
+            ))
+        );
+        let random = config.seeds[1].cells();
+        assert!(random.len() > 1000 && random.len() < 3000);
+        assert_eq!(random, config.seeds[1].cells());
+        assert_eq!(
+            config.bounds.unwrap().boundary_mode(),
+            BoundaryMode::Wrap {
+                min: IVec3::ZERO,
+                size: IVec3::ONE
+            }
+        );
+
+        assert!(matches!(
+            SimulationConfig::parse(r#"(rule: "B5")"#),
+            Err(SimulationConfigError::Rule(RuleParseError::Missing('S')))
+        ));
+        assert!(matches!(
+            SimulationConfig::parse("(rule: 5)"),
+            Err(SimulationConfigError::Syntax(_))
+        ));
+    }
+
+    #[test]
+    fn materials_need_a_live_id() {
+        assert!(matches!(
+            SimulationConfig::parse(r#"(rule: "B4/S4", materials: [(name: "wood")])"#),
+            Err(SimulationConfigError::Syntax(_))
+        ));
+        assert_eq!(
+            SimulationConfig::parse(r#"(rule: "B4/S4", materials: [(id: 0, name: "wood")])"#),
+            Err(SimulationConfigError::EmptyMaterial)
+        );
+    }
+}
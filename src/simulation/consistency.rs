@@ -0,0 +1,181 @@
+use super::{
+    active_rule,
+    stepper::{release_sources, StepSource},
+    AutomataRule, AutomataState, AutomataStepper, BoxedRule, BufferPool, ChunkCells,
+    ChunkCellsNext, ChunkKey, ChunkLod, ChunkSnapshots, SimulationBackend, SimulationClock,
+    CHUNK_EDGE,
+};
+use bevy::prelude::*;
+
+/// Verification mode that re-runs the CPU stepper after every step and diffs its output
+/// against the cells the active backend wrote to [`ChunkCellsNext`].
+///
+/// Meant for catching divergence between the WGSL and Rust implementations of a rule. It
+/// doubles the cost of a step, so it is disabled by default.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct ConsistencyCheck {
+    pub enabled: bool,
+}
+
+/// Sent when the backend output of a step differs from the CPU reference.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConsistencyMismatch {
+    /// Step the mismatch was produced by.
+    pub step: u64,
+    /// First mismatching voxel, chunks being visited in coordinate order.
+    pub voxel: IVec3,
+    /// State computed by the CPU reference.
+    pub expected: AutomataState,
+    /// State written by the backend.
+    pub actual: AutomataState,
+    /// Number of voxels that differ over all chunks.
+    pub mismatched_voxels: usize,
+}
+
+/// Index of the first cell that differs between two buffers.
+pub fn first_mismatch(expected: &[AutomataState], actual: &[AutomataState]) -> Option<usize> {
+    expected.iter().zip(actual).position(|(a, b)| a != b)
+}
+
+pub(super) fn consistency_enabled(check: Res<ConsistencyCheck>) -> bool {
+    check.enabled
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn check_consistency(
+    snapshots: Res<ChunkSnapshots>,
+    rule: Res<AutomataRule>,
+    boxed_rule: Option<Res<BoxedRule>>,
+    stepper: Res<AutomataStepper>,
+    backend: Res<SimulationBackend>,
+    clock: Res<SimulationClock>,
+    pool: Res<BufferPool>,
+    query: Query<(
+        Entity,
+        &ChunkKey,
+        &ChunkCells,
+        &ChunkCellsNext,
+        Option<&ChunkLod>,
+    )>,
+    mut mismatches: EventWriter<ConsistencyMismatch>,
+) {
+    let rule = active_rule(&rule, boxed_rule.as_deref());
+    let tracker = rule.tracker();
+    let mut sources: Vec<_> = query
+        .iter()
+        .filter(|(.., lod)| lod.is_none_or(|lod| lod.steps_cells_on(clock.step)))
+        .map(|(entity, key, cells, ..)| {
+            StepSource::new(entity, key.coords, &snapshots, cells, &pool)
+        })
+        .collect();
+    sources.sort_by_key(|source| source.coords.to_array());
+
+    let mut first = None;
+    let mut mismatched_voxels = 0;
+    let parallel = backend.parallel();
+    let results = stepper.step(
+        &sources, &snapshots, rule, &tracker, &pool, clock.step, parallel,
+    );
+    release_sources(sources, &pool);
+    for (entity, expected) in results {
+        let Ok((_, key, _, next, _)) = query.get(entity) else {
+            pool.release(expected);
+            continue;
+        };
+        let actual = next.as_slice();
+
+        if first.is_none() {
+            first = first_mismatch(&expected, actual).map(|index| {
+                let edge = CHUNK_EDGE as usize;
+                let local = IVec3::new(
+                    (index / (edge * edge)) as i32,
+                    ((index / edge) % edge) as i32,
+                    (index % edge) as i32,
+                );
+                (
+                    key.coords * CHUNK_EDGE + local,
+                    expected[index],
+                    actual[index],
+                )
+            });
+        }
+        mismatched_voxels += expected.iter().zip(actual).filter(|(a, b)| a != b).count();
+        pool.release(expected);
+    }
+
+    if let Some((voxel, expected, actual)) = first {
+        warn!(
+            "Simulation backend diverged from the CPU reference on step {}: {} voxels differ, first at {} (expected {:?}, got {:?})",
+            clock.step, mismatched_voxels, voxel, expected, actual
+        );
+        mismatches.send(ConsistencyMismatch {
+            step: clock.step,
+            voxel,
+            expected,
+            actual,
+            mismatched_voxels,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        CellularAutomataPlugin, ChunkBundle, SimulationControl, SimulationPassSet,
+        SimulationSchedule, LIFE_PASS,
+    };
+
+    #[derive(Resource, Default)]
+    struct Corrupt(bool);
+
+    fn corrupt_next_cells(corrupt: Res<Corrupt>, mut query: Query<&mut ChunkCellsNext>) {
+        if corrupt.0 {
+            for mut next in query.iter_mut() {
+                next.set(IVec3::ZERO, AutomataState::new(9, 0));
+            }
+        }
+    }
+
+    #[test]
+    fn backend_output_is_diffed_against_the_cpu_stepper() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(CellularAutomataPlugin)
+            .init_resource::<Corrupt>()
+            .add_systems(
+                SimulationSchedule,
+                corrupt_next_cells
+                    .after(SimulationPassSet(LIFE_PASS))
+                    .before(check_consistency),
+            );
+        app.world.resource_mut::<ConsistencyCheck>().enabled = true;
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        app.world
+            .spawn(ChunkBundle::from_generator(IVec3::ZERO, |local| {
+                match (local.x + local.y + local.z) % 3 == 0 {
+                    true => AutomataState::new(1, 0),
+                    false => AutomataState::EMPTY,
+                }
+            }));
+        app.update();
+        let step = |app: &mut App| {
+            app.world.resource_mut::<SimulationControl>().step_once();
+            app.update();
+            let events = app.world.resource::<Events<ConsistencyMismatch>>();
+            events
+                .iter_current_update_events()
+                .copied()
+                .collect::<Vec<_>>()
+        };
+
+        assert!(step(&mut app).is_empty());
+
+        app.world.resource_mut::<Corrupt>().0 = true;
+        let mismatches = step(&mut app);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].voxel, IVec3::ZERO);
+        assert_eq!(mismatches[0].actual, AutomataState::new(9, 0));
+        assert_eq!(mismatches[0].mismatched_voxels, 1);
+    }
+}
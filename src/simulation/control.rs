@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+
+/// Playback controls of the simulation, for debugging rules and effects.
+///
+/// While paused no step is accumulated, only those requested with [`step_once`] run.
+/// `steps_per_second` replaces the fixed step rate and the [`SimulationSpeed`] factor, which
+/// allows slow motion down to a single step every few seconds.
+///
+/// [`step_once`]: SimulationControl::step_once
+/// [`SimulationSpeed`]: super::SimulationSpeed
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq)]
+pub struct SimulationControl {
+    pub paused: bool,
+    pub steps_per_second: Option<f32>,
+    pending_steps: u32,
+}
+
+impl SimulationControl {
+    /// Runs one more step on the next frame, also while paused.
+    pub fn step_once(&mut self) {
+        self.pending_steps += 1;
+    }
+
+    #[inline]
+    pub fn pending_steps(&self) -> u32 {
+        self.pending_steps
+    }
+
+    /// Overridden step rate, `None` when the speed factor applies.
+    pub(super) fn step_rate(&self) -> Option<f32> {
+        self.steps_per_second.filter(|rate| *rate > 0.0)
+    }
+
+    pub(super) fn take_pending(&mut self, max: u32) -> u32 {
+        let steps = self.pending_steps.min(max);
+        self.pending_steps -= steps;
+        steps
+    }
+}
+
+/// Space pauses, `.` steps once while paused, `[` and `]` halve and double the step rate and
+/// `\` goes back to the normal rate.
+#[cfg(feature = "debug_controls")]
+pub(super) fn debug_controls(keys: Res<Input<KeyCode>>, mut control: ResMut<SimulationControl>) {
+    use super::FIXED_STEP_SECONDS;
+
+    if keys.just_pressed(KeyCode::Space) {
+        control.paused = !control.paused;
+    }
+    if keys.just_pressed(KeyCode::Period) {
+        control.step_once();
+    }
+
+    let rate = control
+        .steps_per_second
+        .unwrap_or(FIXED_STEP_SECONDS.recip());
+    if keys.just_pressed(KeyCode::BracketLeft) {
+        control.steps_per_second = Some((rate * 0.5).max(0.25));
+    }
+    if keys.just_pressed(KeyCode::BracketRight) {
+        control.steps_per_second = Some((rate * 2.0).min(FIXED_STEP_SECONDS.recip() * 4.0));
+    }
+    if keys.just_pressed(KeyCode::Backslash) {
+        control.steps_per_second = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending_steps_are_taken_up_to_the_frame_limit() {
+        let mut control = SimulationControl {
+            paused: true,
+            ..default()
+        };
+        for _ in 0..3 {
+            control.step_once();
+        }
+        assert_eq!(control.take_pending(2), 2);
+        assert_eq!(control.take_pending(2), 1);
+        assert_eq!(control.take_pending(2), 0);
+
+        assert_eq!(control.step_rate(), None);
+        control.steps_per_second = Some(4.0);
+        assert_eq!(control.step_rate(), Some(4.0));
+        control.steps_per_second = Some(0.0);
+        assert_eq!(control.step_rate(), None);
+    }
+}
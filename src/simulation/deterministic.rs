@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// Integer-only configuration of the simulation core, for lockstep setups where every peer
+/// must run exactly the same steps.
+///
+/// The clock then converts frame times to steps with integer math at a fixed
+/// `steps_per_second`, ignoring [`SimulationSpeed`](super::SimulationSpeed) and
+/// [`SimulationControl::steps_per_second`](super::SimulationControl::steps_per_second). The
+/// step budget is tracked in whole microseconds and only reported, it no longer slows the
+/// simulation down. Rules only ever count neighbors, so the steps themselves use no floats.
+///
+/// [`SimulationAnchor`](super::SimulationAnchor) distances are still floats, lockstep games
+/// should either leave anchors out or keep them identical on every peer.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeterministicCore {
+    pub enabled: bool,
+    pub steps_per_second: u32,
+    /// Step time past which [`EngineEvent::BudgetOverloaded`](crate::EngineEvent) is sent.
+    pub budget_micros: u64,
+}
+
+impl Default for DeterministicCore {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            steps_per_second: 60,
+            budget_micros: 6_000,
+        }
+    }
+}
+
+impl DeterministicCore {
+    /// Clock units `delta` is worth at the fixed step rate, a step taking one second worth of
+    /// nanoseconds.
+    #[inline]
+    pub(super) fn step_units(&self, delta: Duration) -> u64 {
+        delta.as_nanos() as u64 * self.steps_per_second as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimulationClock;
+
+    #[test]
+    fn irregular_frames_add_up_to_exact_steps() {
+        let core = DeterministicCore {
+            enabled: true,
+            ..default()
+        };
+        let mut clock = SimulationClock {
+            max_steps_per_frame: u32::MAX,
+            ..default()
+        };
+
+        // Frames of 7, 11 and 15 ms repeated for 3.3 s, then the last 0.7 s in one frame.
+        let mut steps = 0;
+        for frame in 0..300 {
+            let millis = [7, 11, 15][frame % 3];
+            steps += clock.advance(core.step_units(Duration::from_millis(millis)));
+        }
+        steps += clock.advance(core.step_units(Duration::from_millis(700)));
+
+        assert_eq!(steps, 4 * 60);
+        assert_eq!(clock.steps_behind(), 0);
+        assert_eq!(clock.alpha(), 0.0);
+    }
+}
@@ -0,0 +1,352 @@
+#[cfg(doc)]
+use super::SimulationBackend;
+use super::{
+    linear_index, voxel_to_chunk, AutomataState, CellularAutomataPlugin, ChunkCells, ChunkKey,
+    SimulationControl, CHUNK_EDGE, CHUNK_VOLUME,
+};
+use bevy::prelude::*;
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet},
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+type Configure = Box<dyn Fn(&mut App)>;
+type WorldCells = BTreeMap<[i32; 3], Box<[AutomataState]>>;
+
+/// Debug tool running two simulation setups side by side to find where they stop agreeing,
+/// e.g. two steppers, two builds of a rule or the serial and parallel CPU backends.
+///
+/// Each side runs in its own headless app with `MinimalPlugins` and the
+/// [`CellularAutomataPlugin`], built by the shared setup followed by the side's own
+/// configuration. Both are stepped through [`SimulationControl`] and compared after 1, 2, 4,
+/// ... steps, then the first diverging step is bisected by replaying both sides from scratch,
+/// so the setups must be reproducible.
+///
+/// The headless apps have no render device, so [`SimulationBackend::Gpu`] falls back to the
+/// CPU and cannot be compared against it here. [`SimulationBackend::Cpu`] and
+/// [`SimulationBackend::CpuParallel`] only run differently with the `parallel` feature.
+pub struct DivergenceFinder {
+    /// Steps run at most before giving up.
+    pub max_steps: u64,
+    setup: Configure,
+    sides: [(String, Configure); 2],
+}
+
+/// First difference found by a [`DivergenceFinder`]. Displays as a full report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// Step whose result differs, 0 when the sides already differ after the setup.
+    pub step: u64,
+    /// First differing voxel, chunks being visited in coordinate order.
+    pub voxel: IVec3,
+    pub labels: [String; 2],
+    /// State of the voxel on each side after the step.
+    pub states: [AutomataState; 2],
+    /// The 3×3×3 block around the voxel on each side before the step, indexed by
+    /// `(x + 1) * 9 + (y + 1) * 3 + z + 1`. `None` marks unloaded chunks.
+    pub neighborhoods: [[Option<AutomataState>; 27]; 2],
+    /// Number of voxels that differ over all chunks.
+    pub mismatched_voxels: usize,
+}
+
+impl DivergenceFinder {
+    pub fn new(max_steps: u64, setup: impl Fn(&mut App) + 'static) -> Self {
+        Self {
+            max_steps,
+            setup: Box::new(setup),
+            sides: [
+                ("left".to_string(), Box::new(|_: &mut App| {}) as Configure),
+                ("right".to_string(), Box::new(|_: &mut App| {})),
+            ],
+        }
+    }
+
+    pub fn left(
+        mut self,
+        label: impl Into<String>,
+        configure: impl Fn(&mut App) + 'static,
+    ) -> Self {
+        self.sides[0] = (label.into(), Box::new(configure));
+        self
+    }
+
+    pub fn right(
+        mut self,
+        label: impl Into<String>,
+        configure: impl Fn(&mut App) + 'static,
+    ) -> Self {
+        self.sides[1] = (label.into(), Box::new(configure));
+        self
+    }
+
+    /// Runs both sides, `None` when they agree for `max_steps` steps.
+    pub fn run(&self) -> Option<Divergence> {
+        let mut apps = self.build();
+        if world_hash(&mut apps[0]) != world_hash(&mut apps[1]) {
+            return Some(self.describe(0));
+        }
+
+        // Gallop until the sides differ, stepping the same apps forward.
+        let mut agreed = 0;
+        let mut target = 1;
+        let mut differs = loop {
+            let target_step = target.min(self.max_steps);
+            if target_step == agreed {
+                return None;
+            }
+            for app in &mut apps {
+                advance(app, target_step - agreed);
+            }
+            if world_hash(&mut apps[0]) != world_hash(&mut apps[1]) {
+                break target_step;
+            }
+            agreed = target_step;
+            target *= 2;
+        };
+        drop(apps);
+
+        // Bisect, replaying from scratch as apps cannot be rewound.
+        while differs - agreed > 1 {
+            let middle = agreed + (differs - agreed) / 2;
+            let mut apps = self.build();
+            for app in &mut apps {
+                advance(app, middle);
+            }
+            if world_hash(&mut apps[0]) == world_hash(&mut apps[1]) {
+                agreed = middle;
+            } else {
+                differs = middle;
+            }
+        }
+
+        Some(self.describe(differs))
+    }
+
+    fn build(&self) -> [App; 2] {
+        [0, 1].map(|side| {
+            let (_, configure) = &self.sides[side];
+            let mut app = App::new();
+            app.add_plugins(MinimalPlugins)
+                .add_plugins(CellularAutomataPlugin);
+            (self.setup)(&mut app);
+            configure(&mut app);
+            app.world.resource_mut::<SimulationControl>().paused = true;
+            app.update();
+            app
+        })
+    }
+
+    /// Replays both sides up to `step` and reports the first difference it produced.
+    fn describe(&self, step: u64) -> Divergence {
+        let mut apps = self.build();
+        let mut before = [WorldCells::new(), WorldCells::new()];
+        let mut after = [WorldCells::new(), WorldCells::new()];
+        for ((app, before), after) in apps.iter_mut().zip(&mut before).zip(&mut after) {
+            advance(app, step.saturating_sub(1));
+            *before = world_cells(app);
+            if step > 0 {
+                advance(app, 1);
+            }
+            *after = world_cells(app);
+        }
+
+        let empty: Box<[AutomataState]> = vec![AutomataState::EMPTY; CHUNK_VOLUME].into();
+        let mut first = None;
+        let mut mismatched_voxels = 0;
+        let coords: BTreeSet<_> = after[0].keys().chain(after[1].keys()).collect();
+        for coords in coords {
+            let left = after[0].get(coords).unwrap_or(&empty);
+            let right = after[1].get(coords).unwrap_or(&empty);
+            for (index, (a, b)) in left.iter().zip(right.iter()).enumerate() {
+                if a == b {
+                    continue;
+                }
+                mismatched_voxels += 1;
+                first.get_or_insert_with(|| {
+                    let edge = CHUNK_EDGE as usize;
+                    let local = IVec3::new(
+                        (index / (edge * edge)) as i32,
+                        ((index / edge) % edge) as i32,
+                        (index % edge) as i32,
+                    );
+                    (IVec3::from_array(*coords) * CHUNK_EDGE + local, [*a, *b])
+                });
+            }
+        }
+        let (voxel, states) = first.unwrap_or((IVec3::ZERO, [AutomataState::EMPTY; 2]));
+
+        Divergence {
+            step,
+            voxel,
+            labels: [0, 1].map(|side| self.sides[side].0.clone()),
+            states,
+            neighborhoods: [0, 1].map(|side| neighborhood(&before[side], voxel)),
+            mismatched_voxels,
+        }
+    }
+}
+
+fn advance(app: &mut App, steps: u64) {
+    for _ in 0..steps {
+        app.world.resource_mut::<SimulationControl>().step_once();
+        app.update();
+    }
+}
+
+fn world_cells(app: &mut App) -> WorldCells {
+    app.world
+        .query::<(&ChunkKey, &ChunkCells)>()
+        .iter(&app.world)
+        .map(|(key, cells)| (key.coords.to_array(), cells.clone_box()))
+        .collect()
+}
+
+fn world_hash(app: &mut App) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for (coords, cells) in world_cells(app) {
+        coords.hash(&mut hasher);
+        for state in cells.iter() {
+            state.to_packed().hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+fn neighborhood(cells: &WorldCells, voxel: IVec3) -> [Option<AutomataState>; 27] {
+    let mut neighbors = [None; 27];
+    for (index, neighbor) in neighbors.iter_mut().enumerate() {
+        let index = index as i32;
+        let offset = IVec3::new(index / 9, (index / 3) % 3, index % 3) - IVec3::ONE;
+        let (chunk, local) = voxel_to_chunk(voxel + offset);
+        *neighbor = cells
+            .get(&chunk.to_array())
+            .map(|cells| cells[linear_index(local)]);
+    }
+    neighbors
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (chunk, local) = voxel_to_chunk(self.voxel);
+        writeln!(
+            f,
+            "{} and {} diverge on step {}: {} voxels differ",
+            self.labels[0], self.labels[1], self.step, self.mismatched_voxels
+        )?;
+        writeln!(
+            f,
+            "first at voxel {} (chunk {}, local {})",
+            self.voxel, chunk, local
+        )?;
+        for (label, state) in self.labels.iter().zip(&self.states) {
+            writeln!(f, "  {}: {:?}", label, state)?;
+        }
+        writeln!(
+            f,
+            "neighborhood before the step, as {} / {}:",
+            self.labels[0], self.labels[1]
+        )?;
+        for (index, (a, b)) in self.neighborhoods[0]
+            .iter()
+            .zip(&self.neighborhoods[1])
+            .enumerate()
+        {
+            let index = index as i32;
+            let offset = IVec3::new(index / 9, (index / 3) % 3, index % 3) - IVec3::ONE;
+            let marker = if a != b { " <-" } else { "" };
+            writeln!(
+                f,
+                "  {:>2} {:>2} {:>2}: {:?} / {:?}{}",
+                offset.x, offset.y, offset.z, a, b, marker
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AutomataRule, ChunkBundle, SimulationBackend};
+
+    fn finder() -> DivergenceFinder {
+        DivergenceFinder::new(16, |app| {
+            // A 2×2×2 block, the only cells around with 7 live neighbors.
+            app.world
+                .spawn(ChunkBundle::from_generator(IVec3::ZERO, |local| {
+                    match local.cmpge(IVec3::splat(4)).all() && local.cmplt(IVec3::splat(6)).all() {
+                        true => AutomataState::new(1, 0),
+                        false => AutomataState::EMPTY,
+                    }
+                }));
+            app.insert_resource(AutomataRule {
+                birth: vec![3],
+                survive: vec![7],
+                ..default()
+            });
+        })
+    }
+
+    #[test]
+    fn identical_sides_agree() {
+        assert_eq!(finder().run(), None);
+    }
+
+    #[test]
+    fn finds_the_step_a_seeded_difference_appears() {
+        // The right side kills the block on the first step.
+        let divergence = finder()
+            .left("still", |_| {})
+            .right("dying", |app| {
+                app.world.resource_mut::<AutomataRule>().survive = Vec::new();
+            })
+            .run()
+            .unwrap();
+
+        assert_eq!(divergence.step, 1);
+        assert_eq!(divergence.mismatched_voxels, 8);
+        assert_eq!(divergence.voxel, IVec3::splat(4));
+        assert_eq!(
+            divergence.states,
+            [AutomataState::new(1, 0), AutomataState::EMPTY]
+        );
+        assert_eq!(divergence.neighborhoods[0], divergence.neighborhoods[1]);
+        assert_eq!(
+            divergence.neighborhoods[0][13],
+            Some(AutomataState::new(1, 0))
+        );
+        assert!(divergence
+            .to_string()
+            .starts_with("still and dying diverge on step 1"));
+    }
+
+    #[test]
+    fn serial_and_parallel_steps_match() {
+        let divergence = DivergenceFinder::new(8, |app| {
+            for x in 0..2 {
+                for y in 0..2 {
+                    for z in 0..2 {
+                        let coords = IVec3::new(x, y, z);
+                        app.world
+                            .spawn(ChunkBundle::from_generator(coords, |local| {
+                                let hash = (local + coords * 7).dot(IVec3::new(73, 19, 83));
+                                match hash.rem_euclid(5) < 2 {
+                                    true => AutomataState::new(1, 0),
+                                    false => AutomataState::EMPTY,
+                                }
+                            }));
+                    }
+                }
+            }
+        })
+        .left("serial", |app| {
+            app.insert_resource(SimulationBackend::Cpu);
+        })
+        .right("parallel", |app| {
+            app.insert_resource(SimulationBackend::CpuParallel);
+        })
+        .run();
+        assert_eq!(divergence, None);
+    }
+}
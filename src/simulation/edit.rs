@@ -0,0 +1,554 @@
+use super::{
+    brick_index_of, voxel_to_chunk, AutomataEffect, AutomataState, ChunkBundle, ChunkCells,
+    ChunkCellsNext, ChunkChanges, ChunkIndex, MaterialClass, MaterialRegistry, RegionLockId,
+    RegionLocks, Stamp, StasisVolume, TerraformBrush, VoxelWorldTransform,
+};
+use crate::EngineEvent;
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
+use std::collections::VecDeque;
+
+/// Edits voxels from world space positions.
+///
+/// Edits are applied with the commands, creating missing chunks on demand. Edited bricks are
+/// marked in [`ChunkChanges`] and the next step reads the edited cells, including when a step
+/// is waiting to be applied this frame. Edits touching more chunks than the [`EditBudget`]
+/// allows are split across frames.
+#[derive(SystemParam)]
+pub struct VoxelCommands<'w, 's> {
+    commands: Commands<'w, 's>,
+    world_transform: Res<'w, VoxelWorldTransform>,
+}
+
+impl<'w, 's> VoxelCommands<'w, 's> {
+    pub fn set_voxel(&mut self, world_pos: Vec3, state: AutomataState) {
+        let voxel = self.world_transform.world_to_voxel(world_pos);
+        self.set_voxels(vec![(voxel, state)]);
+    }
+
+    /// Fills every voxel whose center lies in the world space box between `min` and `max`,
+    /// taken along the axes of the voxel grid.
+    pub fn fill_box(&mut self, min: Vec3, max: Vec3, state: AutomataState) {
+        let a = self.world_transform.world_to_voxel_space(min);
+        let b = self.world_transform.world_to_voxel_space(max);
+        let min = (a.min(b) - 0.5).ceil().as_ivec3();
+        let max = (a.max(b) - 0.5).floor().as_ivec3();
+
+        let mut edits = Vec::new();
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    edits.push((IVec3::new(x, y, z), state));
+                }
+            }
+        }
+        self.set_voxels(edits);
+    }
+
+    /// Fills every voxel whose center lies within `radius` world units of `center`.
+    pub fn fill_sphere(&mut self, center: Vec3, radius: f32, state: AutomataState) {
+        let center = self.world_transform.world_to_voxel_space(center);
+        let radius = radius / self.world_transform.voxel_size;
+        let edits = sphere_voxels(center, radius)
+            .map(|voxel| (voxel, state))
+            .collect();
+        self.set_voxels(edits);
+    }
+
+    /// Writes the non-empty cells of `stamp` with its anchor on the voxel at `world_pos`, turned
+    /// by `rotation` in voxel grid axes, see [`Stamp::placed`]. Returns the entity of the
+    /// [`StasisVolume`] holding the stamped box when the stamp is [`Stamp::frozen`].
+    pub fn stamp(&mut self, world_pos: Vec3, stamp: &Stamp, rotation: Quat) -> Option<Entity> {
+        let voxel = self.world_transform.world_to_voxel(world_pos);
+        let edits: Vec<_> = stamp.placed(voxel, rotation).collect();
+        let frozen = stamp.frozen && !edits.is_empty();
+        let (min, max) = edits
+            .iter()
+            .fold((IVec3::MAX, IVec3::MIN), |(min, max), (voxel, _)| {
+                (min.min(*voxel), max.max(*voxel + 1))
+            });
+        self.set_voxels(edits);
+
+        frozen.then(|| {
+            let voxel_size = self.world_transform.voxel_size;
+            let center = self
+                .world_transform
+                .voxel_space_to_world((min + max).as_vec3() / 2.0);
+            self.commands
+                .spawn((
+                    StasisVolume {
+                        half_extents: (max - min).as_vec3() / 2.0 * voxel_size,
+                    },
+                    SpatialBundle::from_transform(Transform::from_translation(center)),
+                ))
+                .id()
+        })
+    }
+
+    /// Clears the voxels within `radius` world units of `center`, sending a [`DebrisSpawned`]
+    /// with the solid voxels it removed.
+    ///
+    /// Debris is made of the live voxels whose material is a
+    /// [`MaterialClass::Solid`](super::MaterialClass::Solid) or a powder in the
+    /// [`MaterialRegistry`], every live voxel without the registry. Each piece is thrown away
+    /// from the center at `impulse` world units per second, fading to nothing at the edge of the
+    /// blast. Only live voxels are cleared, and voxels in locked regions are neither cleared nor
+    /// turned into debris. A blast split across frames by the [`EditBudget`] holds its region
+    /// with a lock until it is written, so the voxels cleared are the ones turned into debris.
+    pub fn explode(&mut self, center: Vec3, radius: f32, impulse: f32) {
+        let world_transform = *self.world_transform;
+        self.commands.add(move |world: &mut World| {
+            let voxel_center = world_transform.world_to_voxel_space(center);
+            let voxel_radius = radius / world_transform.voxel_size;
+            let mut voxels: Vec<IVec3> = sphere_voxels(voxel_center, voxel_radius).collect();
+            if let Some(locks) = world.get_resource::<RegionLocks>() {
+                voxels.retain(|voxel| locks.owner(*voxel).is_none());
+            }
+
+            let registry = world.get_resource::<MaterialRegistry>();
+            let index = world.resource::<ChunkIndex>();
+            let mut cleared = Vec::new();
+            let mut debris = Vec::new();
+            for voxel in voxels {
+                let (coords, local) = voxel_to_chunk(voxel);
+                let Some(cells) = index
+                    .entity(coords)
+                    .and_then(|entity| world.get::<ChunkCells>(entity))
+                else {
+                    continue;
+                };
+                let state = cells.get(local);
+                if !state.is_alive() {
+                    continue;
+                }
+                cleared.push((voxel, AutomataState::EMPTY));
+
+                let solid = registry.map_or(true, |registry| {
+                    matches!(
+                        registry.get(state.material).class,
+                        MaterialClass::Solid | MaterialClass::Powder
+                    )
+                });
+                if solid {
+                    let position = world_transform.voxel_center(voxel);
+                    let offset = position - center;
+                    let falloff = (1.0 - offset.length() / radius.max(f32::EPSILON)).max(0.0);
+                    debris.push(Debris {
+                        voxel,
+                        material: state.material,
+                        position,
+                        velocity: offset.normalize_or_zero() * impulse * falloff,
+                    });
+                }
+            }
+
+            apply_voxel_edits(world, cleared);
+            world.send_event(DebrisSpawned {
+                center,
+                radius,
+                debris,
+            });
+        });
+    }
+
+    /// Applies a terraforming brush to the voxels within `radius` world units of `center`.
+    pub fn terraform(&mut self, center: Vec3, radius: f32, brush: TerraformBrush) {
+        let center = self.world_transform.world_to_voxel_space(center);
+        let radius = radius / self.world_transform.voxel_size;
+        self.commands.add(move |world: &mut World| {
+            super::terraform::apply_brush(world, brush, center, radius)
+        });
+    }
+
+    /// Casts an [`AutomataEffect`] centered on the voxel at `center`, returning its entity.
+    pub fn cast_effect(&mut self, center: Vec3, effect: AutomataEffect) -> Entity {
+        let center = self.world_transform.world_to_voxel(center);
+        let entity = self.commands.spawn(effect).id();
+        self.commands
+            .add(move |world: &mut World| super::effect::cast_effect(world, entity, center));
+        entity
+    }
+
+    /// Sets voxels by voxel coordinates.
+    pub fn set_voxels(&mut self, edits: Vec<(IVec3, AutomataState)>) {
+        if !edits.is_empty() {
+            self.commands
+                .add(move |world: &mut World| apply_voxel_edits(world, edits));
+        }
+    }
+
+    /// Writes the result of an operation holding `lock` and releases the lock.
+    ///
+    /// Edits outside the locked region are dropped and reported like edits into locked regions.
+    /// Nothing is written if the lock was already released.
+    pub fn commit_locked(&mut self, lock: RegionLockId, edits: Vec<(IVec3, AutomataState)>) {
+        self.commands.add(move |world: &mut World| {
+            let Some(region) = world
+                .get_resource_mut::<RegionLocks>()
+                .and_then(|mut locks| {
+                    let region = locks.region(lock).copied();
+                    locks.release(lock);
+                    region
+                })
+            else {
+                return;
+            };
+
+            let (edits, rejected): (Vec<_>, Vec<_>) = edits
+                .into_iter()
+                .partition(|(voxel, _)| region.contains(*voxel));
+            report_rejected(world, rejected.len());
+            apply_voxel_edits(world, edits);
+        });
+    }
+}
+
+/// A solid voxel removed by [`VoxelCommands::explode`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Debris {
+    pub voxel: IVec3,
+    pub material: u8,
+    /// World space center of the voxel.
+    pub position: Vec3,
+    /// World space velocity the blast gave it.
+    pub velocity: Vec3,
+}
+
+/// Sent for every [`VoxelCommands::explode`], for particles or rigid bodies to take over the
+/// removed voxels. Sent even when nothing was removed.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct DebrisSpawned {
+    pub center: Vec3,
+    pub radius: f32,
+    pub debris: Vec<Debris>,
+}
+
+/// Voxels whose center lies within `radius` of `center`, in voxel space.
+fn sphere_voxels(center: Vec3, radius: f32) -> impl Iterator<Item = IVec3> {
+    let min = (center - radius - 0.5).ceil().as_ivec3();
+    let max = (center + radius - 0.5).floor().as_ivec3();
+    (min.x..=max.x)
+        .flat_map(move |x| {
+            (min.y..=max.y).flat_map(move |y| (min.z..=max.z).map(move |z| (x, y, z)))
+        })
+        .map(|(x, y, z)| IVec3::new(x, y, z))
+        .filter(move |voxel| (voxel.as_vec3() + 0.5).distance_squared(center) <= radius * radius)
+}
+
+/// Chunks written per frame by edits from [`VoxelCommands`].
+///
+/// An edit touching more chunks is split: its chunks are written over the following frames,
+/// starting in `PostUpdate` of the frame it was issued, while its bounding box is locked with
+/// [`RegionLocks`] so the simulation holds the half written region and other edits into it are
+/// rejected. An edit whose bounding box overlaps another lock waits for it to be released
+/// before writing anything, holding back the edits split after it. [`SplitEditFinished`] is
+/// sent once it is fully written. Edits are never split without the resource.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EditBudget {
+    pub chunks_per_frame: usize,
+}
+
+impl Default for EditBudget {
+    fn default() -> Self {
+        Self {
+            chunks_per_frame: 64,
+        }
+    }
+}
+
+/// Sent when an edit split by the [`EditBudget`] has been fully written and its region
+/// unlocked.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SplitEditFinished {
+    /// Bounding box of the edited voxels, `min` inclusive and `max` exclusive.
+    pub min: IVec3,
+    pub max: IVec3,
+    pub chunks: usize,
+    pub voxels: usize,
+}
+
+/// Edits split by the [`EditBudget`], written in the order they were issued.
+#[derive(Resource, Default)]
+pub(super) struct SplitEdits(VecDeque<SplitEdit>);
+
+struct SplitEdit {
+    /// `None` until the bounding box could be locked.
+    lock: Option<RegionLockId>,
+    finished: SplitEditFinished,
+    chunks: Vec<(IVec3, Vec<(IVec3, AutomataState)>)>,
+}
+
+pub(super) fn apply_split_edits(world: &mut World) {
+    let Some(budget) = world.get_resource::<EditBudget>().copied() else {
+        return;
+    };
+    let mut remaining = budget.chunks_per_frame.max(1);
+    while remaining > 0 {
+        let Some(edit) = world.resource::<SplitEdits>().0.front() else {
+            break;
+        };
+        if edit.lock.is_none() {
+            let SplitEditFinished { min, max, .. } = edit.finished;
+            // Waits for the overlapping lock to be released.
+            let Ok(lock) = world.resource_mut::<RegionLocks>().lock(min, max) else {
+                break;
+            };
+            world.resource_mut::<SplitEdits>().0[0].lock = Some(lock);
+        }
+
+        let mut split = world.resource_mut::<SplitEdits>();
+        let edit = split.0.front_mut().unwrap();
+        let chunks: Vec<_> = edit
+            .chunks
+            .drain(..remaining.min(edit.chunks.len()))
+            .collect();
+        let done = edit.chunks.is_empty();
+        remaining -= chunks.len();
+        for (coords, edits) in chunks {
+            write_chunk_edits(world, coords, edits);
+        }
+
+        if done {
+            let edit = world.resource_mut::<SplitEdits>().0.pop_front().unwrap();
+            if let Some(lock) = edit.lock {
+                world.resource_mut::<RegionLocks>().release(lock);
+            }
+            world.send_event(edit.finished);
+        }
+    }
+}
+
+fn report_rejected(world: &mut World, voxels: usize) {
+    if voxels > 0 {
+        EngineEvent::EditsRejected { voxels }.report_to_world(world);
+    }
+}
+
+/// Applies edits, except those landing in a region locked with [`RegionLocks`].
+pub(crate) fn apply_voxel_edits(world: &mut World, mut edits: Vec<(IVec3, AutomataState)>) {
+    if let Some(locks) = world
+        .get_resource::<RegionLocks>()
+        .filter(|locks| !locks.is_empty())
+    {
+        let len = edits.len();
+        edits.retain(|(voxel, _)| locks.owner(*voxel).is_none());
+        report_rejected(world, len - edits.len());
+    }
+    if edits.is_empty() {
+        return;
+    }
+
+    let voxels = edits.len();
+    let mut min = IVec3::MAX;
+    let mut max = IVec3::MIN;
+    let mut by_chunk: HashMap<IVec3, Vec<(IVec3, AutomataState)>> = HashMap::new();
+    for (voxel, state) in edits {
+        min = min.min(voxel);
+        max = max.max(voxel + 1);
+        let (chunk, local) = voxel_to_chunk(voxel);
+        by_chunk.entry(chunk).or_default().push((local, state));
+    }
+
+    let budget = world.get_resource::<EditBudget>().copied();
+    if budget.is_some_and(|budget| by_chunk.len() > budget.chunks_per_frame) {
+        // Taken before the first chunks are written if another lock is in the way.
+        let lock = world.resource_mut::<RegionLocks>().lock(min, max).ok();
+        let mut chunks: Vec<_> = by_chunk.into_iter().collect();
+        chunks.sort_unstable_by_key(|(coords, _)| coords.to_array());
+        let finished = SplitEditFinished {
+            min,
+            max,
+            chunks: chunks.len(),
+            voxels,
+        };
+        world.resource_mut::<SplitEdits>().0.push_back(SplitEdit {
+            lock,
+            finished,
+            chunks,
+        });
+        return;
+    }
+
+    for (coords, edits) in by_chunk {
+        write_chunk_edits(world, coords, edits);
+    }
+}
+
+/// Writes edits with chunk local coordinates into the chunk at `coords`, spawning it if needed.
+pub(super) fn write_chunk_edits(
+    world: &mut World,
+    coords: IVec3,
+    edits: Vec<(IVec3, AutomataState)>,
+) {
+    let entity = match world.resource::<ChunkIndex>().entity(coords) {
+        Some(entity) if world.get_entity(entity).is_some() => entity,
+        _ => {
+            let entity = world.spawn(ChunkBundle::new(coords)).id();
+            world.resource_mut::<ChunkIndex>().insert(coords, entity);
+            entity
+        }
+    };
+
+    #[cfg(feature = "voxel_history")]
+    let transitions = world
+        .get::<ChunkCells>(entity)
+        .map(|cells| super::history::record_edits(world, coords, cells, &edits))
+        .unwrap_or_default();
+
+    let mut chunk = world.entity_mut(entity);
+    let mut bricks = 0u64;
+    if let Some(mut cells) = chunk.get_mut::<ChunkCells>() {
+        for &(local, state) in &edits {
+            cells.set(local, state);
+            bricks |= 1 << brick_index_of(local);
+        }
+    }
+    // A step waiting to be applied would otherwise overwrite the edit.
+    if let Some(mut next) = chunk.get_mut::<ChunkCellsNext>() {
+        for &(local, state) in &edits {
+            next.set(local, state);
+        }
+    }
+    if let Some(mut changes) = chunk.get_mut::<ChunkChanges>() {
+        changes.bricks |= bricks;
+    }
+
+    #[cfg(feature = "voxel_history")]
+    if let Some(mut history) = world.get_resource_mut::<super::VoxelHistory>() {
+        for (voxel, transition) in transitions {
+            history.record(voxel, transition);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CellularAutomataPlugin, SimulationControl, CHUNK_EDGE};
+    use bevy::ecs::system::SystemState;
+
+    /// One voxel in each of three chunks along x, split one chunk per frame.
+    fn split_app() -> (App, Vec<(IVec3, AutomataState)>) {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(CellularAutomataPlugin)
+            .insert_resource(EditBudget {
+                chunks_per_frame: 1,
+            });
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        app.update();
+        let edits = (0..3)
+            .map(|chunk| (IVec3::X * chunk * CHUNK_EDGE, AutomataState::new(1, 0)))
+            .collect();
+        (app, edits)
+    }
+
+    fn written(app: &App, voxel: IVec3) -> bool {
+        let (coords, local) = voxel_to_chunk(voxel);
+        app.world
+            .resource::<ChunkIndex>()
+            .entity(coords)
+            .and_then(|entity| app.world.get::<ChunkCells>(entity))
+            .is_some_and(|cells| cells.get(local).is_alive())
+    }
+
+    fn finished(app: &App) -> Vec<SplitEditFinished> {
+        let events = app.world.resource::<Events<SplitEditFinished>>();
+        events.iter_current_update_events().copied().collect()
+    }
+
+    #[test]
+    fn split_edits_are_written_across_frames_under_a_lock() {
+        let (mut app, edits) = split_app();
+        apply_voxel_edits(&mut app.world, edits.clone());
+        let max = IVec3::new(2 * CHUNK_EDGE + 1, 1, 1);
+        assert!(app
+            .world
+            .resource::<RegionLocks>()
+            .owner(IVec3::X)
+            .is_some());
+
+        app.update();
+        assert!(written(&app, edits[0].0));
+        assert!(!written(&app, edits[1].0));
+        // Other edits into the half written box are rejected.
+        apply_voxel_edits(&mut app.world, vec![(IVec3::X, AutomataState::new(1, 0))]);
+        assert!(!written(&app, IVec3::X));
+
+        app.update();
+        assert!(finished(&app).is_empty());
+        app.update();
+        assert!(edits.iter().all(|(voxel, _)| written(&app, *voxel)));
+        assert_eq!(
+            finished(&app),
+            vec![SplitEditFinished {
+                min: IVec3::ZERO,
+                max,
+                chunks: 3,
+                voxels: 3,
+            }]
+        );
+        assert!(app.world.resource::<RegionLocks>().is_empty());
+    }
+
+    #[test]
+    fn split_edits_wait_for_overlapping_locks() {
+        let (mut app, edits) = split_app();
+        let lock = app
+            .world
+            .resource_mut::<RegionLocks>()
+            .lock(IVec3::X, IVec3::new(2, 1, 1))
+            .unwrap();
+        apply_voxel_edits(&mut app.world, edits.clone());
+        app.update();
+        app.update();
+        assert!(!written(&app, edits[0].0));
+
+        app.world.resource_mut::<RegionLocks>().release(lock);
+        for _ in 0..3 {
+            app.update();
+        }
+        assert!(edits.iter().all(|(voxel, _)| written(&app, *voxel)));
+        assert_eq!(finished(&app).len(), 1);
+        assert!(app.world.resource::<RegionLocks>().is_empty());
+    }
+
+    #[test]
+    fn explosions_only_clear_live_voxels() {
+        let (mut app, _) = split_app();
+        // The lower half of the chunk is solid, the blast also reaches an unloaded chunk.
+        let chunk = app
+            .world
+            .spawn(ChunkBundle::from_generator(IVec3::ZERO, |local| {
+                match local.y < 4 {
+                    true => AutomataState::new(1, 0),
+                    false => AutomataState::EMPTY,
+                }
+            }))
+            .id();
+        app.update();
+
+        let mut state = SystemState::<VoxelCommands>::new(&mut app.world);
+        state
+            .get_mut(&mut app.world)
+            .explode(Vec3::new(0.5, 4.0, 8.5), 3.0, 1.0);
+        state.apply(&mut app.world);
+
+        let cleared = sphere_voxels(Vec3::new(0.5, 4.0, 8.5), 3.0)
+            .filter(|voxel| voxel.x >= 0 && voxel.y < 4)
+            .count();
+        let events = app.world.resource::<Events<DebrisSpawned>>();
+        let spawned = events.iter_current_update_events().next().unwrap();
+        assert_eq!(spawned.debris.len(), cleared);
+        let cells = app.world.get::<ChunkCells>(chunk).unwrap();
+        assert!(spawned
+            .debris
+            .iter()
+            .all(|debris| !cells.get(debris.voxel).is_alive()));
+        assert!(cells.get(IVec3::new(0, 0, 8)).is_alive());
+        assert!(app
+            .world
+            .resource::<ChunkIndex>()
+            .entity(IVec3::NEG_X)
+            .is_none());
+    }
+}
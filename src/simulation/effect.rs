@@ -0,0 +1,412 @@
+use super::{
+    sample_cell, voxel_to_chunk, AutomataRuleSet, AutomataState, CellContext, ChunkCells,
+    ChunkCellsNext, ChunkIndex, ChunkSnapshots, MaterialTracker, RegionLocks, SimulationClock,
+    StasisBounds, StasisVolume, VoxelWorldTransform,
+};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use std::sync::Arc;
+
+/// Short lived automaton cast into the world, e.g. a crystal spell spreading from where it
+/// lands. Cast with [`VoxelCommands::cast_effect`](super::VoxelCommands::cast_effect).
+///
+/// While the effect lasts, its rule decides the next state of every voxel it affected and may
+/// take over neighboring voxels, until `max_voxels` are affected. Voxels it never touched stay
+/// under the world's rule, and voxels held by a [`RegionLocks`] lock or a [`StasisVolume`] are
+/// left alone. Once `lifetime` steps have passed the affected voxels are reverted
+/// or left as they are depending on `expiry`, the entity is despawned and
+/// [`AutomataEffectExpired`] is sent. Despawning the entity earlier leaves the voxels as they
+/// are.
+#[derive(Component, Clone)]
+pub struct AutomataEffect {
+    pub rule: Arc<dyn AutomataRuleSet>,
+    /// Voxels written when the effect is cast, relative to its center.
+    pub pattern: Vec<(IVec3, AutomataState)>,
+    /// Steps the effect lasts.
+    pub lifetime: u64,
+    /// Voxels the effect affects at most, the pattern included.
+    pub max_voxels: usize,
+    /// Voxels farther than this from the center are never affected.
+    pub radius: f32,
+    pub expiry: EffectExpiry,
+}
+
+impl AutomataEffect {
+    pub fn new(rule: impl AutomataRuleSet, lifetime: u64, max_voxels: usize) -> Self {
+        Self {
+            rule: Arc::new(rule),
+            pattern: Vec::new(),
+            lifetime,
+            max_voxels,
+            radius: f32::INFINITY,
+            expiry: EffectExpiry::default(),
+        }
+    }
+
+    pub fn with_pattern(mut self, pattern: Vec<(IVec3, AutomataState)>) -> Self {
+        self.pattern = pattern;
+        self
+    }
+
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn with_expiry(mut self, expiry: EffectExpiry) -> Self {
+        self.expiry = expiry;
+        self
+    }
+}
+
+/// What happens to the voxels of an [`AutomataEffect`] once its lifetime is over.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EffectExpiry {
+    /// Every affected voxel gets back the state it had before the effect reached it.
+    #[default]
+    Revert,
+    /// Affected voxels keep their last state, held by a [`StasisVolume`] spawned over the box
+    /// around them and sent with [`AutomataEffectExpired`]. Despawning it hands them back to
+    /// the world's rule.
+    Freeze,
+}
+
+/// Sent when an [`AutomataEffect`] reaches the end of its lifetime.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutomataEffectExpired {
+    pub effect: Entity,
+    /// Voxels the effect affected.
+    pub voxels: usize,
+    pub expiry: EffectExpiry,
+    /// Volume holding the voxels of a [`EffectExpiry::Freeze`] effect.
+    pub stasis: Option<Entity>,
+}
+
+/// Progress of a cast [`AutomataEffect`], inserted once its pattern is written.
+#[derive(Component)]
+pub(super) struct ActiveEffect {
+    center: IVec3,
+    tracker: MaterialTracker,
+    /// State of every affected voxel before the effect reached it.
+    originals: HashMap<IVec3, AutomataState>,
+    age: u64,
+}
+
+impl ActiveEffect {
+    /// Evaluates the effect's rule over its voxels and their neighbors, returning the writes of
+    /// this step. `sample` reads the cells of the previous step.
+    fn step(
+        &mut self,
+        effect: &AutomataEffect,
+        step: u64,
+        sample: impl Fn(IVec3) -> Option<AutomataState>,
+    ) -> Vec<(IVec3, AutomataState)> {
+        let mut candidates: Vec<IVec3> = self
+            .originals
+            .keys()
+            .flat_map(|voxel| neighborhood().map(move |offset| *voxel + offset))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        // Owned voxels first so the growth budget goes to the closest voxels.
+        candidates.sort_by_key(|voxel| {
+            let owned = self.originals.contains_key(voxel);
+            (
+                !owned,
+                (*voxel - self.center).length_squared(),
+                voxel.to_array(),
+            )
+        });
+
+        let radius_squared = effect.radius * effect.radius;
+        let mut writes = Vec::new();
+        for voxel in candidates {
+            let owned = self.originals.contains_key(&voxel);
+            let in_range = (voxel - self.center).as_vec3().length_squared() <= radius_squared;
+            if !owned && (!in_range || self.originals.len() >= effect.max_voxels) {
+                continue;
+            }
+            let Some(current) = sample(voxel) else {
+                continue;
+            };
+
+            let mut neighbors = [None; 27];
+            for (neighbor, offset) in neighbors.iter_mut().zip(neighborhood()) {
+                if offset != IVec3::ZERO {
+                    *neighbor = sample(voxel + offset);
+                }
+            }
+            let ctx = CellContext::new(voxel, step, current, neighbors, &self.tracker);
+            let next = effect.rule.next_state(&ctx);
+            if owned || next != current {
+                self.originals.entry(voxel).or_insert(current);
+                writes.push((voxel, next));
+            }
+        }
+        self.age += 1;
+        writes
+    }
+
+    /// Writes undoing the effect, empty when its voxels are kept.
+    fn expire(&self, expiry: EffectExpiry) -> Vec<(IVec3, AutomataState)> {
+        match expiry {
+            EffectExpiry::Revert => self.originals.iter().map(|(v, s)| (*v, *s)).collect(),
+            EffectExpiry::Freeze => Vec::new(),
+        }
+    }
+
+    /// Voxels affected by the effect, `min` inclusive and `max` exclusive.
+    fn bounds(&self) -> Option<StasisBounds> {
+        let mut voxels = self.originals.keys();
+        let first = *voxels.next()?;
+        let (min, max) = voxels.fold((first, first), |(min, max), voxel| {
+            (min.min(*voxel), max.max(*voxel))
+        });
+        Some(StasisBounds {
+            min,
+            max: max + IVec3::ONE,
+        })
+    }
+}
+
+/// Offsets of the 3×3×3 block, in the order expected by [`CellContext::new`].
+fn neighborhood() -> impl Iterator<Item = IVec3> {
+    (-1..=1).flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| IVec3::new(x, y, z))))
+}
+
+/// Writes the pattern of an effect and starts it.
+pub(super) fn cast_effect(world: &mut World, entity: Entity, center: IVec3) {
+    // Despawned before the commands ran.
+    let Some(effect) = world.get::<AutomataEffect>(entity).cloned() else {
+        return;
+    };
+
+    let mut originals = HashMap::new();
+    let mut edits = Vec::new();
+    for &(offset, state) in effect.pattern.iter().take(effect.max_voxels) {
+        let voxel = center + offset;
+        let (chunk, local) = voxel_to_chunk(voxel);
+        let original = world
+            .resource::<ChunkIndex>()
+            .entity(chunk)
+            .and_then(|chunk| world.get::<ChunkCells>(chunk))
+            .map_or(AutomataState::EMPTY, |cells| cells.get(local));
+        originals.entry(voxel).or_insert(original);
+        edits.push((voxel, state));
+    }
+    super::edit::apply_voxel_edits(world, edits);
+
+    if let Some(mut entity) = world.get_entity_mut(entity) {
+        entity.insert(ActiveEffect {
+            center,
+            tracker: effect.rule.tracker(),
+            originals,
+            age: 0,
+        });
+    }
+}
+
+/// Steps every active effect after the passes, then ends the expired ones.
+///
+/// Writes to voxels held by a lock or a stasis volume are dropped, as they would be undone when
+/// the step is applied.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn step_effects(
+    mut commands: Commands,
+    snapshots: Res<ChunkSnapshots>,
+    index: Res<ChunkIndex>,
+    clock: Res<SimulationClock>,
+    locks: Res<RegionLocks>,
+    world_transform: Res<VoxelWorldTransform>,
+    stasis: Query<&StasisBounds>,
+    mut expired: EventWriter<AutomataEffectExpired>,
+    mut effects: Query<(Entity, &AutomataEffect, &mut ActiveEffect)>,
+    mut next_query: Query<&mut ChunkCellsNext>,
+) {
+    for (entity, effect, mut active) in effects.iter_mut() {
+        let writes = if active.age >= effect.lifetime {
+            commands.entity(entity).despawn_recursive();
+            let frozen = match effect.expiry {
+                EffectExpiry::Freeze => active.bounds(),
+                EffectExpiry::Revert => None,
+            };
+            let volume = frozen.map(|bounds| {
+                let center =
+                    world_transform.voxel_space_to_world((bounds.min + bounds.max).as_vec3() * 0.5);
+                let half_extents =
+                    (bounds.max - bounds.min).as_vec3() * 0.5 * world_transform.voxel_size;
+                commands
+                    .spawn((
+                        StasisVolume { half_extents },
+                        Transform::from_translation(center),
+                        GlobalTransform::from_translation(center),
+                    ))
+                    .id()
+            });
+            expired.send(AutomataEffectExpired {
+                effect: entity,
+                voxels: active.originals.len(),
+                expiry: effect.expiry,
+                stasis: volume,
+            });
+            active.expire(effect.expiry)
+        } else {
+            active.step(effect, clock.step, |voxel| {
+                let (chunk, local) = voxel_to_chunk(voxel);
+                snapshots.get(chunk)?;
+                sample_cell(&snapshots, chunk, local)
+            })
+        };
+
+        for (voxel, state) in writes {
+            let held =
+                locks.owner(voxel).is_some() || stasis.iter().any(|bounds| bounds.contains(voxel));
+            if held {
+                continue;
+            }
+            let (chunk, local) = voxel_to_chunk(voxel);
+            if let Some(mut next) = index
+                .entity(chunk)
+                .and_then(|entity| next_query.get_mut(entity).ok())
+            {
+                next.set(local, state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn growth_stops_at_budget_and_reverts() {
+        const CRYSTAL: u8 = 9;
+        let crystal = AutomataState::new(CRYSTAL, 0);
+        let stone = AutomataState::new(1, 0);
+        // Crystal spreads into every cell touching it.
+        let rule = move |ctx: &CellContext| match neighborhood()
+            .any(|offset| ctx.neighbor(offset) == Some(crystal))
+        {
+            true => crystal,
+            false => ctx.current,
+        };
+        let effect = AutomataEffect::new(rule, 2, 30).with_pattern(vec![(IVec3::ZERO, crystal)]);
+
+        let mut world: HashMap<IVec3, AutomataState> = HashMap::new();
+        world.insert(IVec3::X * 3, stone);
+        world.insert(IVec3::ZERO, crystal);
+        let mut active = ActiveEffect {
+            center: IVec3::ZERO,
+            tracker: MaterialTracker::default(),
+            originals: [(IVec3::ZERO, AutomataState::EMPTY)].into_iter().collect(),
+            age: 0,
+        };
+
+        for step in 0..2 {
+            let snapshot = world.clone();
+            let sample = |voxel: IVec3| {
+                Some(
+                    snapshot
+                        .get(&voxel)
+                        .copied()
+                        .unwrap_or(AutomataState::EMPTY),
+                )
+            };
+            for (voxel, state) in active.step(&effect, step, sample) {
+                world.insert(voxel, state);
+            }
+        }
+        assert_eq!(active.originals.len(), 30);
+        let crystals = world.values().filter(|state| **state == crystal).count();
+        assert_eq!(crystals, 30);
+
+        for (voxel, state) in active.expire(EffectExpiry::Revert) {
+            world.insert(voxel, state);
+        }
+        assert!(world.values().all(|state| *state != crystal));
+        assert_eq!(world[&(IVec3::X * 3)], stone);
+    }
+
+    fn effect_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(crate::CellularAutomataPlugin)
+            // Nothing is born or dies, only the effects change cells.
+            .insert_resource(crate::AutomataRule {
+                birth: Vec::new(),
+                survive: (0..=26).collect(),
+                ..default()
+            });
+        app.world.spawn(crate::ChunkBundle::new(IVec3::ZERO));
+        app.world.resource_mut::<crate::SimulationControl>().paused = true;
+        app.update();
+        app
+    }
+
+    fn step(app: &mut App) {
+        app.world
+            .resource_mut::<crate::SimulationControl>()
+            .step_once();
+        app.update();
+    }
+
+    fn cell(app: &mut App, voxel: IVec3) -> AutomataState {
+        let chunk = app
+            .world
+            .resource::<ChunkIndex>()
+            .entity(IVec3::ZERO)
+            .unwrap();
+        app.world.get::<ChunkCells>(chunk).unwrap().get(voxel)
+    }
+
+    /// Turns every empty cell touching a live one into `state`.
+    fn spreading(state: AutomataState) -> AutomataEffect {
+        let rule = move |ctx: &CellContext| match ctx.current.is_alive() {
+            false if neighborhood().any(|offset| ctx.neighbor(offset) == Some(state)) => state,
+            _ => ctx.current,
+        };
+        AutomataEffect::new(rule, 1, 100).with_pattern(vec![(IVec3::ZERO, state)])
+    }
+
+    #[test]
+    fn frozen_effects_are_held_by_a_stasis_volume() {
+        let crystal = AutomataState::new(9, 0);
+        let mut app = effect_app();
+        let effect = spreading(crystal).with_expiry(EffectExpiry::Freeze);
+        let center = IVec3::splat(8);
+        let entity = app.world.spawn(effect).id();
+        cast_effect(&mut app.world, entity, center);
+        step(&mut app);
+        step(&mut app);
+
+        let events = app.world.resource::<Events<AutomataEffectExpired>>();
+        let expired = *events.iter_current_update_events().next().unwrap();
+        assert_eq!(expired.expiry, EffectExpiry::Freeze);
+        let stasis = expired.stasis.unwrap();
+        app.update();
+        let bounds = *app.world.get::<StasisBounds>(stasis).unwrap();
+        assert_eq!(bounds.min, center - IVec3::ONE);
+        assert_eq!(bounds.max, center + IVec3::splat(2));
+        assert_eq!(cell(&mut app, center + IVec3::X), crystal);
+    }
+
+    #[test]
+    fn effects_leave_locked_voxels_alone() {
+        let crystal = AutomataState::new(9, 0);
+        let mut app = effect_app();
+        let center = IVec3::splat(8);
+        app.world
+            .resource_mut::<RegionLocks>()
+            .lock(center + IVec3::X, center + IVec3::splat(2))
+            .unwrap();
+        let entity = app.world.spawn(spreading(crystal)).id();
+        cast_effect(&mut app.world, entity, center);
+        step(&mut app);
+
+        assert_eq!(cell(&mut app, center - IVec3::X), crystal);
+        assert_eq!(cell(&mut app, center + IVec3::X), AutomataState::EMPTY);
+    }
+}
@@ -0,0 +1,246 @@
+use super::{linear_index, voxel_to_chunk, AutomataState, ChunkSnapshots, CHUNK_EDGE};
+use bevy::{prelude::*, utils::HashSet};
+
+/// Cells read around a chunk: fluxes into a cell depend on cells up to two voxels away.
+const HALO: i32 = 2;
+const PADDED: i32 = CHUNK_EDGE + HALO * 2;
+const LATERAL: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+/// Water style flow run by [`AuxRule::Fluid`](super::AuxRule::Fluid), the [`ChunkAux`] value
+/// of each voxel being how much fluid it holds.
+///
+/// Every step fluid first falls into the voxel below as far as it has room, then voxels
+/// holding more than `capacity` push half of the excess up, and what is left spreads to the
+/// four side neighbors, each taking a fifth of the difference. Levels above `capacity` are
+/// pressure, so connected pools settle to the same height.
+///
+/// Each flow between two voxels is computed from the previous step the same way by both of
+/// them, so the total volume is conserved, including across chunk borders. Live cells of
+/// another material, chunks without [`ChunkAux`] and unloaded chunks are walls. Levels
+/// saturate at `u16::MAX`.
+///
+/// [`ChunkAux`]: super::ChunkAux
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FluidRule {
+    /// Level of a full voxel.
+    pub capacity: u16,
+    /// Material given to voxels holding fluid, and cleared from voxels that ran dry, so the
+    /// fluid shows in the cells. `0` leaves the cells alone.
+    pub material: u8,
+}
+
+impl Default for FluidRule {
+    fn default() -> Self {
+        Self {
+            capacity: 255,
+            material: 0,
+        }
+    }
+}
+
+/// Levels and walls of a chunk and its halo, from the snapshots. Chunks that are unloaded or
+/// not stepped this step are walls.
+struct FluidGrid {
+    levels: Vec<i32>,
+    walls: Vec<bool>,
+}
+
+impl FluidGrid {
+    fn read(
+        rule: &FluidRule,
+        snapshots: &ChunkSnapshots,
+        stepping: &HashSet<IVec3>,
+        coords: IVec3,
+    ) -> Self {
+        let volume = (PADDED * PADDED * PADDED) as usize;
+        let mut grid = Self {
+            levels: vec![0; volume],
+            walls: vec![true; volume],
+        };
+        let origin = coords * CHUNK_EDGE - HALO;
+        for x in 0..PADDED {
+            for y in 0..PADDED {
+                for z in 0..PADDED {
+                    let (chunk, local) = voxel_to_chunk(origin + IVec3::new(x, y, z));
+                    if !stepping.contains(&chunk) {
+                        continue;
+                    }
+                    let (Some(cells), Some(aux)) = (snapshots.get(chunk), snapshots.aux(chunk))
+                    else {
+                        continue;
+                    };
+                    let index = Self::index(IVec3::new(x, y, z));
+                    let state = cells[linear_index(local)];
+                    grid.walls[index] = state.is_alive() && state.material != rule.material;
+                    if !grid.walls[index] {
+                        grid.levels[index] = aux[linear_index(local)] as i32;
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    #[inline]
+    fn index(pos: IVec3) -> usize {
+        (pos.x * PADDED * PADDED + pos.y * PADDED + pos.z) as usize
+    }
+
+    #[inline]
+    fn wall(&self, pos: IVec3) -> bool {
+        let outside = pos.cmplt(IVec3::ZERO).any() || pos.cmpge(IVec3::splat(PADDED)).any();
+        outside || self.walls[Self::index(pos)]
+    }
+
+    #[inline]
+    fn level(&self, pos: IVec3) -> i32 {
+        if self.wall(pos) {
+            0
+        } else {
+            self.levels[Self::index(pos)]
+        }
+    }
+
+    /// Fluid falling out of `pos` into the voxel below.
+    fn down(&self, rule: &FluidRule, pos: IVec3) -> i32 {
+        let below = pos - IVec3::Y;
+        if self.wall(pos) || self.wall(below) {
+            return 0;
+        }
+        let room = (rule.capacity as i32 - self.level(below)).max(0);
+        self.level(pos).min(room)
+    }
+
+    /// Level of `pos` once its fluid fell.
+    fn fallen(&self, rule: &FluidRule, pos: IVec3) -> i32 {
+        self.level(pos) - self.down(rule, pos)
+    }
+
+    /// Fluid pushed out of `pos` into the voxel above.
+    fn up(&self, rule: &FluidRule, pos: IVec3) -> i32 {
+        let above = pos + IVec3::Y;
+        if self.wall(pos) || self.wall(above) {
+            return 0;
+        }
+        let fallen = self.fallen(rule, pos);
+        let excess = fallen - rule.capacity as i32;
+        excess.min((fallen - self.fallen(rule, above)) / 2).max(0)
+    }
+
+    /// Level of `pos` left to spread sideways.
+    fn settled(&self, rule: &FluidRule, pos: IVec3) -> i32 {
+        self.fallen(rule, pos) - self.up(rule, pos)
+    }
+
+    /// Fluid spreading from `from` into the side neighbor `to`.
+    fn spread(&self, rule: &FluidRule, from: IVec3, to: IVec3) -> i32 {
+        if self.wall(from) || self.wall(to) {
+            return 0;
+        }
+        (self.settled(rule, from) - self.settled(rule, to)).max(0) / 5
+    }
+
+    /// Level of `pos` after the step.
+    fn next_level(&self, rule: &FluidRule, pos: IVec3) -> i32 {
+        let mut level = self.settled(rule, pos);
+        for side in LATERAL {
+            level += self.spread(rule, pos + side, pos) - self.spread(rule, pos, pos + side);
+        }
+        level + self.down(rule, pos + IVec3::Y) + self.up(rule, pos - IVec3::Y)
+    }
+}
+
+/// Computes the next fluid levels of the chunk at `coords` into `next_levels`, updating
+/// `next_cells` when the rule has a material.
+pub(super) fn step_fluid(
+    rule: &FluidRule,
+    snapshots: &ChunkSnapshots,
+    stepping: &HashSet<IVec3>,
+    coords: IVec3,
+    next_cells: &mut [AutomataState],
+    next_levels: &mut [u16],
+) {
+    let grid = FluidGrid::read(rule, snapshots, stepping, coords);
+    for x in 0..CHUNK_EDGE {
+        for y in 0..CHUNK_EDGE {
+            for z in 0..CHUNK_EDGE {
+                let local = IVec3::new(x, y, z);
+                let pos = local + HALO;
+                if grid.wall(pos) {
+                    continue;
+                }
+                let index = linear_index(local);
+                let level = grid.next_level(rule, pos).clamp(0, u16::MAX as i32) as u16;
+                next_levels[index] = level;
+
+                if rule.material == 0 {
+                    continue;
+                }
+                let cell = &mut next_cells[index];
+                if level > 0 && !cell.is_alive() {
+                    *cell = AutomataState::new(rule.material, 0);
+                } else if level == 0 && cell.material == rule.material {
+                    *cell = AutomataState::EMPTY;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CHUNK_VOLUME;
+    use std::sync::Arc;
+
+    #[test]
+    fn fluid_volume_is_conserved_across_chunks() {
+        let rule = FluidRule::default();
+        let chunks = [IVec3::ZERO, IVec3::X, IVec3::NEG_Y];
+        let mut cells = vec![AutomataState::EMPTY; CHUNK_VOLUME];
+        // A wall with a gap, so fluid has to find its way around.
+        for y in 0..CHUNK_EDGE {
+            for z in 2..CHUNK_EDGE {
+                cells[linear_index(IVec3::new(20, y, z))] = AutomataState::new(1, 0);
+            }
+        }
+        let mut levels: Vec<Vec<u16>> = vec![vec![0; CHUNK_VOLUME]; chunks.len()];
+        for x in 24..CHUNK_EDGE {
+            for y in 10..20 {
+                levels[0][linear_index(IVec3::new(x, y, 5))] = 1000;
+            }
+        }
+        let total = |levels: &[Vec<u16>]| -> u64 {
+            levels.iter().flatten().map(|level| *level as u64).sum()
+        };
+        let before = total(&levels);
+
+        let mut snapshots = ChunkSnapshots::default();
+        let stepping = HashSet::from_iter(chunks);
+        for _ in 0..20 {
+            let cells: Arc<[AutomataState]> = Arc::from(cells.as_slice());
+            snapshots.rebuild(chunks.iter().map(|coords| (*coords, cells.clone())));
+            snapshots.rebuild_aux(
+                chunks
+                    .iter()
+                    .zip(&levels)
+                    .map(|(coords, levels)| (*coords, Arc::from(levels.as_slice()))),
+            );
+            for (coords, levels) in chunks.iter().zip(&mut levels) {
+                let mut next_cells = cells.to_vec();
+                step_fluid(
+                    &rule,
+                    &snapshots,
+                    &stepping,
+                    *coords,
+                    &mut next_cells,
+                    levels,
+                );
+            }
+            assert_eq!(total(&levels), before);
+        }
+        // Some fluid crossed into the neighboring chunks.
+        assert!(levels[1].iter().any(|level| *level > 0));
+        assert!(levels[2].iter().any(|level| *level > 0));
+    }
+}
@@ -0,0 +1,405 @@
+use super::{
+    backend::report_backend_changes, gather_step_sources, linear_index, sample_cell,
+    stepper::release_sources, write_step_results, AutomataRule, AutomataRuleSet, AutomataState,
+    AutomataStepper, BackendChanged, BoxedRule, BufferPool, ChunkCells, ChunkCellsNext, ChunkKey,
+    ChunkSnapshots, SimulationBackend, SimulationClock, SimulationPassSet, SimulationSchedule,
+    SimulationSet, StepQuery, CHUNK_EDGE, CHUNK_VOLUME, LIFE_PASS,
+};
+use crate::EngineEvent;
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::*,
+        renderer::{RenderDevice, RenderQueue},
+    },
+};
+use std::borrow::Cow;
+
+const PADDED_EDGE: i32 = CHUNK_EDGE + 2;
+const PADDED_VOLUME: usize = (PADDED_EDGE * PADDED_EDGE * PADDED_EDGE) as usize;
+/// Neighbor counts range over `0..=26`, so birth/survive lists fit a `u32` mask.
+const MAX_NEIGHBORS: u8 = 26;
+
+/// Steps the automata in a WGSL compute shader when [`SimulationBackend::Gpu`] is selected.
+///
+/// Chunks are uploaded with a one cell halo, stepped, and read back into [`ChunkCellsNext`]
+/// within the same simulation pass, so everything downstream sees the same data as with the
+/// CPU backend. Only the total-count `birth`/`survive` lists with the synchronous stepper are
+/// supported, material rules and the other steppers stay on the CPU.
+pub struct GpuAutomataPlugin;
+
+impl Plugin for GpuAutomataPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationBackend>()
+            .add_event::<BackendChanged>()
+            .add_systems(
+                SimulationSchedule,
+                step_chunks_gpu
+                    .run_if(gpu_step_active)
+                    .in_set(SimulationPassSet(LIFE_PASS)),
+            )
+            .add_systems(
+                PostUpdate,
+                release_gpu_buffers
+                    .after(report_backend_changes)
+                    .before(SimulationSet::Run),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_device) = app.world.get_resource::<RenderDevice>() else {
+            warn!("No render device available, the GPU automata backend is disabled");
+            return;
+        };
+        let gpu_automata = GpuAutomata::new(render_device);
+        app.insert_resource(gpu_automata);
+    }
+}
+
+/// Pipeline and buffers of the GPU backend, living in the main world.
+#[derive(Resource)]
+pub struct GpuAutomata {
+    pipeline: ComputePipeline,
+    bind_group_layout: BindGroupLayout,
+    uniform_buffer: UniformBuffer<StepUniforms>,
+    /// Number of chunks the buffers below can hold.
+    capacity: usize,
+    input: Buffer,
+    output: Buffer,
+    readback: Buffer,
+}
+
+#[derive(Clone, Default, ShaderType)]
+struct StepUniforms {
+    birth: u32,
+    survive: u32,
+    birth_material: u32,
+    chunk_count: u32,
+}
+
+impl GpuAutomata {
+    fn new(render_device: &RenderDevice) -> Self {
+        let shader = render_device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("automata step shader"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("automata_step.wgsl"))),
+        });
+
+        let bind_group_layout =
+            render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("automata step bind group layout"),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: BufferSize::new(StepUniforms::SHADER_SIZE.into()),
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: BufferSize::new(4),
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: BufferSize::new(4),
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let pipeline_layout = render_device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("automata step pipeline layout"),
+            bind_group_layouts: &[&*bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = render_device.create_compute_pipeline(&RawComputePipelineDescriptor {
+            label: Some("automata step pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "step",
+        });
+
+        let (input, output, readback) = Self::create_buffers(render_device, 1);
+        Self {
+            pipeline,
+            bind_group_layout,
+            uniform_buffer: UniformBuffer::default(),
+            capacity: 1,
+            input,
+            output,
+            readback,
+        }
+    }
+
+    fn create_buffers(render_device: &RenderDevice, capacity: usize) -> (Buffer, Buffer, Buffer) {
+        let input = render_device.create_buffer(&BufferDescriptor {
+            label: Some("automata step input"),
+            size: (capacity * PADDED_VOLUME * 4) as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let output = render_device.create_buffer(&BufferDescriptor {
+            label: Some("automata step output"),
+            size: (capacity * CHUNK_VOLUME * 4) as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = render_device.create_buffer(&BufferDescriptor {
+            label: Some("automata step readback"),
+            size: (capacity * CHUNK_VOLUME * 4) as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        (input, output, readback)
+    }
+
+    /// Whether the compute shader implements the rule and stepper.
+    pub fn supports(rule: &AutomataRule, stepper: &AutomataStepper) -> bool {
+        *stepper == AutomataStepper::Synchronous
+            && rule.totalistic().is_some()
+            && rule
+                .birth
+                .iter()
+                .chain(rule.survive.iter())
+                .all(|&count| count <= MAX_NEIGHBORS)
+    }
+}
+
+/// Shrinks the GPU buffers back to a single chunk once the GPU backend is left.
+fn release_gpu_buffers(
+    mut changes: EventReader<BackendChanged>,
+    gpu_automata: Option<ResMut<GpuAutomata>>,
+    render_device: Option<Res<RenderDevice>>,
+) {
+    let left_gpu = changes
+        .read()
+        .any(|change| change.previous == SimulationBackend::Gpu);
+    let (Some(mut gpu_automata), Some(render_device)) = (gpu_automata, render_device) else {
+        return;
+    };
+    if left_gpu && gpu_automata.capacity > 1 {
+        let (input, output, readback) = GpuAutomata::create_buffers(&render_device, 1);
+        gpu_automata.capacity = 1;
+        gpu_automata.input = input;
+        gpu_automata.output = output;
+        gpu_automata.readback = readback;
+    }
+}
+
+fn gpu_step_active(
+    backend: Res<SimulationBackend>,
+    rule: Res<AutomataRule>,
+    boxed_rule: Option<Res<BoxedRule>>,
+    stepper: Res<AutomataStepper>,
+    gpu_automata: Option<Res<GpuAutomata>>,
+) -> bool {
+    *backend == SimulationBackend::Gpu
+        && gpu_automata.is_some()
+        && boxed_rule.is_none()
+        && GpuAutomata::supports(&rule, &stepper)
+}
+
+/// Run condition of the CPU step, which covers every case the GPU backend does not.
+pub(super) fn cpu_step_active(
+    backend: Res<SimulationBackend>,
+    rule: Res<AutomataRule>,
+    boxed_rule: Option<Res<BoxedRule>>,
+    stepper: Res<AutomataStepper>,
+    gpu_automata: Option<Res<GpuAutomata>>,
+) -> bool {
+    !gpu_step_active(backend, rule, boxed_rule, stepper, gpu_automata)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn step_chunks_gpu(
+    mut gpu_automata: ResMut<GpuAutomata>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    snapshots: Res<ChunkSnapshots>,
+    rule: Res<AutomataRule>,
+    clock: Res<SimulationClock>,
+    pool: Res<BufferPool>,
+    query: StepQuery,
+    mut next_query: Query<&mut ChunkCellsNext>,
+    mut engine_events: EventWriter<EngineEvent>,
+) {
+    let sources = gather_step_sources(&snapshots, &clock, &query, &mut next_query, &pool);
+    if sources.is_empty() {
+        return;
+    }
+
+    let gpu_automata = gpu_automata.as_mut();
+    if sources.len() > gpu_automata.capacity {
+        let capacity = sources.len().next_power_of_two();
+        let (input, output, readback) = GpuAutomata::create_buffers(&render_device, capacity);
+        gpu_automata.capacity = capacity;
+        gpu_automata.input = input;
+        gpu_automata.output = output;
+        gpu_automata.readback = readback;
+    }
+
+    // Pack every chunk along with a one cell halo of its neighbors.
+    let mut input = Vec::with_capacity(sources.len() * PADDED_VOLUME);
+    for source in sources.iter() {
+        for x in -1..=CHUNK_EDGE {
+            for y in -1..=CHUNK_EDGE {
+                for z in -1..=CHUNK_EDGE {
+                    let local = IVec3::new(x, y, z);
+                    let inside = local.cmpge(IVec3::ZERO).all()
+                        && local.cmplt(IVec3::splat(CHUNK_EDGE)).all();
+                    let state = if inside {
+                        source.cells[linear_index(local)]
+                    } else {
+                        sample_cell(&snapshots, source.coords, local)
+                            .unwrap_or(AutomataState::EMPTY)
+                    };
+                    input.push(state.to_packed() as u32);
+                }
+            }
+        }
+    }
+    render_queue.write_buffer(&gpu_automata.input, 0, bytemuck::cast_slice(&input));
+
+    let mask = |counts: &[u8]| counts.iter().fold(0u32, |mask, &count| mask | 1 << count);
+    gpu_automata.uniform_buffer.set(StepUniforms {
+        birth: mask(&rule.birth),
+        survive: mask(&rule.survive),
+        birth_material: rule.birth_material as u32,
+        chunk_count: sources.len() as u32,
+    });
+    gpu_automata
+        .uniform_buffer
+        .write_buffer(&render_device, &render_queue);
+
+    let bind_group = render_device.create_bind_group(
+        "automata step bind group",
+        &gpu_automata.bind_group_layout,
+        &[
+            BindGroupEntry {
+                binding: 0,
+                resource: gpu_automata.uniform_buffer.binding().unwrap(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: gpu_automata.input.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: gpu_automata.output.as_entire_binding(),
+            },
+        ],
+    );
+
+    let output_size = (sources.len() * CHUNK_VOLUME * 4) as u64;
+    let mut encoder = render_device.create_command_encoder(&CommandEncoderDescriptor::default());
+    {
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor::default());
+        pass.set_pipeline(&gpu_automata.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        let edge = CHUNK_EDGE as u32 / 4;
+        pass.dispatch_workgroups(sources.len() as u32 * edge, edge, edge);
+    }
+    encoder.copy_buffer_to_buffer(
+        &gpu_automata.output,
+        0,
+        &gpu_automata.readback,
+        0,
+        output_size,
+    );
+    render_queue.submit([encoder.finish()]);
+
+    // Wait for the results so they land in this step, like the physics readback.
+    let readback_slice = gpu_automata.readback.slice(..output_size);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    readback_slice.map_async(MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    render_device.poll(wgpu::Maintain::Wait);
+
+    if !matches!(receiver.try_recv(), Ok(Ok(()))) {
+        // Keep the previous cells rather than stepping from garbage.
+        let results = sources
+            .iter()
+            .map(|source| (source.entity, pool.acquire(&source.cells)))
+            .collect();
+        release_sources(sources, &pool);
+        write_step_results(results, &mut next_query, &pool);
+        EngineEvent::GpuReadbackFailed {
+            source: "automata step",
+        }
+        .report(&mut engine_events);
+        return;
+    }
+
+    let data = readback_slice.get_mapped_range();
+    let output: &[u32] = bytemuck::cast_slice(&data);
+    let results = sources
+        .iter()
+        .zip(output.chunks_exact(CHUNK_VOLUME))
+        .map(|(source, readback)| {
+            let mut cells = pool.acquire(&source.cells);
+            for (cell, &packed) in cells.iter_mut().zip(readback) {
+                *cell = AutomataState::from_packed(packed as u16);
+            }
+            (source.entity, cells)
+        })
+        .collect();
+
+    drop(data);
+    gpu_automata.readback.unmap();
+
+    release_sources(sources, &pool);
+    write_step_results(results, &mut next_query, &pool);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MaterialRule, Neighborhood};
+
+    #[test]
+    fn only_plain_synchronous_rules_run_on_the_gpu() {
+        let rule = AutomataRule::default();
+        assert!(GpuAutomata::supports(&rule, &AutomataStepper::Synchronous));
+        assert!(!GpuAutomata::supports(
+            &rule,
+            &AutomataStepper::Checkerboard
+        ));
+
+        let unsupported = [
+            AutomataRule {
+                survive: vec![MAX_NEIGHBORS + 1],
+                ..default()
+            },
+            AutomataRule {
+                neighborhood: Neighborhood::VonNeumann,
+                ..default()
+            },
+            AutomataRule {
+                material_rules: vec![MaterialRule {
+                    material: 1,
+                    birth: vec![],
+                    survive: vec![],
+                }],
+                ..default()
+            },
+        ];
+        for rule in unsupported {
+            assert!(!GpuAutomata::supports(&rule, &AutomataStepper::Synchronous));
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use super::{stepper::block_cell, AutomataRuleSet, AutomataState, CellContext};
+
+/// Lower cells of a 2×2×2 block indexed by `x | y << 1 | z << 2`, the cell above each one is
+/// at `index | 2`.
+const FLOOR: [usize; 4] = [0, 1, 4, 5];
+
+/// Falling sand rule run by the [`AutomataStepper::Granular`](super::AutomataStepper::Granular)
+/// stepper.
+///
+/// Works on the same 2×2×2 block partition as the Margolus stepper: in every block, grains
+/// fall onto the empty cell below them, and grains resting on something slide down to an empty
+/// lower cell of another column when the cell above it is empty too, so piles spread out into
+/// slopes. Every block is a permutation of its cells computed the same way by both chunks it
+/// straddles, so material is conserved across chunk borders and the result does not depend on
+/// the order chunks are stepped in. Blocks with a cell in an unloaded chunk, or in a chunk not
+/// stepped this step because of its [`ChunkLod`](super::ChunkLod) or because it sleeps, are
+/// left untouched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GranularRule {
+    /// Material that never moves, for floors and walls. `0` lets every material move.
+    pub fixed: u8,
+    /// Grains slide diagonally off piles, otherwise they only fall and stack into columns.
+    pub slide: bool,
+}
+
+impl Default for GranularRule {
+    fn default() -> Self {
+        Self {
+            fixed: 0,
+            slide: true,
+        }
+    }
+}
+
+impl GranularRule {
+    /// Applies the rule to a block indexed by `x | y << 1 | z << 2`, Y pointing up. The order
+    /// in which sliding grains try the other columns alternates with `step`, so slopes form
+    /// evenly on every side.
+    pub fn apply(&self, block: [AutomataState; 8], step: u64) -> [AutomataState; 8] {
+        let moves = |state: AutomataState| state.is_alive() && state.material != self.fixed;
+
+        let mut output = block;
+        for lower in FLOOR {
+            let upper = lower | 2;
+            if moves(output[upper]) && !output[lower].is_alive() {
+                output.swap(upper, lower);
+            }
+        }
+        if !self.slide {
+            return output;
+        }
+
+        // Across the block first, then along X and Z in an order that flips every step.
+        let sides = match step & 1 {
+            0 => [5, 1, 4],
+            _ => [5, 4, 1],
+        };
+        for lower in FLOOR {
+            let upper = lower | 2;
+            if !moves(output[upper]) {
+                continue;
+            }
+            let target = sides
+                .iter()
+                .map(|side| lower ^ side)
+                .find(|target| !output[*target].is_alive() && !output[*target | 2].is_alive());
+            if let Some(target) = target {
+                output.swap(upper, target);
+            }
+        }
+        output
+    }
+}
+
+/// Evaluates the block rule cell by cell, so it can also be installed as a
+/// [`BoxedRule`](super::BoxedRule). With
+/// [`AutomataStepper::Synchronous`](super::AutomataStepper::Synchronous) it matches
+/// [`AutomataStepper::Granular`](super::AutomataStepper::Granular) as long as every chunk steps
+/// together, blocks straddling a chunk that is not stepped are not held back.
+impl AutomataRuleSet for GranularRule {
+    fn next_state(&self, ctx: &CellContext) -> AutomataState {
+        block_cell(ctx, |block| self.apply(block, ctx.step))
+    }
+
+    fn name(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AutomataStepper, CellularAutomataPlugin, ChunkBundle, ChunkCells, SimulationAnchor,
+        SimulationControl, SimulationProfile,
+    };
+    use bevy::prelude::*;
+
+    #[test]
+    fn grains_fall_slide_and_rest() {
+        let sand = AutomataState::new(2, 0);
+        let rule = GranularRule::default();
+
+        let mut falling = [AutomataState::EMPTY; 8];
+        falling[2] = sand;
+        assert_eq!(rule.apply(falling, 0)[0], sand);
+
+        let mut pile = [AutomataState::EMPTY; 8];
+        pile[0] = sand;
+        pile[2] = sand;
+        let slid = rule.apply(pile, 0);
+        assert_eq!(slid[0], sand);
+        assert_eq!(slid[5], sand);
+        assert_eq!(slid.iter().filter(|state| state.is_alive()).count(), 2);
+        let fall = GranularRule {
+            slide: false,
+            ..rule
+        };
+        assert_eq!(fall.apply(pile, 0), pile);
+
+        let floor = AutomataState::new(1, 0);
+        let mut resting = [sand; 8];
+        for lower in FLOOR {
+            resting[lower] = floor;
+        }
+        assert_eq!(rule.apply(resting, 1), resting);
+        let walls = GranularRule { fixed: 2, ..rule };
+        assert_eq!(walls.apply(falling, 0), falling);
+    }
+
+    #[test]
+    fn grains_are_conserved_next_to_throttled_chunks() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(CellularAutomataPlugin)
+            .insert_resource(AutomataStepper::Granular(GranularRule::default()));
+        // The anchor steps the first chunk every step and its neighbor every other step.
+        app.world.spawn((
+            SimulationAnchor {
+                profile: SimulationProfile {
+                    full_rate_radius: 1.0,
+                    reduced_rate_radius: 100.0,
+                    frozen_radius: 100.0,
+                    reduced_rate_interval: 2,
+                    ..default()
+                },
+            },
+            GlobalTransform::IDENTITY,
+        ));
+        let sand = AutomataState::new(2, 0);
+        let grains = |local: IVec3| match (local.x + local.y * 3 + local.z * 7) % 4 == 0 {
+            true => sand,
+            false => AutomataState::EMPTY,
+        };
+        let chunks = [IVec3::ZERO, IVec3::X].map(|coords| {
+            app.world
+                .spawn(ChunkBundle::from_generator(coords, grains))
+                .id()
+        });
+        let mass = |app: &App| {
+            chunks
+                .iter()
+                .map(|chunk| {
+                    let cells = app.world.get::<ChunkCells>(*chunk).unwrap();
+                    cells
+                        .storage()
+                        .iter()
+                        .filter(|cell| cell.is_alive())
+                        .count()
+                })
+                .sum::<usize>()
+        };
+
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        app.update();
+        let before = mass(&app);
+        for _ in 0..8 {
+            app.world.resource_mut::<SimulationControl>().step_once();
+            app.update();
+        }
+        assert_eq!(mass(&app), before);
+    }
+}
@@ -0,0 +1,185 @@
+use super::{
+    linear_index, voxel_to_chunk, AutomataState, ChunkCells, ChunkCellsNext, ChunkIndex,
+    SimulationClock, CHUNK_EDGE,
+};
+use bevy::{prelude::*, utils::HashMap};
+use std::collections::VecDeque;
+
+/// What changed a voxel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionCause {
+    /// The simulation passes, including stasis volumes restoring cells.
+    Rule,
+    /// [`VoxelCommands`](super::VoxelCommands) or other direct edits.
+    Edit,
+    /// State received from a remote peer.
+    Network,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoxelTransition {
+    /// Step during which the transition happened.
+    pub step: u64,
+    pub old: AutomataState,
+    pub new: AutomataState,
+    pub cause: TransitionCause,
+}
+
+/// Debug log of the last transitions of voxels inside watched regions, available with the
+/// `voxel_history` feature.
+///
+/// Meant for figuring out why a voxel of a custom rule died. Watching large regions is slow,
+/// every watched voxel is compared on every step.
+#[derive(Resource, Debug, Clone)]
+pub struct VoxelHistory {
+    /// Number of transitions kept per voxel.
+    pub capacity: usize,
+    /// Watched voxel boxes, `min` inclusive and `max` exclusive.
+    watched: Vec<(IVec3, IVec3)>,
+    transitions: HashMap<IVec3, VecDeque<VoxelTransition>>,
+}
+
+impl Default for VoxelHistory {
+    fn default() -> Self {
+        Self {
+            capacity: 16,
+            watched: Vec::new(),
+            transitions: HashMap::new(),
+        }
+    }
+}
+
+impl VoxelHistory {
+    pub fn watch(&mut self, min: IVec3, max: IVec3) {
+        self.watched.push((min, max));
+    }
+
+    /// Stops watching every region and forgets the recorded transitions.
+    pub fn clear(&mut self) {
+        self.watched.clear();
+        self.transitions.clear();
+    }
+
+    pub fn is_watched(&self, voxel: IVec3) -> bool {
+        self.watched
+            .iter()
+            .any(|(min, max)| voxel.cmpge(*min).all() && voxel.cmplt(*max).all())
+    }
+
+    /// Recorded transitions of a voxel, oldest first.
+    pub fn transitions(&self, voxel: IVec3) -> impl Iterator<Item = &VoxelTransition> {
+        self.transitions.get(&voxel).into_iter().flatten()
+    }
+
+    /// Records a transition if the voxel is watched and actually changed.
+    pub fn record(&mut self, voxel: IVec3, transition: VoxelTransition) {
+        if transition.old == transition.new || !self.is_watched(voxel) {
+            return;
+        }
+
+        let transitions = self.transitions.entry(voxel).or_default();
+        transitions.push_back(transition);
+        while transitions.len() > self.capacity {
+            transitions.pop_front();
+        }
+    }
+}
+
+/// Records the transitions of the step about to be applied.
+pub(super) fn record_step_transitions(
+    mut history: ResMut<VoxelHistory>,
+    clock: Res<SimulationClock>,
+    index: Res<ChunkIndex>,
+    chunks: Query<(&ChunkCells, &ChunkCellsNext)>,
+) {
+    // `end_step` already advanced the clock.
+    let step = clock.step.saturating_sub(1);
+    let watched = history.watched.clone();
+
+    for (min, max) in watched {
+        for x in min.x..max.x {
+            for y in min.y..max.y {
+                for z in min.z..max.z {
+                    let voxel = IVec3::new(x, y, z);
+                    let (chunk, local) = voxel_to_chunk(voxel);
+                    let Some((cells, next)) = index
+                        .entity(chunk)
+                        .and_then(|entity| chunks.get(entity).ok())
+                    else {
+                        continue;
+                    };
+
+                    let i = linear_index(local);
+                    history.record(
+                        voxel,
+                        VoxelTransition {
+                            step,
+                            old: cells.get(local),
+                            new: next.as_slice()[i],
+                            cause: TransitionCause::Rule,
+                        },
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Records edits made to a chunk before they are written.
+pub(super) fn record_edits(
+    world: &World,
+    coords: IVec3,
+    cells: &ChunkCells,
+    edits: &[(IVec3, AutomataState)],
+) -> Vec<(IVec3, VoxelTransition)> {
+    let Some(history) = world.get_resource::<VoxelHistory>() else {
+        return Vec::new();
+    };
+    let step = world.resource::<SimulationClock>().step;
+
+    edits
+        .iter()
+        .map(|&(local, new)| (coords * CHUNK_EDGE + local, local, new))
+        .filter(|(voxel, ..)| history.is_watched(*voxel))
+        .map(|(voxel, local, new)| {
+            (
+                voxel,
+                VoxelTransition {
+                    step,
+                    old: cells.get(local),
+                    new,
+                    cause: TransitionCause::Edit,
+                },
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_last_transitions_of_watched_voxels() {
+        let mut history = VoxelHistory {
+            capacity: 2,
+            ..default()
+        };
+        history.watch(IVec3::ZERO, IVec3::splat(4));
+
+        for step in 0..3 {
+            let transition = VoxelTransition {
+                step,
+                old: AutomataState::new(step as u8, 0),
+                new: AutomataState::new(step as u8 + 1, 0),
+                cause: TransitionCause::Rule,
+            };
+            history.record(IVec3::ONE, transition);
+            history.record(IVec3::splat(4), transition);
+        }
+
+        let steps: Vec<_> = history.transitions(IVec3::ONE).map(|t| t.step).collect();
+        assert_eq!(steps, vec![1, 2]);
+        assert_eq!(history.transitions(IVec3::splat(4)).count(), 0);
+    }
+}
@@ -0,0 +1,116 @@
+use super::{
+    linear_index, voxel_to_chunk, AutomataState, ChunkCells, ChunkIndex, ChunkSnapshots,
+    SimulationClock,
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+/// State of a voxel before and after the last applied simulation step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoxelOccupancy {
+    pub previous: AutomataState,
+    pub current: AutomataState,
+}
+
+impl VoxelOccupancy {
+    /// Occupancy blended between the two steps, `alpha` usually being
+    /// [`SimulationClock::alpha`].
+    #[inline]
+    pub fn lerp(&self, alpha: f32) -> f32 {
+        let previous = self.previous.is_alive() as u8 as f32;
+        let current = self.current.is_alive() as u8 as f32;
+        previous + (current - previous) * alpha.clamp(0.0, 1.0)
+    }
+
+    #[inline]
+    pub fn changed(&self) -> bool {
+        self.previous != self.current
+    }
+}
+
+/// Read-only access to the last two simulation steps for code running every frame.
+///
+/// Cells are only written in [`SimulationSet::Apply`](super::SimulationSet::Apply), so reads
+/// through this parameter never observe a half-applied step. Combine [`Self::occupancy`] with
+/// [`Self::alpha`] to smooth effects over the fixed simulation cadence.
+#[derive(SystemParam)]
+pub struct InterpolatedVoxels<'w, 's> {
+    clock: Res<'w, SimulationClock>,
+    index: Res<'w, ChunkIndex>,
+    snapshots: Res<'w, ChunkSnapshots>,
+    chunks: Query<'w, 's, &'static ChunkCells>,
+}
+
+impl<'w, 's> InterpolatedVoxels<'w, 's> {
+    /// Progress towards the next simulation step, in `0..1`.
+    #[inline]
+    pub fn alpha(&self) -> f32 {
+        self.clock.alpha()
+    }
+
+    /// State of a voxel after the last applied step, `None` if its chunk is not loaded.
+    pub fn current(&self, voxel: IVec3) -> Option<AutomataState> {
+        let (chunk, local) = voxel_to_chunk(voxel);
+        let entity = self.index.entity(chunk)?;
+        self.chunks.get(entity).ok().map(|cells| cells.get(local))
+    }
+
+    /// State of a voxel before the last applied step, `None` if its chunk was not loaded.
+    pub fn previous(&self, voxel: IVec3) -> Option<AutomataState> {
+        let (chunk, local) = voxel_to_chunk(voxel);
+        let cells = self.snapshots.get(chunk)?;
+        Some(cells[linear_index(local)])
+    }
+
+    /// Previous and current state of a voxel. Voxels of chunks loaded since the last step
+    /// report their current state for both.
+    pub fn occupancy(&self, voxel: IVec3) -> Option<VoxelOccupancy> {
+        let current = self.current(voxel)?;
+        Some(VoxelOccupancy {
+            previous: self.previous(voxel).unwrap_or(current),
+            current,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CellularAutomataPlugin, ChunkBundle, SimulationControl};
+    use bevy::ecs::system::SystemState;
+
+    #[test]
+    fn occupancy_spans_the_last_applied_step() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(CellularAutomataPlugin);
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        let lone = IVec3::splat(4);
+        let alive = AutomataState::new(1, 0);
+        app.world
+            .spawn(ChunkBundle::from_generator(IVec3::ZERO, |local| {
+                match local == lone {
+                    true => alive,
+                    false => AutomataState::EMPTY,
+                }
+            }));
+        app.update();
+        app.world.resource_mut::<SimulationControl>().step_once();
+        app.update();
+
+        let mut state = SystemState::<InterpolatedVoxels>::new(&mut app.world);
+        let voxels = state.get(&app.world);
+        // The lone cell has no neighbors to survive with.
+        let occupancy = voxels.occupancy(lone).unwrap();
+        assert_eq!(
+            occupancy,
+            VoxelOccupancy {
+                previous: alive,
+                current: AutomataState::EMPTY,
+            }
+        );
+        assert!(occupancy.changed());
+        assert_eq!(occupancy.lerp(0.25), 0.75);
+        assert!(!voxels.occupancy(IVec3::ZERO).unwrap().changed());
+        assert_eq!(voxels.occupancy(IVec3::splat(-1)), None);
+    }
+}
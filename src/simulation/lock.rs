@@ -0,0 +1,116 @@
+use super::{stasis::hold_region, ChunkCells, ChunkCellsNext, ChunkIndex};
+use bevy::prelude::*;
+
+/// Handle of a lock taken with [`RegionLocks::lock`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RegionLockId(u64);
+
+/// Voxel box held by a lock, `min` inclusive and `max` exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockedRegion {
+    pub id: RegionLockId,
+    pub min: IVec3,
+    pub max: IVec3,
+}
+
+impl LockedRegion {
+    #[inline]
+    pub fn contains(&self, voxel: IVec3) -> bool {
+        voxel.cmpge(self.min).all() && voxel.cmplt(self.max).all()
+    }
+
+    fn overlaps(&self, min: IVec3, max: IVec3) -> bool {
+        self.min.cmplt(max).all() && min.cmplt(self.max).all()
+    }
+}
+
+/// Returned by [`RegionLocks::lock`] when the box overlaps a region that is already locked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegionLockConflict {
+    pub existing: LockedRegion,
+}
+
+/// Locks voxel regions for long running operations such as imports or erosion bakes.
+///
+/// Locked cells are held by the simulation like a [`StasisVolume`](super::StasisVolume), and
+/// edits from [`VoxelCommands`](super::VoxelCommands) landing in them are dropped and reported
+/// with [`EngineEvent::EditsRejected`](crate::EngineEvent::EditsRejected). The owner writes its
+/// result with [`VoxelCommands::commit_locked`](super::VoxelCommands::commit_locked), which
+/// releases the lock and applies it, split across frames past the
+/// [`EditBudget`](super::EditBudget).
+#[derive(Resource, Debug, Default)]
+pub struct RegionLocks {
+    next_id: u64,
+    regions: Vec<LockedRegion>,
+}
+
+impl RegionLocks {
+    pub fn lock(&mut self, min: IVec3, max: IVec3) -> Result<RegionLockId, RegionLockConflict> {
+        if let Some(existing) = self.regions.iter().find(|region| region.overlaps(min, max)) {
+            return Err(RegionLockConflict {
+                existing: *existing,
+            });
+        }
+
+        let id = RegionLockId(self.next_id);
+        self.next_id += 1;
+        self.regions.push(LockedRegion { id, min, max });
+        Ok(id)
+    }
+
+    /// Releases a lock without writing anything, returns whether it was held.
+    pub fn release(&mut self, id: RegionLockId) -> bool {
+        let len = self.regions.len();
+        self.regions.retain(|region| region.id != id);
+        self.regions.len() != len
+    }
+
+    pub fn region(&self, id: RegionLockId) -> Option<&LockedRegion> {
+        self.regions.iter().find(|region| region.id == id)
+    }
+
+    /// Lock holding a voxel, if any.
+    pub fn owner(&self, voxel: IVec3) -> Option<RegionLockId> {
+        self.regions
+            .iter()
+            .find(|region| region.contains(voxel))
+            .map(|region| region.id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &LockedRegion> {
+        self.regions.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+}
+
+pub(super) fn hold_locked_regions(
+    locks: Res<RegionLocks>,
+    index: Res<ChunkIndex>,
+    mut chunks: Query<(&ChunkCells, &mut ChunkCellsNext)>,
+) {
+    for region in locks.iter() {
+        hold_region(&index, &mut chunks, region.min, region.max);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_locks_conflict() {
+        let mut locks = RegionLocks::default();
+        let a = locks.lock(IVec3::ZERO, IVec3::splat(8)).unwrap();
+        assert!(locks.lock(IVec3::splat(8), IVec3::splat(16)).is_ok());
+
+        let conflict = locks.lock(IVec3::splat(4), IVec3::splat(12)).unwrap_err();
+        assert_eq!(conflict.existing.id, a);
+        assert_eq!(locks.owner(IVec3::splat(7)), Some(a));
+
+        assert!(locks.release(a));
+        assert!(locks.lock(IVec3::splat(4), IVec3::splat(8)).is_ok());
+    }
+}
@@ -0,0 +1,223 @@
+use super::{
+    active_rule, linear_index, sample_cell, AutomataRule, AutomataState, BoxedRule, CellContext,
+    ChunkCells, ChunkCellsNext, ChunkKey, ChunkLod, ChunkSnapshots, SimulationClock, CHUNK_EDGE,
+};
+use bevy::prelude::*;
+
+/// Edge length of a downsampled chunk, in cells.
+pub const LOD_EDGE: i32 = CHUNK_EDGE / 2;
+
+/// Cells of a distant chunk downsampled 2:1, each standing for a 2×2×2 block of voxels.
+///
+/// Present while [`ChunkLod::downsampled`] is set. The rule then steps these cells at the
+/// chunk's [`SimulationRate`](super::SimulationRate), and the chunk's [`ChunkCells`] are
+/// replaced by their upsampled result, so meshing and rendering keep working. The rule sees
+/// downsampled coordinates in [`CellContext::voxel`] and always updates synchronously. Fine
+/// details are lost on the way, chunks keep their blocky cells when the anchor comes back.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct ChunkCellsLod {
+    cells: Box<[AutomataState]>,
+}
+
+impl ChunkCellsLod {
+    /// Keeps the most common live state of every block with at least half of its cells alive,
+    /// the other blocks become empty.
+    pub fn downsample(cells: &[AutomataState]) -> Self {
+        let mut lod = Self {
+            cells: vec![AutomataState::EMPTY; (LOD_EDGE * LOD_EDGE * LOD_EDGE) as usize].into(),
+        };
+        for x in 0..LOD_EDGE {
+            for y in 0..LOD_EDGE {
+                for z in 0..LOD_EDGE {
+                    let local = IVec3::new(x, y, z);
+                    let block = block_cells(local).map(|voxel| Some(cells[linear_index(voxel)]));
+                    lod.set(local, majority(block));
+                }
+            }
+        }
+        lod
+    }
+
+    /// Writes every cell into its 2×2×2 block of full resolution `cells`.
+    pub fn upsample(&self, cells: &mut [AutomataState]) {
+        for x in 0..LOD_EDGE {
+            for y in 0..LOD_EDGE {
+                for z in 0..LOD_EDGE {
+                    let local = IVec3::new(x, y, z);
+                    let state = self.get(local);
+                    for voxel in block_cells(local) {
+                        cells[linear_index(voxel)] = state;
+                    }
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, local: IVec3) -> AutomataState {
+        self.cells[lod_index(local)]
+    }
+
+    #[inline]
+    pub fn set(&mut self, local: IVec3, state: AutomataState) {
+        self.cells[lod_index(local)] = state;
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &[AutomataState] {
+        &self.cells
+    }
+}
+
+#[inline]
+fn lod_index(local: IVec3) -> usize {
+    let edge = LOD_EDGE as usize;
+    (local.x as usize * edge * edge) + (local.y as usize * edge) + local.z as usize
+}
+
+/// Full resolution cells covered by the downsampled cell at `local`.
+fn block_cells(local: IVec3) -> [IVec3; 8] {
+    let origin = local * 2;
+    std::array::from_fn(|i| {
+        let i = i as i32;
+        origin + IVec3::new(i & 1, (i >> 1) & 1, (i >> 2) & 1)
+    })
+}
+
+fn majority(block: [Option<AutomataState>; 8]) -> AutomataState {
+    let alive: Vec<_> = block
+        .into_iter()
+        .flatten()
+        .filter(|s| s.is_alive())
+        .collect();
+    if alive.len() < 4 {
+        return AutomataState::EMPTY;
+    }
+    alive
+        .iter()
+        .copied()
+        .max_by_key(|state| alive.iter().filter(|other| *other == state).count())
+        .unwrap()
+}
+
+/// Downsampled cell at `local`, read from the full resolution snapshots so chunks on either
+/// side of the border see the same neighbors.
+fn sample_lod_cell(
+    snapshots: &ChunkSnapshots,
+    coords: IVec3,
+    local: IVec3,
+) -> Option<AutomataState> {
+    let block = block_cells(local).map(|voxel| sample_cell(snapshots, coords, voxel));
+    block.iter().any(Option::is_some).then(|| majority(block))
+}
+
+/// Adds or drops the downsampled cells of chunks whose [`ChunkLod::downsampled`] changed.
+pub(super) fn update_chunk_resolution(
+    mut commands: Commands,
+    chunks: Query<(Entity, &ChunkLod, &ChunkCells, Has<ChunkCellsLod>), Changed<ChunkLod>>,
+) {
+    for (entity, lod, cells, has_lod) in chunks.iter() {
+        if lod.downsampled && !has_lod {
+            commands
+                .entity(entity)
+                .insert(ChunkCellsLod::downsample(&cells.as_slice()));
+        } else if !lod.downsampled && has_lod {
+            // The cells already hold the upsampled result of the last downsampled step.
+            commands.entity(entity).remove::<ChunkCellsLod>();
+        }
+    }
+}
+
+/// Steps the downsampled chunks, writing their upsampled cells into [`ChunkCellsNext`].
+pub(super) fn step_lod_chunks(
+    snapshots: Res<ChunkSnapshots>,
+    rule: Res<AutomataRule>,
+    boxed_rule: Option<Res<BoxedRule>>,
+    clock: Res<SimulationClock>,
+    mut chunks: Query<(
+        &ChunkKey,
+        &ChunkLod,
+        &mut ChunkCellsLod,
+        &mut ChunkCellsNext,
+    )>,
+) {
+    let rule = active_rule(&rule, boxed_rule.as_deref());
+    let tracker = rule.tracker();
+
+    chunks
+        .par_iter_mut()
+        .for_each(|(key, lod, mut cells_lod, mut next)| {
+            if !lod.downsampled || !lod.rate.steps_on(clock.step) {
+                return;
+            }
+            // Resampled from the snapshot every step so voxel edits are picked up.
+            let Some(snapshot) = snapshots.get(key.coords) else {
+                return;
+            };
+
+            let current = ChunkCellsLod::downsample(snapshot);
+            let mut output = current.clone();
+            for x in 0..LOD_EDGE {
+                for y in 0..LOD_EDGE {
+                    for z in 0..LOD_EDGE {
+                        let local = IVec3::new(x, y, z);
+                        let mut neighbors = [None; 27];
+                        for (i, neighbor) in neighbors.iter_mut().enumerate() {
+                            let i = i as i32;
+                            let offset = IVec3::new(i / 9, (i / 3) % 3, i % 3) - IVec3::ONE;
+                            let cell = local + offset;
+                            *neighbor = if cell.cmpge(IVec3::ZERO).all()
+                                && cell.cmplt(IVec3::splat(LOD_EDGE)).all()
+                            {
+                                Some(current.get(cell))
+                            } else {
+                                sample_lod_cell(&snapshots, key.coords, cell)
+                            };
+                        }
+
+                        let ctx = CellContext::new(
+                            key.coords * LOD_EDGE + local,
+                            clock.step,
+                            current.get(local),
+                            neighbors,
+                            &tracker,
+                        );
+                        output.set(local, rule.next_state(&ctx));
+                    }
+                }
+            }
+
+            output.upsample(next.as_mut_slice());
+            *cells_lod = output;
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CHUNK_VOLUME;
+
+    #[test]
+    fn downsample_keeps_majority_and_upsample_restores_blocks() {
+        let sand = AutomataState::new(2, 0);
+        let mut cells = vec![AutomataState::EMPTY; CHUNK_VOLUME];
+        // Five live cells in the first block, three in the second.
+        for voxel in &block_cells(IVec3::ZERO)[..5] {
+            cells[linear_index(*voxel)] = sand;
+        }
+        for voxel in &block_cells(IVec3::X)[..3] {
+            cells[linear_index(*voxel)] = sand;
+        }
+
+        let lod = ChunkCellsLod::downsample(&cells);
+        assert_eq!(lod.get(IVec3::ZERO), sand);
+        assert_eq!(lod.get(IVec3::X), AutomataState::EMPTY);
+
+        let mut upsampled = vec![AutomataState::EMPTY; CHUNK_VOLUME];
+        lod.upsample(&mut upsampled);
+        assert!(block_cells(IVec3::ZERO)
+            .iter()
+            .all(|voxel| upsampled[linear_index(*voxel)] == sand));
+        assert_eq!(ChunkCellsLod::downsample(&upsampled), lod);
+    }
+}
@@ -0,0 +1,474 @@
+use super::AutomataState;
+#[cfg(feature = "render")]
+use crate::{PaletteChanged, VoxelPalette};
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use std::fmt;
+
+/// Keeps the [`MaterialRegistry`] and loads [`MaterialTable`] files into it.
+///
+/// Tables are `.materials` assets applied to the registry whenever they finish loading or are
+/// reloaded. With the `render` feature, the colors of materials that have one and their shading
+/// are pushed to the `VoxelPalette`, so the chunk meshes and the traced world follow the
+/// registry.
+pub struct MaterialRegistryPlugin;
+
+impl Plugin for MaterialRegistryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MaterialRegistry>()
+            .init_asset::<MaterialTable>()
+            .register_asset_loader(MaterialTableLoader)
+            .add_systems(Update, apply_material_tables);
+
+        #[cfg(feature = "render")]
+        app.add_systems(
+            Update,
+            sync_material_palette
+                .run_if(resource_exists::<Events<PaletteChanged>>())
+                .after(apply_material_tables),
+        );
+    }
+}
+
+/// How a material behaves in the simulation, for rules and physics to tell materials apart.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MaterialClass {
+    /// Stays in place.
+    #[default]
+    Solid,
+    /// Falls and piles up, like sand.
+    Powder,
+    /// Flows and levels out, like water.
+    Liquid,
+    /// Rises and spreads out, like smoke.
+    Gas,
+}
+
+/// Properties of a material, consulted by the simulation, the [`ThermalPlugin`] and rendering.
+///
+/// [`ThermalPlugin`]: super::ThermalPlugin
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterialProperties {
+    /// Name used to look the material up with [`MaterialRegistry::find`].
+    pub name: String,
+    pub class: MaterialClass,
+    /// Mass of a voxel relative to water.
+    pub density: f32,
+    /// Color of the material, `None` keeps the color of the palette.
+    #[cfg(feature = "render")]
+    pub color: Option<Color>,
+    /// Light emitted by the material in the traced world, see `VoxelPalette::set`.
+    pub emissive: f32,
+    /// Shades the chunk meshes of the material with smooth normals, see
+    /// `VoxelPalette::set_smooth`.
+    pub smooth: bool,
+    /// Share of the difference to the average temperature of the six face neighbors taken every
+    /// step, out of 255.
+    pub conductivity: u8,
+    /// Temperature at which the material catches fire, `None` if it does not burn.
+    pub ignition: Option<u16>,
+    /// Heat a burning voxel adds to itself every step.
+    pub burn_heat: u16,
+    /// Steps a voxel burns for before it is consumed.
+    pub burn_steps: u8,
+    /// State left behind by a burned out voxel, such as ash or empty.
+    pub burns_into: AutomataState,
+}
+
+impl Default for MaterialProperties {
+    /// Unnamed solid with the density of water, conducting heat moderately and not burning.
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            class: MaterialClass::Solid,
+            density: 1.0,
+            #[cfg(feature = "render")]
+            color: None,
+            emissive: 0.0,
+            smooth: false,
+            conductivity: 64,
+            ignition: None,
+            burn_heat: 0,
+            burn_steps: 0,
+            burns_into: AutomataState::EMPTY,
+        }
+    }
+}
+
+impl MaterialProperties {
+    pub fn named(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..default()
+        }
+    }
+
+    /// Material catching fire at `ignition`, heating itself by `burn_heat` for `burn_steps`
+    /// steps before leaving `burns_into`.
+    pub fn flammable(
+        ignition: u16,
+        burn_heat: u16,
+        burn_steps: u8,
+        burns_into: AutomataState,
+    ) -> Self {
+        Self {
+            ignition: Some(ignition),
+            burn_heat,
+            burn_steps: burn_steps.max(1),
+            burns_into,
+            ..default()
+        }
+    }
+
+    #[inline]
+    pub fn is_flammable(&self) -> bool {
+        self.ignition.is_some()
+    }
+}
+
+/// [`MaterialProperties`] of every material id, material `0` standing for empty space.
+#[derive(Resource, Debug, Clone)]
+pub struct MaterialRegistry {
+    materials: Box<[MaterialProperties]>,
+}
+
+impl Default for MaterialRegistry {
+    fn default() -> Self {
+        Self {
+            materials: vec![MaterialProperties::default(); 256].into_boxed_slice(),
+        }
+    }
+}
+
+impl MaterialRegistry {
+    #[inline]
+    pub fn get(&self, material: u8) -> &MaterialProperties {
+        &self.materials[material as usize]
+    }
+
+    pub fn set(&mut self, material: u8, properties: MaterialProperties) -> &mut Self {
+        self.materials[material as usize] = properties;
+        self
+    }
+
+    /// Id of the first material called `name`.
+    pub fn find(&self, name: &str) -> Option<u8> {
+        self.materials
+            .iter()
+            .position(|properties| !name.is_empty() && properties.name == name)
+            .map(|material| material as u8)
+    }
+
+    /// Every material id with its properties.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &MaterialProperties)> {
+        self.materials
+            .iter()
+            .enumerate()
+            .map(|(material, properties)| (material as u8, properties))
+    }
+
+    /// Sets the materials listed in `table`, leaving the others untouched.
+    pub fn apply(&mut self, table: &MaterialTable) -> &mut Self {
+        for (material, properties) in &table.materials {
+            self.set(*material, properties.clone());
+        }
+        self
+    }
+}
+
+/// Registers materials on an [`App`].
+pub trait MaterialRegistryAppExt {
+    /// Sets the properties of `material` in the [`MaterialRegistry`], adding the registry if
+    /// it is missing.
+    fn register_material(&mut self, material: u8, properties: MaterialProperties) -> &mut Self;
+}
+
+impl MaterialRegistryAppExt for App {
+    fn register_material(&mut self, material: u8, properties: MaterialProperties) -> &mut Self {
+        self.init_resource::<MaterialRegistry>();
+        self.world
+            .resource_mut::<MaterialRegistry>()
+            .set(material, properties);
+        self
+    }
+}
+
+/// Material definitions read from a `.materials` file, see [`MaterialTable::parse`].
+#[derive(Asset, TypePath, Debug, Clone, Default, PartialEq)]
+pub struct MaterialTable {
+    pub materials: Vec<(u8, MaterialProperties)>,
+}
+
+/// Why a material table could not be read by [`MaterialTable::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MaterialParseError {
+    /// The file could not be read.
+    Io(String),
+    /// A line does not start with a material id from 1 to 255 followed by a name.
+    InvalidMaterial { line: usize },
+    /// A material id is defined twice.
+    Repeated { line: usize, material: u8 },
+    /// A property is not one of the known keys or has no value.
+    UnknownProperty { line: usize, property: String },
+    /// A property value could not be parsed.
+    InvalidValue { line: usize, property: String },
+}
+
+impl fmt::Display for MaterialParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaterialParseError::Io(error) => write!(f, "could not read materials: {}", error),
+            MaterialParseError::InvalidMaterial { line } => {
+                write!(f, "line {}: expected a material id and a name", line)
+            }
+            MaterialParseError::Repeated { line, material } => {
+                write!(f, "line {}: material {} is defined twice", line, material)
+            }
+            MaterialParseError::UnknownProperty { line, property } => {
+                write!(f, "line {}: unknown property {}", line, property)
+            }
+            MaterialParseError::InvalidValue { line, property } => {
+                write!(f, "line {}: invalid value for {}", line, property)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MaterialParseError {}
+
+impl From<std::io::Error> for MaterialParseError {
+    fn from(error: std::io::Error) -> Self {
+        MaterialParseError::Io(error.to_string())
+    }
+}
+
+impl MaterialTable {
+    /// Parses one material per line, written as its id, its name and `key=value` properties,
+    /// e.g. `3 wood color=#8b5a2b density=0.6 ignition=200 burn_heat=600 burns_into=4`.
+    ///
+    /// The keys are the fields of [`MaterialProperties`]. `class` is one of `solid`, `powder`,
+    /// `liquid` or `gas`, `color` a hexadecimal color and `burns_into` a material id. Missing
+    /// properties keep their default value. Empty lines and lines starting with `#` are
+    /// skipped.
+    pub fn parse(text: &str) -> Result<Self, MaterialParseError> {
+        let mut table = MaterialTable::default();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let invalid = MaterialParseError::InvalidMaterial { line: line_number };
+            let material = words
+                .next()
+                .and_then(|id| id.parse::<u8>().ok())
+                .filter(|material| *material != 0)
+                .ok_or(invalid.clone())?;
+            let name = words.next().ok_or(invalid)?;
+            if table.materials.iter().any(|(id, _)| *id == material) {
+                return Err(MaterialParseError::Repeated {
+                    line: line_number,
+                    material,
+                });
+            }
+
+            let mut properties = MaterialProperties::named(name);
+            for word in words {
+                parse_property(&mut properties, word).map_err(|error| error.at(line_number))?;
+            }
+            table.materials.push((material, properties));
+        }
+        Ok(table)
+    }
+}
+
+/// Property error before the line is known.
+enum PropertyError {
+    Unknown(String),
+    Invalid(String),
+}
+
+impl PropertyError {
+    fn at(self, line: usize) -> MaterialParseError {
+        match self {
+            PropertyError::Unknown(property) => {
+                MaterialParseError::UnknownProperty { line, property }
+            }
+            PropertyError::Invalid(property) => MaterialParseError::InvalidValue { line, property },
+        }
+    }
+}
+
+fn parse_property(properties: &mut MaterialProperties, word: &str) -> Result<(), PropertyError> {
+    let Some((key, value)) = word.split_once('=') else {
+        return Err(PropertyError::Unknown(word.to_string()));
+    };
+    fn value_of<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, PropertyError> {
+        value
+            .parse()
+            .map_err(|_| PropertyError::Invalid(key.to_string()))
+    }
+
+    match key {
+        "class" => {
+            properties.class = match value {
+                "solid" => MaterialClass::Solid,
+                "powder" => MaterialClass::Powder,
+                "liquid" => MaterialClass::Liquid,
+                "gas" => MaterialClass::Gas,
+                _ => return Err(PropertyError::Invalid(key.to_string())),
+            }
+        }
+        "density" => properties.density = value_of(key, value)?,
+        #[cfg(feature = "render")]
+        "color" => {
+            let color = Color::hex(value).map_err(|_| PropertyError::Invalid(key.to_string()))?;
+            properties.color = Some(color);
+        }
+        // Kept so the same tables load in headless apps, which have no palette.
+        #[cfg(not(feature = "render"))]
+        "color" => {}
+        "emissive" => properties.emissive = value_of(key, value)?,
+        "smooth" => properties.smooth = value_of(key, value)?,
+        "conductivity" => properties.conductivity = value_of(key, value)?,
+        "ignition" => properties.ignition = Some(value_of(key, value)?),
+        "burn_heat" => properties.burn_heat = value_of(key, value)?,
+        "burn_steps" => properties.burn_steps = value_of::<u8>(key, value)?.max(1),
+        "burns_into" => properties.burns_into = AutomataState::new(value_of(key, value)?, 0),
+        _ => return Err(PropertyError::Unknown(key.to_string())),
+    }
+    Ok(())
+}
+
+/// Loads `.materials` files as [`MaterialTable`] assets.
+#[derive(Default)]
+struct MaterialTableLoader;
+
+impl AssetLoader for MaterialTableLoader {
+    type Asset = MaterialTable;
+    type Settings = ();
+    type Error = MaterialParseError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<MaterialTable, MaterialParseError>> {
+        Box::pin(async move {
+            let mut text = String::new();
+            reader.read_to_string(&mut text).await?;
+            MaterialTable::parse(&text)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["materials"]
+    }
+}
+
+fn apply_material_tables(
+    mut events: EventReader<AssetEvent<MaterialTable>>,
+    tables: Res<Assets<MaterialTable>>,
+    mut registry: ResMut<MaterialRegistry>,
+) {
+    for event in events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event
+        else {
+            continue;
+        };
+        if let Some(table) = tables.get(*id) {
+            registry.apply(table);
+        }
+    }
+}
+
+/// Pushes the registry colors and shading that changed since the last sync to the palette.
+#[cfg(feature = "render")]
+fn sync_material_palette(
+    registry: Res<MaterialRegistry>,
+    mut palette: VoxelPalette,
+    mut synced: Local<Vec<(Option<(Color, f32)>, bool)>>,
+) {
+    if !registry.is_changed() {
+        return;
+    }
+
+    synced.resize(256, (None, false));
+    for (material, properties) in registry.iter() {
+        let render = properties.color.map(|color| (color, properties.emissive));
+        let (synced_render, synced_smooth) = &mut synced[material as usize];
+        if render != *synced_render {
+            *synced_render = render;
+            if let Some((color, emissive)) = render {
+                palette.set(material, color, emissive);
+            }
+        }
+        if properties.smooth != *synced_smooth {
+            *synced_smooth = properties.smooth;
+            palette.set_smooth(material, properties.smooth);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn material_tables_parse_into_the_registry() {
+        let table = MaterialTable::parse(
+            "# id name properties\n\
+             1 stone density=2.5 color=#808080\n\
+             \n\
+             3 wood color=8b5a2b ignition=200 burn_heat=600 burn_steps=4 burns_into=4\n\
+             5 water class=liquid emissive=0.5\n",
+        )
+        .unwrap();
+        assert_eq!(table.materials.len(), 3);
+
+        let mut registry = MaterialRegistry::default();
+        registry.apply(&table);
+        let wood = registry.find("wood").unwrap();
+        assert_eq!(wood, 3);
+        assert!(registry.get(wood).is_flammable());
+        assert_eq!(registry.get(wood).burns_into, AutomataState::new(4, 0));
+        assert_eq!(registry.get(1).density, 2.5);
+        assert_eq!(registry.get(5).class, MaterialClass::Liquid);
+        #[cfg(feature = "render")]
+        assert_eq!(registry.get(5).color, None);
+        assert_eq!(registry.find("lava"), None);
+
+        assert_eq!(
+            MaterialTable::parse("1 a\n1 b"),
+            Err(MaterialParseError::Repeated {
+                line: 2,
+                material: 1
+            })
+        );
+        assert_eq!(
+            MaterialTable::parse("0 air"),
+            Err(MaterialParseError::InvalidMaterial { line: 1 })
+        );
+        assert_eq!(
+            MaterialTable::parse("2 sand class=goo"),
+            Err(MaterialParseError::InvalidValue {
+                line: 1,
+                property: "class".to_string()
+            })
+        );
+        assert_eq!(
+            MaterialTable::parse("2 sand weight=3"),
+            Err(MaterialParseError::UnknownProperty {
+                line: 1,
+                property: "weight".to_string()
+            })
+        );
+    }
+}
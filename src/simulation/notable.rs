@@ -0,0 +1,82 @@
+use super::{voxel_to_chunk, ChunkCells, ChunkIndex, VoxelWorldTransform};
+use bevy::prelude::*;
+
+/// Proxy entity standing in for a single voxel, so gameplay code (chests, spawners, machines)
+/// can attach regular components to it.
+///
+/// The engine keeps the proxy's `Transform` at the voxel center, aligned with the
+/// [`VoxelWorldTransform`], and despawns it, children included, once the voxel is emptied or
+/// replaced by another material. Proxies of voxels in unloaded chunks are left alone.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct NotableVoxel {
+    pub voxel: IVec3,
+    /// Material the voxel is expected to have, recorded on the first check when `None`.
+    pub material: Option<u8>,
+}
+
+impl NotableVoxel {
+    pub fn new(voxel: IVec3) -> Self {
+        Self {
+            voxel,
+            material: None,
+        }
+    }
+}
+
+/// Sent right before the proxy of a destroyed notable voxel is despawned.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct NotableVoxelDestroyed {
+    pub entity: Entity,
+    pub voxel: IVec3,
+    pub material: Option<u8>,
+}
+
+pub(super) fn track_notable_voxels(
+    mut commands: Commands,
+    mut destroyed: EventWriter<NotableVoxelDestroyed>,
+    index: Res<ChunkIndex>,
+    world_transform: Res<VoxelWorldTransform>,
+    chunks: Query<&ChunkCells>,
+    mut proxies: Query<(Entity, &mut NotableVoxel, Option<&mut Transform>)>,
+) {
+    for (entity, mut notable, transform) in proxies.iter_mut() {
+        let target = Transform {
+            translation: world_transform.voxel_center(notable.voxel),
+            rotation: world_transform.rotation,
+            ..default()
+        };
+        match transform {
+            Some(mut transform) => {
+                if transform.translation != target.translation
+                    || transform.rotation != target.rotation
+                {
+                    transform.translation = target.translation;
+                    transform.rotation = target.rotation;
+                }
+            }
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(TransformBundle::from_transform(target));
+            }
+        }
+
+        let (chunk, local) = voxel_to_chunk(notable.voxel);
+        let Some(cells) = index.entity(chunk).and_then(|chunk| chunks.get(chunk).ok()) else {
+            continue;
+        };
+
+        let state = cells.get(local);
+        let exists = state.is_alive() && notable.material.is_none_or(|m| m == state.material);
+        if !exists {
+            destroyed.send(NotableVoxelDestroyed {
+                entity,
+                voxel: notable.voxel,
+                material: notable.material,
+            });
+            commands.entity(entity).despawn_recursive();
+        } else if notable.material.is_none() {
+            notable.material = Some(state.material);
+        }
+    }
+}
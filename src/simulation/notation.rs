@@ -0,0 +1,272 @@
+use super::{AutomataRule, Neighborhood, MAX_STATES};
+use bevy::prelude::IVec3;
+use std::{fmt, str::FromStr};
+
+/// Highest neighbor count a rule can list.
+const MAX_COUNT: u8 = 26;
+
+/// Why a rule string could not be parsed by [`AutomataRule::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleParseError {
+    /// The `B` or `S` part is missing.
+    Missing(char),
+    /// A part is given twice.
+    Repeated(String),
+    /// A part is not a count list, a number of states or a neighborhood.
+    UnknownPart(String),
+    /// A count is not a number or is above 26.
+    InvalidCount(String),
+    /// The number of states is not within `2..=MAX_STATES`.
+    InvalidStates(String),
+    /// A custom neighborhood is not a hexadecimal cell mask.
+    InvalidNeighborhood(String),
+    /// An offset of a custom neighborhood lies outside the 3x3x3 block.
+    OffsetOutOfRange(IVec3),
+}
+
+impl fmt::Display for RuleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleParseError::Missing(part) => write!(f, "rule has no {} part", part),
+            RuleParseError::Repeated(part) => write!(f, "rule part {} is given twice", part),
+            RuleParseError::UnknownPart(part) => write!(f, "unknown rule part {}", part),
+            RuleParseError::InvalidCount(count) => {
+                write!(f, "invalid neighbor count {}, expected 0 to 26", count)
+            }
+            RuleParseError::InvalidStates(states) => {
+                write!(
+                    f,
+                    "invalid number of states {}, expected 2 to {}",
+                    states, MAX_STATES
+                )
+            }
+            RuleParseError::InvalidNeighborhood(neighborhood) => {
+                write!(f, "invalid custom neighborhood {}", neighborhood)
+            }
+            RuleParseError::OffsetOutOfRange(offset) => {
+                write!(
+                    f,
+                    "neighborhood offset {} is outside the 3x3x3 block",
+                    offset
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for RuleParseError {}
+
+impl AutomataRule {
+    /// Parses a rule written as `B<counts>/S<counts>`, optionally followed by the number of
+    /// [`states`](Self::states) and the neighborhood, `M` for Moore or `N` for von Neumann,
+    /// e.g. `B5/S45`, `B4/S4/5/M` or `B13-14,17-19/S13-26`.
+    ///
+    /// A [`Neighborhood::Custom`] is written `C` followed by a hexadecimal mask of the 3x3x3
+    /// block, bit `(x + 1) * 9 + (y + 1) * 3 + z + 1` standing for the offset `(x, y, z)`, so
+    /// `C415410` is the von Neumann neighborhood. Its offsets read back in bit order.
+    ///
+    /// Counts are single digits written together, or separated by commas when a count above 9
+    /// or a range appears, so `B13` means 1 and 3 while `B13-13` means 13. Parts may come in
+    /// any order. Material rules cannot be written, the parsed rule has none and gives born
+    /// cells material 1.
+    pub fn parse(rule: &str) -> Result<Self, RuleParseError> {
+        let mut birth = None;
+        let mut survive = None;
+        let mut states = None;
+        let mut neighborhood = None;
+        for part in rule.trim().split('/').map(str::trim) {
+            let mut chars = part.chars();
+            let (slot, value) = match chars.next().map(|c| c.to_ascii_uppercase()) {
+                Some('B') => (&mut birth, parse_counts(chars.as_str())?),
+                Some('S') => (&mut survive, parse_counts(chars.as_str())?),
+                Some('M') if part.len() == 1 => {
+                    set_once(&mut neighborhood, Neighborhood::Moore, part)?;
+                    continue;
+                }
+                Some('N') if part.len() == 1 => {
+                    set_once(&mut neighborhood, Neighborhood::VonNeumann, part)?;
+                    continue;
+                }
+                Some('C') => {
+                    let mask = u32::from_str_radix(chars.as_str(), 16)
+                        .ok()
+                        .filter(|mask| *mask < 1 << 27 && mask & 1 << 13 == 0)
+                        .ok_or_else(|| RuleParseError::InvalidNeighborhood(part.to_string()))?;
+                    set_once(&mut neighborhood, custom_neighborhood(mask), part)?;
+                    continue;
+                }
+                Some(c) if c.is_ascii_digit() => {
+                    let value = part
+                        .parse()
+                        .ok()
+                        .filter(|states| (2..=MAX_STATES).contains(states))
+                        .ok_or_else(|| RuleParseError::InvalidStates(part.to_string()))?;
+                    set_once(&mut states, value, part)?;
+                    continue;
+                }
+                _ => return Err(RuleParseError::UnknownPart(part.to_string())),
+            };
+            set_once(slot, value, part)?;
+        }
+
+        Ok(Self {
+            birth: birth.ok_or(RuleParseError::Missing('B'))?,
+            survive: survive.ok_or(RuleParseError::Missing('S'))?,
+            states: states.unwrap_or(2),
+            neighborhood: neighborhood.unwrap_or_default(),
+            ..Self::default()
+        })
+    }
+}
+
+impl FromStr for AutomataRule {
+    type Err = RuleParseError;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        Self::parse(rule)
+    }
+}
+
+/// Writes the rule in the notation read by [`AutomataRule::parse`], leaving out the states and
+/// neighborhood when they are the defaults. Material rules and the birth material are not
+/// written.
+impl fmt::Display for AutomataRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "B")?;
+        write_counts(f, &self.birth)?;
+        write!(f, "/S")?;
+        write_counts(f, &self.survive)?;
+        if self.states != 2 || self.neighborhood != Neighborhood::Moore {
+            write!(f, "/{}/", self.states)?;
+            match self.neighborhood {
+                Neighborhood::Moore => write!(f, "M")?,
+                Neighborhood::VonNeumann => write!(f, "N")?,
+                Neighborhood::Custom(_) => write!(f, "C{:X}", self.neighborhood.mask())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn set_once<T>(slot: &mut Option<T>, value: T, part: &str) -> Result<(), RuleParseError> {
+    if slot.is_some() {
+        return Err(RuleParseError::Repeated(part.to_string()));
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+fn custom_neighborhood(mask: u32) -> Neighborhood {
+    let offsets = (0..27)
+        .filter(|index| mask & 1 << index != 0)
+        .map(|index| IVec3::new(index / 9, index / 3 % 3, index % 3) - IVec3::ONE)
+        .collect();
+    Neighborhood::Custom(offsets)
+}
+
+fn parse_counts(list: &str) -> Result<Vec<u8>, RuleParseError> {
+    let count = |count: &str| {
+        count
+            .trim()
+            .parse::<u8>()
+            .ok()
+            .filter(|count| *count <= MAX_COUNT)
+            .ok_or_else(|| RuleParseError::InvalidCount(count.to_string()))
+    };
+
+    let mut counts = Vec::new();
+    if list.contains([',', '-']) {
+        for item in list.split(',') {
+            match item.split_once('-') {
+                Some((low, high)) => counts.extend(count(low)?..=count(high)?),
+                None => counts.push(count(item)?),
+            }
+        }
+    } else {
+        for digit in list.chars() {
+            counts.push(count(&digit.to_string())?);
+        }
+    }
+    counts.sort_unstable();
+    counts.dedup();
+    Ok(counts)
+}
+
+fn write_counts(f: &mut fmt::Formatter<'_>, counts: &[u8]) -> fmt::Result {
+    let mut counts = counts.to_vec();
+    counts.sort_unstable();
+    counts.dedup();
+    if counts.iter().all(|count| *count <= 9) {
+        for count in counts {
+            write!(f, "{}", count)?;
+        }
+        return Ok(());
+    }
+
+    // Runs of consecutive counts, a lone count above 9 is written as a range so it does not
+    // read back as digits.
+    let mut runs: Vec<(u8, u8)> = Vec::new();
+    for count in counts {
+        match runs.last_mut() {
+            Some((_, high)) if *high + 1 == count => *high = count,
+            _ => runs.push((count, count)),
+        }
+    }
+    let single = runs.len() == 1;
+    for (index, (low, high)) in runs.into_iter().enumerate() {
+        if index > 0 {
+            write!(f, ",")?;
+        }
+        if low == high && !single {
+            write!(f, "{}", low)?;
+        } else {
+            write!(f, "{}-{}", low, high)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RulePreset;
+
+    #[test]
+    fn rules_round_trip_through_strings() {
+        let rule = AutomataRule::parse("B5/S45").unwrap();
+        assert_eq!(rule, AutomataRule::default());
+        assert_eq!(rule.to_string(), "B5/S45");
+
+        let amoeba = AutomataRule::parse("S9-26/B5-7,12-13,15/5/M").unwrap();
+        assert_eq!(amoeba.birth, vec![5, 6, 7, 12, 13, 15]);
+        assert_eq!(amoeba.states, 5);
+        assert_eq!(amoeba.to_string(), "B5-7,12-13,15/S9-26/5/M");
+
+        let lone = AutomataRule::parse("B13-13/S/2/N").unwrap();
+        assert_eq!(lone.birth, vec![13]);
+        assert_eq!(lone.neighborhood, Neighborhood::VonNeumann);
+        assert_eq!(lone.to_string().parse::<AutomataRule>(), Ok(lone));
+
+        let faces = AutomataRule::parse("B1/S1/C415410").unwrap();
+        assert_eq!(faces.neighborhood.mask(), Neighborhood::VonNeumann.mask());
+        assert_eq!(faces.to_string(), "B1/S1/2/C415410");
+
+        for preset in RulePreset::ALL {
+            let rule = AutomataRule {
+                birth_material: 1,
+                ..AutomataRule::preset(preset)
+            };
+            assert_eq!(AutomataRule::parse(&rule.to_string()), Ok(rule));
+        }
+
+        assert_eq!(
+            AutomataRule::parse("B5,27/S4"),
+            Err(RuleParseError::InvalidCount("27".to_string()))
+        );
+        assert_eq!(AutomataRule::parse("B5"), Err(RuleParseError::Missing('S')));
+        assert_eq!(
+            AutomataRule::parse("B5/S4/10"),
+            Err(RuleParseError::InvalidStates("10".to_string()))
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use super::{AutomataState, ChunkStorage, CHUNK_EDGE};
+use bevy::prelude::*;
+
+const EDGE: usize = CHUNK_EDGE as usize;
+
+/// One bit per cell of a chunk telling whether it is alive, kept by [`ChunkCells`].
+///
+/// Rows run along Z, bit `z` of row `x * CHUNK_EDGE + y`, so a row of 32 cells is a single
+/// word. Consumers only asking whether a cell is solid read this instead of decoding the
+/// [`AutomataState`].
+///
+/// [`ChunkCells`]: super::ChunkCells
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OccupancyMask {
+    rows: Box<[u32]>,
+}
+
+impl OccupancyMask {
+    pub fn filled(alive: bool) -> Self {
+        let row = if alive { u32::MAX } else { 0 };
+        Self {
+            rows: vec![row; EDGE * EDGE].into(),
+        }
+    }
+
+    pub fn from_storage(storage: &ChunkStorage) -> Self {
+        match storage.uniform() {
+            Some(state) => Self::filled(state.is_alive()),
+            None => Self::from_cells(storage.iter()),
+        }
+    }
+
+    /// Mask of `CHUNK_VOLUME` cells in chunk order.
+    pub fn from_cells(cells: impl IntoIterator<Item = AutomataState>) -> Self {
+        let mut mask = Self::filled(false);
+        for (index, state) in cells.into_iter().enumerate() {
+            mask.rows[index / EDGE] |= (state.is_alive() as u32) << (index % EDGE);
+        }
+        mask
+    }
+
+    #[inline]
+    pub fn contains(&self, local: IVec3) -> bool {
+        self.row(local.x, local.y) & (1 << local.z) != 0
+    }
+
+    /// Cells of the row at `x, y`, bit `z` set for live cells.
+    #[inline]
+    pub fn row(&self, x: i32, y: i32) -> u32 {
+        self.rows[x as usize * EDGE + y as usize]
+    }
+
+    #[inline]
+    pub(super) fn set(&mut self, local: IVec3, alive: bool) {
+        let row = &mut self.rows[local.x as usize * EDGE + local.y as usize];
+        *row = (*row & !(1 << local.z)) | ((alive as u32) << local.z);
+    }
+
+    /// Number of live cells.
+    pub fn count(&self) -> u32 {
+        self.rows.iter().map(|row| row.count_ones()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.iter().all(|row| *row == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChunkCells;
+
+    #[test]
+    fn mask_follows_cell_writes() {
+        let mut cells = ChunkCells::from_generator(|local| match local.x == local.z {
+            true => AutomataState::new(1, 0),
+            false => AutomataState::new(0, 4),
+        });
+        assert_eq!(
+            cells.occupancy().count(),
+            CHUNK_EDGE as u32 * CHUNK_EDGE as u32
+        );
+        assert!(cells.is_alive(IVec3::new(3, 7, 3)));
+        assert!(!cells.is_alive(IVec3::new(3, 7, 4)));
+
+        cells.set(IVec3::new(3, 7, 3), AutomataState::EMPTY);
+        cells.set(IVec3::new(3, 7, 4), AutomataState::new(2, 0));
+        assert!(!cells.is_alive(IVec3::new(3, 7, 3)));
+        assert!(cells.is_alive(IVec3::new(3, 7, 4)));
+
+        let copy = cells.clone_box();
+        cells.write_from_slice(&copy);
+        assert_eq!(
+            cells.occupancy(),
+            &OccupancyMask::from_cells(cells.storage().iter())
+        );
+        cells.write_from_packed(&[0; crate::CHUNK_VOLUME]);
+        assert!(cells.occupancy().is_empty());
+    }
+}
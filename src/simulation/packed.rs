@@ -0,0 +1,141 @@
+use super::{AutomataState, ChunkCells, ChunkStorage, CHUNK_VOLUME};
+use std::fmt;
+
+/// Byte order of packed cells stored in byte buffers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Endianness {
+    #[default]
+    Little,
+    Big,
+}
+
+impl Endianness {
+    #[inline]
+    fn read(self, bytes: [u8; 2]) -> u16 {
+        match self {
+            Endianness::Little => u16::from_le_bytes(bytes),
+            Endianness::Big => u16::from_be_bytes(bytes),
+        }
+    }
+
+    #[inline]
+    fn write(self, value: u16) -> [u8; 2] {
+        match self {
+            Endianness::Little => value.to_le_bytes(),
+            Endianness::Big => value.to_be_bytes(),
+        }
+    }
+}
+
+/// Error returned when a raw buffer does not hold exactly one chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkDataError {
+    /// Expected length of the buffer, in elements of the buffer.
+    pub expected: usize,
+    pub actual: usize,
+}
+
+impl fmt::Display for ChunkDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "chunk buffer has {} elements, expected {}",
+            self.actual, self.expected
+        )
+    }
+}
+
+impl std::error::Error for ChunkDataError {}
+
+fn check_len(expected: usize, actual: usize) -> Result<(), ChunkDataError> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(ChunkDataError { expected, actual })
+    }
+}
+
+/// Bulk conversions between chunks and raw packed buffers (`material | flags << 8`, in chunk
+/// order). These never allocate besides the chunk itself, so they can read straight out of
+/// mmap-backed files or network buffers.
+impl ChunkCells {
+    pub fn from_packed(data: &[u16]) -> Result<Self, ChunkDataError> {
+        let mut cells = Self::default();
+        cells.load_packed(data)?;
+        Ok(cells)
+    }
+
+    pub fn from_bytes(data: &[u8], endianness: Endianness) -> Result<Self, ChunkDataError> {
+        let mut cells = Self::default();
+        cells.load_bytes(data, endianness)?;
+        Ok(cells)
+    }
+
+    pub fn load_packed(&mut self, data: &[u16]) -> Result<(), ChunkDataError> {
+        check_len(CHUNK_VOLUME, data.len())?;
+        self.write_from_packed(data);
+        Ok(())
+    }
+
+    pub fn store_packed(&self, out: &mut [u16]) -> Result<(), ChunkDataError> {
+        check_len(CHUNK_VOLUME, out.len())?;
+        for (packed, state) in out.iter_mut().zip(self.storage().iter()) {
+            *packed = state.to_packed();
+        }
+        Ok(())
+    }
+
+    /// Loads `CHUNK_VOLUME * 2` bytes of packed cells.
+    pub fn load_bytes(
+        &mut self,
+        data: &[u8],
+        endianness: Endianness,
+    ) -> Result<(), ChunkDataError> {
+        check_len(CHUNK_VOLUME * 2, data.len())?;
+        *self =
+            Self::from_storage(ChunkStorage::collect(data.chunks_exact(2).map(|bytes| {
+                AutomataState::from_packed(endianness.read([bytes[0], bytes[1]]))
+            })));
+        Ok(())
+    }
+
+    /// Stores the cells as `CHUNK_VOLUME * 2` bytes.
+    pub fn store_bytes(
+        &self,
+        out: &mut [u8],
+        endianness: Endianness,
+    ) -> Result<(), ChunkDataError> {
+        check_len(CHUNK_VOLUME * 2, out.len())?;
+        for (bytes, state) in out.chunks_exact_mut(2).zip(self.storage().iter()) {
+            bytes.copy_from_slice(&endianness.write(state.to_packed()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_round_trip_in_both_orders() {
+        let cells = ChunkCells::from_generator(|pos| {
+            AutomataState::new((pos.x + pos.y) as u8, pos.z as u8)
+        });
+
+        for endianness in [Endianness::Little, Endianness::Big] {
+            let mut bytes = vec![0; CHUNK_VOLUME * 2];
+            cells.store_bytes(&mut bytes, endianness).unwrap();
+            let loaded = ChunkCells::from_bytes(&bytes, endianness).unwrap();
+            assert_eq!(loaded.as_slice(), cells.as_slice());
+        }
+
+        let mut big = vec![0; CHUNK_VOLUME * 2];
+        cells.store_bytes(&mut big, Endianness::Big).unwrap();
+        assert_eq!(
+            u16::from_be_bytes([big[2], big[3]]),
+            cells.as_slice()[1].to_packed()
+        );
+        assert!(ChunkCells::from_packed(&[0; 3]).is_err());
+    }
+}
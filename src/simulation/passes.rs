@@ -0,0 +1,417 @@
+use super::{SimulationSchedule, SimulationSet};
+use bevy::{
+    ecs::schedule::SystemSet,
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use std::fmt;
+
+/// Data a simulation pass can read or write during a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PassChannel {
+    /// The per-voxel `AutomataState` stored in the chunk cells.
+    Cells,
+    /// Any user defined channel, identified by name.
+    Custom(&'static str),
+}
+
+/// Declaration of a simulation pass: its name, ordering constraints and data access.
+#[derive(Debug, Clone)]
+pub struct SimulationPass {
+    pub name: &'static str,
+    after: Vec<&'static str>,
+    before: Vec<&'static str>,
+    reads: Vec<PassChannel>,
+    writes: Vec<PassChannel>,
+}
+
+impl SimulationPass {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            after: Vec::new(),
+            before: Vec::new(),
+            reads: Vec::new(),
+            writes: Vec::new(),
+        }
+    }
+
+    /// Run this pass after the named pass.
+    pub fn after(mut self, pass: &'static str) -> Self {
+        self.after.push(pass);
+        self
+    }
+
+    /// Run this pass before the named pass.
+    pub fn before(mut self, pass: &'static str) -> Self {
+        self.before.push(pass);
+        self
+    }
+
+    pub fn reads(mut self, channel: PassChannel) -> Self {
+        self.reads.push(channel);
+        self
+    }
+
+    pub fn writes(mut self, channel: PassChannel) -> Self {
+        self.writes.push(channel);
+        self
+    }
+
+    fn conflicts_with(&self, other: &SimulationPass) -> bool {
+        self.writes
+            .iter()
+            .any(|channel| other.writes.contains(channel) || other.reads.contains(channel))
+            || other
+                .writes
+                .iter()
+                .any(|channel| self.reads.contains(channel))
+    }
+}
+
+/// System set containing the systems of a single registered pass.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SimulationPassSet(pub &'static str);
+
+/// Registry of every pass added through [`SimulationPassAppExt::add_simulation_pass`].
+#[derive(Resource, Default, Debug)]
+pub struct SimulationPasses {
+    passes: Vec<SimulationPass>,
+}
+
+impl SimulationPasses {
+    pub fn iter(&self) -> impl Iterator<Item = &SimulationPass> {
+        self.passes.iter()
+    }
+
+    pub fn register(&mut self, pass: SimulationPass) {
+        self.passes.push(pass);
+    }
+
+    /// Validates the declared dependencies and groups the passes into stages.
+    ///
+    /// Passes inside a stage have no ordering or data hazards between them and may run in
+    /// parallel. Passes touching the same channel without an explicit ordering run in
+    /// registration order so the result stays deterministic.
+    pub fn build_schedule(&self) -> Result<PassSchedule, PassGraphError> {
+        let mut index = HashMap::new();
+        for (i, pass) in self.passes.iter().enumerate() {
+            if index.insert(pass.name, i).is_some() {
+                return Err(PassGraphError::Duplicate(pass.name));
+            }
+        }
+
+        let count = self.passes.len();
+        let mut edges: Vec<HashSet<usize>> = vec![HashSet::new(); count];
+        for (i, pass) in self.passes.iter().enumerate() {
+            for dependency in &pass.after {
+                let &j = index
+                    .get(dependency)
+                    .ok_or(PassGraphError::UnknownDependency {
+                        pass: pass.name,
+                        dependency: *dependency,
+                    })?;
+                edges[j].insert(i);
+            }
+            for dependent in &pass.before {
+                let &j = index
+                    .get(dependent)
+                    .ok_or(PassGraphError::UnknownDependency {
+                        pass: pass.name,
+                        dependency: *dependent,
+                    })?;
+                edges[i].insert(j);
+            }
+        }
+
+        // Serialize data hazards that the user left unordered.
+        for i in 0..count {
+            for j in (i + 1)..count {
+                if self.passes[i].conflicts_with(&self.passes[j])
+                    && !reachable(&edges, i, j)
+                    && !reachable(&edges, j, i)
+                {
+                    edges[i].insert(j);
+                }
+            }
+        }
+
+        let mut in_degree = vec![0usize; count];
+        for targets in &edges {
+            for &target in targets {
+                in_degree[target] += 1;
+            }
+        }
+
+        let mut stages = Vec::new();
+        let mut ready: Vec<usize> = (0..count).filter(|&i| in_degree[i] == 0).collect();
+        let mut visited = 0;
+        while !ready.is_empty() {
+            ready.sort_unstable();
+            let mut next = Vec::new();
+            for &i in &ready {
+                visited += 1;
+                for &target in &edges[i] {
+                    in_degree[target] -= 1;
+                    if in_degree[target] == 0 {
+                        next.push(target);
+                    }
+                }
+            }
+            stages.push(ready.iter().map(|&i| self.passes[i].name).collect());
+            ready = next;
+        }
+
+        if visited != count {
+            let cycle = (0..count)
+                .filter(|&i| in_degree[i] > 0)
+                .map(|i| self.passes[i].name)
+                .collect();
+            return Err(PassGraphError::Cycle(cycle));
+        }
+
+        let mut ordering = Vec::new();
+        for (i, targets) in edges.iter().enumerate() {
+            let mut targets: Vec<_> = targets.iter().copied().collect();
+            targets.sort_unstable();
+            for j in targets {
+                ordering.push((self.passes[i].name, self.passes[j].name));
+            }
+        }
+
+        Ok(PassSchedule { stages, ordering })
+    }
+}
+
+fn reachable(edges: &[HashSet<usize>], from: usize, to: usize) -> bool {
+    let mut stack = vec![from];
+    let mut seen = HashSet::new();
+    while let Some(node) = stack.pop() {
+        if node == to {
+            return true;
+        }
+        if seen.insert(node) {
+            stack.extend(edges[node].iter().copied());
+        }
+    }
+    false
+}
+
+/// Execution plan computed from the registered passes.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct PassSchedule {
+    /// Groups of passes that can run concurrently, in execution order.
+    pub stages: Vec<Vec<&'static str>>,
+    /// Every `(before, after)` ordering constraint applied to the schedule.
+    pub ordering: Vec<(&'static str, &'static str)>,
+}
+
+/// Passes switched off at runtime, for debugging or gameplay such as a time stop that only
+/// freezes fluids.
+///
+/// Systems of a disabled pass are skipped while the other passes keep stepping, without
+/// rebuilding the pass graph. Channels only a disabled pass writes keep their values. When the
+/// life pass is disabled the cells carry over unchanged into the cells the other passes
+/// update. Every pass starts enabled.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct PassControl {
+    disabled: HashSet<&'static str>,
+}
+
+impl PassControl {
+    pub fn is_enabled(&self, pass: &str) -> bool {
+        !self.disabled.contains(pass)
+    }
+
+    /// Switches `pass` on or off, returning whether it changed.
+    pub fn set_enabled(&mut self, pass: &'static str, enabled: bool) -> bool {
+        match enabled {
+            true => self.disabled.remove(pass),
+            false => self.disabled.insert(pass),
+        }
+    }
+
+    pub fn enable(&mut self, pass: &'static str) -> bool {
+        self.set_enabled(pass, true)
+    }
+
+    pub fn disable(&mut self, pass: &'static str) -> bool {
+        self.set_enabled(pass, false)
+    }
+
+    /// Flips `pass`, returning whether it is now enabled.
+    pub fn toggle(&mut self, pass: &'static str) -> bool {
+        let enabled = !self.is_enabled(pass);
+        self.set_enabled(pass, enabled);
+        enabled
+    }
+
+    /// Names of the disabled passes, in no particular order.
+    pub fn disabled(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.disabled.iter().copied()
+    }
+}
+
+/// Switches a pass on or off through the [`PassControl`] before the next step.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetPassEnabled {
+    pub pass: &'static str,
+    pub enabled: bool,
+}
+
+/// Run condition true while `pass` is enabled in the [`PassControl`].
+pub fn pass_enabled(pass: &'static str) -> impl FnMut(Res<PassControl>) -> bool + Clone {
+    move |control: Res<PassControl>| control.is_enabled(pass)
+}
+
+pub(super) fn apply_pass_toggles(
+    mut requests: EventReader<SetPassEnabled>,
+    passes: Res<SimulationPasses>,
+    mut control: ResMut<PassControl>,
+) {
+    for request in requests.read() {
+        if !passes.iter().any(|pass| pass.name == request.pass) {
+            warn!("Cannot toggle unknown simulation pass \"{}\"", request.pass);
+            continue;
+        }
+        control.set_enabled(request.pass, request.enabled);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PassGraphError {
+    Duplicate(&'static str),
+    UnknownDependency {
+        pass: &'static str,
+        dependency: &'static str,
+    },
+    Cycle(Vec<&'static str>),
+}
+
+impl fmt::Display for PassGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PassGraphError::Duplicate(name) => {
+                write!(f, "simulation pass \"{}\" registered twice", name)
+            }
+            PassGraphError::UnknownDependency { pass, dependency } => write!(
+                f,
+                "simulation pass \"{}\" depends on unknown pass \"{}\"",
+                pass, dependency
+            ),
+            PassGraphError::Cycle(passes) => {
+                write!(f, "simulation passes form a cycle: {:?}", passes)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PassGraphError {}
+
+/// Registers simulation passes on an [`App`].
+pub trait SimulationPassAppExt {
+    fn add_simulation_pass<M>(
+        &mut self,
+        pass: SimulationPass,
+        systems: impl IntoSystemConfigs<M>,
+    ) -> &mut Self;
+}
+
+impl SimulationPassAppExt for App {
+    fn add_simulation_pass<M>(
+        &mut self,
+        pass: SimulationPass,
+        systems: impl IntoSystemConfigs<M>,
+    ) -> &mut Self {
+        let set = SimulationPassSet(pass.name);
+        self.init_resource::<SimulationPasses>();
+        self.world.resource_mut::<SimulationPasses>().register(pass);
+        self.add_systems(SimulationSchedule, systems.in_set(set))
+    }
+}
+
+/// Validates the registered passes and applies their ordering to the schedule.
+pub(super) fn configure_passes(app: &mut App) {
+    let schedule = match app.world.resource::<SimulationPasses>().build_schedule() {
+        Ok(schedule) => schedule,
+        Err(err) => panic!("Invalid simulation pass graph: {}", err),
+    };
+
+    let names: Vec<_> = app
+        .world
+        .resource::<SimulationPasses>()
+        .iter()
+        .map(|pass| pass.name)
+        .collect();
+    for name in names {
+        app.configure_sets(
+            SimulationSchedule,
+            SimulationPassSet(name)
+                .run_if(pass_enabled(name))
+                .in_set(SimulationSet::Step)
+                .after(super::begin_step)
+                .before(super::end_step),
+        );
+    }
+    for &(before, after) in &schedule.ordering {
+        app.configure_sets(
+            SimulationSchedule,
+            SimulationPassSet(after).after(SimulationPassSet(before)),
+        );
+    }
+
+    app.insert_resource(schedule);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn independent_passes_share_a_stage() {
+        let mut passes = SimulationPasses::default();
+        passes.register(SimulationPass::new("life").writes(PassChannel::Cells));
+        passes.register(SimulationPass::new("light").writes(PassChannel::Custom("light")));
+        passes.register(
+            SimulationPass::new("sand")
+                .writes(PassChannel::Cells)
+                .after("life"),
+        );
+
+        let schedule = passes.build_schedule().unwrap();
+        assert_eq!(schedule.stages, vec![vec!["life", "light"], vec!["sand"]]);
+    }
+
+    #[test]
+    fn passes_toggle_at_runtime() {
+        let mut control = PassControl::default();
+        assert!(control.is_enabled("fluid"));
+        assert!(control.disable("fluid"));
+        assert!(!control.disable("fluid"));
+        assert!(!control.is_enabled("fluid"));
+        assert_eq!(control.disabled().collect::<Vec<_>>(), vec!["fluid"]);
+        assert!(control.toggle("fluid"));
+        assert!(control.is_enabled("fluid"));
+    }
+
+    #[test]
+    fn cycles_and_unknown_dependencies_are_rejected() {
+        let mut passes = SimulationPasses::default();
+        passes.register(SimulationPass::new("a").after("b"));
+        passes.register(SimulationPass::new("b").after("a"));
+        assert!(matches!(
+            passes.build_schedule(),
+            Err(PassGraphError::Cycle(_))
+        ));
+
+        let mut passes = SimulationPasses::default();
+        passes.register(SimulationPass::new("a").after("missing"));
+        assert_eq!(
+            passes.build_schedule().unwrap_err(),
+            PassGraphError::UnknownDependency {
+                pass: "a",
+                dependency: "missing"
+            }
+        );
+    }
+}
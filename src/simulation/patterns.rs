@@ -0,0 +1,451 @@
+use super::{AutomataState, ChunkCells, Stamp, CHUNK_EDGE};
+use crate::ChunkSource;
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use std::fmt;
+
+/// Stamped at the center of every chunk generated from now on, before its first step.
+///
+/// Restored and loaded chunks are left alone. Patterns come from `.rle3` files loaded as
+/// [`Stamp`] assets, or from the [`BuiltinPattern`]s.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct SeedPattern {
+    pub stamp: Option<Stamp>,
+}
+
+impl SeedPattern {
+    pub fn builtin(pattern: BuiltinPattern) -> Self {
+        Self {
+            stamp: Some(pattern.stamp()),
+        }
+    }
+}
+
+/// Starter patterns shipped with the engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BuiltinPattern {
+    /// A solid 2x2x2 block.
+    Cube,
+    /// A cell with its six face neighbors.
+    Cross,
+    /// A hollow 3x3x3 box.
+    Shell,
+    /// A deterministic 5x5x5 soup with about 40% live cells.
+    Soup,
+}
+
+impl BuiltinPattern {
+    pub const ALL: [BuiltinPattern; 4] = [
+        BuiltinPattern::Cube,
+        BuiltinPattern::Cross,
+        BuiltinPattern::Shell,
+        BuiltinPattern::Soup,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BuiltinPattern::Cube => "cube",
+            BuiltinPattern::Cross => "cross",
+            BuiltinPattern::Shell => "shell",
+            BuiltinPattern::Soup => "soup",
+        }
+    }
+
+    /// The pattern in the `.rle3` format.
+    pub fn source(self) -> &'static str {
+        match self {
+            BuiltinPattern::Cube => include_str!("patterns/cube.rle3"),
+            BuiltinPattern::Cross => include_str!("patterns/cross.rle3"),
+            BuiltinPattern::Shell => include_str!("patterns/shell.rle3"),
+            BuiltinPattern::Soup => include_str!("patterns/soup.rle3"),
+        }
+    }
+
+    pub fn stamp(self) -> Stamp {
+        Stamp::from_rle(self.source()).expect("builtin patterns are valid")
+    }
+}
+
+/// Cells a pattern read by [`Stamp::from_rle`] holds at most, a 256x256x256 box.
+pub const MAX_PATTERN_CELLS: i32 = 1 << 24;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternParseError {
+    /// The file could not be read.
+    Io(String),
+    /// The header line is missing, or has an unknown key or an invalid value.
+    InvalidHeader { line: usize },
+    /// A character of the body is not a cell, a run count or a separator.
+    InvalidCell { line: usize, found: char },
+    /// The cells run past the size given in the header.
+    OutOfBounds { line: usize },
+    /// The size holds more than [`MAX_PATTERN_CELLS`] cells, or a run count overflows.
+    TooLarge { line: usize },
+}
+
+impl fmt::Display for PatternParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternParseError::Io(error) => write!(f, "could not read pattern: {}", error),
+            PatternParseError::InvalidHeader { line } => {
+                write!(
+                    f,
+                    "line {}: expected a header like x = 3, y = 3, z = 3",
+                    line
+                )
+            }
+            PatternParseError::InvalidCell { line, found } => {
+                write!(f, "line {}: unexpected {:?} in the cells", line, found)
+            }
+            PatternParseError::OutOfBounds { line } => {
+                write!(f, "line {}: cells outside of the pattern size", line)
+            }
+            PatternParseError::TooLarge { line } => {
+                write!(f, "line {}: pattern size or run count is too large", line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatternParseError {}
+
+impl From<std::io::Error> for PatternParseError {
+    fn from(error: std::io::Error) -> Self {
+        PatternParseError::Io(error.to_string())
+    }
+}
+
+impl Stamp {
+    /// Parses a pattern in the `.rle3` format, the run length encoding of Life patterns
+    /// extended to three dimensions and to materials.
+    ///
+    /// Lines starting with `#` are comments. The header gives the size with `x`, `y` and an
+    /// optional `z` defaulting to 1, an optional `anchor = x y z`, and a `rule` that is ignored,
+    /// so 2D Life files load as a single layer. The body lists cells along `x`, each optionally
+    /// preceded by a run count: `b` or `.` for empty cells, `o` for material 1 and `A` to `X`,
+    /// optionally prefixed by `p` to `y`, for materials 1 to 255 as in multi-state Golly files.
+    /// `$` moves to the next row along `y`, `/` to the next layer along `z` and `!` ends the
+    /// pattern. Missing cells are empty.
+    pub fn from_rle(text: &str) -> Result<Stamp, PatternParseError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        let (header_line, header) = lines
+            .next()
+            .ok_or(PatternParseError::InvalidHeader { line: 1 })?;
+        let invalid_header = PatternParseError::InvalidHeader { line: header_line };
+        let mut size = IVec3::new(-1, -1, 1);
+        let mut anchor = IVec3::ZERO;
+        for field in header.split(',') {
+            let (key, value) = field.split_once('=').ok_or(invalid_header.clone())?;
+            let value = value.trim();
+            let axis = |value: &str| value.parse::<i32>().ok().filter(|value| *value >= 0);
+            match key.trim() {
+                "x" => size.x = axis(value).ok_or(invalid_header.clone())?,
+                "y" => size.y = axis(value).ok_or(invalid_header.clone())?,
+                "z" => size.z = axis(value).ok_or(invalid_header.clone())?,
+                "anchor" => {
+                    let values: Vec<i32> = value
+                        .split_whitespace()
+                        .map(|value| value.parse().map_err(|_| invalid_header.clone()))
+                        .collect::<Result<_, _>>()?;
+                    let [x, y, z] = values[..] else {
+                        return Err(invalid_header);
+                    };
+                    anchor = IVec3::new(x, y, z);
+                }
+                "rule" => {}
+                _ => return Err(invalid_header),
+            }
+        }
+        if size.cmplt(IVec3::ZERO).any() {
+            return Err(invalid_header);
+        }
+        let volume = size
+            .x
+            .checked_mul(size.y)
+            .and_then(|area| area.checked_mul(size.z))
+            .filter(|volume| *volume <= MAX_PATTERN_CELLS);
+        if volume.is_none() {
+            return Err(PatternParseError::TooLarge { line: header_line });
+        }
+
+        let mut stamp = Stamp::new(size).with_anchor(anchor);
+        let mut cursor = IVec3::ZERO;
+        let mut count = 0;
+        let mut prefix = None;
+        'body: for (line, body) in lines {
+            for found in body.chars() {
+                let invalid = PatternParseError::InvalidCell { line, found };
+                let too_large = PatternParseError::TooLarge { line };
+                let run = count.max(1);
+                let material = match found {
+                    '0'..='9' if prefix.is_none() => {
+                        count = count
+                            .checked_mul(10)
+                            .and_then(|count| count.checked_add(found.to_digit(10)? as i32))
+                            .ok_or(too_large)?;
+                        continue;
+                    }
+                    'p'..='y' if prefix.is_none() => {
+                        prefix = Some(found as u32 - 'p' as u32);
+                        continue;
+                    }
+                    'A'..='X' => {
+                        let letter = found as u32 - 'A' as u32 + 1;
+                        let material = match prefix.take() {
+                            Some(prefix) => 24 * (prefix + 1) + letter,
+                            None => letter,
+                        };
+                        u8::try_from(material).map_err(|_| invalid)?
+                    }
+                    _ if prefix.is_some() => return Err(invalid),
+                    'b' | '.' => 0,
+                    'o' => 1,
+                    '$' => {
+                        let y = cursor.y.checked_add(run).ok_or(too_large)?;
+                        cursor = IVec3::new(0, y, cursor.z);
+                        count = 0;
+                        continue;
+                    }
+                    '/' => {
+                        let z = cursor.z.checked_add(run).ok_or(too_large)?;
+                        cursor = IVec3::new(0, 0, z);
+                        count = 0;
+                        continue;
+                    }
+                    '!' => break 'body,
+                    _ if found.is_whitespace() => continue,
+                    _ => return Err(invalid),
+                };
+
+                let end = cursor.x.checked_add(run).ok_or(too_large)?;
+                if material != 0 {
+                    for x in cursor.x..end {
+                        let position = IVec3::new(x, cursor.y, cursor.z);
+                        if stamp.get(position).is_none() {
+                            return Err(PatternParseError::OutOfBounds { line });
+                        }
+                        stamp.set(position, AutomataState::new(material, 0));
+                    }
+                }
+                cursor.x = end;
+                count = 0;
+            }
+        }
+        Ok(stamp)
+    }
+
+    /// Writes the stamp in the `.rle3` format read by [`Stamp::from_rle`]. Cell flags are not
+    /// saved.
+    pub fn to_rle(&self) -> String {
+        let size = self.size();
+        let mut text = format!("x = {}, y = {}, z = {}", size.x, size.y, size.z);
+        if self.anchor != IVec3::ZERO {
+            let anchor = self.anchor;
+            text += &format!(", anchor = {} {} {}", anchor.x, anchor.y, anchor.z);
+        }
+        text.push('\n');
+
+        let layers: Vec<String> = (0..size.z)
+            .map(|z| {
+                let rows: Vec<String> = (0..size.y)
+                    .map(|y| {
+                        let cells = (0..size.x)
+                            .map(|x| self.get(IVec3::new(x, y, z)).unwrap().material)
+                            .collect::<Vec<_>>();
+                        encode_row(&cells)
+                    })
+                    .collect();
+                rows.join("$")
+            })
+            .collect();
+        text += &layers.join("/");
+        text += "!\n";
+        text
+    }
+}
+
+/// Runs of cell tags, without the empty cells ending the row.
+fn encode_row(cells: &[u8]) -> String {
+    let end = cells
+        .iter()
+        .rposition(|material| *material != 0)
+        .map_or(0, |i| i + 1);
+    let mut row = String::new();
+    let mut start = 0;
+    while start < end {
+        let material = cells[start];
+        let run = cells[start..end]
+            .iter()
+            .take_while(|other| **other == material)
+            .count();
+        if run > 1 {
+            row += &run.to_string();
+        }
+        match material {
+            0 => row.push('b'),
+            1 => row.push('o'),
+            2..=24 => row.push((b'A' + material - 1) as char),
+            _ => {
+                let index = material - 25;
+                row.push((b'p' + index / 24) as char);
+                row.push((b'A' + index % 24) as char);
+            }
+        }
+        start += run;
+    }
+    row
+}
+
+/// Loads `.rle3` files as [`Stamp`] assets, see [`Stamp::from_rle`].
+#[derive(Default)]
+pub struct StampLoader;
+
+impl AssetLoader for StampLoader {
+    type Asset = Stamp;
+    type Settings = ();
+    type Error = PatternParseError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Stamp, PatternParseError>> {
+        Box::pin(async move {
+            let mut text = String::new();
+            reader.read_to_string(&mut text).await?;
+            Stamp::from_rle(&text)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["rle3"]
+    }
+}
+
+pub(super) fn seed_pattern_set(seed: Res<SeedPattern>) -> bool {
+    seed.stamp.is_some()
+}
+
+pub(super) fn seed_generated_chunks(
+    seed: Res<SeedPattern>,
+    mut chunks: Query<(&ChunkSource, &mut ChunkCells), Added<ChunkSource>>,
+) {
+    let Some(stamp) = &seed.stamp else {
+        return;
+    };
+    let center = IVec3::splat(CHUNK_EDGE / 2);
+    for (source, mut cells) in chunks.iter_mut() {
+        if *source != ChunkSource::Generated {
+            continue;
+        }
+        for (local, state) in stamp.placed(center, Quat::IDENTITY) {
+            if local.cmpge(IVec3::ZERO).all() && local.cmplt(IVec3::splat(CHUNK_EDGE)).all() {
+                cells.set(local, state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_round_trip_through_rle() {
+        for pattern in BuiltinPattern::ALL {
+            let stamp = pattern.stamp();
+            assert_eq!(
+                Stamp::from_rle(&stamp.to_rle()),
+                Ok(stamp),
+                "{}",
+                pattern.name()
+            );
+        }
+
+        let cross = BuiltinPattern::Cross.stamp();
+        assert_eq!(cross.anchor, IVec3::ONE);
+        assert_eq!(cross.placed(IVec3::ZERO, Quat::IDENTITY).count(), 7);
+
+        let mut stamp = Stamp::new(IVec3::new(4, 1, 1));
+        stamp.set(IVec3::ZERO, AutomataState::new(2, 0));
+        stamp.set(IVec3::new(2, 0, 0), AutomataState::new(200, 0));
+        let text = stamp.to_rle();
+        assert_eq!(text, "x = 4, y = 1, z = 1\nBbwH!\n");
+        assert_eq!(Stamp::from_rle(&text), Ok(stamp));
+
+        // Plain 2D Life files load as one layer.
+        let glider = Stamp::from_rle("#N glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!").unwrap();
+        assert_eq!(glider.size(), IVec3::new(3, 3, 1));
+        assert_eq!(
+            glider.get(IVec3::new(2, 1, 0)),
+            Some(AutomataState::new(1, 0))
+        );
+
+        assert_eq!(
+            Stamp::from_rle("x = 2, y = 1\n3o!"),
+            Err(PatternParseError::OutOfBounds { line: 2 })
+        );
+        assert_eq!(
+            Stamp::from_rle("x = 2, y = 1\noq!"),
+            Err(PatternParseError::InvalidCell {
+                line: 2,
+                found: '!'
+            })
+        );
+    }
+
+    #[test]
+    fn oversized_patterns_are_rejected() {
+        assert_eq!(
+            Stamp::from_rle("x = 65536, y = 65536, z = 65536\n!"),
+            Err(PatternParseError::TooLarge { line: 1 })
+        );
+        assert_eq!(
+            Stamp::from_rle("x = 257, y = 256, z = 256\n!"),
+            Err(PatternParseError::TooLarge { line: 1 })
+        );
+        assert_eq!(
+            Stamp::from_rle("x = 3, y = 3\n99999999999o!"),
+            Err(PatternParseError::TooLarge { line: 2 })
+        );
+        assert_eq!(
+            Stamp::from_rle("x = 3, y = 3\n2147483647b2147483647b!"),
+            Err(PatternParseError::TooLarge { line: 2 })
+        );
+    }
+
+    #[test]
+    fn generated_chunks_are_seeded() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(crate::CellularAutomataPlugin)
+            .insert_resource(SeedPattern::builtin(BuiltinPattern::Cube));
+        let generated = app
+            .world
+            .spawn((crate::ChunkBundle::new(IVec3::ZERO), ChunkSource::Generated))
+            .id();
+        let restored = app
+            .world
+            .spawn((crate::ChunkBundle::new(IVec3::X), ChunkSource::Restored))
+            .id();
+        app.world.resource_mut::<crate::SimulationControl>().paused = true;
+        app.update();
+
+        let center = IVec3::splat(CHUNK_EDGE / 2);
+        let cells = app.world.get::<ChunkCells>(generated).unwrap();
+        assert!(cells.get(center).is_alive());
+        assert!(cells.get(center + IVec3::ONE).is_alive());
+        assert!(!cells.get(center + IVec3::splat(2)).is_alive());
+        let cells = app.world.get::<ChunkCells>(restored).unwrap();
+        assert!(!cells.get(center).is_alive());
+    }
+}
@@ -0,0 +1,187 @@
+use super::{AutomataState, SimulationSet, CHUNK_VOLUME};
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+use std::sync::{Arc, Mutex};
+
+/// Chunk sized cell buffers reused across steps instead of being allocated for every chunk.
+///
+/// The steppers take their scratch buffers from here, and snapshots reuse the buffers of the
+/// previous snapshot once no step reads them anymore. Safe to share between the threads
+/// stepping chunks. Statistics are reported as diagnostics, see [`BufferPool::POOLED`].
+#[derive(Resource, Debug)]
+pub struct BufferPool {
+    /// Most buffers of each kind kept around, the rest are freed on release.
+    pub max_pooled: usize,
+    inner: Mutex<PoolInner>,
+}
+
+#[derive(Debug, Default)]
+struct PoolInner {
+    boxed: Vec<Box<[AutomataState]>>,
+    shared: Vec<Arc<[AutomataState]>>,
+    stats: BufferPoolStats,
+}
+
+/// Counters of a [`BufferPool`] since it was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BufferPoolStats {
+    /// Buffers handed out that had to be allocated.
+    pub allocated: u64,
+    /// Buffers handed out from the pool.
+    pub reused: u64,
+    /// Buffers freed on release because the pool was full or they were still shared.
+    pub dropped: u64,
+    /// Buffers currently waiting in the pool.
+    pub pooled: usize,
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        Self {
+            max_pooled: 256,
+            inner: Mutex::default(),
+        }
+    }
+}
+
+impl BufferPool {
+    /// Number of buffers waiting in the pool.
+    pub const POOLED: DiagnosticId =
+        DiagnosticId::from_u128(0x7a1c_5e0b_3f64_4d2a_9c1e_80b4_25f6_d301);
+    /// Buffers allocated during the frame.
+    pub const ALLOCATED: DiagnosticId =
+        DiagnosticId::from_u128(0x7a1c_5e0b_3f64_4d2a_9c1e_80b4_25f6_d302);
+    /// Buffers reused from the pool during the frame.
+    pub const REUSED: DiagnosticId =
+        DiagnosticId::from_u128(0x7a1c_5e0b_3f64_4d2a_9c1e_80b4_25f6_d303);
+
+    /// Buffer holding a copy of `cells`, which must be a whole chunk.
+    pub fn acquire(&self, cells: &[AutomataState]) -> Box<[AutomataState]> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.boxed.pop() {
+            Some(mut buffer) => {
+                inner.stats.reused += 1;
+                drop(inner);
+                buffer.copy_from_slice(cells);
+                buffer
+            }
+            None => {
+                inner.stats.allocated += 1;
+                drop(inner);
+                Box::from(cells)
+            }
+        }
+    }
+
+    pub fn release(&self, buffer: Box<[AutomataState]>) {
+        let mut inner = self.inner.lock().unwrap();
+        if buffer.len() == CHUNK_VOLUME && inner.boxed.len() < self.max_pooled {
+            inner.boxed.push(buffer);
+        } else {
+            inner.stats.dropped += 1;
+        }
+    }
+
+    /// Shared buffer filled by `fill`, which is handed the whole chunk.
+    pub fn acquire_shared(&self, fill: impl FnOnce(&mut [AutomataState])) -> Arc<[AutomataState]> {
+        let mut inner = self.inner.lock().unwrap();
+        let pooled = inner.shared.pop();
+        if pooled.is_some() {
+            inner.stats.reused += 1;
+        } else {
+            inner.stats.allocated += 1;
+        }
+        drop(inner);
+
+        let mut buffer =
+            pooled.unwrap_or_else(|| Arc::from(vec![AutomataState::EMPTY; CHUNK_VOLUME]));
+        // Only unique buffers are pooled, see `release_shared`.
+        fill(Arc::get_mut(&mut buffer).unwrap());
+        buffer
+    }
+
+    /// Returns a shared buffer, kept only once nothing else holds it.
+    pub fn release_shared(&self, mut buffer: Arc<[AutomataState]>) {
+        let mut inner = self.inner.lock().unwrap();
+        let unique = Arc::get_mut(&mut buffer).is_some();
+        if unique && buffer.len() == CHUNK_VOLUME && inner.shared.len() < self.max_pooled {
+            inner.shared.push(buffer);
+        } else {
+            inner.stats.dropped += 1;
+        }
+    }
+
+    pub fn stats(&self) -> BufferPoolStats {
+        let inner = self.inner.lock().unwrap();
+        BufferPoolStats {
+            pooled: inner.boxed.len() + inner.shared.len(),
+            ..inner.stats
+        }
+    }
+
+    /// Frees every pooled buffer.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.boxed.clear();
+        inner.shared.clear();
+    }
+}
+
+pub(super) fn register_diagnostics(app: &mut App) {
+    app.register_diagnostic(Diagnostic::new(
+        BufferPool::POOLED,
+        "buffer_pool_pooled",
+        20,
+    ))
+    .register_diagnostic(Diagnostic::new(
+        BufferPool::ALLOCATED,
+        "buffer_pool_allocated",
+        20,
+    ))
+    .register_diagnostic(Diagnostic::new(
+        BufferPool::REUSED,
+        "buffer_pool_reused",
+        20,
+    ))
+    .add_systems(PostUpdate, report_buffer_pool.after(SimulationSet::Run));
+}
+
+fn report_buffer_pool(
+    pool: Res<BufferPool>,
+    mut diagnostics: Diagnostics,
+    mut last: Local<BufferPoolStats>,
+) {
+    let stats = pool.stats();
+    diagnostics.add_measurement(BufferPool::POOLED, || stats.pooled as f64);
+    diagnostics.add_measurement(BufferPool::ALLOCATED, || {
+        (stats.allocated - last.allocated) as f64
+    });
+    diagnostics.add_measurement(BufferPool::REUSED, || (stats.reused - last.reused) as f64);
+    *last = stats;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn released_buffers_are_reused_once_unique() {
+        let pool = BufferPool::default();
+        let cells = vec![AutomataState::new(1, 0); CHUNK_VOLUME];
+
+        let buffer = pool.acquire(&cells);
+        pool.release(buffer);
+        assert_eq!(&*pool.acquire(&cells), &cells[..]);
+
+        let shared = pool.acquire_shared(|buffer| buffer.fill(AutomataState::EMPTY));
+        let reader = shared.clone();
+        pool.release_shared(shared);
+        assert_eq!(pool.stats().pooled, 0);
+        pool.release_shared(reader);
+        let stats = pool.stats();
+        assert_eq!(stats.pooled, 1);
+        assert_eq!((stats.allocated, stats.reused, stats.dropped), (2, 1, 1));
+    }
+}
@@ -0,0 +1,147 @@
+use super::{voxel_to_chunk, AutomataState, ChunkCells, ChunkIndex, VoxelWorldTransform};
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+/// First live voxel hit by a ray.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoxelHit {
+    pub voxel: IVec3,
+    /// Normal of the face the ray entered through in voxel space, zero when the ray started
+    /// inside the voxel.
+    pub normal: IVec3,
+    pub state: AutomataState,
+    /// World space distance from the ray origin.
+    pub distance: f32,
+    /// World space point where the ray entered the voxel.
+    pub position: Vec3,
+}
+
+/// Casts rays against the automata cells, e.g. for mouse picking and editing tools.
+///
+/// Cells of chunks missing from the [`ChunkIndex`] count as empty.
+#[derive(SystemParam)]
+pub struct VoxelRaycast<'w, 's> {
+    world_transform: Res<'w, VoxelWorldTransform>,
+    index: Res<'w, ChunkIndex>,
+    chunks: Query<'w, 's, &'static ChunkCells>,
+}
+
+impl<'w, 's> VoxelRaycast<'w, 's> {
+    /// Casts a world space ray, `max_dist` being in world units.
+    pub fn cast(&self, origin: Vec3, dir: Vec3, max_dist: f32) -> Option<VoxelHit> {
+        let dir = dir.normalize_or_zero();
+        if dir == Vec3::ZERO {
+            return None;
+        }
+
+        let voxel_origin = self.world_transform.world_to_voxel_space(origin);
+        let voxel_dir = self.world_transform.world_to_voxel_direction(dir);
+
+        let mut cached: Option<(IVec3, Option<&ChunkCells>)> = None;
+        let (voxel, normal, state, distance) =
+            raycast_voxels(voxel_origin, voxel_dir, max_dist, |voxel| {
+                let (chunk, local) = voxel_to_chunk(voxel);
+                let cells = match cached {
+                    Some((coords, cells)) if coords == chunk => cells,
+                    _ => {
+                        let cells = self
+                            .index
+                            .entity(chunk)
+                            .and_then(|entity| self.chunks.get(entity).ok());
+                        cached = Some((chunk, cells));
+                        cells
+                    }
+                };
+                // Most samples are empty, the occupancy mask skips decoding them.
+                cells
+                    .filter(|cells| cells.is_alive(local))
+                    .map(|cells| cells.get(local))
+            })?;
+
+        Some(VoxelHit {
+            voxel,
+            normal,
+            state,
+            distance,
+            position: origin + dir * distance,
+        })
+    }
+}
+
+/// Walks the voxels along `origin + t * dir` in voxel space for `t` in `0..=max_t`, returning
+/// the first voxel whose sampled state is alive, its entry normal, state and `t`.
+pub fn raycast_voxels<F>(
+    origin: Vec3,
+    dir: Vec3,
+    max_t: f32,
+    mut sample: F,
+) -> Option<(IVec3, IVec3, AutomataState, f32)>
+where
+    F: FnMut(IVec3) -> Option<AutomataState>,
+{
+    let mut voxel = origin.floor().as_ivec3();
+    let step = dir.signum().as_ivec3();
+    let delta = dir.recip().abs();
+    let mut next = Vec3::ZERO;
+    for axis in 0..3 {
+        next[axis] = if dir[axis] > 0.0 {
+            (voxel[axis] as f32 + 1.0 - origin[axis]) * delta[axis]
+        } else if dir[axis] < 0.0 {
+            (origin[axis] - voxel[axis] as f32) * delta[axis]
+        } else {
+            f32::INFINITY
+        };
+    }
+
+    let mut normal = IVec3::ZERO;
+    let mut t = 0.0;
+    while t <= max_t {
+        if let Some(state) = sample(voxel).filter(|state| state.is_alive()) {
+            return Some((voxel, normal, state, t));
+        }
+
+        let axis = if next.x < next.y {
+            if next.x < next.z {
+                0
+            } else {
+                2
+            }
+        } else if next.y < next.z {
+            1
+        } else {
+            2
+        };
+        t = next[axis];
+        next[axis] += delta[axis];
+        voxel[axis] += step[axis];
+        normal = IVec3::ZERO;
+        normal[axis] = -step[axis];
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ray_hits_face_facing_origin() {
+        let target = IVec3::new(5, 2, -3);
+        let sample = |voxel: IVec3| {
+            Some(if voxel == target {
+                AutomataState::new(1, 0)
+            } else {
+                AutomataState::EMPTY
+            })
+        };
+
+        let origin = Vec3::new(0.5, 2.5, -2.5);
+        let dir = Vec3::new(1.0, 0.0, -0.1).normalize();
+        let (voxel, normal, _, t) = raycast_voxels(origin, dir, 20.0, sample).unwrap();
+        assert_eq!(voxel, target);
+        assert_eq!(normal, IVec3::NEG_X);
+        assert!(((origin + dir * t).x - 5.0).abs() < 1e-4);
+
+        assert!(raycast_voxels(origin, dir, 4.0, sample).is_none());
+    }
+}
@@ -0,0 +1,298 @@
+use super::{
+    edit::write_chunk_edits, voxel_to_chunk, AutomataState, ChunkCells, ChunkIndex, RegionLockId,
+    RegionLocks,
+};
+use bevy::{prelude::*, utils::HashMap};
+use std::sync::Arc;
+
+/// Cells of a voxel box over consecutive steps, captured by a [`RegionRecorder`] and played back
+/// by a [`RegionReplay`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegionRecording {
+    size: IVec3,
+    frames: Vec<Box<[AutomataState]>>,
+}
+
+impl RegionRecording {
+    pub fn new(size: IVec3) -> Self {
+        Self {
+            size: size.max(IVec3::ZERO),
+            frames: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn size(&self) -> IVec3 {
+        self.size
+    }
+
+    /// Number of recorded steps.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Appends a frame, `sample` being called with every position of the box relative to its
+    /// minimum corner.
+    pub fn push_frame(&mut self, sample: impl Fn(IVec3) -> AutomataState) {
+        let frame = self.positions().map(sample).collect();
+        self.frames.push(frame);
+    }
+
+    /// Cell at a position relative to the minimum corner in the given frame.
+    pub fn get(&self, frame: usize, offset: IVec3) -> Option<AutomataState> {
+        if !offset.cmpge(IVec3::ZERO).all() || !offset.cmplt(self.size).all() {
+            return None;
+        }
+        let index =
+            (offset.x * self.size.y * self.size.z + offset.y * self.size.z + offset.z) as usize;
+        self.frames.get(frame)?.get(index).copied()
+    }
+
+    /// Positions of the box relative to its minimum corner, in the order frames store them.
+    fn positions(&self) -> impl Iterator<Item = IVec3> {
+        let size = self.size;
+        (0..size.x).flat_map(move |x| {
+            (0..size.y).flat_map(move |y| (0..size.z).map(move |z| IVec3::new(x, y, z)))
+        })
+    }
+}
+
+/// Records the voxel box at `min` of [`RegionRecording::size`] after every step, until it
+/// holds `steps` frames and [`RegionRecorded`] is sent. Missing chunks record as empty.
+#[derive(Component, Debug, Clone)]
+pub struct RegionRecorder {
+    pub min: IVec3,
+    pub steps: usize,
+    pub recording: RegionRecording,
+}
+
+impl RegionRecorder {
+    pub fn new(min: IVec3, size: IVec3, steps: usize) -> Self {
+        Self {
+            min,
+            steps,
+            recording: RegionRecording::new(size),
+        }
+    }
+
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.recording.len() >= self.steps
+    }
+}
+
+/// Sent once a [`RegionRecorder`] holds all its frames.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegionRecorded {
+    pub entity: Entity,
+}
+
+/// Plays a [`RegionRecording`] back into the live world with its minimum corner at `origin`,
+/// one frame per step, for authored moments such as a structure growing in a cutscene.
+///
+/// The region is locked with [`RegionLocks`] while the replay runs, so the simulation does not
+/// step it and edits into it are rejected. A replay whose region overlaps another lock waits for
+/// it to be released. Once the last frame is written the lock is released, the region resumes
+/// stepping from that frame, the component is removed and [`ReplayFinished`] is sent.
+#[derive(Component, Debug, Clone)]
+pub struct RegionReplay {
+    pub recording: Arc<RegionRecording>,
+    pub origin: IVec3,
+    /// Next frame to write.
+    pub frame: usize,
+    lock: Option<RegionLockId>,
+}
+
+impl RegionReplay {
+    pub fn new(recording: Arc<RegionRecording>, origin: IVec3) -> Self {
+        Self {
+            recording,
+            origin,
+            frame: 0,
+            lock: None,
+        }
+    }
+
+    #[inline]
+    pub fn is_playing(&self) -> bool {
+        self.lock.is_some()
+    }
+}
+
+/// Sent when a [`RegionReplay`] wrote its last frame and released its region.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayFinished {
+    pub entity: Entity,
+}
+
+pub(super) fn record_regions(
+    mut recorded: EventWriter<RegionRecorded>,
+    index: Res<ChunkIndex>,
+    mut recorders: Query<(Entity, &mut RegionRecorder)>,
+    chunks: Query<&ChunkCells>,
+) {
+    for (entity, mut recorder) in recorders.iter_mut() {
+        if recorder.is_finished() {
+            continue;
+        }
+        let min = recorder.min;
+        recorder.recording.push_frame(|offset| {
+            let (coords, local) = voxel_to_chunk(min + offset);
+            index
+                .entity(coords)
+                .and_then(|entity| chunks.get(entity).ok())
+                .map_or(AutomataState::EMPTY, |cells| cells.get(local))
+        });
+        if recorder.is_finished() {
+            recorded.send(RegionRecorded { entity });
+        }
+    }
+}
+
+/// Writes the next frame of every replay before the step is applied, marking the written
+/// bricks in [`ChunkChanges`](super::ChunkChanges) like edits.
+pub(super) fn play_region_replays(world: &mut World) {
+    let mut replays = world.query::<(Entity, &mut RegionReplay)>();
+    let mut frames = Vec::new();
+    let mut finished = Vec::new();
+    world.resource_scope(|world, mut locks: Mut<RegionLocks>| {
+        for (entity, mut replay) in replays.iter_mut(world) {
+            let size = replay.recording.size();
+            if replay.lock.is_none() {
+                match locks.lock(replay.origin, replay.origin + size) {
+                    Ok(lock) => replay.lock = Some(lock),
+                    Err(_) => continue,
+                }
+            }
+
+            let frame = replay.frame;
+            let mut by_chunk: HashMap<IVec3, Vec<(IVec3, AutomataState)>> = HashMap::new();
+            for x in 0..size.x {
+                for y in 0..size.y {
+                    for z in 0..size.z {
+                        let offset = IVec3::new(x, y, z);
+                        let Some(state) = replay.recording.get(frame, offset) else {
+                            continue;
+                        };
+                        let (coords, local) = voxel_to_chunk(replay.origin + offset);
+                        by_chunk.entry(coords).or_default().push((local, state));
+                    }
+                }
+            }
+            frames.extend(by_chunk);
+
+            replay.frame += 1;
+            if replay.frame >= replay.recording.len() {
+                if let Some(lock) = replay.lock.take() {
+                    locks.release(lock);
+                }
+                finished.push(entity);
+            }
+        }
+    });
+
+    for (coords, edits) in frames {
+        write_chunk_edits(world, coords, edits);
+    }
+    for entity in finished {
+        world.entity_mut(entity).remove::<RegionReplay>();
+        world.send_event(ReplayFinished { entity });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recordings_index_frames_by_offset() {
+        let mut recording = RegionRecording::new(IVec3::new(2, 3, 4));
+        for step in 0..3u8 {
+            recording.push_frame(|offset| AutomataState::new(step + offset.x as u8, 0));
+        }
+        assert_eq!(recording.len(), 3);
+        assert_eq!(
+            recording.get(2, IVec3::new(1, 2, 3)),
+            Some(AutomataState::new(3, 0))
+        );
+        assert_eq!(
+            recording.get(0, IVec3::ZERO),
+            Some(AutomataState::new(0, 0))
+        );
+        assert_eq!(recording.get(0, IVec3::new(2, 0, 0)), None);
+        assert_eq!(recording.get(3, IVec3::ZERO), None);
+    }
+
+    #[test]
+    fn replays_wait_for_their_region_then_write_frames_and_release_it() {
+        use crate::{CellularAutomataPlugin, SimulationControl};
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(CellularAutomataPlugin);
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        app.update();
+
+        let alive = AutomataState::new(1, 0);
+        let mut recording = RegionRecording::new(IVec3::new(2, 1, 1));
+        for frame in 0..2 {
+            recording.push_frame(|offset| match offset.x == frame {
+                true => alive,
+                false => AutomataState::EMPTY,
+            });
+        }
+        let origin = IVec3::splat(4);
+        let blocker = app
+            .world
+            .resource_mut::<RegionLocks>()
+            .lock(origin, origin + 1)
+            .unwrap();
+        let entity = app
+            .world
+            .spawn(RegionReplay::new(Arc::new(recording), origin))
+            .id();
+        let step = |app: &mut App| {
+            app.world.resource_mut::<SimulationControl>().step_once();
+            app.update();
+        };
+        let cell = |app: &App, x: i32| {
+            let chunk = app.world.resource::<ChunkIndex>().entity(IVec3::ZERO)?;
+            let cells = app.world.get::<ChunkCells>(chunk)?;
+            Some(cells.get(origin + IVec3::X * x))
+        };
+        let finished = |app: &App| {
+            let events = app.world.resource::<Events<ReplayFinished>>();
+            events
+                .iter_current_update_events()
+                .copied()
+                .collect::<Vec<_>>()
+        };
+
+        // The overlapping lock holds the replay back.
+        step(&mut app);
+        assert!(!app.world.get::<RegionReplay>(entity).unwrap().is_playing());
+        assert_ne!(cell(&app, 0), Some(alive));
+
+        app.world.resource_mut::<RegionLocks>().release(blocker);
+        step(&mut app);
+        let replay = app.world.get::<RegionReplay>(entity).unwrap();
+        assert!(replay.is_playing());
+        assert_eq!(replay.frame, 1);
+        assert_eq!(cell(&app, 0), Some(alive));
+        assert!(app.world.resource::<RegionLocks>().owner(origin).is_some());
+        assert!(finished(&app).is_empty());
+
+        step(&mut app);
+        assert_eq!(cell(&app, 0), Some(AutomataState::EMPTY));
+        assert_eq!(cell(&app, 1), Some(alive));
+        assert_eq!(finished(&app), [ReplayFinished { entity }]);
+        assert!(app.world.get::<RegionReplay>(entity).is_none());
+        assert!(app.world.resource::<RegionLocks>().is_empty());
+    }
+}
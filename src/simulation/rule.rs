@@ -0,0 +1,815 @@
+use super::{linear_index, voxel_to_chunk, AutomataState, ChunkSnapshots, RuleParseError};
+use crate::EngineEvent;
+use bevy::prelude::*;
+use std::{fmt, ops::RangeInclusive, sync::Arc};
+
+/// Maximum number of distinct materials a rule can count neighbors of.
+pub const MAX_TRACKED_MATERIALS: usize = 8;
+const UNTRACKED: u8 = u8::MAX;
+/// Most states of a "generations" rule. Decaying cells count their steps left in the three flag
+/// bits below `Flags::SAND_FLAG`, so the engine flags are never set on them.
+pub const MAX_STATES: u8 = 9;
+const DECAY_MASK: u8 = 0b111;
+
+/// Requires the number of neighbors made of `material` to lie within `min..=max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaterialCondition {
+    pub material: u8,
+    pub min: u8,
+    pub max: u8,
+}
+
+impl MaterialCondition {
+    pub fn at_least(material: u8, min: u8) -> Self {
+        Self {
+            material,
+            min,
+            max: u8::MAX,
+        }
+    }
+
+    pub fn between(material: u8, min: u8, max: u8) -> Self {
+        Self { material, min, max }
+    }
+
+    #[inline]
+    fn matches(&self, counts: &NeighborCounts, tracker: &MaterialTracker) -> bool {
+        let count = counts.of(self.material, tracker);
+        count >= self.min && count <= self.max
+    }
+}
+
+/// Birth/survival conditions for a single material, evaluated on per-material neighbor counts.
+///
+/// An empty cell becomes `material` when every birth condition holds (an empty list never
+/// births). A cell of `material` survives when every survive condition holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaterialRule {
+    pub material: u8,
+    pub birth: Vec<MaterialCondition>,
+    pub survive: Vec<MaterialCondition>,
+}
+
+/// Why [`AutomataRule::with_material_rules`] or [`AutomataRule::check_materials`] rejected the
+/// material rules: their conditions count neighbors of more distinct materials than the
+/// [`MAX_TRACKED_MATERIALS`] a [`NeighborCounts`] histogram holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooManyMaterials {
+    /// Distinct materials referenced by the conditions.
+    pub materials: usize,
+}
+
+impl fmt::Display for TooManyMaterials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "material conditions reference {} materials, at most {} can be counted",
+            self.materials, MAX_TRACKED_MATERIALS
+        )
+    }
+}
+
+impl std::error::Error for TooManyMaterials {}
+
+/// Birth/survival rule configured for the MVP.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct AutomataRule {
+    pub birth: Vec<u8>,
+    pub survive: Vec<u8>,
+    /// Material given to cells born through the `birth` counts.
+    pub birth_material: u8,
+    /// Material specific rules, checked before the total-count `birth`/`survive` lists. Set
+    /// them through [`AutomataRule::with_material_rules`], rules written directly are checked
+    /// once they become the resource and reported as [`EngineEvent::MaterialsUntracked`].
+    pub material_rules: Vec<MaterialRule>,
+    /// Number of states of a "generations" rule, 2 for plain birth/survive. Cells that fail to
+    /// survive decay through `states - 2` steps before they are empty again, kept as empty
+    /// cells whose low flag bits count the steps left. Decaying cells are not counted as
+    /// neighbors and cannot be born into. Values past [`MAX_STATES`] step like `MAX_STATES`.
+    pub states: u8,
+    /// Neighbors counted for the `birth`/`survive` lists.
+    pub neighborhood: Neighborhood,
+}
+
+impl Default for AutomataRule {
+    fn default() -> Self {
+        // Use a 3D Life variant (B5/S45) that produces interesting structures.
+        Self {
+            birth: vec![5],
+            survive: vec![4, 5],
+            birth_material: 1,
+            material_rules: Vec::new(),
+            states: 2,
+            neighborhood: Neighborhood::Moore,
+        }
+    }
+}
+
+/// Cells around a cell counted as its neighbors.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum Neighborhood {
+    /// The 26 cells sharing a face, edge or corner.
+    #[default]
+    Moore,
+    /// The 6 cells sharing a face.
+    VonNeumann,
+    /// The cells at the given offsets. Each axis must lie in `-1..=1` as the border cache keeps
+    /// a single cell around each chunk, see [`Neighborhood::validate`]. The cell itself and
+    /// repeated offsets are ignored.
+    Custom(Vec<IVec3>),
+}
+
+impl Neighborhood {
+    /// Fails on the first offset of a [`Neighborhood::Custom`] outside the 3x3x3 block, which
+    /// would not be counted. Checked when rules are read from configs and saves.
+    pub fn validate(&self) -> Result<(), RuleParseError> {
+        match self {
+            Neighborhood::Custom(offsets) => offsets
+                .iter()
+                .find(|offset| offset.abs().max_element() > 1)
+                .map_or(Ok(()), |offset| {
+                    Err(RuleParseError::OffsetOutOfRange(*offset))
+                }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Bit `i` is set when the cell at index `i` of [`CellContext::neighbor`]'s 3x3x3 block is
+    /// counted.
+    pub(crate) fn mask(&self) -> u32 {
+        const FACES: [usize; 6] = [4, 10, 12, 14, 16, 22];
+        match self {
+            Neighborhood::Moore => ((1 << 27) - 1) & !(1 << 13),
+            Neighborhood::VonNeumann => FACES.iter().fold(0, |mask, index| mask | 1 << index),
+            Neighborhood::Custom(offsets) => {
+                offsets
+                    .iter()
+                    .filter(|offset| offset.abs().max_element() <= 1)
+                    .map(|offset| {
+                        let offset = *offset + IVec3::ONE;
+                        offset.x * 9 + offset.y * 3 + offset.z
+                    })
+                    .fold(0, |mask, index| mask | 1 << index)
+                    & !(1 << 13)
+            }
+        }
+    }
+}
+
+impl AutomataRule {
+    #[inline]
+    pub(super) fn next_state(
+        &self,
+        current: AutomataState,
+        neighbors: &NeighborCounts,
+        tracker: &MaterialTracker,
+    ) -> AutomataState {
+        if current.is_alive() {
+            let survives = match self.material_rule(current.material) {
+                Some(rule) => rule
+                    .survive
+                    .iter()
+                    .all(|condition| condition.matches(neighbors, tracker)),
+                None => self.survive.contains(&neighbors.total),
+            };
+
+            if survives {
+                current
+            } else {
+                AutomataState::new(0, self.decay_steps())
+            }
+        } else if current.flags & DECAY_MASK > 0 && self.decay_steps() > 0 {
+            AutomataState::new(0, (current.flags & DECAY_MASK).min(self.decay_steps()) - 1)
+        } else {
+            for rule in &self.material_rules {
+                if !rule.birth.is_empty()
+                    && rule
+                        .birth
+                        .iter()
+                        .all(|condition| condition.matches(neighbors, tracker))
+                {
+                    return AutomataState::new(rule.material, 0);
+                }
+            }
+
+            if self.birth.contains(&neighbors.total) {
+                AutomataState::new(self.birth_material, 0)
+            } else {
+                AutomataState::EMPTY
+            }
+        }
+    }
+
+    /// Steps a dying cell decays through, which always fit in [`DECAY_MASK`].
+    #[inline]
+    fn decay_steps(&self) -> u8 {
+        self.states.clamp(2, MAX_STATES) - 2
+    }
+
+    fn material_rule(&self, material: u8) -> Option<&MaterialRule> {
+        self.material_rules
+            .iter()
+            .find(|rule| rule.material == material)
+    }
+}
+
+impl AutomataRule {
+    /// Replaces the material rules, failing when their conditions reference more than
+    /// [`MAX_TRACKED_MATERIALS`] distinct materials.
+    pub fn with_material_rules(
+        mut self,
+        material_rules: Vec<MaterialRule>,
+    ) -> Result<Self, TooManyMaterials> {
+        self.material_rules = material_rules;
+        self.check_materials()?;
+        Ok(self)
+    }
+
+    /// Fails when the conditions of the material rules reference more than
+    /// [`MAX_TRACKED_MATERIALS`] distinct materials, the [`MaterialTracker`] would not count the
+    /// neighbors of the ones past the limit.
+    pub fn check_materials(&self) -> Result<(), TooManyMaterials> {
+        let mut referenced = [false; 256];
+        for rule in &self.material_rules {
+            for condition in rule.birth.iter().chain(&rule.survive) {
+                referenced[condition.material as usize] = true;
+            }
+        }
+        let materials = referenced.iter().filter(|referenced| **referenced).count();
+        match materials > MAX_TRACKED_MATERIALS {
+            true => Err(TooManyMaterials { materials }),
+            false => Ok(()),
+        }
+    }
+
+    /// "Generations" rule with `states` states, clamped to `2..=MAX_STATES`, dying cells
+    /// decaying through `states - 2` steps before they can be born again.
+    pub fn generations(birth: Vec<u8>, survive: Vec<u8>, states: u8) -> Self {
+        Self {
+            birth,
+            survive,
+            states: states.clamp(2, MAX_STATES),
+            ..default()
+        }
+    }
+
+    /// How far a decaying cell is from empty, `1.0` right after it died down to `0.0` on its
+    /// last decay step, or `None` if the cell is alive or empty. Meant for effects fading
+    /// trails and structures out.
+    pub fn decay(&self, state: AutomataState) -> Option<f32> {
+        let steps = self.decay_steps();
+        if state.is_alive() || state.flags & DECAY_MASK == 0 || steps == 0 {
+            return None;
+        }
+        Some((state.flags & DECAY_MASK).min(steps) as f32 / steps as f32)
+    }
+
+    /// Rule of a [`RulePreset`], born cells taking the preset's
+    /// [`birth_material`](RulePreset::birth_material).
+    pub fn preset(preset: RulePreset) -> Self {
+        let (birth, survive, states): (&[RangeInclusive<u8>], &[RangeInclusive<u8>], u8) =
+            match preset {
+                RulePreset::Life => (&[5..=5], &[4..=5], 2),
+                RulePreset::FourFourFive => (&[4..=4], &[4..=4], 5),
+                RulePreset::Clouds => (&[13..=14, 17..=19], &[13..=26], 2),
+                RulePreset::Pyroclastic => (&[6..=8], &[4..=7], 9),
+                RulePreset::Amoeba => (&[5..=7, 12..=13, 15..=15], &[9..=26], 5),
+                RulePreset::Architecture => (&[3..=3], &[4..=6], 2),
+                RulePreset::Builder => (&[4..=4, 6..=6, 8..=9], &[2..=2, 6..=6, 9..=9], 9),
+                RulePreset::Coral => (&[6..=7, 9..=9, 12..=12], &[5..=8], 4),
+            };
+        let counts = |ranges: &[RangeInclusive<u8>]| ranges.iter().cloned().flatten().collect();
+        Self {
+            birth: counts(birth),
+            survive: counts(survive),
+            birth_material: preset.birth_material(),
+            states,
+            ..default()
+        }
+    }
+}
+
+/// Known 3D rules over the 26 cell Moore neighborhood, built with [`AutomataRule::preset`].
+/// Several are "generations" rules, where dying cells fade over a few steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RulePreset {
+    /// B5/S45, the default rule, growing slow stable structures.
+    Life,
+    /// B4/S4/5, crystalline growth from a small dense seed.
+    FourFourFive,
+    /// B13-14,17-19/S13-26, dense noise settling into smooth cloud shapes.
+    Clouds,
+    /// B6-8/S4-7/9, bursting and collapsing shells, one state short of the usual 10.
+    Pyroclastic,
+    /// B5-7,12-13,15/S9-26/5, blobs that wobble and merge.
+    Amoeba,
+    /// B3/S4-6, walls and pillars grown from a sparse seed.
+    Architecture,
+    /// B4,6,8-9/S2,6,9/9, scaffolding that keeps extending, one state short of the usual 10.
+    Builder,
+    /// B6-7,9,12/S5-8/4, slowly branching coral.
+    Coral,
+}
+
+impl RulePreset {
+    pub const ALL: [RulePreset; 8] = [
+        RulePreset::Life,
+        RulePreset::FourFourFive,
+        RulePreset::Clouds,
+        RulePreset::Pyroclastic,
+        RulePreset::Amoeba,
+        RulePreset::Architecture,
+        RulePreset::Builder,
+        RulePreset::Coral,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RulePreset::Life => "Life 5/45",
+            RulePreset::FourFourFive => "445",
+            RulePreset::Clouds => "Clouds",
+            RulePreset::Pyroclastic => "Pyroclastic",
+            RulePreset::Amoeba => "Amoeba",
+            RulePreset::Architecture => "Architecture",
+            RulePreset::Builder => "Builder",
+            RulePreset::Coral => "Coral",
+        }
+    }
+
+    /// Material given to born cells, distinct per preset so mixed worlds stay readable.
+    pub fn birth_material(self) -> u8 {
+        match self {
+            RulePreset::Life => 1,
+            RulePreset::FourFourFive => 2,
+            RulePreset::Clouds => 3,
+            RulePreset::Pyroclastic => 4,
+            RulePreset::Amoeba => 5,
+            RulePreset::Architecture => 6,
+            RulePreset::Builder => 7,
+            RulePreset::Coral => 8,
+        }
+    }
+
+    /// Recommended fraction of live cells when seeding a region at random.
+    pub fn seed_density(self) -> f32 {
+        match self {
+            RulePreset::Life => 0.3,
+            RulePreset::FourFourFive => 0.1,
+            RulePreset::Clouds => 0.5,
+            RulePreset::Pyroclastic => 0.2,
+            RulePreset::Amoeba => 0.4,
+            RulePreset::Architecture => 0.05,
+            RulePreset::Builder => 0.1,
+            RulePreset::Coral => 0.25,
+        }
+    }
+}
+
+impl AutomataRuleSet for AutomataRule {
+    #[inline]
+    fn next_state(&self, ctx: &CellContext) -> AutomataState {
+        match self.neighborhood {
+            Neighborhood::Moore => {
+                AutomataRule::next_state(self, ctx.current, &ctx.counts, ctx.tracker)
+            }
+            ref neighborhood => {
+                let counts = ctx.counts_in(neighborhood);
+                AutomataRule::next_state(self, ctx.current, &counts, ctx.tracker)
+            }
+        }
+    }
+
+    fn tracker(&self) -> MaterialTracker {
+        MaterialTracker::from_rule(self)
+    }
+
+    fn totalistic(&self) -> Option<&AutomataRule> {
+        let plain = self.states == 2 && self.neighborhood == Neighborhood::Moore;
+        (plain && self.material_rules.is_empty()).then_some(self)
+    }
+
+    fn name(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Reports an [`AutomataRule`] whose material rules the [`MaterialTracker`] cannot fully count,
+/// however it was set.
+pub(super) fn check_rule_materials(rule: Res<AutomataRule>, mut events: EventWriter<EngineEvent>) {
+    if !rule.is_changed() {
+        return;
+    }
+    if let Err(TooManyMaterials { materials }) = rule.check_materials() {
+        EngineEvent::MaterialsUntracked { materials }.report(&mut events);
+    }
+}
+
+/// Rule computing the next state of a cell from its Moore neighborhood.
+///
+/// Implemented by [`AutomataRule`], [`MargolusRule`](super::MargolusRule) and closures taking a
+/// [`CellContext`]. Rules other than the [`AutomataRule`] resource are activated through
+/// [`BoxedRule`].
+pub trait AutomataRuleSet: Send + Sync + 'static {
+    fn next_state(&self, ctx: &CellContext) -> AutomataState;
+
+    /// Materials counted separately in [`CellContext::counts`].
+    fn tracker(&self) -> MaterialTracker {
+        MaterialTracker::default()
+    }
+
+    /// The rule as an [`AutomataRule`] reading only [`NeighborCounts::total`], which the `simd`
+    /// feature steps with bitmasks instead of building a [`CellContext`] per cell.
+    fn totalistic(&self) -> Option<&AutomataRule> {
+        None
+    }
+
+    /// Identifies the rule in [`EngineReport`](crate::EngineReport)s, so peers can tell whether
+    /// they run the same one. Defaults to the type name, which all closures of a function share,
+    /// give those a name with [`BoxedRule::named`].
+    fn name(&self) -> String {
+        std::any::type_name::<Self>().to_string()
+    }
+}
+
+impl<F> AutomataRuleSet for F
+where
+    F: Fn(&CellContext) -> AutomataState + Send + Sync + 'static,
+{
+    #[inline]
+    fn next_state(&self, ctx: &CellContext) -> AutomataState {
+        self(ctx)
+    }
+}
+
+/// Replaces the [`AutomataRule`] resource as the rule evaluated by the CPU stepper while it
+/// exists. The GPU backend only runs the [`AutomataRule`], so it is skipped meanwhile.
+#[derive(Resource, Clone)]
+pub struct BoxedRule(pub Arc<dyn AutomataRuleSet>);
+
+impl BoxedRule {
+    pub fn new(rule: impl AutomataRuleSet) -> Self {
+        Self(Arc::new(rule))
+    }
+
+    /// Same as [`Self::new`], with the closure argument type inferred.
+    pub fn from_fn<F>(rule: F) -> Self
+    where
+        F: Fn(&CellContext) -> AutomataState + Send + Sync + 'static,
+    {
+        Self(Arc::new(rule))
+    }
+
+    /// Same as [`Self::new`], reporting `name` as the [`AutomataRuleSet::name`] of the rule.
+    pub fn named(name: impl Into<String>, rule: impl AutomataRuleSet) -> Self {
+        Self(Arc::new(NamedRule {
+            name: name.into(),
+            rule,
+        }))
+    }
+}
+
+/// Rule given a name by [`BoxedRule::named`].
+struct NamedRule<R> {
+    name: String,
+    rule: R,
+}
+
+impl<R: AutomataRuleSet> AutomataRuleSet for NamedRule<R> {
+    #[inline]
+    fn next_state(&self, ctx: &CellContext) -> AutomataState {
+        self.rule.next_state(ctx)
+    }
+
+    fn tracker(&self) -> MaterialTracker {
+        self.rule.tracker()
+    }
+
+    fn totalistic(&self) -> Option<&AutomataRule> {
+        self.rule.totalistic()
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+/// Everything a rule sees of a cell while it is stepped.
+pub struct CellContext<'a> {
+    /// Voxel position of the cell.
+    pub voxel: IVec3,
+    /// Step being computed.
+    pub step: u64,
+    pub current: AutomataState,
+    /// Live neighbor counts, per material for the materials of the rule's tracker.
+    pub counts: NeighborCounts,
+    pub tracker: &'a MaterialTracker,
+    neighbors: [Option<AutomataState>; 27],
+    snapshots: Option<&'a ChunkSnapshots>,
+}
+
+impl<'a> CellContext<'a> {
+    /// Builds the context from the 3×3×3 block around the cell, indexed by
+    /// `(x + 1) * 9 + (y + 1) * 3 + z + 1`. `None` marks cells in unloaded chunks.
+    pub(super) fn new(
+        voxel: IVec3,
+        step: u64,
+        current: AutomataState,
+        mut neighbors: [Option<AutomataState>; 27],
+        tracker: &'a MaterialTracker,
+    ) -> Self {
+        neighbors[13] = Some(current);
+
+        let mut counts = NeighborCounts::default();
+        for (i, state) in neighbors.iter().enumerate() {
+            match state {
+                Some(state) if i != 13 => counts.add(*state, tracker),
+                _ => {}
+            }
+        }
+
+        Self {
+            voxel,
+            step,
+            current,
+            counts,
+            tracker,
+            neighbors,
+            snapshots: None,
+        }
+    }
+
+    /// Reads [`aux`](Self::aux) values from the snapshots of the step being computed.
+    pub(super) fn with_snapshots(mut self, snapshots: &'a ChunkSnapshots) -> Self {
+        self.snapshots = Some(snapshots);
+        self
+    }
+
+    /// [`ChunkAux`](super::ChunkAux) value of the cell at `offset` before the step, or `None`
+    /// if its chunk has no aux data or is not loaded. Always `None` for cells stepped at a
+    /// reduced level of detail or by an [`AutomataEffect`](super::AutomataEffect).
+    pub fn aux(&self, offset: IVec3) -> Option<u16> {
+        let (chunk, local) = voxel_to_chunk(self.voxel + offset);
+        self.snapshots?
+            .aux(chunk)
+            .map(|values| values[linear_index(local)])
+    }
+
+    /// State of the cell at `offset`, each axis in `-1..=1`, or `None` if its chunk is not
+    /// loaded.
+    #[inline]
+    pub fn neighbor(&self, offset: IVec3) -> Option<AutomataState> {
+        let offset = offset + IVec3::ONE;
+        self.neighbors[(offset.x * 9 + offset.y * 3 + offset.z) as usize]
+    }
+
+    /// Live neighbor counts over the cells of `neighborhood` only.
+    pub fn counts_in(&self, neighborhood: &Neighborhood) -> NeighborCounts {
+        if *neighborhood == Neighborhood::Moore {
+            return self.counts;
+        }
+        let mask = neighborhood.mask();
+        let mut counts = NeighborCounts::default();
+        for (index, state) in self.neighbors.iter().enumerate() {
+            match state {
+                Some(state) if mask & 1 << index != 0 => counts.add(*state, self.tracker),
+                _ => {}
+            }
+        }
+        counts
+    }
+}
+
+/// Maps the materials referenced by a rule onto slots of a [`NeighborCounts`] histogram.
+///
+/// Only the first [`MAX_TRACKED_MATERIALS`] distinct materials are tracked, conditions on any
+/// other material always see a count of zero. Rules referencing more are rejected by
+/// [`AutomataRule::with_material_rules`] and when loading saves.
+#[derive(Debug, Clone)]
+pub struct MaterialTracker {
+    slots: [u8; 256],
+}
+
+impl Default for MaterialTracker {
+    /// Tracks no material.
+    fn default() -> Self {
+        Self {
+            slots: [UNTRACKED; 256],
+        }
+    }
+}
+
+impl MaterialTracker {
+    pub fn from_rule(rule: &AutomataRule) -> Self {
+        let mut tracker = Self::default();
+        let mut next = 0;
+
+        let conditions = rule
+            .material_rules
+            .iter()
+            .flat_map(|rule| rule.birth.iter().chain(rule.survive.iter()));
+        for condition in conditions {
+            let slot = &mut tracker.slots[condition.material as usize];
+            if *slot == UNTRACKED && next < MAX_TRACKED_MATERIALS {
+                *slot = next as u8;
+                next += 1;
+            }
+        }
+
+        tracker
+    }
+
+    #[inline]
+    pub fn slot(&self, material: u8) -> Option<usize> {
+        match self.slots[material as usize] {
+            UNTRACKED => None,
+            slot => Some(slot as usize),
+        }
+    }
+}
+
+/// Live neighbor count of a cell along with a small per-material histogram.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NeighborCounts {
+    pub total: u8,
+    by_material: [u8; MAX_TRACKED_MATERIALS],
+}
+
+impl NeighborCounts {
+    #[inline]
+    pub fn add(&mut self, state: AutomataState, tracker: &MaterialTracker) {
+        if !state.is_alive() {
+            return;
+        }
+
+        self.total = self.total.saturating_add(1);
+        if let Some(slot) = tracker.slot(state.material) {
+            self.by_material[slot] = self.by_material[slot].saturating_add(1);
+        }
+    }
+
+    /// Number of neighbors made of `material`, zero if the material is not tracked.
+    #[inline]
+    pub fn of(&self, material: u8, tracker: &MaterialTracker) -> u8 {
+        tracker
+            .slot(material)
+            .map_or(0, |slot| self.by_material[slot])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn material_conditions_select_birth_material() {
+        const WATER: u8 = 2;
+        const CORAL: u8 = 3;
+
+        let rule = AutomataRule {
+            birth: vec![],
+            survive: vec![],
+            birth_material: 1,
+            material_rules: vec![MaterialRule {
+                material: CORAL,
+                birth: vec![
+                    MaterialCondition::at_least(WATER, 3),
+                    MaterialCondition::at_least(CORAL, 1),
+                ],
+                survive: vec![MaterialCondition::at_least(WATER, 1)],
+            }],
+            ..default()
+        };
+        let tracker = MaterialTracker::from_rule(&rule);
+
+        let mut counts = NeighborCounts::default();
+        for _ in 0..3 {
+            counts.add(AutomataState::new(WATER, 0), &tracker);
+        }
+        assert_eq!(
+            rule.next_state(AutomataState::EMPTY, &counts, &tracker),
+            AutomataState::EMPTY
+        );
+
+        counts.add(AutomataState::new(CORAL, 0), &tracker);
+        assert_eq!(counts.total, 4);
+        assert_eq!(
+            rule.next_state(AutomataState::EMPTY, &counts, &tracker),
+            AutomataState::new(CORAL, 0)
+        );
+    }
+
+    /// Rules for the materials `1..=materials`, each counting neighbors of its own material.
+    fn rules(materials: u8) -> Vec<MaterialRule> {
+        (1..=materials)
+            .map(|material| MaterialRule {
+                material,
+                birth: vec![MaterialCondition::at_least(material, 1)],
+                survive: vec![MaterialCondition::at_least(1, 1)],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rules_referencing_too_many_materials_are_rejected() {
+        let limit = MAX_TRACKED_MATERIALS as u8;
+        assert!(AutomataRule::default()
+            .with_material_rules(rules(limit))
+            .is_ok());
+        assert_eq!(
+            AutomataRule::default().with_material_rules(rules(limit + 1)),
+            Err(TooManyMaterials {
+                materials: MAX_TRACKED_MATERIALS + 1
+            })
+        );
+    }
+
+    #[test]
+    fn rules_written_directly_are_reported() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(crate::CellularAutomataPlugin);
+        app.update();
+
+        let untracked = |app: &App| {
+            let events = app.world.resource::<Events<EngineEvent>>();
+            events
+                .iter_current_update_events()
+                .filter(|event| matches!(event, EngineEvent::MaterialsUntracked { .. }))
+                .count()
+        };
+        app.world.resource_mut::<AutomataRule>().material_rules =
+            rules(MAX_TRACKED_MATERIALS as u8);
+        app.update();
+        assert_eq!(untracked(&app), 0);
+
+        app.world.resource_mut::<AutomataRule>().material_rules =
+            rules(MAX_TRACKED_MATERIALS as u8 + 1);
+        app.update();
+        let events = app.world.resource::<Events<EngineEvent>>();
+        assert!(events.iter_current_update_events().any(|event| {
+            *event
+                == EngineEvent::MaterialsUntracked {
+                    materials: MAX_TRACKED_MATERIALS + 1,
+                }
+        }));
+        // Only reported when the rule changes.
+        app.update();
+        assert_eq!(untracked(&app), 0);
+    }
+
+    #[test]
+    fn generations_decay_before_rebirth() {
+        let rule = AutomataRule::generations(vec![1], vec![], 4);
+        let tracker = MaterialTracker::from_rule(&rule);
+        let mut counts = NeighborCounts::default();
+        counts.add(AutomataState::new(1, 0), &tracker);
+
+        let mut state = rule.next_state(AutomataState::new(1, 0), &counts, &tracker);
+        let mut decay = Vec::new();
+        while let Some(fraction) = rule.decay(state) {
+            decay.push(fraction);
+            state = rule.next_state(state, &counts, &tracker);
+        }
+        assert_eq!(decay, vec![1.0, 0.5]);
+        assert_eq!(state, AutomataState::EMPTY);
+        assert_eq!(
+            rule.next_state(state, &counts, &tracker),
+            AutomataState::new(1, 0)
+        );
+    }
+
+    #[test]
+    fn presets_expand_count_ranges() {
+        assert_eq!(
+            AutomataRule::preset(RulePreset::Life),
+            AutomataRule::default()
+        );
+        let clouds = AutomataRule::preset(RulePreset::Clouds);
+        assert_eq!(clouds.birth, vec![13, 14, 17, 18, 19]);
+        assert_eq!(clouds.survive, (13..=26).collect::<Vec<u8>>());
+        for preset in RulePreset::ALL {
+            let rule = AutomataRule::preset(preset);
+            let mut counts = rule.birth.iter().chain(&rule.survive);
+            assert!(counts.all(|count| *count <= 26));
+            assert!((0.0..=1.0).contains(&preset.seed_density()));
+        }
+    }
+
+    #[test]
+    fn decaying_cells_never_set_engine_flags() {
+        let counts = NeighborCounts::default();
+        for rule in RulePreset::ALL
+            .map(AutomataRule::preset)
+            .into_iter()
+            .chain([AutomataRule::generations(vec![], vec![], u8::MAX)])
+        {
+            let tracker = MaterialTracker::from_rule(&rule);
+            let mut state = AutomataState::new(rule.birth_material, 0);
+            for _ in 0..=rule.states {
+                state = rule.next_state(state, &counts, &tracker);
+                assert_eq!(state.flags & !DECAY_MASK, 0, "{:?}", rule);
+            }
+            assert_eq!(state, AutomataState::EMPTY);
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use super::{
+    brick_origin, AutomataRule, AutomataStepper, BoundaryMode, BoxedRule, ChunkCells, ChunkChanges,
+    ChunkIndex, ChunkKey, ChunkLod, PassControl, RuleChanged, BRICKS_PER_AXIS, BRICK_EDGE,
+};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+/// Stops stepping chunks whose cells did not change for [`after_steps`](Self::after_steps)
+/// steps in a row, marking them [`ChunkSleeping`].
+///
+/// A sleeping chunk wakes up when its cells are edited, when a step or an edit changes the
+/// border layer of a neighbor it reads, when a neighbor is loaded or unloaded, when its
+/// [`ChunkLod`] changes, or when the [`AutomataRule`], the [`BoxedRule`], the
+/// [`AutomataStepper`], the [`BoundaryMode`] or the enabled passes change. Only steps that
+/// actually ran the rule on a chunk count towards putting it to sleep. Sleeping is exact for
+/// rules where a still neighborhood stays still, which is why it is disabled by default: enable
+/// it only for rules that do not change cells at random.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkSleep {
+    pub enabled: bool,
+    pub after_steps: u32,
+}
+
+impl Default for ChunkSleep {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            after_steps: 8,
+        }
+    }
+}
+
+/// Marks a chunk skipped by the steps, its cells being kept as they are and whatever the passes
+/// write for it being ignored. See [`ChunkSleep`].
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChunkSleeping;
+
+/// Steps in a row that left a chunk unchanged, and the bricks changed by its last step.
+///
+/// Added to every chunk while [`ChunkSleep`] is enabled, and updated when the steps are applied.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChunkStillness {
+    pub steps: u32,
+    pub bricks: u64,
+}
+
+impl ChunkStillness {
+    pub(super) fn record(&mut self, bricks: u64) {
+        self.steps = if bricks == 0 {
+            self.steps.saturating_add(1)
+        } else {
+            0
+        };
+        self.bricks = bricks;
+    }
+}
+
+/// Offsets of the chunks whose border layer holds cells of `bricks`, including across edges and
+/// corners.
+fn border_neighbors(bricks: u64) -> HashSet<IVec3> {
+    let last = (BRICKS_PER_AXIS - 1) * BRICK_EDGE;
+    let mut offsets = HashSet::new();
+    for index in (0..64).filter(|index| bricks & (1 << index) != 0) {
+        let origin = brick_origin(index);
+        let sides = |axis: usize| {
+            let mut sides = vec![0];
+            if origin[axis] == 0 {
+                sides.push(-1);
+            }
+            if origin[axis] == last {
+                sides.push(1);
+            }
+            sides
+        };
+        for x in sides(0) {
+            for y in sides(1) {
+                for z in sides(2) {
+                    offsets.insert(IVec3::new(x, y, z));
+                }
+            }
+        }
+    }
+    offsets.remove(&IVec3::ZERO);
+    offsets
+}
+
+/// Puts still chunks to sleep and wakes the ones with activity around them, before the step
+/// is snapshotted.
+///
+/// Activity is a step or an edit changing the border layer of a neighbor, or a neighbor being
+/// loaded or unloaded. `loaded` remembers the coordinates of every chunk to find the neighbors
+/// of despawned ones.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub(super) fn update_chunk_sleep(
+    mut commands: Commands,
+    sleep: Res<ChunkSleep>,
+    index: Res<ChunkIndex>,
+    passes: Res<PassControl>,
+    rule: Res<AutomataRule>,
+    boxed_rule: Option<Res<BoxedRule>>,
+    stepper: Res<AutomataStepper>,
+    boundary: Res<BoundaryMode>,
+    mut rule_changes: EventReader<RuleChanged>,
+    mut removed: RemovedComponents<ChunkCells>,
+    mut loaded: Local<HashMap<Entity, IVec3>>,
+    mut had_boxed_rule: Local<bool>,
+    mut chunks: Query<(
+        Entity,
+        Ref<ChunkKey>,
+        Ref<ChunkCells>,
+        Option<Ref<ChunkChanges>>,
+        Option<Ref<ChunkLod>>,
+        Option<&mut ChunkStillness>,
+        Has<ChunkSleeping>,
+    )>,
+) {
+    // Installing or removing the boxed rule changes the rule as much as replacing it.
+    let boxed_rule_changed = boxed_rule.as_ref().is_some_and(|boxed| boxed.is_changed())
+        || boxed_rule.is_some() != *had_boxed_rule;
+    *had_boxed_rule = boxed_rule.is_some();
+    let wake_all = rule_changes.read().count() > 0
+        || passes.is_changed()
+        || rule.is_changed()
+        || boxed_rule_changed
+        || stepper.is_changed()
+        || boundary.is_changed();
+    let mut woken = HashSet::new();
+    let mut wake_around = |coords: IVec3, bricks: u64| {
+        woken.extend(
+            border_neighbors(bricks)
+                .into_iter()
+                .filter_map(|offset| index.entity(coords + offset)),
+        );
+    };
+
+    for entity in removed.read() {
+        if let Some(coords) = loaded.remove(&entity) {
+            wake_around(coords, u64::MAX);
+        }
+    }
+    for (entity, key, cells, changes, _, stillness, _) in chunks.iter() {
+        if key.is_changed() {
+            loaded.insert(entity, key.coords);
+        }
+        let mut bricks = stillness.map_or(0, |stillness| stillness.bricks);
+        if let Some(changes) = changes.filter(|changes| changes.is_changed()) {
+            bricks |= changes.bricks;
+        }
+        if cells.is_added() {
+            bricks = u64::MAX;
+        }
+        if bricks != 0 {
+            wake_around(key.coords, bricks);
+        }
+    }
+
+    for (entity, _, cells, _, lod, stillness, sleeping) in chunks.iter_mut() {
+        let Some(mut stillness) = stillness else {
+            if sleep.enabled {
+                commands.entity(entity).insert(ChunkStillness::default());
+            }
+            continue;
+        };
+        let wake = !sleep.enabled
+            || wake_all
+            || cells.is_changed()
+            || lod.is_some_and(|lod| lod.is_changed())
+            || woken.contains(&entity);
+        if wake {
+            if stillness.steps != 0 {
+                stillness.steps = 0;
+            }
+            if sleeping {
+                commands.entity(entity).remove::<ChunkSleeping>();
+            }
+        } else if !sleeping && stillness.steps >= sleep.after_steps {
+            commands.entity(entity).insert(ChunkSleeping);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::edit::apply_voxel_edits;
+    use super::*;
+    use crate::{
+        AutomataRule, AutomataState, CellularAutomataPlugin, ChunkBundle, SimulationControl,
+        CHUNK_EDGE,
+    };
+
+    fn step(app: &mut App) {
+        app.world.resource_mut::<SimulationControl>().step_once();
+        app.update();
+    }
+
+    #[test]
+    fn edits_across_the_border_wake_the_neighbor() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(CellularAutomataPlugin)
+            .insert_resource(ChunkSleep {
+                enabled: true,
+                after_steps: 2,
+            })
+            // Nothing dies and three neighbors give birth, so a lone cell is still.
+            .insert_resource(AutomataRule {
+                birth: vec![3],
+                survive: (0..=26).collect(),
+                ..default()
+            });
+        let alive = AutomataState::new(1, 0);
+        let still = app
+            .world
+            .spawn(ChunkBundle::from_generator(IVec3::ZERO, |local| {
+                match local == IVec3::splat(5) {
+                    true => alive,
+                    false => AutomataState::EMPTY,
+                }
+            }))
+            .id();
+        let neighbor = app.world.spawn(ChunkBundle::new(IVec3::X)).id();
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        app.update();
+        for _ in 0..5 {
+            step(&mut app);
+        }
+        assert!(app.world.get::<ChunkSleeping>(still).is_some());
+        assert!(app.world.get::<ChunkSleeping>(neighbor).is_some());
+
+        // Three cells on the shared face give birth to a cell in the neighbor's border layer.
+        let edge = CHUNK_EDGE - 1;
+        apply_voxel_edits(
+            &mut app.world,
+            [(edge, 5, 5), (edge, 5, 6), (edge, 6, 5)]
+                .map(|(x, y, z)| (IVec3::new(x, y, z), alive))
+                .to_vec(),
+        );
+        step(&mut app);
+        assert!(app.world.get::<ChunkSleeping>(neighbor).is_none());
+        let cells = app.world.get::<ChunkCells>(neighbor).unwrap();
+        assert!(cells.get(IVec3::new(0, 5, 5)).is_alive());
+    }
+
+    #[test]
+    fn changing_how_cells_step_wakes_every_chunk() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(CellularAutomataPlugin)
+            .insert_resource(ChunkSleep {
+                enabled: true,
+                after_steps: 2,
+            });
+        let chunk = app.world.spawn(ChunkBundle::new(IVec3::ZERO)).id();
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        app.update();
+        let fall_asleep = |app: &mut App| {
+            for _ in 0..4 {
+                step(app);
+            }
+            assert!(app.world.get::<ChunkSleeping>(chunk).is_some());
+        };
+
+        fall_asleep(&mut app);
+        app.world.resource_mut::<AutomataRule>().birth = vec![6];
+        step(&mut app);
+        assert!(app.world.get::<ChunkSleeping>(chunk).is_none());
+
+        fall_asleep(&mut app);
+        *app.world.resource_mut::<AutomataStepper>() = AutomataStepper::Checkerboard;
+        step(&mut app);
+        assert!(app.world.get::<ChunkSleeping>(chunk).is_none());
+
+        fall_asleep(&mut app);
+        *app.world.resource_mut::<BoundaryMode>() = BoundaryMode::default();
+        step(&mut app);
+        assert!(app.world.get::<ChunkSleeping>(chunk).is_none());
+    }
+
+    #[test]
+    fn border_bricks_wake_the_neighbors_reading_them() {
+        // The inner bricks are not read by any neighbor.
+        let inner = 1 << 21;
+        assert_eq!(brick_origin(21), IVec3::splat(BRICK_EDGE));
+        assert!(border_neighbors(inner).is_empty());
+
+        // The corner brick at the origin is read by the seven chunks around that corner.
+        let corner = border_neighbors(1);
+        assert_eq!(corner.len(), 7);
+        assert!(corner.contains(&IVec3::NEG_ONE));
+        assert!(corner.contains(&IVec3::NEG_X));
+        assert!(!corner.contains(&IVec3::X));
+
+        let mut stillness = ChunkStillness::default();
+        stillness.record(0);
+        stillness.record(0);
+        assert_eq!(stillness.steps, 2);
+        stillness.record(1);
+        assert_eq!(stillness.steps, 0);
+    }
+}
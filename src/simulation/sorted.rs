@@ -0,0 +1,148 @@
+use super::{morton_encode, ChunkKey};
+use bevy::{prelude::*, utils::HashMap};
+
+/// Additions beyond which the list is re-sorted once rather than inserted into one by one.
+const BULK_INSERT: usize = 64;
+
+/// Loaded chunks kept sorted by their [`ChunkKey::morton`] key, for deterministic iteration,
+/// range queries and batch work touching chunks in memory friendly order without sorting a
+/// query every time.
+///
+/// Updated incrementally from added, changed and removed [`ChunkKey`]s in `PostUpdate`, before
+/// the simulation steps. Chunks sharing a key are ordered by entity.
+#[derive(Resource, Debug, Default)]
+pub struct SortedChunks {
+    entries: Vec<(u64, Entity, IVec3)>,
+    keys: HashMap<Entity, u64>,
+}
+
+impl SortedChunks {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Chunk coordinates and entities in Morton order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (IVec3, Entity)> + '_ {
+        self.entries
+            .iter()
+            .map(|(_, entity, coords)| (*coords, *entity))
+    }
+
+    /// Chunks with coordinates within `min..=max` on every axis, in Morton order. Only the
+    /// Morton range spanned by the box is scanned.
+    pub fn in_region(&self, min: IVec3, max: IVec3) -> impl Iterator<Item = (IVec3, Entity)> + '_ {
+        let (low, high) = (morton_encode(min), morton_encode(max));
+        let start = self.entries.partition_point(|(morton, ..)| *morton < low);
+        let end = self.entries.partition_point(|(morton, ..)| *morton <= high);
+        self.entries[start..end.max(start)]
+            .iter()
+            .filter(move |(_, _, coords)| coords.cmpge(min).all() && coords.cmple(max).all())
+            .map(|(_, entity, coords)| (*coords, *entity))
+    }
+
+    /// Position of the chunk in Morton order, or `None` if it is not listed.
+    pub fn position(&self, entity: Entity) -> Option<usize> {
+        let morton = *self.keys.get(&entity)?;
+        self.entries
+            .binary_search_by_key(&(morton, entity), |(morton, entity, _)| (*morton, *entity))
+            .ok()
+    }
+
+    fn insert_all(&mut self, chunks: Vec<(Entity, ChunkKey)>) {
+        for (entity, _) in &chunks {
+            self.remove(*entity);
+        }
+        let bulk = chunks.len() > BULK_INSERT;
+        for (entity, key) in chunks {
+            self.keys.insert(entity, key.morton);
+            let entry = (key.morton, entity, key.coords);
+            if bulk {
+                self.entries.push(entry);
+            } else {
+                let at = self
+                    .entries
+                    .partition_point(|(morton, other, _)| (*morton, *other) < (key.morton, entity));
+                self.entries.insert(at, entry);
+            }
+        }
+        if bulk {
+            self.entries
+                .sort_unstable_by_key(|(morton, entity, _)| (*morton, *entity));
+        }
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        if let Some(position) = self.position(entity) {
+            self.entries.remove(position);
+            self.keys.remove(&entity);
+        }
+    }
+}
+
+pub(super) fn update_sorted_chunks(
+    mut sorted: ResMut<SortedChunks>,
+    mut removed: RemovedComponents<ChunkKey>,
+    changed: Query<(Entity, &ChunkKey), Changed<ChunkKey>>,
+) {
+    for entity in removed.read() {
+        sorted.remove(entity);
+    }
+    let changed: Vec<(Entity, ChunkKey)> =
+        changed.iter().map(|(entity, key)| (entity, *key)).collect();
+    if !changed.is_empty() {
+        sorted.insert_all(changed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_stay_sorted_and_answer_regions() {
+        let mut sorted = SortedChunks::default();
+        let chunk = |index: u32, coords: IVec3| (Entity::from_raw(index), ChunkKey::new(coords));
+        let mut chunks = Vec::new();
+        for x in -3..3 {
+            for y in -3..3 {
+                for z in -3..3 {
+                    chunks.push(chunk(chunks.len() as u32, IVec3::new(x, y, z)));
+                }
+            }
+        }
+        sorted.insert_all(chunks[..100].to_vec());
+        for single in chunks[100..].chunks(7) {
+            sorted.insert_all(single.to_vec());
+        }
+        assert_eq!(sorted.len(), 216);
+
+        let moved = chunk(5, IVec3::new(9, 9, 9));
+        sorted.insert_all(vec![moved]);
+        sorted.remove(Entity::from_raw(6));
+        assert_eq!(sorted.len(), 215);
+        assert_eq!(sorted.position(moved.0), Some(214));
+        assert!(sorted.position(Entity::from_raw(6)).is_none());
+        assert!(sorted
+            .entries
+            .windows(2)
+            .all(|pair| (pair[0].0, pair[0].1) < (pair[1].0, pair[1].1)));
+
+        let (min, max) = (IVec3::new(-1, 0, -2), IVec3::new(1, 2, 0));
+        let mut region: Vec<IVec3> = sorted
+            .in_region(min, max)
+            .map(|(coords, _)| coords)
+            .collect();
+        region.sort_by_key(|coords| coords.to_array());
+        let mut expected: Vec<IVec3> = chunks
+            .iter()
+            .map(|(_, key)| key.coords)
+            .filter(|coords| coords.cmpge(min).all() && coords.cmple(max).all())
+            .collect();
+        expected.sort_by_key(|coords| coords.to_array());
+        assert_eq!(region, expected);
+    }
+}
@@ -0,0 +1,140 @@
+use super::{voxel_to_chunk, VoxelWorldTransform};
+use bevy::{prelude::*, utils::HashMap};
+
+/// Marks an entity to be listed in the [`ChunkEntities`] under the chunk containing its
+/// `GlobalTransform` translation.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct ChunkTracked;
+
+/// Spatial hash of the [`ChunkTracked`] entities by the chunk they are in, so voxel events can
+/// find the gameplay entities they affect without scanning every entity, e.g. to damage the
+/// entities standing on a collapsing voxel.
+///
+/// Chunks do not have to be loaded for entities to be listed under them. Updated in
+/// `PostUpdate` after transform propagation, for entities whose `GlobalTransform` changed and
+/// for every entity when the [`VoxelWorldTransform`] changed.
+#[derive(Resource, Debug, Default)]
+pub struct ChunkEntities {
+    by_chunk: HashMap<IVec3, Vec<Entity>>,
+    chunks: HashMap<Entity, IVec3>,
+}
+
+impl ChunkEntities {
+    /// Tracked entities in the chunk at `coords`, in no particular order.
+    pub fn entities_in_chunk(&self, coords: IVec3) -> &[Entity] {
+        self.by_chunk.get(&coords).map_or(&[], Vec::as_slice)
+    }
+
+    /// Tracked entities in the chunk containing `voxel`.
+    pub fn entities_near_voxel(&self, voxel: IVec3) -> &[Entity] {
+        self.entities_in_chunk(voxel_to_chunk(voxel).0)
+    }
+
+    /// Tracked entities in the chunks with coordinates within `min..=max` on every axis.
+    pub fn entities_in_chunks(&self, min: IVec3, max: IVec3) -> impl Iterator<Item = Entity> + '_ {
+        self.by_chunk
+            .iter()
+            .filter(move |(coords, _)| coords.cmpge(min).all() && coords.cmple(max).all())
+            .flat_map(|(_, entities)| entities.iter().copied())
+    }
+
+    /// Chunk the entity was last seen in, `None` if it is not tracked.
+    pub fn chunk_of(&self, entity: Entity) -> Option<IVec3> {
+        self.chunks.get(&entity).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    fn insert(&mut self, entity: Entity, coords: IVec3) {
+        match self.chunks.insert(entity, coords) {
+            Some(previous) if previous == coords => return,
+            Some(previous) => self.remove_from_chunk(entity, previous),
+            None => {}
+        }
+        self.by_chunk.entry(coords).or_default().push(entity);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        if let Some(coords) = self.chunks.remove(&entity) {
+            self.remove_from_chunk(entity, coords);
+        }
+    }
+
+    fn remove_from_chunk(&mut self, entity: Entity, coords: IVec3) {
+        let Some(entities) = self.by_chunk.get_mut(&coords) else {
+            return;
+        };
+        if let Some(position) = entities.iter().position(|other| *other == entity) {
+            entities.swap_remove(position);
+        }
+        if entities.is_empty() {
+            self.by_chunk.remove(&coords);
+        }
+    }
+}
+
+pub(super) fn update_chunk_entities(
+    mut index: ResMut<ChunkEntities>,
+    world_transform: Res<VoxelWorldTransform>,
+    mut removed: RemovedComponents<ChunkTracked>,
+    all: Query<(Entity, &GlobalTransform), With<ChunkTracked>>,
+    changed: Query<
+        (Entity, &GlobalTransform),
+        (
+            With<ChunkTracked>,
+            Or<(Changed<GlobalTransform>, Added<ChunkTracked>)>,
+        ),
+    >,
+) {
+    for entity in removed.read() {
+        index.remove(entity);
+    }
+
+    let mut track = |entity: Entity, transform: &GlobalTransform| {
+        index.insert(
+            entity,
+            world_transform.world_to_chunk(transform.translation()),
+        );
+    };
+    if world_transform.is_changed() {
+        all.iter()
+            .for_each(|(entity, transform)| track(entity, transform));
+    } else {
+        changed
+            .iter()
+            .for_each(|(entity, transform)| track(entity, transform));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entities_move_between_chunks() {
+        let mut index = ChunkEntities::default();
+        let a = Entity::from_raw(1);
+        let b = Entity::from_raw(2);
+        index.insert(a, IVec3::ZERO);
+        index.insert(b, IVec3::ZERO);
+        assert_eq!(index.entities_in_chunk(IVec3::ZERO).len(), 2);
+        assert_eq!(index.entities_near_voxel(IVec3::new(5, 31, 0)).len(), 2);
+
+        index.insert(a, IVec3::X);
+        assert_eq!(index.entities_in_chunk(IVec3::ZERO), &[b]);
+        assert_eq!(index.entities_in_chunk(IVec3::X), &[a]);
+        assert_eq!(index.chunk_of(a), Some(IVec3::X));
+        assert_eq!(index.entities_in_chunks(IVec3::ZERO, IVec3::X).count(), 2);
+
+        index.remove(b);
+        assert!(index.entities_in_chunk(IVec3::ZERO).is_empty());
+        assert_eq!(index.chunk_of(b), None);
+        assert_eq!(index.len(), 1);
+    }
+}
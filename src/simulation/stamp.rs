@@ -0,0 +1,151 @@
+use super::AutomataState;
+use bevy::prelude::*;
+
+/// Pre-built voxel pattern written into the world with
+/// [`VoxelCommands::stamp`](super::VoxelCommands::stamp), such as a glider gun, a building or a
+/// tree.
+///
+/// Empty cells are left out when stamping, so the surroundings show through the gaps of the
+/// pattern.
+#[derive(Asset, TypePath, Debug, Clone, PartialEq)]
+pub struct Stamp {
+    size: IVec3,
+    cells: Vec<AutomataState>,
+    /// Cell placed on the stamped position, and the center of rotations.
+    pub anchor: IVec3,
+    /// Holds the stamped box in a [`StasisVolume`](super::StasisVolume) so the simulation
+    /// leaves the pattern as is.
+    pub frozen: bool,
+}
+
+impl Stamp {
+    /// Empty stamp of `size` cells anchored on its minimum corner.
+    pub fn new(size: IVec3) -> Self {
+        let size = size.max(IVec3::ZERO);
+        Self {
+            size,
+            cells: vec![AutomataState::EMPTY; (size.x * size.y * size.z) as usize],
+            anchor: IVec3::ZERO,
+            frozen: false,
+        }
+    }
+
+    /// Stamp of `size` cells filled by calling `cell` with every position.
+    pub fn from_fn(size: IVec3, cell: impl Fn(IVec3) -> AutomataState) -> Self {
+        let mut stamp = Self::new(size);
+        for (index, state) in stamp.cells.iter_mut().enumerate() {
+            *state = cell(position(size, index));
+        }
+        stamp
+    }
+
+    pub fn with_anchor(mut self, anchor: IVec3) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Sets [`frozen`](Self::frozen).
+    pub fn freeze(mut self) -> Self {
+        self.frozen = true;
+        self
+    }
+
+    #[inline]
+    pub fn size(&self) -> IVec3 {
+        self.size
+    }
+
+    pub fn get(&self, position: IVec3) -> Option<AutomataState> {
+        self.index(position).map(|index| self.cells[index])
+    }
+
+    /// Sets a cell, ignored outside the stamp.
+    pub fn set(&mut self, position: IVec3, state: AutomataState) {
+        if let Some(index) = self.index(position) {
+            self.cells[index] = state;
+        }
+    }
+
+    /// Voxels and states of the non-empty cells with the anchor placed on `voxel` and the
+    /// pattern turned by `rotation` around it. Positions are rounded to the voxel grid, so
+    /// rotations other than right angles leave gaps.
+    pub fn placed(
+        &self,
+        voxel: IVec3,
+        rotation: Quat,
+    ) -> impl Iterator<Item = (IVec3, AutomataState)> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, state)| **state != AutomataState::EMPTY)
+            .map(move |(index, state)| {
+                let offset = (position(self.size, index) - self.anchor).as_vec3();
+                (voxel + (rotation * offset).round().as_ivec3(), *state)
+            })
+    }
+
+    fn index(&self, position: IVec3) -> Option<usize> {
+        let inside = position.cmpge(IVec3::ZERO).all() && position.cmplt(self.size).all();
+        inside.then(|| {
+            (position.x * self.size.y * self.size.z + position.y * self.size.z + position.z)
+                as usize
+        })
+    }
+}
+
+#[inline]
+fn position(size: IVec3, index: usize) -> IVec3 {
+    let index = index as i32;
+    IVec3::new(
+        index / (size.y * size.z),
+        index / size.z % size.y,
+        index % size.z,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn stamps_rotate_around_their_anchor() {
+        let wall = AutomataState::new(1, 0);
+        let roof = AutomataState::new(2, 0);
+        let stamp = Stamp::from_fn(IVec3::new(3, 2, 1), |position| match position.y {
+            0 => wall,
+            _ if position.x == 1 => roof,
+            _ => AutomataState::EMPTY,
+        })
+        .with_anchor(IVec3::new(1, 0, 0));
+
+        let origin = IVec3::new(10, 5, -3);
+        let mut placed: Vec<_> = stamp.placed(origin, Quat::IDENTITY).collect();
+        placed.sort_by_key(|(voxel, _)| voxel.to_array());
+        assert_eq!(
+            placed,
+            vec![
+                (IVec3::new(9, 5, -3), wall),
+                (IVec3::new(10, 5, -3), wall),
+                (IVec3::new(10, 6, -3), roof),
+                (IVec3::new(11, 5, -3), wall),
+            ]
+        );
+
+        // A quarter turn around y swings the wall from the x axis onto the z axis.
+        let mut turned: Vec<_> = stamp
+            .placed(origin, Quat::from_rotation_y(FRAC_PI_2))
+            .map(|(voxel, _)| voxel)
+            .collect();
+        turned.sort_by_key(|voxel| voxel.to_array());
+        assert_eq!(
+            turned,
+            vec![
+                IVec3::new(10, 5, -4),
+                IVec3::new(10, 5, -3),
+                IVec3::new(10, 5, -2),
+                IVec3::new(10, 6, -3),
+            ]
+        );
+    }
+}
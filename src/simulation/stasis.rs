@@ -0,0 +1,246 @@
+use super::{
+    linear_index, voxel_to_chunk, ChunkCells, ChunkCellsNext, ChunkIndex, VoxelWorldTransform,
+    CHUNK_EDGE,
+};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+/// Box in which the simulation is paused, centered on the entity's `GlobalTransform`.
+///
+/// Voxels inside keep their state through every pass, and their neighbors see them as static
+/// matter. Useful for cutscenes, build previews or protecting spawn areas from spreading rules.
+/// Moving the entity moves the volume, with [`StasisEntered`]/[`StasisLeft`] sent as chunks
+/// start or stop overlapping it.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct StasisVolume {
+    /// Half size of the box in world units, along the axes of the voxel grid.
+    pub half_extents: Vec3,
+}
+
+/// Voxels covered by a [`StasisVolume`], `min` inclusive and `max` exclusive. Kept up to date
+/// by the engine.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StasisBounds {
+    pub min: IVec3,
+    pub max: IVec3,
+}
+
+impl StasisBounds {
+    #[inline]
+    pub fn contains(&self, voxel: IVec3) -> bool {
+        voxel.cmpge(self.min).all() && voxel.cmplt(self.max).all()
+    }
+
+    /// Coordinates of every chunk overlapping the bounds.
+    pub fn chunks(&self) -> impl Iterator<Item = IVec3> {
+        let (min, _) = voxel_to_chunk(self.min);
+        let (max, _) = voxel_to_chunk(self.max - IVec3::ONE);
+        (min.x..=max.x).flat_map(move |x| {
+            (min.y..=max.y).flat_map(move |y| (min.z..=max.z).map(move |z| IVec3::new(x, y, z)))
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.min.cmpge(self.max).any()
+    }
+}
+
+/// Sent when a chunk starts overlapping a stasis volume.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StasisEntered {
+    pub volume: Entity,
+    pub chunk: IVec3,
+}
+
+/// Sent when a chunk stops overlapping a stasis volume, including when the volume is removed.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StasisLeft {
+    pub volume: Entity,
+    pub chunk: IVec3,
+}
+
+pub(super) fn update_stasis_volumes(
+    mut commands: Commands,
+    mut covered: Local<HashMap<Entity, HashSet<IVec3>>>,
+    mut entered: EventWriter<StasisEntered>,
+    mut left: EventWriter<StasisLeft>,
+    mut removed: RemovedComponents<StasisVolume>,
+    world_transform: Res<VoxelWorldTransform>,
+    mut volumes: Query<(
+        Entity,
+        &StasisVolume,
+        &GlobalTransform,
+        Option<&mut StasisBounds>,
+    )>,
+) {
+    for volume in removed.read() {
+        for chunk in covered.remove(&volume).unwrap_or_default() {
+            left.send(StasisLeft { volume, chunk });
+        }
+        if let Some(mut entity) = commands.get_entity(volume) {
+            entity.remove::<StasisBounds>();
+        }
+    }
+
+    for (entity, volume, transform, bounds) in volumes.iter_mut() {
+        let center = world_transform.world_to_voxel_space(transform.translation());
+        let half = volume.half_extents / world_transform.voxel_size;
+        let target = StasisBounds {
+            min: (center - half).round().as_ivec3(),
+            max: (center + half).round().as_ivec3(),
+        };
+
+        match bounds {
+            Some(mut bounds) => {
+                if *bounds == target {
+                    continue;
+                }
+                *bounds = target;
+            }
+            None => {
+                commands.entity(entity).insert(target);
+            }
+        }
+
+        let chunks: HashSet<IVec3> = if target.is_empty() {
+            HashSet::new()
+        } else {
+            target.chunks().collect()
+        };
+        let previous = covered.entry(entity).or_default();
+        for &chunk in chunks.difference(previous) {
+            entered.send(StasisEntered {
+                volume: entity,
+                chunk,
+            });
+        }
+        for &chunk in previous.difference(&chunks) {
+            left.send(StasisLeft {
+                volume: entity,
+                chunk,
+            });
+        }
+        *previous = chunks;
+    }
+}
+
+/// Restores the pre-step state of every voxel inside a stasis volume, undoing whatever the
+/// passes wrote there.
+pub(super) fn apply_stasis(
+    index: Res<ChunkIndex>,
+    volumes: Query<&StasisBounds>,
+    mut chunks: Query<(&ChunkCells, &mut ChunkCellsNext)>,
+) {
+    for bounds in volumes.iter().filter(|bounds| !bounds.is_empty()) {
+        hold_region(&index, &mut chunks, bounds.min, bounds.max);
+    }
+}
+
+/// Overwrites the next cells between `min` and `max` with the current ones.
+pub(super) fn hold_region(
+    index: &ChunkIndex,
+    chunks: &mut Query<(&ChunkCells, &mut ChunkCellsNext)>,
+    min: IVec3,
+    max: IVec3,
+) {
+    let bounds = StasisBounds { min, max };
+    if bounds.is_empty() {
+        return;
+    }
+
+    for coords in bounds.chunks() {
+        let Some((cells, mut next)) = index
+            .entity(coords)
+            .and_then(|entity| chunks.get_mut(entity).ok())
+        else {
+            continue;
+        };
+
+        let origin = coords * CHUNK_EDGE;
+        let min = (min - origin).max(IVec3::ZERO);
+        let max = (max - origin).min(IVec3::splat(CHUNK_EDGE));
+        let next = next.as_mut_slice();
+        for x in min.x..max.x {
+            for y in min.y..max.y {
+                for z in min.z..max.z {
+                    let cell = linear_index(IVec3::new(x, y, z));
+                    next[cell] = cells.storage().get(cell);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AutomataState, CellularAutomataPlugin, ChunkBundle, SimulationControl};
+
+    #[test]
+    fn voxels_in_stasis_hold_their_state() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(CellularAutomataPlugin);
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        // Lone cells, which die on the next step unless held.
+        let held = IVec3::splat(4);
+        let free = IVec3::splat(20);
+        let alive = AutomataState::new(1, 0);
+        let chunk = app
+            .world
+            .spawn(ChunkBundle::from_generator(IVec3::ZERO, |local| {
+                match local == held || local == free {
+                    true => alive,
+                    false => AutomataState::EMPTY,
+                }
+            }))
+            .id();
+        let volume = app
+            .world
+            .spawn((
+                StasisVolume {
+                    half_extents: Vec3::splat(2.0),
+                },
+                GlobalTransform::from_translation(held.as_vec3() + 0.5),
+            ))
+            .id();
+        app.update();
+
+        let entered = app.world.resource::<Events<StasisEntered>>();
+        let entered: Vec<_> = entered.iter_current_update_events().copied().collect();
+        assert_eq!(
+            entered,
+            [StasisEntered {
+                volume,
+                chunk: IVec3::ZERO
+            }]
+        );
+        assert_eq!(
+            app.world.get::<StasisBounds>(volume),
+            Some(&StasisBounds {
+                min: IVec3::splat(3),
+                max: IVec3::splat(7),
+            })
+        );
+
+        app.world.resource_mut::<SimulationControl>().step_once();
+        app.update();
+        let cells = app.world.get::<ChunkCells>(chunk).unwrap();
+        assert_eq!(cells.get(held), alive);
+        assert_eq!(cells.get(free), AutomataState::EMPTY);
+
+        app.world.despawn(volume);
+        app.update();
+        let left = app.world.resource::<Events<StasisLeft>>();
+        let left: Vec<_> = left.iter_current_update_events().copied().collect();
+        assert_eq!(
+            left,
+            [StasisLeft {
+                volume,
+                chunk: IVec3::ZERO
+            }]
+        );
+    }
+}
@@ -0,0 +1,450 @@
+use super::{
+    AutomataState, ChunkCells, ChunkCellsNext, ChunkKey, ChunkSleeping, SimulationClock,
+    SimulationSet, CHUNK_EDGE,
+};
+use crate::EngineEvent;
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// Statistics of one simulation step, see [`SimulationStats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StepStatistics {
+    pub step: u64,
+    /// Live voxels after the step.
+    pub population: u64,
+    pub births: u64,
+    pub deaths: u64,
+    /// Live voxels of every material present after the step, by material.
+    pub materials: Vec<(u8, u64)>,
+    /// Smallest box holding every voxel the step changed, as inclusive voxel coordinates.
+    pub activity: Option<(IVec3, IVec3)>,
+}
+
+impl StepStatistics {
+    /// Header of the [`StatisticsFormat::Csv`] rows.
+    pub const CSV_HEADER: &'static str =
+        "step,population,births,deaths,min_x,min_y,min_z,max_x,max_y,max_z,materials";
+
+    /// The statistics as one CSV row without line break. Activity bounds are empty when nothing
+    /// changed, and materials are written as `material:count` pairs separated by `;`.
+    pub fn to_csv(&self) -> String {
+        let mut row = format!(
+            "{},{},{},{}",
+            self.step, self.population, self.births, self.deaths
+        );
+        match self.activity {
+            Some((min, max)) => {
+                let _ = write!(
+                    row,
+                    ",{},{},{},{},{},{}",
+                    min.x, min.y, min.z, max.x, max.y, max.z
+                );
+            }
+            None => row.push_str(",,,,,,"),
+        }
+        row.push(',');
+        for (i, (material, count)) in self.materials.iter().enumerate() {
+            let separator = if i == 0 { "" } else { ";" };
+            let _ = write!(row, "{separator}{material}:{count}");
+        }
+        row
+    }
+
+    /// The statistics as one JSON object without line break, materials keyed by their number.
+    pub fn to_json(&self) -> String {
+        let mut line = format!(
+            "{{\"step\":{},\"population\":{},\"births\":{},\"deaths\":{},\"activity\":",
+            self.step, self.population, self.births, self.deaths
+        );
+        match self.activity {
+            Some((min, max)) => {
+                let _ = write!(
+                    line,
+                    "{{\"min\":[{},{},{}],\"max\":[{},{},{}]}}",
+                    min.x, min.y, min.z, max.x, max.y, max.z
+                );
+            }
+            None => line.push_str("null"),
+        }
+        line.push_str(",\"materials\":{");
+        for (i, (material, count)) in self.materials.iter().enumerate() {
+            let separator = if i == 0 { "" } else { "," };
+            let _ = write!(line, "{separator}\"{material}\":{count}");
+        }
+        line.push_str("}}");
+        line
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum StatisticsFormat {
+    /// Comma separated values with a header row.
+    #[default]
+    Csv,
+    /// One JSON object per line.
+    JsonLines,
+}
+
+/// Streams [`StepStatistics`] to a file while the simulation runs, for analyzing long
+/// experiments outside of the engine.
+///
+/// Steps are gathered like the [`SimulationStats`], also while those are disabled, and one step
+/// out of [`every`](Self::every) is written. Rows are buffered and flushed when the export stops
+/// or is dropped. Write errors stop the export and are reported as an [`EngineEvent`].
+#[derive(Resource)]
+pub struct StatisticsExport {
+    /// Records one step out of this many.
+    pub every: u64,
+    output: Option<StatisticsOutput>,
+}
+
+struct StatisticsOutput {
+    path: PathBuf,
+    format: StatisticsFormat,
+    writer: BufWriter<File>,
+}
+
+impl Default for StatisticsExport {
+    fn default() -> Self {
+        Self {
+            every: 1,
+            output: None,
+        }
+    }
+}
+
+impl StatisticsExport {
+    /// Starts writing to `path`, replacing the file and any running export.
+    pub fn start(&mut self, path: impl AsRef<Path>, format: StatisticsFormat) -> io::Result<()> {
+        self.stop()?;
+        let path = path.as_ref().to_path_buf();
+        let mut writer = BufWriter::new(File::create(&path)?);
+        if format == StatisticsFormat::Csv {
+            writeln!(writer, "{}", StepStatistics::CSV_HEADER)?;
+        }
+        self.output = Some(StatisticsOutput {
+            path,
+            format,
+            writer,
+        });
+        Ok(())
+    }
+
+    /// Flushes and closes the file of the running export, if any.
+    pub fn stop(&mut self) -> io::Result<()> {
+        match self.output.take() {
+            Some(mut output) => output.writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    pub fn is_running(&self) -> bool {
+        self.output.is_some()
+    }
+
+    /// File written by the running export.
+    pub fn path(&self) -> Option<&Path> {
+        self.output.as_ref().map(|output| output.path.as_path())
+    }
+
+    fn record(&mut self, statistics: &StepStatistics) -> io::Result<()> {
+        let Some(output) = &mut self.output else {
+            return Ok(());
+        };
+        let line = match output.format {
+            StatisticsFormat::Csv => statistics.to_csv(),
+            StatisticsFormat::JsonLines => statistics.to_json(),
+        };
+        writeln!(output.writer, "{line}")
+    }
+}
+
+/// Live voxels, births, deaths and material counts of the latest steps.
+///
+/// Gathered every step by comparing the cells of every loaded chunk with the ones the step
+/// computed, right before they are applied. Also reported as the [`SimulationStats::POPULATION`],
+/// [`SimulationStats::BIRTHS`] and [`SimulationStats::DEATHS`] diagnostics after the steps of
+/// each frame. The scan goes over every loaded chunk, so it is disabled by default.
+#[derive(Resource, Debug, Clone)]
+pub struct SimulationStats {
+    pub enabled: bool,
+    /// Steps kept in the history.
+    pub capacity: usize,
+    history: VecDeque<StepStatistics>,
+}
+
+impl Default for SimulationStats {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            capacity: 120,
+            history: VecDeque::new(),
+        }
+    }
+}
+
+impl SimulationStats {
+    /// Live voxels after the latest step.
+    pub const POPULATION: DiagnosticId =
+        DiagnosticId::from_u128(0x3d58_92c1_7e0a_4b6f_a1d4_5c3e_90b2_6e01);
+    /// Voxels born during the latest step.
+    pub const BIRTHS: DiagnosticId =
+        DiagnosticId::from_u128(0x3d58_92c1_7e0a_4b6f_a1d4_5c3e_90b2_6e02);
+    /// Voxels that died during the latest step.
+    pub const DEATHS: DiagnosticId =
+        DiagnosticId::from_u128(0x3d58_92c1_7e0a_4b6f_a1d4_5c3e_90b2_6e03);
+
+    pub fn latest(&self) -> Option<&StepStatistics> {
+        self.history.back()
+    }
+
+    /// Statistics of the recent steps, oldest first.
+    pub fn history(&self) -> impl DoubleEndedIterator<Item = &StepStatistics> + '_ {
+        self.history.iter()
+    }
+
+    pub fn population(&self) -> u64 {
+        self.latest().map_or(0, |latest| latest.population)
+    }
+
+    pub fn births(&self) -> u64 {
+        self.latest().map_or(0, |latest| latest.births)
+    }
+
+    pub fn deaths(&self) -> u64 {
+        self.latest().map_or(0, |latest| latest.deaths)
+    }
+
+    /// Live voxels of `material` after the latest step.
+    pub fn material_count(&self, material: u8) -> u64 {
+        self.latest()
+            .and_then(|latest| {
+                latest
+                    .materials
+                    .iter()
+                    .find(|(other, _)| *other == material)
+            })
+            .map_or(0, |(_, count)| *count)
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+
+    fn push(&mut self, statistics: StepStatistics) {
+        while self.history.len() >= self.capacity.max(1) {
+            self.history.pop_front();
+        }
+        self.history.push_back(statistics);
+    }
+}
+
+pub(super) fn statistics_wanted(
+    stats: Res<SimulationStats>,
+    export: Res<StatisticsExport>,
+) -> bool {
+    stats.enabled || export.is_running()
+}
+
+/// Compares the cells of every chunk with the ones computed by the step, before they are applied.
+pub(super) fn gather_step_statistics(
+    mut stats: ResMut<SimulationStats>,
+    mut export: ResMut<StatisticsExport>,
+    mut events: EventWriter<EngineEvent>,
+    clock: Res<SimulationClock>,
+    chunks: Query<(&ChunkKey, &ChunkCells, &ChunkCellsNext, Has<ChunkSleeping>)>,
+) {
+    let mut counts = [0u64; 256];
+    let mut statistics = StepStatistics {
+        step: clock.step,
+        ..default()
+    };
+    for (key, cells, next, sleeping) in chunks.iter() {
+        let origin = key.coords * CHUNK_EDGE;
+        // Sleeping chunks keep their cells, their back buffer is stale.
+        if sleeping && !next.stepped {
+            let pairs = cells.storage().iter().map(|state| (state, state));
+            gather_chunk(&mut statistics, &mut counts, origin, pairs);
+        } else {
+            let pairs = cells.storage().iter().zip(next.as_slice().iter().copied());
+            gather_chunk(&mut statistics, &mut counts, origin, pairs);
+        }
+    }
+    statistics.materials = (0..=255u8)
+        .zip(counts)
+        .filter(|(_, count)| *count > 0)
+        .collect();
+
+    if export.is_running() && clock.step % export.every.max(1) == 0 {
+        if let Err(error) = export.record(&statistics) {
+            let path = export.path().map(Path::to_path_buf).unwrap_or_default();
+            let _ = export.stop();
+            EngineEvent::StatisticsWriteFailed {
+                path,
+                reason: error.to_string(),
+            }
+            .report(&mut events);
+        }
+    }
+    if stats.enabled {
+        stats.push(statistics);
+    }
+}
+
+pub(super) fn register_diagnostics(app: &mut App) {
+    app.register_diagnostic(Diagnostic::new(
+        SimulationStats::POPULATION,
+        "simulation_population",
+        20,
+    ))
+    .register_diagnostic(Diagnostic::new(
+        SimulationStats::BIRTHS,
+        "simulation_births",
+        20,
+    ))
+    .register_diagnostic(Diagnostic::new(
+        SimulationStats::DEATHS,
+        "simulation_deaths",
+        20,
+    ))
+    .add_systems(
+        PostUpdate,
+        report_simulation_stats.after(SimulationSet::Run),
+    );
+}
+
+fn report_simulation_stats(stats: Res<SimulationStats>, mut diagnostics: Diagnostics) {
+    let Some(latest) = stats.latest().filter(|_| stats.enabled) else {
+        return;
+    };
+    diagnostics.add_measurement(SimulationStats::POPULATION, || latest.population as f64);
+    diagnostics.add_measurement(SimulationStats::BIRTHS, || latest.births as f64);
+    diagnostics.add_measurement(SimulationStats::DEATHS, || latest.deaths as f64);
+}
+
+fn gather_chunk(
+    statistics: &mut StepStatistics,
+    counts: &mut [u64; 256],
+    origin: IVec3,
+    pairs: impl Iterator<Item = (AutomataState, AutomataState)>,
+) {
+    let edge = CHUNK_EDGE as usize;
+    for (index, (prev, next)) in pairs.enumerate() {
+        if next.is_alive() {
+            statistics.population += 1;
+            counts[next.material as usize] += 1;
+        }
+        if prev == next {
+            continue;
+        }
+        if next.is_alive() && !prev.is_alive() {
+            statistics.births += 1;
+        } else if prev.is_alive() && !next.is_alive() {
+            statistics.deaths += 1;
+        }
+        let voxel = origin
+            + IVec3::new(
+                (index / (edge * edge)) as i32,
+                ((index / edge) % edge) as i32,
+                (index % edge) as i32,
+            );
+        statistics.activity = Some(match statistics.activity {
+            Some((min, max)) => (min.min(voxel), max.max(voxel)),
+            None => (voxel, voxel),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CellularAutomataPlugin, ChunkBundle, SimulationControl, CHUNK_VOLUME};
+    use bevy::diagnostic::DiagnosticsStore;
+
+    #[test]
+    fn latest_steps_are_kept_and_reported_as_diagnostics() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(CellularAutomataPlugin)
+            .insert_resource(SimulationStats {
+                enabled: true,
+                capacity: 3,
+                ..default()
+            })
+            // Every cell survives and none is born.
+            .insert_resource(crate::AutomataRule {
+                birth: Vec::new(),
+                survive: (0..=26).collect(),
+                ..default()
+            });
+        app.world
+            .spawn(ChunkBundle::from_generator(IVec3::ZERO, |local| {
+                match local.x == 0 {
+                    true => AutomataState::new(4, 0),
+                    false => AutomataState::EMPTY,
+                }
+            }));
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        app.update();
+        for _ in 0..5 {
+            app.world.resource_mut::<SimulationControl>().step_once();
+            app.update();
+        }
+
+        let stats = app.world.resource::<SimulationStats>();
+        let steps: Vec<_> = stats.history().map(|statistics| statistics.step).collect();
+        assert_eq!(steps.len(), 3);
+        assert!(steps.windows(2).all(|pair| pair[0] + 1 == pair[1]));
+        let population = (CHUNK_EDGE * CHUNK_EDGE) as u64;
+        assert_eq!(stats.population(), population);
+        assert_eq!(stats.material_count(4), population);
+        assert_eq!((stats.births(), stats.deaths()), (0, 0));
+
+        let diagnostics = app.world.resource::<DiagnosticsStore>();
+        let measured = diagnostics
+            .get(SimulationStats::POPULATION)
+            .and_then(|diagnostic| diagnostic.value());
+        assert_eq!(measured, Some(population as f64));
+    }
+
+    #[test]
+    fn statistics_count_births_deaths_and_activity() {
+        let mut prev = vec![AutomataState::EMPTY; CHUNK_VOLUME];
+        let mut next = prev.clone();
+        prev[0] = AutomataState::new(1, 0);
+        next[1] = AutomataState::new(2, 0);
+        next[CHUNK_VOLUME - 1] = AutomataState::new(2, 0);
+
+        let mut statistics = StepStatistics {
+            step: 7,
+            ..default()
+        };
+        let mut counts = [0; 256];
+        let pairs = prev.iter().copied().zip(next.iter().copied());
+        gather_chunk(&mut statistics, &mut counts, IVec3::X * CHUNK_EDGE, pairs);
+        statistics.materials = vec![(2, counts[2])];
+
+        assert_eq!(statistics.population, 2);
+        assert_eq!((statistics.births, statistics.deaths), (2, 1));
+        assert_eq!(
+            statistics.activity,
+            Some((IVec3::new(32, 0, 0), IVec3::new(63, 31, 31)))
+        );
+        assert_eq!(statistics.to_csv(), "7,2,2,1,32,0,0,63,31,31,2:2");
+        assert_eq!(
+            statistics.to_json(),
+            "{\"step\":7,\"population\":2,\"births\":2,\"deaths\":1,\
+             \"activity\":{\"min\":[32,0,0],\"max\":[63,31,31]},\"materials\":{\"2\":2}}"
+        );
+    }
+}
@@ -0,0 +1,462 @@
+use super::{
+    border::BorderCache, linear_index, step_chunk, AutomataRuleSet, AutomataState, BufferPool,
+    CellContext, ChunkCells, ChunkSnapshots, GranularRule, MaterialTracker, CHUNK_EDGE,
+};
+use bevy::{prelude::*, utils::HashSet};
+use std::{ops::Deref, sync::Arc};
+
+/// Update scheme used to advance the automata each step.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AutomataStepper {
+    /// Every cell reads the previous step and updates at once.
+    #[default]
+    Synchronous,
+    /// Cells with an even coordinate sum update first, then odd cells update against that
+    /// result. Movement style rules use this to avoid moving the same material twice in a step.
+    Checkerboard,
+    /// Partitions the world into 2×2×2 blocks, shifting the partition by one cell every other
+    /// step, and transforms each block as a whole. Ignores the active rule.
+    Margolus(MargolusRule),
+    /// Falling sand on the Margolus block partition, see [`GranularRule`]. Ignores the active
+    /// rule.
+    Granular(GranularRule),
+}
+
+/// Block transformation applied by the [`AutomataStepper::Margolus`] stepper.
+///
+/// Both rules permute the cells of a block, so they conserve material and are reversible.
+/// Blocks with a cell in an unloaded chunk, or in a chunk not stepped this step, are left
+/// untouched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MargolusRule {
+    /// Rotates every block a quarter turn around the Y axis.
+    #[default]
+    Rotate,
+    /// Particles cross to the opposite corner of their block. Two particles meeting head-on on
+    /// a diagonal scatter onto the next diagonal instead.
+    LatticeGas,
+}
+
+impl MargolusRule {
+    /// Applies the rule to a block indexed by `x | y << 1 | z << 2`.
+    pub fn apply(&self, block: [AutomataState; 8]) -> [AutomataState; 8] {
+        let mut output = [AutomataState::EMPTY; 8];
+        match self {
+            MargolusRule::Rotate => {
+                for (i, &state) in block.iter().enumerate() {
+                    let (x, y, z) = (i & 1, (i >> 1) & 1, (i >> 2) & 1);
+                    output[(1 - z) | (y << 1) | (x << 2)] = state;
+                }
+            }
+            MargolusRule::LatticeGas => {
+                let mut alive = 0;
+                let mut first = 0;
+                let mut last = 0;
+                for (i, state) in block.iter().enumerate() {
+                    if state.is_alive() {
+                        if alive == 0 {
+                            first = i;
+                        }
+                        last = i;
+                        alive += 1;
+                    }
+                }
+
+                if alive == 2 && first ^ last == 7 {
+                    let diagonal = first;
+                    let next = (diagonal + 1) % 4;
+                    output[next] = block[diagonal];
+                    output[next ^ 7] = block[diagonal ^ 7];
+                } else {
+                    for (i, &state) in block.iter().enumerate() {
+                        output[i ^ 7] = state;
+                    }
+                }
+            }
+        }
+        output
+    }
+}
+
+/// Evaluates the block rule cell by cell, so it can also be installed as a
+/// [`BoxedRule`](super::BoxedRule). With [`AutomataStepper::Synchronous`] it matches
+/// [`AutomataStepper::Margolus`].
+impl AutomataRuleSet for MargolusRule {
+    fn next_state(&self, ctx: &CellContext) -> AutomataState {
+        block_cell(ctx, |block| self.apply(block))
+    }
+
+    fn name(&self) -> String {
+        format!("Margolus {:?}", self)
+    }
+}
+
+/// Next state of the cell in `ctx` under a block rule on the Margolus partition of its step.
+pub(super) fn block_cell(
+    ctx: &CellContext,
+    apply: impl Fn([AutomataState; 8]) -> [AutomataState; 8],
+) -> AutomataState {
+    let offset = (ctx.step & 1) as i32;
+    let corner = IVec3::new(
+        (ctx.voxel.x - offset).rem_euclid(2),
+        (ctx.voxel.y - offset).rem_euclid(2),
+        (ctx.voxel.z - offset).rem_euclid(2),
+    );
+
+    let mut block = [AutomataState::EMPTY; 8];
+    for (i, cell) in block.iter_mut().enumerate() {
+        let i = i as i32;
+        match ctx.neighbor(IVec3::new(i & 1, (i >> 1) & 1, (i >> 2) & 1) - corner) {
+            Some(state) => *cell = state,
+            None => return ctx.current,
+        }
+    }
+
+    let index = (corner.x | (corner.y << 1) | (corner.z << 2)) as usize;
+    apply(block)[index]
+}
+
+/// Cells of a single chunk fed into a step.
+pub(super) struct StepSource<'a> {
+    pub entity: Entity,
+    pub coords: IVec3,
+    pub cells: SourceCells<'a>,
+}
+
+impl<'a> StepSource<'a> {
+    /// Source reading the snapshot of the chunk, or its current cells when it has none yet
+    /// (chunk added mid-frame). Cells not stored densely are unpacked into a buffer of `pool`.
+    pub fn new(
+        entity: Entity,
+        coords: IVec3,
+        snapshots: &'a ChunkSnapshots,
+        cells: &'a ChunkCells,
+        pool: &BufferPool,
+    ) -> Self {
+        let cells = match snapshots.get(coords) {
+            Some(snapshot) => SourceCells::Borrowed(snapshot),
+            None => match cells.storage().as_dense() {
+                Some(data) => SourceCells::Borrowed(data),
+                None => SourceCells::Pooled(
+                    pool.acquire_shared(|buffer| cells.storage().write_to(buffer)),
+                ),
+            },
+        };
+        Self {
+            entity,
+            coords,
+            cells,
+        }
+    }
+}
+
+/// Cells of a [`StepSource`], borrowed or unpacked into a pooled buffer.
+pub(super) enum SourceCells<'a> {
+    Borrowed(&'a [AutomataState]),
+    Pooled(Arc<[AutomataState]>),
+}
+
+impl Deref for SourceCells<'_> {
+    type Target = [AutomataState];
+
+    fn deref(&self) -> &[AutomataState] {
+        match self {
+            SourceCells::Borrowed(cells) => cells,
+            SourceCells::Pooled(cells) => cells,
+        }
+    }
+}
+
+/// Returns the buffers unpacked for `sources` to the pool.
+pub(super) fn release_sources(sources: Vec<StepSource>, pool: &BufferPool) {
+    for source in sources {
+        if let SourceCells::Pooled(buffer) = source.cells {
+            pool.release_shared(buffer);
+        }
+    }
+}
+
+impl AutomataStepper {
+    /// Advances every source by one step, returning the next cells of each chunk in buffers
+    /// taken from `pool`.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn step(
+        &self,
+        sources: &[StepSource],
+        snapshots: &ChunkSnapshots,
+        rule: &dyn AutomataRuleSet,
+        tracker: &MaterialTracker,
+        pool: &BufferPool,
+        step: u64,
+        parallel: bool,
+    ) -> Vec<(Entity, Box<[AutomataState]>)> {
+        match self {
+            AutomataStepper::Synchronous => map_sources(sources, parallel, |source| {
+                let mut buffer = pool.acquire(&source.cells);
+                step_chunk(
+                    &source.cells,
+                    source.coords,
+                    snapshots,
+                    rule,
+                    tracker,
+                    &mut buffer,
+                    None,
+                    step,
+                );
+                (source.entity, buffer)
+            }),
+            AutomataStepper::Checkerboard => {
+                // Even phase reads the previous step.
+                let mut intermediate = ChunkSnapshots {
+                    boundary: snapshots.boundary,
+                    ..default()
+                };
+                let even = map_sources(sources, parallel, |source| {
+                    let mut buffer = pool.acquire(&source.cells);
+                    step_chunk(
+                        &source.cells,
+                        source.coords,
+                        snapshots,
+                        rule,
+                        tracker,
+                        &mut buffer,
+                        Some(0),
+                        step,
+                    );
+                    let snapshot = Arc::from(&*buffer);
+                    pool.release(buffer);
+                    (source.coords, snapshot)
+                });
+                intermediate.rebuild(even.into_iter());
+
+                // Odd phase reads the cells written by the even phase, across chunk borders.
+                map_sources(sources, parallel, |source| {
+                    let current = intermediate.get(source.coords)?;
+                    let mut buffer = pool.acquire(current);
+                    step_chunk(
+                        current,
+                        source.coords,
+                        &intermediate,
+                        rule,
+                        tracker,
+                        &mut buffer,
+                        Some(1),
+                        step,
+                    );
+                    Some((source.entity, buffer))
+                })
+                .into_iter()
+                .flatten()
+                .collect()
+            }
+            AutomataStepper::Margolus(block_rule) => {
+                let offset = (step & 1) as i32;
+                let stepping = stepping_chunks(sources);
+                map_sources(sources, parallel, |source| {
+                    let mut buffer = pool.acquire(&source.cells);
+                    let apply = |block| block_rule.apply(block);
+                    let chunk = BlockChunk::new(source.coords, snapshots, &stepping);
+                    step_blocks(chunk, apply, offset, &mut buffer);
+                    (source.entity, buffer)
+                })
+            }
+            AutomataStepper::Granular(granular) => {
+                let offset = (step & 1) as i32;
+                let stepping = stepping_chunks(sources);
+                map_sources(sources, parallel, |source| {
+                    let mut buffer = pool.acquire(&source.cells);
+                    let apply = |block| granular.apply(block, step);
+                    let chunk = BlockChunk::new(source.coords, snapshots, &stepping);
+                    step_blocks(chunk, apply, offset, &mut buffer);
+                    (source.entity, buffer)
+                })
+            }
+        }
+    }
+}
+
+/// Steps every source, spread across the rayon thread pool when `parallel` is set and the
+/// `parallel` feature is on.
+///
+/// Chunks only read the immutable snapshots, so they can be stepped in any order.
+#[cfg(feature = "parallel")]
+fn map_sources<'s, T, F>(sources: &[StepSource<'s>], parallel: bool, step: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(&StepSource<'s>) -> T + Sync + Send,
+{
+    use rayon::prelude::*;
+
+    if parallel {
+        sources.par_iter().map(step).collect()
+    } else {
+        sources.iter().map(step).collect()
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+fn map_sources<'s, T, F>(sources: &[StepSource<'s>], _parallel: bool, step: F) -> Vec<T>
+where
+    F: Fn(&StepSource<'s>) -> T,
+{
+    sources.iter().map(step).collect()
+}
+
+fn stepping_chunks(sources: &[StepSource]) -> HashSet<IVec3> {
+    sources.iter().map(|source| source.coords).collect()
+}
+
+/// Chunk stepped by a block rule, with the chunks stepped alongside it.
+struct BlockChunk<'a> {
+    coords: IVec3,
+    snapshots: &'a ChunkSnapshots,
+    /// Chunks stepped this step. A block straddling a chunk that is not in here would only be
+    /// moved on one side of the border, creating or destroying material.
+    stepping: &'a HashSet<IVec3>,
+}
+
+impl<'a> BlockChunk<'a> {
+    fn new(coords: IVec3, snapshots: &'a ChunkSnapshots, stepping: &'a HashSet<IVec3>) -> Self {
+        Self {
+            coords,
+            snapshots,
+            stepping,
+        }
+    }
+
+    #[inline]
+    fn steps(&self, position: IVec3) -> bool {
+        let chunk = position.div_euclid(IVec3::splat(CHUNK_EDGE));
+        chunk == IVec3::ZERO || self.stepping.contains(&(self.coords + chunk))
+    }
+}
+
+/// Steps a chunk with a block rule on the Margolus partition shifted by `offset`.
+fn step_blocks(
+    target: BlockChunk,
+    apply: impl Fn([AutomataState; 8]) -> [AutomataState; 8],
+    offset: i32,
+    output: &mut [AutomataState],
+) {
+    let Some(chunk) = target.snapshots.get(target.coords) else {
+        return;
+    };
+    let border = BorderCache::new(target.snapshots, target.coords);
+    for x in 0..CHUNK_EDGE {
+        for y in 0..CHUNK_EDGE {
+            for z in 0..CHUNK_EDGE {
+                let local = IVec3::new(x, y, z);
+                let corner = IVec3::new(
+                    (x - offset).rem_euclid(2),
+                    (y - offset).rem_euclid(2),
+                    (z - offset).rem_euclid(2),
+                );
+                let origin = local - corner;
+
+                let mut block = [AutomataState::EMPTY; 8];
+                let mut complete = true;
+                for (i, cell) in block.iter_mut().enumerate() {
+                    let i = i as i32;
+                    let position = origin + IVec3::new(i & 1, (i >> 1) & 1, (i >> 2) & 1);
+                    match border.sample(chunk, position) {
+                        Some(state) if target.steps(position) => *cell = state,
+                        _ => {
+                            complete = false;
+                            break;
+                        }
+                    }
+                }
+
+                if complete {
+                    let index = (corner.x | (corner.y << 1) | (corner.z << 2)) as usize;
+                    output[linear_index(local)] = apply(block)[index];
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkerboard_odd_cells_see_the_even_phase() {
+        let center = IVec3::splat(4);
+        let mut cells = ChunkCells::filled(AutomataState::EMPTY);
+        cells.set(center, AutomataState::new(1, 0));
+        let mut snapshots = ChunkSnapshots::default();
+        snapshots.rebuild(std::iter::once((IVec3::ZERO, Arc::from(cells.as_slice()))));
+        let rule = crate::AutomataRule {
+            birth: vec![1],
+            survive: vec![],
+            ..default()
+        };
+        let pool = BufferPool::default();
+        let sources = [StepSource::new(
+            Entity::PLACEHOLDER,
+            IVec3::ZERO,
+            &snapshots,
+            &cells,
+            &pool,
+        )];
+        let step = |stepper: AutomataStepper| {
+            let tracker = rule.tracker();
+            let next = stepper.step(&sources, &snapshots, &rule, &tracker, &pool, 0, false);
+            let alive = |offset: IVec3| next[0].1[linear_index(center + offset)].is_alive();
+            (
+                alive(IVec3::ZERO),
+                alive(IVec3::X),
+                alive(IVec3::new(1, 1, 0)),
+            )
+        };
+
+        // Synchronously every neighbor sees the lone center cell, in a checkerboard the odd
+        // face neighbor sees the edge neighbors born in the even phase instead.
+        assert_eq!(step(AutomataStepper::Synchronous), (false, true, true));
+        assert_eq!(step(AutomataStepper::Checkerboard), (false, false, true));
+    }
+
+    #[test]
+    fn margolus_rules_conserve_material() {
+        let mut block = [AutomataState::EMPTY; 8];
+        block[0] = AutomataState::new(1, 0);
+        block[7] = AutomataState::new(2, 0);
+        block[3] = AutomataState::new(3, 0);
+
+        for rule in [MargolusRule::Rotate, MargolusRule::LatticeGas] {
+            let mut before: Vec<_> = block.iter().map(|state| state.material).collect();
+            let mut after: Vec<_> = rule.apply(block).iter().map(|s| s.material).collect();
+            before.sort_unstable();
+            after.sort_unstable();
+            assert_eq!(before, after);
+        }
+
+        let mut pair = [AutomataState::EMPTY; 8];
+        pair[1] = AutomataState::new(1, 0);
+        pair[6] = AutomataState::new(1, 0);
+        let scattered = MargolusRule::LatticeGas.apply(pair);
+        assert!(scattered[2].is_alive() && scattered[5].is_alive());
+    }
+
+    #[test]
+    fn unpacked_sources_reuse_pooled_buffers() {
+        let pool = BufferPool::default();
+        let snapshots = ChunkSnapshots::default();
+        let cells = ChunkCells::filled(AutomataState::new(1, 0));
+        let source = |pool: &BufferPool| {
+            StepSource::new(Entity::PLACEHOLDER, IVec3::ZERO, &snapshots, &cells, pool)
+        };
+
+        let first = source(&pool);
+        assert!(first
+            .cells
+            .iter()
+            .all(|cell| *cell == AutomataState::new(1, 0)));
+        release_sources(vec![first], &pool);
+        release_sources(vec![source(&pool)], &pool);
+
+        let stats = pool.stats();
+        assert_eq!((stats.allocated, stats.reused, stats.pooled), (1, 1, 1));
+    }
+}
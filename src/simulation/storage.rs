@@ -0,0 +1,326 @@
+use super::{AutomataState, CHUNK_VOLUME};
+use std::mem;
+
+/// Largest number of distinct states kept in a [`ChunkStorage::Palette`] before the chunk falls
+/// back to [`ChunkStorage::Dense`].
+pub const MAX_PALETTE_LEN: usize = 16;
+
+/// Cells of a chunk, stored as compactly as their contents allow.
+///
+/// Writing a state the current representation cannot hold promotes the storage, from uniform to
+/// palette to dense. [`Self::compact`] demotes it again, the simulation does so whenever it
+/// writes a step. Indices are in chunk order.
+#[derive(Debug, Clone)]
+pub enum ChunkStorage {
+    /// Every cell has the same state.
+    Uniform(AutomataState),
+    /// A few distinct states, each cell holding a small index into the palette.
+    Palette(PaletteCells),
+    /// One state per cell.
+    Dense(Box<[AutomataState]>),
+}
+
+impl ChunkStorage {
+    /// Builds the most compact storage for `CHUNK_VOLUME` cells in chunk order.
+    pub fn from_slice(data: &[AutomataState]) -> Self {
+        if exceeds_palette(data) {
+            ChunkStorage::Dense(data.into())
+        } else {
+            Self::collect(data.iter().copied())
+        }
+    }
+
+    /// Builds the storage from `CHUNK_VOLUME` cells in chunk order, without a dense copy unless
+    /// the cells need it.
+    pub(super) fn collect(cells: impl IntoIterator<Item = AutomataState>) -> Self {
+        let mut cells = cells.into_iter();
+        let mut storage = ChunkStorage::Uniform(cells.next().unwrap_or_default());
+        for (index, state) in cells.enumerate().take(CHUNK_VOLUME - 1) {
+            storage.set(index + 1, state);
+        }
+        storage
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> AutomataState {
+        match self {
+            ChunkStorage::Uniform(state) => *state,
+            ChunkStorage::Palette(palette) => palette.get(index),
+            ChunkStorage::Dense(data) => data[index],
+        }
+    }
+
+    #[inline]
+    pub fn set(&mut self, index: usize, state: AutomataState) {
+        match self {
+            ChunkStorage::Uniform(current) if *current == state => {}
+            ChunkStorage::Uniform(current) => {
+                let mut palette = PaletteCells::filled(*current);
+                palette.set(index, state);
+                *self = ChunkStorage::Palette(palette);
+            }
+            ChunkStorage::Palette(palette) => {
+                if !palette.set(index, state) {
+                    let mut data = self.to_vec();
+                    data[index] = state;
+                    *self = ChunkStorage::Dense(data.into_boxed_slice());
+                }
+            }
+            ChunkStorage::Dense(data) => data[index] = state,
+        }
+    }
+
+    /// State shared by every cell of a uniform chunk.
+    #[inline]
+    pub fn uniform(&self) -> Option<AutomataState> {
+        match self {
+            ChunkStorage::Uniform(state) => Some(*state),
+            _ => None,
+        }
+    }
+
+    /// Cells of a dense chunk.
+    #[inline]
+    pub fn as_dense(&self) -> Option<&[AutomataState]> {
+        match self {
+            ChunkStorage::Dense(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Whether any cell is made of `material`. Palettes may still list overwritten states, so
+    /// the answer can be a false positive for them.
+    pub fn contains_material(&self, material: u8) -> bool {
+        match self {
+            ChunkStorage::Uniform(state) => state.material == material,
+            ChunkStorage::Palette(palette) => palette
+                .palette
+                .iter()
+                .any(|state| state.material == material),
+            ChunkStorage::Dense(data) => data.iter().any(|state| state.material == material),
+        }
+    }
+
+    /// Cells in chunk order.
+    pub fn iter(&self) -> impl Iterator<Item = AutomataState> + '_ {
+        (0..CHUNK_VOLUME).map(|index| self.get(index))
+    }
+
+    pub fn to_vec(&self) -> Vec<AutomataState> {
+        match self {
+            ChunkStorage::Dense(data) => data.to_vec(),
+            _ => self.iter().collect(),
+        }
+    }
+
+    /// Writes every cell to `out`, which must hold `CHUNK_VOLUME` cells.
+    pub fn write_to(&self, out: &mut [AutomataState]) {
+        match self {
+            ChunkStorage::Uniform(state) => out.fill(*state),
+            ChunkStorage::Palette(palette) => {
+                for (index, cell) in out.iter_mut().enumerate() {
+                    *cell = palette.get(index);
+                }
+            }
+            ChunkStorage::Dense(data) => out.copy_from_slice(data),
+        }
+    }
+
+    /// Replaces every cell, reusing the dense buffer when the new cells still need one.
+    pub fn assign(&mut self, data: &[AutomataState]) {
+        match self {
+            ChunkStorage::Dense(dense) if exceeds_palette(data) => dense.copy_from_slice(data),
+            _ => *self = Self::from_slice(data),
+        }
+    }
+
+    /// Replaces every cell with the ones in `back`. Dense cells are swapped in instead of copied,
+    /// `back` then holding the previous cells, or a buffer of undefined cells when the storage
+    /// was not dense.
+    pub fn swap_in(&mut self, back: &mut Box<[AutomataState]>) {
+        if !exceeds_palette(back) {
+            *self = Self::collect(back.iter().copied());
+            return;
+        }
+        match self {
+            ChunkStorage::Dense(front) => mem::swap(front, back),
+            _ => {
+                let fresh = vec![AutomataState::EMPTY; CHUNK_VOLUME].into_boxed_slice();
+                *self = ChunkStorage::Dense(mem::replace(back, fresh));
+            }
+        }
+    }
+
+    /// Switches to the most compact representation of the current cells.
+    pub fn compact(&mut self) {
+        match self {
+            ChunkStorage::Uniform(_) => {}
+            ChunkStorage::Palette(_) => *self = Self::collect(self.iter()),
+            ChunkStorage::Dense(data) => {
+                if !exceeds_palette(data) {
+                    *self = Self::collect(data.iter().copied());
+                }
+            }
+        }
+    }
+
+    /// Bytes allocated for the cells.
+    pub fn heap_size(&self) -> usize {
+        match self {
+            ChunkStorage::Uniform(_) => 0,
+            ChunkStorage::Palette(palette) => {
+                palette.palette.capacity() * mem::size_of::<AutomataState>()
+                    + palette.words.len() * mem::size_of::<u64>()
+            }
+            ChunkStorage::Dense(data) => data.len() * mem::size_of::<AutomataState>(),
+        }
+    }
+}
+
+impl Default for ChunkStorage {
+    fn default() -> Self {
+        ChunkStorage::Uniform(AutomataState::EMPTY)
+    }
+}
+
+/// Cells indexing up to [`MAX_PALETTE_LEN`] states with 1, 2 or 4 bits each.
+#[derive(Debug, Clone)]
+pub struct PaletteCells {
+    palette: Vec<AutomataState>,
+    bits: u32,
+    words: Box<[u64]>,
+}
+
+impl PaletteCells {
+    fn filled(state: AutomataState) -> Self {
+        Self::with_bits(vec![state], 1)
+    }
+
+    fn with_bits(palette: Vec<AutomataState>, bits: u32) -> Self {
+        Self {
+            palette,
+            bits,
+            words: vec![0; CHUNK_VOLUME * bits as usize / 64].into_boxed_slice(),
+        }
+    }
+
+    /// States referenced by the cells. May hold states that are no longer used by any cell.
+    pub fn palette(&self) -> &[AutomataState] {
+        &self.palette
+    }
+
+    /// Bits used per cell.
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> AutomataState {
+        self.palette[self.slot(index)]
+    }
+
+    /// Returns `false` without writing when the palette is full.
+    fn set(&mut self, index: usize, state: AutomataState) -> bool {
+        let slot = match self.palette.iter().position(|entry| *entry == state) {
+            Some(slot) => slot,
+            None if self.palette.len() == MAX_PALETTE_LEN => return false,
+            None => {
+                self.palette.push(state);
+                let bits = bits_for(self.palette.len());
+                if bits > self.bits {
+                    self.repack(bits);
+                }
+                self.palette.len() - 1
+            }
+        };
+        self.set_slot(index, slot);
+        true
+    }
+
+    #[inline]
+    fn slot(&self, index: usize) -> usize {
+        let bit = index * self.bits as usize;
+        let mask = (1 << self.bits) - 1;
+        ((self.words[bit / 64] >> (bit % 64)) & mask) as usize
+    }
+
+    #[inline]
+    fn set_slot(&mut self, index: usize, slot: usize) {
+        let bit = index * self.bits as usize;
+        let mask = ((1 << self.bits) - 1) << (bit % 64);
+        let word = &mut self.words[bit / 64];
+        *word = (*word & !mask) | ((slot as u64) << (bit % 64));
+    }
+
+    fn repack(&mut self, bits: u32) {
+        let mut wider = Self::with_bits(mem::take(&mut self.palette), bits);
+        for index in 0..CHUNK_VOLUME {
+            wider.set_slot(index, self.slot(index));
+        }
+        *self = wider;
+    }
+}
+
+fn bits_for(palette_len: usize) -> u32 {
+    match palette_len {
+        0..=2 => 1,
+        3..=4 => 2,
+        _ => 4,
+    }
+}
+
+fn exceeds_palette(data: &[AutomataState]) -> bool {
+    let mut seen = Vec::with_capacity(MAX_PALETTE_LEN);
+    for state in data {
+        if !seen.contains(state) {
+            if seen.len() == MAX_PALETTE_LEN {
+                return true;
+            }
+            seen.push(*state);
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn storage_promotes_and_compacts() {
+        let mut storage = ChunkStorage::default();
+        assert_eq!(storage.heap_size(), 0);
+
+        storage.set(5, AutomataState::new(1, 0));
+        assert!(matches!(storage, ChunkStorage::Palette(_)));
+        for material in 2..MAX_PALETTE_LEN as u8 {
+            storage.set(material as usize * 100, AutomataState::new(material, 0));
+        }
+        assert!(matches!(storage, ChunkStorage::Palette(ref cells) if cells.bits() == 4));
+        assert_eq!(storage.get(5), AutomataState::new(1, 0));
+        assert_eq!(storage.get(300), AutomataState::new(3, 0));
+        assert_eq!(storage.get(6), AutomataState::EMPTY);
+
+        storage.set(7, AutomataState::new(0, 1));
+        assert!(storage.as_dense().is_some());
+        assert_eq!(storage.get(300), AutomataState::new(3, 0));
+
+        let mut data = storage.to_vec();
+        data.fill(AutomataState::new(4, 0));
+        storage.assign(&data);
+        assert_eq!(storage.uniform(), Some(AutomataState::new(4, 0)));
+
+        // Dense cells trade buffers with the back buffer.
+        let mut back: Box<[AutomataState]> = (0..CHUNK_VOLUME)
+            .map(|index| AutomataState::new((index % 32) as u8, 0))
+            .collect();
+        storage.swap_in(&mut back);
+        assert_eq!(storage.get(33), AutomataState::new(1, 0));
+        let mut next = storage.to_vec().into_boxed_slice();
+        next[0] = AutomataState::new(9, 0);
+        storage.swap_in(&mut next);
+        assert_eq!(storage.get(0), AutomataState::new(9, 0));
+        assert_eq!(next[0], AutomataState::EMPTY);
+        assert_eq!(next[33], AutomataState::new(1, 0));
+    }
+}
@@ -0,0 +1,389 @@
+use super::{edit::apply_voxel_edits, voxel_to_chunk, AutomataState, ChunkCells, ChunkIndex};
+use bevy::{prelude::*, utils::HashMap};
+
+/// Large scale edit applied to every voxel within a sphere, see
+/// [`VoxelCommands::terraform`](super::VoxelCommands::terraform).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TerraformBrush {
+    /// Voxels take the majority of their 3×3×3 neighborhood, filling with the first live
+    /// neighbor, which rounds off edges and fills small holes.
+    Smooth,
+    /// Clears everything above the voxel row `height` and fills empty voxels at or below it.
+    Flatten { height: i32, state: AutomataState },
+    /// Shifts the voxels up by `amount` at the center, falling off to zero at the edge of the
+    /// sphere. Negative amounts lower the terrain.
+    Raise { amount: i32 },
+    /// Turns live voxels exposed to an empty neighbor into `into`, eroding one layer per use.
+    Melt { into: AutomataState },
+    /// Adds a sphere of `state`, merged into the terrain with a smooth union of their distance
+    /// fields. `hardness` in `0..=1` sets how sharp the seam is: `1` adds a plain sphere, lower
+    /// values grow a wider fillet where both meet, dithering `state` into the material around
+    /// it.
+    Add { state: AutomataState, hardness: f32 },
+    /// Carves a sphere out of the terrain with a smooth subtraction, `hardness` as for `Add`.
+    Carve { hardness: f32 },
+}
+
+impl TerraformBrush {
+    fn hardness(&self) -> f32 {
+        match *self {
+            TerraformBrush::Add { hardness, .. } | TerraformBrush::Carve { hardness } => {
+                hardness.clamp(0.0, 1.0)
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Voxels read around the brush box on each axis.
+    fn halo(&self) -> IVec3 {
+        match *self {
+            TerraformBrush::Raise { amount } => IVec3::new(1, amount.abs().max(1), 1),
+            _ => IVec3::ONE,
+        }
+    }
+}
+
+/// Voxel box covered by a brush, with `center` and `radius` in voxels.
+struct BrushRegion {
+    brush: TerraformBrush,
+    center: Vec3,
+    radius: f32,
+    /// First voxel of the output box.
+    min: IVec3,
+    size: IVec3,
+    halo: IVec3,
+}
+
+impl BrushRegion {
+    fn new(brush: TerraformBrush, center: Vec3, radius: f32) -> Self {
+        let min = (center - radius - 0.5).ceil().as_ivec3();
+        let max = (center + radius - 0.5).floor().as_ivec3();
+        Self {
+            brush,
+            center,
+            radius,
+            min,
+            size: (max - min + 1).max(IVec3::ZERO),
+            halo: brush.halo(),
+        }
+    }
+
+    fn input_size(&self) -> IVec3 {
+        self.size + self.halo * 2
+    }
+
+    fn input_index(&self, pos: IVec3) -> Option<usize> {
+        let size = self.input_size();
+        (pos.cmpge(IVec3::ZERO).all() && pos.cmplt(size).all())
+            .then(|| (pos.x * size.y * size.z + pos.y * size.z + pos.z) as usize)
+    }
+
+    /// Output positions in the order of the output buffer.
+    fn output_positions(&self) -> impl Iterator<Item = IVec3> + '_ {
+        (0..self.size.x).flat_map(move |x| {
+            (0..self.size.y).flat_map(move |y| (0..self.size.z).map(move |z| IVec3::new(x, y, z)))
+        })
+    }
+
+    /// Cells of the input box, voxels of unloaded chunks read as empty.
+    fn read_input(&self, world: &World) -> Vec<AutomataState> {
+        let origin = self.min - self.halo;
+        let size = self.input_size();
+        let index = world.resource::<ChunkIndex>();
+        let mut chunks: HashMap<IVec3, Option<&ChunkCells>> = HashMap::new();
+
+        let mut input = Vec::with_capacity((size.x * size.y * size.z) as usize);
+        for x in 0..size.x {
+            for y in 0..size.y {
+                for z in 0..size.z {
+                    let (chunk, local) = voxel_to_chunk(origin + IVec3::new(x, y, z));
+                    let cells = *chunks.entry(chunk).or_insert_with(|| {
+                        index
+                            .entity(chunk)
+                            .and_then(|entity| world.get::<ChunkCells>(entity))
+                    });
+                    input.push(cells.map_or(AutomataState::EMPTY, |cells| cells.get(local)));
+                }
+            }
+        }
+        input
+    }
+}
+
+/// The brush at output position `pos`.
+fn brush_voxel(region: &BrushRegion, input: &[AutomataState], pos: IVec3) -> AutomataState {
+    let read = |pos: IVec3| {
+        region
+            .input_index(pos)
+            .map_or(AutomataState::EMPTY, |index| input[index])
+    };
+    let neighborhood = |pos: IVec3| {
+        (0..27).map(move |i| read(pos + IVec3::new(i / 9, (i / 3) % 3, i % 3) - IVec3::ONE))
+    };
+
+    let input_pos = pos + region.halo;
+    let current = read(input_pos);
+    let distance = (pos.as_vec3() + 0.5).distance(region.center - region.min.as_vec3());
+    if distance > region.radius {
+        return current;
+    }
+
+    match region.brush {
+        TerraformBrush::Smooth => {
+            let alive: Vec<_> = neighborhood(input_pos)
+                .filter(|state| state.is_alive())
+                .collect();
+            if alive.len() < 14 {
+                AutomataState::EMPTY
+            } else if !current.is_alive() {
+                alive[0]
+            } else {
+                current
+            }
+        }
+        TerraformBrush::Flatten { height, state } => {
+            if pos.y > height - region.min.y {
+                AutomataState::EMPTY
+            } else if !current.is_alive() {
+                state
+            } else {
+                current
+            }
+        }
+        TerraformBrush::Raise { amount } => {
+            let falloff = 1.0 - distance / region.radius;
+            let offset = (amount as f32 * falloff).round() as i32;
+            read(input_pos - IVec3::Y * offset)
+        }
+        TerraformBrush::Melt { into } => {
+            let exposed = neighborhood(input_pos).any(|state| !state.is_alive());
+            if current.is_alive() && current != into && exposed {
+                into
+            } else {
+                current
+            }
+        }
+        TerraformBrush::Add { state, .. } => {
+            if current.is_alive() {
+                return current;
+            }
+            let (terrain, brush, blend) =
+                blend_distances(region, distance, neighborhood(input_pos));
+            let (union, weight) = smooth_min(brush, terrain, blend);
+            if union >= 0.0 {
+                return current;
+            }
+            // Dither the brush material into the surrounding one across the fillet.
+            let surrounding = neighborhood(input_pos).find(|state| state.is_alive());
+            match surrounding {
+                Some(surrounding) if dither(region.min + pos) >= weight => surrounding,
+                _ => state,
+            }
+        }
+        TerraformBrush::Carve { .. } => {
+            let (terrain, brush, blend) =
+                blend_distances(region, distance, neighborhood(input_pos));
+            let (carved, _) = smooth_min(-terrain, brush, blend);
+            if current.is_alive() && carved <= 0.0 {
+                AutomataState::EMPTY
+            } else {
+                current
+            }
+        }
+    }
+}
+
+/// Distances used by the blending brushes: the terrain's, estimated from how full the 3×3×3
+/// neighborhood is, the brush sphere's and the blend width, tapering to zero at the edge of the
+/// sphere so the edit stops without a seam.
+fn blend_distances(
+    region: &BrushRegion,
+    distance: f32,
+    neighborhood: impl Iterator<Item = AutomataState>,
+) -> (f32, f32, f32) {
+    let alive = neighborhood.filter(|state| state.is_alive()).count();
+    let terrain = (0.5 - alive as f32 / 27.0) * 3.0;
+    let width = (1.0 - region.brush.hardness()) * region.radius;
+    // The smooth union reaches up to a quarter of the width past the sphere it blends.
+    let brush = distance - (region.radius - width * 0.25);
+    let blend = width * (1.0 - distance / region.radius);
+    (terrain, brush, blend)
+}
+
+/// Polynomial smooth minimum of two distances over `blend` voxels, along with the weight of
+/// `a` in it.
+fn smooth_min(a: f32, b: f32, blend: f32) -> (f32, f32) {
+    if blend <= 0.0 {
+        return if a < b { (a, 1.0) } else { (b, 0.0) };
+    }
+    let weight = (0.5 + 0.5 * (b - a) / blend).clamp(0.0, 1.0);
+    let value = b + (a - b) * weight - blend * weight * (1.0 - weight);
+    (value, weight)
+}
+
+/// Threshold in `0..1` hashed from a voxel, stable across edits.
+fn dither(voxel: IVec3) -> f32 {
+    let mut hash = (voxel.x as u32).wrapping_mul(73_856_093)
+        ^ (voxel.y as u32).wrapping_mul(19_349_663)
+        ^ (voxel.z as u32).wrapping_mul(83_492_791);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x7feb_352d);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x846c_a68b);
+    hash ^= hash >> 16;
+    (hash >> 8) as f32 / (1 << 24) as f32
+}
+
+/// Applies `brush` to the sphere around `center`, both in voxels.
+pub(super) fn apply_brush(world: &mut World, brush: TerraformBrush, center: Vec3, radius: f32) {
+    let region = BrushRegion::new(brush, center, radius);
+    if region.size.cmpeq(IVec3::ZERO).any() {
+        return;
+    }
+    let input = region.read_input(world);
+    let output = region
+        .output_positions()
+        .map(|pos| brush_voxel(&region, &input, pos));
+
+    let edits = region
+        .output_positions()
+        .zip(output)
+        .filter(|(pos, state)| {
+            let index = region.input_index(*pos + region.halo).unwrap();
+            input[index] != *state
+        })
+        .map(|(pos, state)| (region.min + pos, state))
+        .collect();
+    apply_voxel_edits(world, edits);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_cpu(region: &BrushRegion, input: &[AutomataState]) -> Vec<AutomataState> {
+        region
+            .output_positions()
+            .map(|pos| brush_voxel(region, input, pos))
+            .collect()
+    }
+
+    #[test]
+    fn brushes_flatten_and_raise_within_the_sphere() {
+        let stone = AutomataState::new(1, 0);
+        let flatten = BrushRegion::new(
+            TerraformBrush::Flatten {
+                height: 2,
+                state: stone,
+            },
+            Vec3::splat(4.0),
+            4.0,
+        );
+        let size = flatten.input_size();
+        let input = vec![AutomataState::EMPTY; (size.x * size.y * size.z) as usize];
+        let output = run_cpu(&flatten, &input);
+        let center = flatten
+            .output_positions()
+            .position(|pos| pos == IVec3::new(3, 2, 3));
+        assert_eq!(output[center.unwrap()], stone);
+        let above = flatten
+            .output_positions()
+            .position(|pos| pos == IVec3::new(3, 3, 3));
+        assert_eq!(output[above.unwrap()], AutomataState::EMPTY);
+        // Corners of the box lie outside the sphere.
+        assert_eq!(output[0], AutomataState::EMPTY);
+
+        // Raising a flat floor lifts its middle by the full amount.
+        let raise = BrushRegion::new(TerraformBrush::Raise { amount: 2 }, Vec3::splat(4.0), 4.0);
+        let size = raise.input_size();
+        let mut input = Vec::new();
+        for _x in 0..size.x {
+            for y in 0..size.y {
+                for _z in 0..size.z {
+                    input.push(if y - raise.halo.y <= 1 {
+                        stone
+                    } else {
+                        AutomataState::EMPTY
+                    });
+                }
+            }
+        }
+        let output = run_cpu(&raise, &input);
+        let top = raise
+            .output_positions()
+            .position(|pos| pos == IVec3::new(3, 3, 3));
+        assert_eq!(output[top.unwrap()], stone);
+    }
+
+    #[test]
+    fn blending_brushes_add_and_carve_spheres() {
+        let stone = AutomataState::new(1, 0);
+        let sand = AutomataState::new(2, 0);
+        let add = BrushRegion::new(
+            TerraformBrush::Add {
+                state: sand,
+                hardness: 1.0,
+            },
+            Vec3::splat(4.0),
+            4.0,
+        );
+        let size = add.input_size();
+        let volume = (size.x * size.y * size.z) as usize;
+        let center = add
+            .output_positions()
+            .position(|pos| pos == IVec3::splat(3))
+            .unwrap();
+        let output = run_cpu(&add, &vec![AutomataState::EMPTY; volume]);
+        assert_eq!(output[center], sand);
+        assert_eq!(output[0], AutomataState::EMPTY);
+        // Already solid voxels keep their material.
+        assert!(run_cpu(&add, &vec![stone; volume])
+            .iter()
+            .all(|state| *state == stone));
+
+        let carve = BrushRegion::new(
+            TerraformBrush::Carve { hardness: 0.5 },
+            Vec3::splat(4.0),
+            4.0,
+        );
+        let output = run_cpu(&carve, &vec![stone; volume]);
+        assert_eq!(output[center], AutomataState::EMPTY);
+        assert_eq!(output[0], stone);
+    }
+
+    #[test]
+    fn smooth_fills_holes_and_melt_erodes_the_surface() {
+        let stone = AutomataState::new(1, 0);
+        let sand = AutomataState::new(2, 0);
+        let smooth = BrushRegion::new(TerraformBrush::Smooth, Vec3::splat(4.0), 4.0);
+        let size = smooth.input_size();
+        let volume = (size.x * size.y * size.z) as usize;
+        let center = smooth
+            .output_positions()
+            .position(|pos| pos == IVec3::splat(3))
+            .unwrap();
+
+        // A hole in solid stone is filled, a lone voxel in the air is removed.
+        let mut input = vec![stone; volume];
+        input[smooth.input_index(IVec3::splat(3) + smooth.halo).unwrap()] = AutomataState::EMPTY;
+        assert_eq!(run_cpu(&smooth, &input)[center], stone);
+        let mut input = vec![AutomataState::EMPTY; volume];
+        input[smooth.input_index(IVec3::splat(3) + smooth.halo).unwrap()] = stone;
+        assert_eq!(run_cpu(&smooth, &input)[center], AutomataState::EMPTY);
+
+        // Only the voxels next to empty ones melt, the buried ones keep their material.
+        let melt = BrushRegion::new(TerraformBrush::Melt { into: sand }, Vec3::splat(4.0), 4.0);
+        let mut input = vec![stone; volume];
+        input[melt.input_index(IVec3::new(3, 4, 3) + melt.halo).unwrap()] = AutomataState::EMPTY;
+        let output = run_cpu(&melt, &input);
+        let at = |pos: IVec3| output[melt.output_positions().position(|p| p == pos).unwrap()];
+        assert_eq!(at(IVec3::new(3, 3, 3)), sand);
+        assert_eq!(at(IVec3::new(3, 4, 3)), AutomataState::EMPTY);
+        assert_eq!(at(IVec3::new(3, 1, 3)), stone);
+        // Melting again does not touch what already melted.
+        assert_eq!(
+            run_cpu(&melt, &vec![sand; volume]),
+            vec![sand; output.len()]
+        );
+    }
+}
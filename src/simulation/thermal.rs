@@ -0,0 +1,278 @@
+use super::{
+    apply_next_cells, linear_index, voxel_to_chunk, AutomataState, ChunkCellsNext, ChunkKey,
+    MaterialRegistry, PassChannel, SimulationClock, SimulationPass, SimulationPassAppExt,
+    SimulationSchedule, SimulationSet, CHUNK_EDGE, CHUNK_VOLUME, LIFE_PASS,
+};
+use bevy::{prelude::*, utils::HashMap};
+
+/// Name of the pass diffusing heat and burning voxels, added by the [`ThermalPlugin`].
+pub const THERMAL_PASS: &str = "thermal";
+
+const FACES: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// Layers a temperature field over the voxels, with fire spreading through flammable
+/// materials.
+///
+/// Every chunk gets a [`ChunkTemperature`] starting at [`ThermalSettings::ambient`]. Each step
+/// the [`THERMAL_PASS`] moves every temperature towards the average of its six face neighbors
+/// by the [`MaterialProperties::conductivity`](super::MaterialProperties::conductivity) of
+/// the voxel, neighbors in chunks without a temperature counting as ambient. Live voxels at or
+/// above their ignition temperature catch fire, heat themselves for their burn steps and then
+/// turn into what they burn into. Properties come from the [`MaterialRegistry`].
+pub struct ThermalPlugin;
+
+impl Plugin for ThermalPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MaterialRegistry>()
+            .init_resource::<ThermalSettings>()
+            .add_systems(PreUpdate, add_chunk_temperatures)
+            .add_simulation_pass(
+                SimulationPass::new(THERMAL_PASS)
+                    .after(LIFE_PASS)
+                    .reads(PassChannel::Cells)
+                    .writes(PassChannel::Cells)
+                    .writes(PassChannel::Custom(THERMAL_PASS)),
+                step_heat,
+            )
+            .add_systems(
+                SimulationSchedule,
+                apply_heat
+                    .in_set(SimulationSet::Apply)
+                    .before(apply_next_cells),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThermalSettings {
+    /// Temperature of new chunks and of the space outside the chunks with a temperature.
+    pub ambient: u16,
+}
+
+impl Default for ThermalSettings {
+    fn default() -> Self {
+        Self { ambient: 20 }
+    }
+}
+
+/// Temperature of every voxel of a chunk and the steps left for the burning ones.
+#[derive(Component, Clone)]
+pub struct ChunkTemperature {
+    data: Box<[u16]>,
+    next: Box<[u16]>,
+    burning: Box<[u8]>,
+}
+
+impl ChunkTemperature {
+    pub fn filled(temperature: u16) -> Self {
+        Self {
+            data: vec![temperature; CHUNK_VOLUME].into_boxed_slice(),
+            next: vec![temperature; CHUNK_VOLUME].into_boxed_slice(),
+            burning: vec![0; CHUNK_VOLUME].into_boxed_slice(),
+        }
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &[u16] {
+        &self.data
+    }
+
+    #[inline]
+    pub fn get(&self, local: IVec3) -> u16 {
+        self.data[linear_index(local)]
+    }
+
+    /// Sets a temperature, also overriding the value computed for the step being applied.
+    #[inline]
+    pub fn set(&mut self, local: IVec3, temperature: u16) {
+        let index = linear_index(local);
+        self.data[index] = temperature;
+        self.next[index] = temperature;
+    }
+
+    #[inline]
+    pub fn is_burning(&self, local: IVec3) -> bool {
+        self.burning[linear_index(local)] > 0
+    }
+}
+
+fn add_chunk_temperatures(
+    mut commands: Commands,
+    settings: Res<ThermalSettings>,
+    chunks: Query<Entity, (With<ChunkKey>, Without<ChunkTemperature>)>,
+) {
+    for entity in chunks.iter() {
+        commands
+            .entity(entity)
+            .insert(ChunkTemperature::filled(settings.ambient));
+    }
+}
+
+/// Next temperatures, burn counters and cells of a chunk.
+struct HeatStep {
+    temperatures: Box<[u16]>,
+    burning: Box<[u8]>,
+    cells: Vec<(usize, AutomataState)>,
+}
+
+fn step_heat(
+    registry: Res<MaterialRegistry>,
+    settings: Res<ThermalSettings>,
+    mut chunks: Query<(
+        Entity,
+        &ChunkKey,
+        &mut ChunkTemperature,
+        &mut ChunkCellsNext,
+    )>,
+) {
+    let by_coords: HashMap<IVec3, Entity> = chunks
+        .iter()
+        .map(|(entity, key, ..)| (key.coords, entity))
+        .collect();
+
+    let steps: Vec<(Entity, HeatStep)> = chunks
+        .iter()
+        .map(|(entity, key, temperature, next)| {
+            let origin = key.coords * CHUNK_EDGE;
+            let outside = |local: IVec3| {
+                let (chunk, local) = voxel_to_chunk(origin + local);
+                by_coords
+                    .get(&chunk)
+                    .and_then(|entity| chunks.get(*entity).ok())
+                    .map_or(settings.ambient, |(_, _, temperature, _)| {
+                        temperature.get(local)
+                    })
+            };
+            let step = heat_chunk(&registry, &temperature, next.as_slice(), outside);
+            (entity, step)
+        })
+        .collect();
+
+    for (entity, step) in steps {
+        let Ok((_, _, mut temperature, mut next)) = chunks.get_mut(entity) else {
+            continue;
+        };
+        temperature.next = step.temperatures;
+        temperature.burning = step.burning;
+        for (index, state) in step.cells {
+            next.as_mut_slice()[index] = state;
+        }
+    }
+}
+
+/// Diffuses and burns one chunk against the cells computed for the step so far, reading
+/// temperatures past the chunk border through `outside`.
+fn heat_chunk(
+    registry: &MaterialRegistry,
+    temperature: &ChunkTemperature,
+    cells: &[AutomataState],
+    outside: impl Fn(IVec3) -> u16,
+) -> HeatStep {
+    let mut step = HeatStep {
+        temperatures: temperature.data.clone(),
+        burning: temperature.burning.clone(),
+        cells: Vec::new(),
+    };
+    for x in 0..CHUNK_EDGE {
+        for y in 0..CHUNK_EDGE {
+            for z in 0..CHUNK_EDGE {
+                let local = IVec3::new(x, y, z);
+                let index = linear_index(local);
+                let cell = cells[index];
+                let properties = registry.get(cell.material);
+                let current = temperature.data[index] as i32;
+
+                let mut sum = 0;
+                for face in FACES {
+                    let neighbor = local + face;
+                    let inside = neighbor.cmpge(IVec3::ZERO).all()
+                        && neighbor.cmplt(IVec3::splat(CHUNK_EDGE)).all();
+                    sum += match inside {
+                        true => temperature.data[linear_index(neighbor)],
+                        false => outside(neighbor),
+                    } as i32;
+                }
+                let mut next = current + (sum / 6 - current) * properties.conductivity as i32 / 255;
+
+                let burning = &mut step.burning[index];
+                if !cell.is_alive() {
+                    *burning = 0;
+                } else if *burning > 0 {
+                    next += properties.burn_heat as i32;
+                    *burning -= 1;
+                    if *burning == 0 {
+                        step.cells.push((index, properties.burns_into));
+                    }
+                } else if properties
+                    .ignition
+                    .is_some_and(|ignition| current >= ignition as i32)
+                {
+                    *burning = properties.burn_steps.max(1);
+                }
+                step.temperatures[index] = next.clamp(0, u16::MAX as i32) as u16;
+            }
+        }
+    }
+    step
+}
+
+/// Copies the temperatures computed by the [`THERMAL_PASS`] into the chunks.
+fn apply_heat(clock: Res<SimulationClock>, mut chunks: Query<&mut ChunkTemperature>) {
+    if !clock.executed_step {
+        return;
+    }
+
+    chunks.par_iter_mut().for_each(|mut temperature| {
+        let ChunkTemperature { data, next, .. } = &mut *temperature;
+        data.copy_from_slice(next);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MaterialProperties;
+
+    #[test]
+    fn fire_spreads_through_flammable_voxels() {
+        const WOOD: u8 = 3;
+        const ASH: u8 = 4;
+        let mut registry = MaterialRegistry::default();
+        registry.set(
+            WOOD,
+            MaterialProperties::flammable(200, 600, 4, AutomataState::new(ASH, 0)),
+        );
+
+        let mut cells = vec![AutomataState::EMPTY; CHUNK_VOLUME];
+        for x in 0..4 {
+            cells[linear_index(IVec3::new(x, 0, 0))] = AutomataState::new(WOOD, 0);
+        }
+        let mut temperature = ChunkTemperature::filled(20);
+        temperature.set(IVec3::ZERO, 500);
+
+        let mut burned = false;
+        for _ in 0..40 {
+            let step = heat_chunk(&registry, &temperature, &cells, |_| 20);
+            for (index, state) in step.cells {
+                cells[index] = state;
+            }
+            temperature.data = step.temperatures;
+            temperature.burning = step.burning;
+            burned = cells[linear_index(IVec3::new(3, 0, 0))].material == ASH;
+            if burned {
+                break;
+            }
+        }
+        assert!(burned);
+        assert_eq!(cells[0].material, ASH);
+        // Empty voxels never burn.
+        assert!(!temperature.is_burning(IVec3::new(0, 5, 0)));
+    }
+}
@@ -0,0 +1,173 @@
+use super::{AutomataRule, SimulationClock};
+use bevy::prelude::*;
+use std::sync::Arc;
+
+/// Callback adjusting the rule before a step, given the step about to run.
+pub type RuleDriver = Arc<dyn Fn(u64, &mut AutomataRule) + Send + Sync>;
+
+/// Rule in effect from `step` onwards, until the next keyframe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleKeyframe {
+    pub step: u64,
+    pub rule: AutomataRule,
+}
+
+/// Schedule of rule changes over simulation steps, e.g. shifting from B5/S45 to B4/S45.
+///
+/// Before every step the [`AutomataRule`] is set to the latest keyframe at or before the step,
+/// then passed through the driver if any. Both only depend on the step number, so the same
+/// timeline always produces the same run. An empty timeline leaves the rule untouched.
+#[derive(Resource, Clone, Default)]
+pub struct RuleTimeline {
+    keyframes: Vec<RuleKeyframe>,
+    driver: Option<RuleDriver>,
+}
+
+impl RuleTimeline {
+    /// Adds a keyframe, keeping keyframes sorted by step. A keyframe at the same step as an
+    /// existing one replaces it.
+    pub fn keyframe(mut self, step: u64, rule: AutomataRule) -> Self {
+        match self
+            .keyframes
+            .binary_search_by_key(&step, |keyframe| keyframe.step)
+        {
+            Ok(index) => self.keyframes[index].rule = rule,
+            Err(index) => self.keyframes.insert(index, RuleKeyframe { step, rule }),
+        }
+        self
+    }
+
+    pub fn driven_by(
+        mut self,
+        driver: impl Fn(u64, &mut AutomataRule) + Send + Sync + 'static,
+    ) -> Self {
+        self.driver = Some(Arc::new(driver));
+        self
+    }
+
+    pub fn keyframes(&self) -> &[RuleKeyframe] {
+        &self.keyframes
+    }
+
+    /// Replaces the keyframes with a loaded list, sorted by step, keeping the driver.
+    pub(crate) fn set_keyframes(&mut self, keyframes: Vec<RuleKeyframe>) {
+        self.keyframes = keyframes;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty() && self.driver.is_none()
+    }
+
+    /// Keyframed rule in effect at `step`, ignoring the driver.
+    pub fn keyframe_at(&self, step: u64) -> Option<&AutomataRule> {
+        let next = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.step <= step);
+        next.checked_sub(1).map(|index| &self.keyframes[index].rule)
+    }
+
+    /// Rule the timeline produces for `step`, starting from `current` before the first keyframe.
+    pub fn rule_at(&self, step: u64, current: &AutomataRule) -> AutomataRule {
+        let mut rule = self.keyframe_at(step).unwrap_or(current).clone();
+        if let Some(driver) = &self.driver {
+            driver(step, &mut rule);
+        }
+        rule
+    }
+}
+
+pub(super) fn apply_rule_timeline(
+    timeline: Res<RuleTimeline>,
+    clock: Res<SimulationClock>,
+    mut rule: ResMut<AutomataRule>,
+) {
+    if clock.steps_requested == 0 || timeline.is_empty() {
+        return;
+    }
+
+    let target = timeline.rule_at(clock.step, &rule);
+    if *rule != target {
+        *rule = target;
+    }
+}
+
+/// Rule swapped in at the next step boundary, after the cells of a step were applied and
+/// before the next snapshot, so a step never mixes two rules. Prefer it over writing the
+/// [`AutomataRule`] directly from systems that may run while steps are in flight, such as
+/// hot reloading.
+///
+/// A [`RuleChanged`] is sent once the swap took effect. A non-empty [`RuleTimeline`] still
+/// overrides the rule before every step.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct PendingRule(Option<AutomataRule>);
+
+impl PendingRule {
+    /// Queues `rule`, replacing any rule still waiting.
+    pub fn set(&mut self, rule: AutomataRule) {
+        self.0 = Some(rule);
+    }
+
+    pub fn get(&self) -> Option<&AutomataRule> {
+        self.0.as_ref()
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Drops the waiting rule, returning it.
+    pub fn cancel(&mut self) -> Option<AutomataRule> {
+        self.0.take()
+    }
+}
+
+/// Sent when a [`PendingRule`] became the [`AutomataRule`].
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct RuleChanged {
+    /// First step run with the new rule.
+    pub step: u64,
+    pub rule: AutomataRule,
+}
+
+pub(super) fn apply_pending_rule(
+    mut pending: ResMut<PendingRule>,
+    clock: Res<SimulationClock>,
+    mut rule: ResMut<AutomataRule>,
+    mut changed: EventWriter<RuleChanged>,
+) {
+    let Some(next) = pending.0.take() else {
+        return;
+    };
+
+    *rule = next.clone();
+    changed.send(RuleChanged {
+        step: clock.step,
+        rule: next,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyframes_hold_until_the_next_one() {
+        let b4 = AutomataRule {
+            birth: vec![4],
+            ..default()
+        };
+        let timeline = RuleTimeline::default()
+            .keyframe(100, b4.clone())
+            .keyframe(0, AutomataRule::default())
+            .driven_by(|step, rule| rule.birth_material = (step / 100) as u8 + 1);
+
+        assert!(timeline
+            .keyframe_at(50)
+            .is_some_and(|rule| rule.birth == vec![5]));
+        assert_eq!(timeline.rule_at(99, &b4).birth, vec![5]);
+
+        let late = timeline.rule_at(250, &AutomataRule::default());
+        assert_eq!(late.birth, vec![4]);
+        assert_eq!(late.birth_material, 3);
+    }
+}
@@ -0,0 +1,116 @@
+use super::{ChunkKey, SimulationSet};
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+use std::time::Duration;
+
+/// Time spent by the simulation steps of the current frame, summed over all of them.
+///
+/// Reported after the steps of every frame as diagnostics in milliseconds, along with the number
+/// of loaded chunks, so `LogDiagnosticsPlugin` and diagnostics overlays show the simulation costs.
+/// Buffer pool usage is reported as the [`BufferPool`](super::BufferPool) diagnostics.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimulationTimings {
+    /// Steps run this frame.
+    pub steps: u32,
+    /// Copying the chunks into the snapshots read by the passes.
+    pub snapshot: Duration,
+    /// Running the passes, from the start of [`SimulationSet::Step`] until its end.
+    pub step: Duration,
+    /// Writing the computed cells back into the chunks.
+    pub apply: Duration,
+}
+
+impl SimulationTimings {
+    pub const STEP_TIME: DiagnosticId =
+        DiagnosticId::from_u128(0x5b20_e7d4_19c3_4f8a_b6e2_0d71_c4a9_3f01);
+    pub const SNAPSHOT_TIME: DiagnosticId =
+        DiagnosticId::from_u128(0x5b20_e7d4_19c3_4f8a_b6e2_0d71_c4a9_3f02);
+    pub const APPLY_TIME: DiagnosticId =
+        DiagnosticId::from_u128(0x5b20_e7d4_19c3_4f8a_b6e2_0d71_c4a9_3f03);
+    /// Loaded chunks, not a timing.
+    pub const CHUNK_COUNT: DiagnosticId =
+        DiagnosticId::from_u128(0x5b20_e7d4_19c3_4f8a_b6e2_0d71_c4a9_3f04);
+
+    #[inline]
+    pub fn total(&self) -> Duration {
+        self.snapshot + self.step + self.apply
+    }
+}
+
+pub(super) fn register_diagnostics(app: &mut App) {
+    app.register_diagnostic(
+        Diagnostic::new(SimulationTimings::STEP_TIME, "simulation_step_time", 20).with_suffix("ms"),
+    )
+    .register_diagnostic(
+        Diagnostic::new(
+            SimulationTimings::SNAPSHOT_TIME,
+            "simulation_snapshot_time",
+            20,
+        )
+        .with_suffix("ms"),
+    )
+    .register_diagnostic(
+        Diagnostic::new(SimulationTimings::APPLY_TIME, "simulation_apply_time", 20)
+            .with_suffix("ms"),
+    )
+    .register_diagnostic(Diagnostic::new(
+        SimulationTimings::CHUNK_COUNT,
+        "simulation_chunk_count",
+        20,
+    ))
+    .add_systems(PostUpdate, report_timings.after(SimulationSet::Run));
+}
+
+fn report_timings(
+    timings: Res<SimulationTimings>,
+    mut diagnostics: Diagnostics,
+    chunks: Query<(), With<ChunkKey>>,
+) {
+    let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    diagnostics.add_measurement(SimulationTimings::STEP_TIME, || millis(timings.step));
+    diagnostics.add_measurement(SimulationTimings::SNAPSHOT_TIME, || {
+        millis(timings.snapshot)
+    });
+    diagnostics.add_measurement(SimulationTimings::APPLY_TIME, || millis(timings.apply));
+    diagnostics.add_measurement(SimulationTimings::CHUNK_COUNT, || {
+        chunks.iter().len() as f64
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CellularAutomataPlugin, ChunkBundle, SimulationControl};
+    use bevy::diagnostic::DiagnosticsStore;
+
+    #[test]
+    fn frames_report_their_steps_and_chunks() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(CellularAutomataPlugin);
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        for coords in [IVec3::ZERO, IVec3::X] {
+            app.world.spawn(ChunkBundle::new(coords));
+        }
+        app.world.resource_mut::<SimulationControl>().step_once();
+        app.update();
+
+        let timings = *app.world.resource::<SimulationTimings>();
+        assert_eq!(timings.steps, 1);
+        let diagnostics = app.world.resource::<DiagnosticsStore>();
+        let value = |id| {
+            diagnostics
+                .get(id)
+                .and_then(|diagnostic| diagnostic.value())
+        };
+        assert_eq!(value(SimulationTimings::CHUNK_COUNT), Some(2.0));
+        assert!(value(SimulationTimings::STEP_TIME).is_some());
+
+        // Frames without a step report nothing spent.
+        app.update();
+        let timings = *app.world.resource::<SimulationTimings>();
+        assert_eq!(timings, SimulationTimings::default());
+    }
+}
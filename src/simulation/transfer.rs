@@ -0,0 +1,237 @@
+use super::{
+    ChunkActivity, ChunkAux, ChunkBundle, ChunkCells, ChunkChanges, ChunkIndex, ChunkKey, ChunkLod,
+    ChunkSnapshots,
+};
+use bevy::{prelude::*, utils::HashMap};
+
+/// Clones the chunks of `src` within `region` into `dst`, shifted by `offset`.
+///
+/// Worlds act as layers here, for example a preview or an undo sandbox next to the main world.
+/// `region` is a `(min, max)` box of chunk coordinates with `max` exclusive, and `offset` is in
+/// chunks. Cells, [`ChunkAux`], [`ChunkActivity`] and [`ChunkLod`] are carried over, replacing
+/// those of chunks already loaded in `dst`, and the whole chunk is marked in [`ChunkChanges`].
+/// The [`ChunkSnapshots`] of `dst` are refreshed right away, so its other chunks see the copies
+/// before the next step. Unloaded chunks of the region are skipped. Returns the number of chunks
+/// copied.
+pub fn copy_chunks(
+    src: &mut World,
+    dst: &mut World,
+    region: (IVec3, IVec3),
+    offset: IVec3,
+) -> usize {
+    let chunks = collect_chunks(src, region);
+    let copied = chunks.len();
+    insert_chunks(dst, chunks, offset);
+    copied
+}
+
+/// Same as [`copy_chunks`], despawning the chunks from `src` afterwards.
+pub fn move_chunks(
+    src: &mut World,
+    dst: &mut World,
+    region: (IVec3, IVec3),
+    offset: IVec3,
+) -> usize {
+    let chunks = collect_chunks(src, region);
+    for chunk in &chunks {
+        src.entity_mut(chunk.entity).despawn_recursive();
+        if let Some(mut index) = src.get_resource_mut::<ChunkIndex>() {
+            index.remove(chunk.coords);
+        }
+        if let Some(mut snapshots) = src.get_resource_mut::<ChunkSnapshots>() {
+            snapshots.remove(chunk.coords);
+        }
+    }
+
+    let moved = chunks.len();
+    insert_chunks(dst, chunks, offset);
+    moved
+}
+
+struct ChunkCopy {
+    entity: Entity,
+    coords: IVec3,
+    cells: ChunkCells,
+    aux: Option<ChunkAux>,
+    activity: Option<ChunkActivity>,
+    lod: Option<ChunkLod>,
+}
+
+fn collect_chunks(world: &mut World, (min, max): (IVec3, IVec3)) -> Vec<ChunkCopy> {
+    let mut query = world.query::<(
+        Entity,
+        &ChunkKey,
+        &ChunkCells,
+        Option<&ChunkAux>,
+        Option<&ChunkActivity>,
+        Option<&ChunkLod>,
+    )>();
+    query
+        .iter(world)
+        .filter(|(_, key, ..)| key.coords.cmpge(min).all() && key.coords.cmplt(max).all())
+        .map(|(entity, key, cells, aux, activity, lod)| ChunkCopy {
+            entity,
+            coords: key.coords,
+            cells: cells.clone(),
+            aux: aux.cloned(),
+            activity: activity.cloned(),
+            lod: lod.copied(),
+        })
+        .collect()
+}
+
+fn insert_chunks(world: &mut World, chunks: Vec<ChunkCopy>, offset: IVec3) {
+    let mut query = world.query::<(Entity, &ChunkKey)>();
+    let existing: HashMap<_, _> = query
+        .iter(world)
+        .map(|(entity, key)| (key.coords, entity))
+        .collect();
+
+    for chunk in chunks {
+        let coords = chunk.coords + offset;
+        let entity = match existing.get(&coords) {
+            Some(&entity) => {
+                world
+                    .entity_mut(entity)
+                    .insert((chunk.cells, ChunkChanges { bricks: u64::MAX }));
+                entity
+            }
+            None => world
+                .spawn(ChunkBundle {
+                    cells: chunk.cells,
+                    ..ChunkBundle::new(coords)
+                })
+                .id(),
+        };
+
+        let mut entity_mut = world.entity_mut(entity);
+        match chunk.aux {
+            Some(aux) => entity_mut.insert(aux),
+            None => entity_mut.remove::<ChunkAux>(),
+        };
+        match chunk.activity {
+            Some(activity) => entity_mut.insert(activity),
+            None => entity_mut.remove::<ChunkActivity>(),
+        };
+        match chunk.lod {
+            Some(lod) => entity_mut.insert(lod),
+            None => entity_mut.remove::<ChunkLod>(),
+        };
+
+        if let Some(mut index) = world.get_resource_mut::<ChunkIndex>() {
+            index.insert(coords, entity);
+        }
+        if world.contains_resource::<ChunkSnapshots>() {
+            world.resource_scope(|world, mut snapshots: Mut<ChunkSnapshots>| {
+                let chunk = world.entity(entity);
+                let cells = chunk.get::<ChunkCells>().expect("chunks have cells");
+                snapshots.refresh(coords, cells, chunk.get::<ChunkAux>());
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AutomataState, CellularAutomataPlugin, SimulationControl, SimulationRate};
+
+    const WOOD: AutomataState = AutomataState::new(2, 0);
+
+    /// A paused world with its chunks snapshotted once.
+    fn world_with(chunks: &[IVec3]) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(CellularAutomataPlugin);
+        for &coords in chunks {
+            app.world.spawn(ChunkBundle::new(coords));
+        }
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        app.update();
+        app.world.resource_mut::<SimulationControl>().step_once();
+        app.update();
+        app
+    }
+
+    fn lod() -> ChunkLod {
+        ChunkLod {
+            rate: SimulationRate::Frozen,
+            meshed: false,
+            downsampled: false,
+        }
+    }
+
+    #[test]
+    fn copies_replace_loaded_chunks_and_their_snapshots() {
+        let mut src = App::new();
+        src.world.spawn((
+            ChunkBundle::from_generator(IVec3::ZERO, |_| WOOD),
+            ChunkAux::filled(7),
+            lod(),
+        ));
+        let mut dst = world_with(&[IVec3::X]);
+        let loaded = dst.world.resource::<ChunkIndex>().entity(IVec3::X).unwrap();
+
+        let copied = copy_chunks(
+            &mut src.world,
+            &mut dst.world,
+            (IVec3::ZERO, IVec3::ONE),
+            IVec3::X,
+        );
+
+        assert_eq!(copied, 1);
+        assert_eq!(src.world.query::<&ChunkKey>().iter(&src.world).count(), 1);
+        assert_eq!(
+            dst.world.get::<ChunkCells>(loaded).unwrap().get(IVec3::ONE),
+            WOOD
+        );
+        assert_eq!(dst.world.get::<ChunkLod>(loaded), Some(&lod()));
+        let snapshots = dst.world.resource::<ChunkSnapshots>();
+        assert!(snapshots
+            .get(IVec3::X)
+            .unwrap()
+            .iter()
+            .all(|cell| *cell == WOOD));
+        assert!(snapshots
+            .aux(IVec3::X)
+            .unwrap()
+            .iter()
+            .all(|value| *value == 7));
+    }
+
+    #[test]
+    fn moves_leave_no_trace_in_the_source() {
+        let mut src = world_with(&[IVec3::ZERO]);
+        let mut dst = world_with(&[]);
+
+        let moved = move_chunks(
+            &mut src.world,
+            &mut dst.world,
+            (IVec3::ZERO, IVec3::ONE),
+            IVec3::Y,
+        );
+
+        assert_eq!(moved, 1);
+        assert!(src
+            .world
+            .resource::<ChunkIndex>()
+            .entity(IVec3::ZERO)
+            .is_none());
+        assert!(src
+            .world
+            .resource::<ChunkSnapshots>()
+            .get(IVec3::ZERO)
+            .is_none());
+        let entity = dst.world.resource::<ChunkIndex>().entity(IVec3::Y).unwrap();
+        assert_eq!(dst.world.get::<ChunkKey>(entity).unwrap().coords, IVec3::Y);
+        assert!(dst
+            .world
+            .resource::<ChunkSnapshots>()
+            .get(IVec3::Y)
+            .is_some());
+        assert_eq!(
+            dst.world.get::<ChunkLod>(entity),
+            Some(&ChunkLod::default())
+        );
+    }
+}
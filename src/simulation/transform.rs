@@ -0,0 +1,88 @@
+use super::CHUNK_EDGE;
+use bevy::{math::Affine3A, prelude::*};
+
+/// Placement of the voxel grid in the Bevy world.
+///
+/// Voxel space has one unit per voxel with voxel `(0, 0, 0)` spanning `0..1` on every axis.
+/// Everything that converts between world positions and voxels (chunk LOD, visibility,
+/// proxies, raycasts, meshing) goes through this resource.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct VoxelWorldTransform {
+    /// Edge length of a voxel in meters.
+    pub voxel_size: f32,
+    /// World position of the corner of voxel `(0, 0, 0)`.
+    pub origin: Vec3,
+    pub rotation: Quat,
+}
+
+impl Default for VoxelWorldTransform {
+    fn default() -> Self {
+        Self {
+            voxel_size: 1.0,
+            origin: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+        }
+    }
+}
+
+impl VoxelWorldTransform {
+    /// Affine transform from voxel space to world space.
+    pub fn affine(&self) -> Affine3A {
+        Affine3A::from_scale_rotation_translation(
+            Vec3::splat(self.voxel_size),
+            self.rotation,
+            self.origin,
+        )
+    }
+
+    /// Transform placing voxel space in the world, e.g. for chunk meshes built in voxel space.
+    pub fn transform(&self) -> Transform {
+        Transform {
+            translation: self.origin,
+            rotation: self.rotation,
+            scale: Vec3::splat(self.voxel_size),
+        }
+    }
+
+    #[inline]
+    pub fn world_to_voxel_space(&self, world: Vec3) -> Vec3 {
+        self.rotation.inverse() * (world - self.origin) / self.voxel_size
+    }
+
+    #[inline]
+    pub fn voxel_space_to_world(&self, voxel_space: Vec3) -> Vec3 {
+        self.origin + self.rotation * (voxel_space * self.voxel_size)
+    }
+
+    /// Direction in voxel space matching a world space direction, without normalization.
+    #[inline]
+    pub fn world_to_voxel_direction(&self, direction: Vec3) -> Vec3 {
+        self.rotation.inverse() * direction / self.voxel_size
+    }
+
+    /// World space direction matching a direction in voxel space, without normalization.
+    #[inline]
+    pub fn voxel_to_world_direction(&self, direction: Vec3) -> Vec3 {
+        self.rotation * direction * self.voxel_size
+    }
+
+    /// Voxel containing a world position.
+    #[inline]
+    pub fn world_to_voxel(&self, world: Vec3) -> IVec3 {
+        self.world_to_voxel_space(world).floor().as_ivec3()
+    }
+
+    /// World position of the center of a voxel.
+    #[inline]
+    pub fn voxel_center(&self, voxel: IVec3) -> Vec3 {
+        self.voxel_space_to_world(voxel.as_vec3() + Vec3::splat(0.5))
+    }
+
+    /// Coordinates of the chunk containing a world position.
+    #[inline]
+    pub fn world_to_chunk(&self, world: Vec3) -> IVec3 {
+        (self.world_to_voxel_space(world) / CHUNK_EDGE as f32)
+            .floor()
+            .as_ivec3()
+    }
+}
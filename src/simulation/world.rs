@@ -0,0 +1,264 @@
+use super::{
+    voxel_to_chunk, AutomataState, ChunkCells, ChunkIndex, ChunkKey, VoxelWorldTransform,
+    CHUNK_EDGE,
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+/// Read-only access to the cells from world space positions, for gameplay systems.
+///
+/// Takes care of the world transform and of splitting positions into chunk and local
+/// coordinates. Voxels of chunks that are not loaded read as `None`, and as not solid.
+///
+/// The placement queries ([`Self::find_surface_position`], [`Self::find_clear_volume`] and
+/// [`Self::is_region_empty`]) test whole rows of the chunks' [`OccupancyMask`] at once and
+/// skip empty chunks, so they stay cheap enough for spawners to call every frame.
+///
+/// [`OccupancyMask`]: super::OccupancyMask
+#[derive(SystemParam)]
+pub struct VoxelWorld<'w, 's> {
+    world_transform: Res<'w, VoxelWorldTransform>,
+    index: Res<'w, ChunkIndex>,
+    chunks: Query<'w, 's, (&'static ChunkKey, &'static ChunkCells)>,
+}
+
+impl<'w, 's> VoxelWorld<'w, 's> {
+    /// State of the voxel containing `world_pos`.
+    #[inline]
+    pub fn get(&self, world_pos: Vec3) -> Option<AutomataState> {
+        self.voxel(self.world_transform.world_to_voxel(world_pos))
+    }
+
+    /// State of a voxel by voxel coordinates.
+    pub fn voxel(&self, voxel: IVec3) -> Option<AutomataState> {
+        let (chunk, local) = voxel_to_chunk(voxel);
+        self.chunk(chunk).map(|cells| cells.get(local))
+    }
+
+    /// Whether the voxel containing `world_pos` is alive, read from the chunk's
+    /// [`OccupancyMask`](super::OccupancyMask).
+    pub fn is_solid(&self, world_pos: Vec3) -> bool {
+        let voxel = self.world_transform.world_to_voxel(world_pos);
+        let (chunk, local) = voxel_to_chunk(voxel);
+        self.chunk(chunk).is_some_and(|cells| cells.is_alive(local))
+    }
+
+    /// Loaded voxels whose center lies in the world space box between `min` and `max`, taken
+    /// along the axes of the voxel grid, chunk by chunk.
+    pub fn iter_region(
+        &self,
+        min: Vec3,
+        max: Vec3,
+    ) -> impl Iterator<Item = (IVec3, AutomataState)> + '_ {
+        let a = self.world_transform.world_to_voxel_space(min);
+        let b = self.world_transform.world_to_voxel_space(max);
+        let min = (a.min(b) - 0.5).ceil().as_ivec3();
+        let max = (a.max(b) - 0.5).floor().as_ivec3();
+        let (min_chunk, _) = voxel_to_chunk(min);
+        let (max_chunk, _) = voxel_to_chunk(max);
+
+        let chunks = (min_chunk.x..=max_chunk.x).flat_map(move |x| {
+            (min_chunk.y..=max_chunk.y)
+                .flat_map(move |y| (min_chunk.z..=max_chunk.z).map(move |z| IVec3::new(x, y, z)))
+        });
+        chunks
+            .filter_map(move |coords| Some((coords, self.chunk(coords)?)))
+            .flat_map(move |(coords, cells)| {
+                let origin = coords * CHUNK_EDGE;
+                let from = (min - origin).max(IVec3::ZERO);
+                let to = (max - origin).min(IVec3::splat(CHUNK_EDGE - 1));
+                (from.x..=to.x).flat_map(move |x| {
+                    (from.y..=to.y).flat_map(move |y| {
+                        (from.z..=to.z).map(move |z| {
+                            let local = IVec3::new(x, y, z);
+                            (origin + local, cells.get(local))
+                        })
+                    })
+                })
+            })
+    }
+
+    /// World position of the top face of the highest live voxel in the column of voxels
+    /// containing `column`, along the Y axis of the voxel grid. Only loaded chunks are
+    /// searched, `None` when the column has no live voxel.
+    pub fn find_surface_position(&self, column: Vec3) -> Option<Vec3> {
+        let voxel = self.world_transform.world_to_voxel(column);
+        let (column_chunk, local) = voxel_to_chunk(voxel);
+        let chunks = self
+            .index
+            .column(column_chunk.x, column_chunk.z)
+            .filter_map(|(coords, entity)| Some((coords.y, self.chunks.get(entity).ok()?.1)))
+            .filter(|(_, cells)| !cells.occupancy().is_empty());
+
+        chunks.find_map(|(chunk_y, cells)| {
+            let occupancy = cells.occupancy();
+            let y = (0..CHUNK_EDGE)
+                .rev()
+                .find(|y| occupancy.row(local.x, *y) & (1 << local.z) != 0)?;
+            let top = IVec3::new(voxel.x, chunk_y * CHUNK_EDGE + y + 1, voxel.z);
+            let top_face = top.as_vec3() + Vec3::new(0.5, 0.0, 0.5);
+            Some(self.world_transform.voxel_space_to_world(top_face))
+        })
+    }
+
+    /// Center of a box of `size` world units around `near`, within `max_distance`, that holds
+    /// no live voxel and lies in loaded chunks. Candidates are aligned to the voxel grid and
+    /// visited in cubic shells of growing distance, so the box found is near `near` though
+    /// not always the closest one.
+    pub fn find_clear_volume(&self, size: Vec3, near: Vec3, max_distance: f32) -> Option<Vec3> {
+        let voxel_size = self.world_transform.voxel_size;
+        let extent = (size / voxel_size).ceil().as_ivec3().max(IVec3::ONE);
+        let half = extent.as_vec3() / 2.0;
+        let near_min = (self.world_transform.world_to_voxel_space(near) - half)
+            .round()
+            .as_ivec3();
+        let reach = (max_distance / voxel_size).floor() as i32;
+        let reach_squared = reach * reach;
+
+        (0..=reach).find_map(|shell| {
+            let mut candidates: Vec<IVec3> = (-shell..=shell)
+                .flat_map(|x| {
+                    (-shell..=shell)
+                        .flat_map(move |y| (-shell..=shell).map(move |z| IVec3::new(x, y, z)))
+                })
+                .filter(|offset| offset.abs().max_element() == shell)
+                .filter(|offset| offset.length_squared() <= reach_squared)
+                .collect();
+            candidates.sort_by_key(|offset| (offset.length_squared(), offset.to_array()));
+            candidates.into_iter().find_map(|offset| {
+                let min = near_min + offset;
+                self.is_box_clear(min, min + extent - 1, false).then(|| {
+                    self.world_transform
+                        .voxel_space_to_world(min.as_vec3() + half)
+                })
+            })
+        })
+    }
+
+    /// Whether no live voxel overlaps the world space box between `min` and `max`, taken along
+    /// the axes of the voxel grid. Voxels of unloaded chunks count as empty.
+    pub fn is_region_empty(&self, min: Vec3, max: Vec3) -> bool {
+        let a = self.world_transform.world_to_voxel_space(min);
+        let b = self.world_transform.world_to_voxel_space(max);
+        let min = a.min(b).floor().as_ivec3();
+        let max = (a.max(b).ceil().as_ivec3() - 1).max(min);
+        self.is_box_clear(min, max, true)
+    }
+
+    #[inline]
+    pub fn world_transform(&self) -> &VoxelWorldTransform {
+        &self.world_transform
+    }
+
+    fn chunk(&self, coords: IVec3) -> Option<&ChunkCells> {
+        let entity = self.index.entity(coords)?;
+        self.chunks.get(entity).ok().map(|(_, cells)| cells)
+    }
+
+    /// Whether no live voxel in `min..=max`, in voxel coordinates, and every chunk it spans is
+    /// loaded unless `allow_unloaded`.
+    fn is_box_clear(&self, min: IVec3, max: IVec3, allow_unloaded: bool) -> bool {
+        let (min_chunk, _) = voxel_to_chunk(min);
+        let (max_chunk, _) = voxel_to_chunk(max);
+        for chunk_x in min_chunk.x..=max_chunk.x {
+            for chunk_y in min_chunk.y..=max_chunk.y {
+                for chunk_z in min_chunk.z..=max_chunk.z {
+                    let coords = IVec3::new(chunk_x, chunk_y, chunk_z);
+                    let Some(cells) = self.chunk(coords) else {
+                        if allow_unloaded {
+                            continue;
+                        }
+                        return false;
+                    };
+                    let occupancy = cells.occupancy();
+                    if occupancy.is_empty() {
+                        continue;
+                    }
+                    let origin = coords * CHUNK_EDGE;
+                    let from = (min - origin).max(IVec3::ZERO);
+                    let to = (max - origin).min(IVec3::splat(CHUNK_EDGE - 1));
+                    let row_mask = (u32::MAX >> (31 - to.z)) & (u32::MAX << from.z);
+                    for x in from.x..=to.x {
+                        for y in from.y..=to.y {
+                            if occupancy.row(x, y) & row_mask != 0 {
+                                return false;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::ChunkBundle;
+    use bevy::ecs::system::SystemState;
+
+    fn world_with(chunks: &[(IVec3, &[IVec3])]) -> World {
+        let mut world = World::new();
+        world.init_resource::<VoxelWorldTransform>();
+        let mut index = ChunkIndex::default();
+        for &(coords, alive) in chunks {
+            let bundle = ChunkBundle::from_generator(coords, |local| {
+                match alive.contains(&(coords * CHUNK_EDGE + local)) {
+                    true => AutomataState::new(1, 0),
+                    false => AutomataState::EMPTY,
+                }
+            });
+            index.insert(coords, world.spawn(bundle).id());
+        }
+        world.insert_resource(index);
+        world
+    }
+
+    #[test]
+    fn surface_is_found_in_the_highest_chunk_of_the_column() {
+        let low = IVec3::new(3, 4, 5);
+        let high = IVec3::new(3, CHUNK_EDGE + 2, 5);
+        let mut world = world_with(&[
+            (IVec3::ZERO, &[low]),
+            (IVec3::Y, &[high]),
+            (
+                IVec3::new(1, 2, 0),
+                &[IVec3::new(CHUNK_EDGE + 3, 3 * CHUNK_EDGE, 5)],
+            ),
+        ]);
+        let column = Vec3::new(3.5, 0.0, 5.5);
+
+        let mut state = SystemState::<VoxelWorld>::new(&mut world);
+        let surface = state.get(&world).find_surface_position(column);
+        assert_eq!(surface, Some(Vec3::new(3.5, (high.y + 1) as f32, 5.5)));
+        assert_eq!(
+            state
+                .get(&world)
+                .find_surface_position(Vec3::new(9.5, 0.0, 5.5)),
+            None
+        );
+
+        let upper = world.resource::<ChunkIndex>().entity(IVec3::Y).unwrap();
+        world.despawn(upper);
+        world.resource_mut::<ChunkIndex>().remove(IVec3::Y);
+        let surface = state.get(&world).find_surface_position(column);
+        assert_eq!(surface, Some(Vec3::new(3.5, (low.y + 1) as f32, 5.5)));
+    }
+
+    #[test]
+    fn clear_volumes_avoid_live_and_unloaded_voxels() {
+        let mut world = world_with(&[(IVec3::ZERO, &[IVec3::splat(8)])]);
+        let mut state = SystemState::<VoxelWorld>::new(&mut world);
+        let voxels = state.get(&world);
+
+        assert!(!voxels.is_region_empty(Vec3::splat(7.0), Vec3::splat(10.0)));
+        assert!(voxels.is_region_empty(Vec3::splat(9.0), Vec3::splat(12.0)));
+        assert!(voxels.is_region_empty(Vec3::splat(-4.0), Vec3::splat(-1.0)));
+
+        let clear = voxels
+            .find_clear_volume(Vec3::splat(3.0), Vec3::splat(8.5), 8.0)
+            .unwrap();
+        assert!(voxels.is_region_empty(clear - 1.5, clear + 1.5));
+        assert!(clear.min_element() >= 1.5);
+    }
+}
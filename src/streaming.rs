@@ -0,0 +1,712 @@
+use crate::{
+    voxel_to_chunk, AutomataState, ChunkAux, ChunkBundle, ChunkCells, ChunkIndex, ChunkKey,
+    ChunkSnapshots, ChunkSource, SimulationAnchor, SimulationProfile, SimulationSet,
+    VoxelWorldTransform, CHUNK_EDGE,
+};
+use bevy::{
+    prelude::*,
+    tasks::{futures_lite::future, AsyncComputeTaskPool, Task},
+    utils::{HashMap, HashSet},
+};
+use std::{borrow::Cow, sync::Arc};
+
+/// Loads chunks around and ahead of every [`SimulationAnchor`] and unloads the ones left behind.
+///
+/// The radii of an anchor are capped by the [`frozen_radius`](SimulationProfile::frozen_radius)
+/// of its profile, so chunks past it for every anchor are unloaded and not streamed back in.
+///
+/// Streaming runs in `First`, so chunks spawned or unloaded this frame are part of the next
+/// snapshot. [`ChunkIndex`] and [`ChunkSnapshots`] are updated right away. Without any anchor
+/// the loaded chunks are left alone.
+///
+/// With [`ChunkStreaming::async_generation`], chunks are generated on the
+/// [`AsyncComputeTaskPool`] as [`PendingChunk`]s and become chunks in the `First` following the
+/// end of their task.
+pub struct ChunkStreamingPlugin;
+
+impl Plugin for ChunkStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkStreaming>()
+            .init_resource::<HibernatedChunks>()
+            .add_systems(
+                First,
+                (finish_pending_chunks, stream_chunks)
+                    .chain()
+                    .after(SimulationSet::Tick),
+            );
+    }
+}
+
+/// Fills a freshly streamed chunk, called with voxel positions.
+pub type ChunkGenerator = Arc<dyn Fn(IVec3) -> AutomataState + Send + Sync>;
+
+/// Fills a freshly streamed chunk like a [`ChunkGenerator`], also reading the chunks generated
+/// around it so structures can continue across chunk borders.
+pub type ContextChunkGenerator =
+    Arc<dyn Fn(&GenerationContext, IVec3) -> AutomataState + Send + Sync>;
+
+/// Chunks surrounding one being generated by a [`ContextChunkGenerator`].
+///
+/// Covers the 26 neighbors, loaded or hibernated. Streaming never generates two neighbors in
+/// the same frame, so of any two adjacent chunks the one generated last sees the other.
+pub struct GenerationContext<'a> {
+    coords: IVec3,
+    neighbors: HashMap<IVec3, Cow<'a, ChunkCells>>,
+}
+
+impl<'a> GenerationContext<'a> {
+    /// Coordinates of the chunk being generated.
+    #[inline]
+    pub fn coords(&self) -> IVec3 {
+        self.coords
+    }
+
+    /// Whether the chunk at `coords`, one of the 26 neighbors, exists already.
+    #[inline]
+    pub fn is_generated(&self, coords: IVec3) -> bool {
+        self.neighbors.contains_key(&coords)
+    }
+
+    /// State of a voxel in a neighbor, `None` if that neighbor does not exist yet or is not
+    /// adjacent to the chunk being generated.
+    pub fn get(&self, voxel: IVec3) -> Option<AutomataState> {
+        let (chunk, local) = voxel_to_chunk(voxel);
+        self.neighbors.get(&chunk).map(|cells| cells.get(local))
+    }
+
+    /// Copies the neighbors, for generating in a background task.
+    fn into_owned(self) -> GenerationContext<'static> {
+        GenerationContext {
+            coords: self.coords,
+            neighbors: self
+                .neighbors
+                .into_iter()
+                .map(|(coords, cells)| (coords, Cow::Owned(cells.into_owned())))
+                .collect(),
+        }
+    }
+}
+
+/// Chunk generated in the background, replaced by a [`ChunkBundle`] once its task is done. Not
+/// in the [`ChunkIndex`] until then, and dropped with its task when streamed out first.
+#[derive(Component)]
+pub struct PendingChunk {
+    pub coords: IVec3,
+    task: Task<ChunkCells>,
+}
+
+/// What happens to chunks that leave the streaming radius.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChunkUnloadMode {
+    /// The cells are dropped, the chunk is generated again when it comes back.
+    Despawn,
+    /// The cells are kept in [`HibernatedChunks`] and restored when the chunk comes back.
+    #[default]
+    Hibernate,
+}
+
+#[derive(Resource, Clone)]
+pub struct ChunkStreaming {
+    /// Chunks whose center lies within this many chunks of an anchor are loaded.
+    pub load_radius: f32,
+    /// Chunks farther than this from every anchor are unloaded. Larger than `load_radius` so
+    /// chunks on the border do not load and unload every frame.
+    pub unload_radius: f32,
+    pub unload_mode: ChunkUnloadMode,
+    /// Chunks spawned per frame at most, closest first.
+    pub max_loads_per_frame: usize,
+    /// Seconds of anchor movement chunks are loaded ahead of. The radii then cover the path
+    /// from an anchor to where it is heading, so fast anchors find their chunks generated.
+    /// Zero keeps the radii centered on the anchors.
+    pub prefetch_seconds: f32,
+    /// Farthest, in chunks, the loaded area reaches ahead of a moving anchor.
+    pub max_prefetch_distance: f32,
+    /// Leaves new chunks empty when `None`.
+    pub generator: Option<ChunkGenerator>,
+    /// Used instead of `generator` when set. Chunks next to one generated this frame wait for
+    /// the next frame, which lowers the number of chunks generated per frame.
+    pub context_generator: Option<ContextChunkGenerator>,
+    /// Runs the generators in background tasks instead of during the frame. Chunks next to a
+    /// [`PendingChunk`] also wait for it with a `context_generator`.
+    pub async_generation: bool,
+}
+
+impl Default for ChunkStreaming {
+    fn default() -> Self {
+        Self {
+            load_radius: 6.0,
+            unload_radius: 8.0,
+            unload_mode: ChunkUnloadMode::default(),
+            max_loads_per_frame: 8,
+            prefetch_seconds: 1.0,
+            max_prefetch_distance: 4.0,
+            generator: None,
+            context_generator: None,
+            async_generation: false,
+        }
+    }
+}
+
+/// Cells of chunks unloaded with [`ChunkUnloadMode::Hibernate`].
+#[derive(Resource, Default)]
+pub struct HibernatedChunks {
+    chunks: HashMap<IVec3, (ChunkCells, Option<ChunkAux>)>,
+}
+
+impl HibernatedChunks {
+    pub fn contains(&self, coords: IVec3) -> bool {
+        self.chunks.contains_key(&coords)
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Drops every hibernated chunk, they are generated again when streamed back in.
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+    }
+}
+
+/// Weight of the latest frame in the estimated anchor velocity.
+const VELOCITY_SMOOTHING: f32 = 0.2;
+
+/// Path from an anchor to where it is heading and the radii streamed around it, in chunks.
+struct AnchorPath {
+    start: Vec3,
+    end: Vec3,
+    load_radius: f32,
+    unload_radius: f32,
+}
+
+impl AnchorPath {
+    /// Caps the streaming radii by the frozen radius of `profile`, keeping the margin between
+    /// them.
+    fn new(
+        start: Vec3,
+        end: Vec3,
+        streaming: &ChunkStreaming,
+        profile: &SimulationProfile,
+    ) -> Self {
+        let unload_radius = streaming.unload_radius.min(profile.frozen_radius);
+        let margin = streaming.unload_radius - streaming.load_radius;
+        Self {
+            start,
+            end,
+            load_radius: streaming.load_radius.min(unload_radius - margin).max(0.0),
+            unload_radius,
+        }
+    }
+
+    fn distance(&self, coords: IVec3) -> f32 {
+        distance_to_segment(coords.as_vec3() + Vec3::splat(0.5), self.start, self.end)
+    }
+}
+
+/// Position and smoothed velocity of an anchor, in chunks.
+#[derive(Default)]
+struct AnchorMotion {
+    position: Vec3,
+    velocity: Vec3,
+}
+
+/// Offsets of the 26 chunks around a chunk.
+fn neighbor_offsets() -> impl Iterator<Item = IVec3> {
+    (-1..=1)
+        .flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| IVec3::new(x, y, z))))
+        .filter(|offset| *offset != IVec3::ZERO)
+}
+
+fn distance_to_segment(point: Vec3, start: Vec3, end: Vec3) -> f32 {
+    let segment = end - start;
+    let t = (point - start).dot(segment) / segment.length_squared().max(f32::EPSILON);
+    point.distance(start + segment * t.clamp(0.0, 1.0))
+}
+
+fn finish_pending_chunks(
+    mut commands: Commands,
+    mut index: ResMut<ChunkIndex>,
+    mut pending: Query<(Entity, &mut PendingChunk)>,
+) {
+    for (entity, mut chunk) in pending.iter_mut() {
+        let Some(cells) = future::block_on(future::poll_once(&mut chunk.task)) else {
+            continue;
+        };
+        // Edits made during generation already spawned the chunk, they win over the generated
+        // cells.
+        if index.entity(chunk.coords).is_some() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        commands.entity(entity).remove::<PendingChunk>().insert((
+            ChunkBundle {
+                cells,
+                ..ChunkBundle::new(chunk.coords)
+            },
+            ChunkSource::Generated,
+        ));
+        index.insert(chunk.coords, entity);
+    }
+}
+
+/// Neighbors of `coords` seen by a [`ContextChunkGenerator`].
+fn generation_context<'a>(
+    coords: IVec3,
+    index: &ChunkIndex,
+    chunks: &'a Query<(Entity, &ChunkKey, &ChunkCells, Option<&ChunkAux>)>,
+    hibernated: &'a HibernatedChunks,
+) -> GenerationContext<'a> {
+    let neighbors = neighbor_offsets()
+        .map(|offset| coords + offset)
+        .filter_map(|neighbor| {
+            let cells = match index.entity(neighbor) {
+                Some(entity) => chunks.get(entity).ok().map(|(_, _, cells, _)| cells),
+                None => hibernated.chunks.get(&neighbor).map(|(cells, _)| cells),
+            };
+            Some((neighbor, Cow::Borrowed(cells?)))
+        })
+        .collect();
+    GenerationContext { coords, neighbors }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn stream_chunks(
+    mut commands: Commands,
+    time: Res<Time>,
+    streaming: Res<ChunkStreaming>,
+    world_transform: Res<VoxelWorldTransform>,
+    mut index: ResMut<ChunkIndex>,
+    mut snapshots: ResMut<ChunkSnapshots>,
+    mut hibernated: ResMut<HibernatedChunks>,
+    anchors: Query<(Entity, &SimulationAnchor, &GlobalTransform)>,
+    chunks: Query<(Entity, &ChunkKey, &ChunkCells, Option<&ChunkAux>)>,
+    pending: Query<(Entity, &PendingChunk)>,
+    mut motions: Local<HashMap<Entity, AnchorMotion>>,
+) {
+    motions.retain(|entity, _| anchors.contains(*entity));
+    let delta = time.delta_seconds();
+    // Each anchor streams the path from its position to where it is predicted to be.
+    let paths: Vec<_> = anchors
+        .iter()
+        .map(|(entity, anchor, transform)| {
+            let position =
+                world_transform.world_to_voxel_space(transform.translation()) / CHUNK_EDGE as f32;
+            let motion = motions.entry(entity).or_insert_with(|| AnchorMotion {
+                position,
+                ..default()
+            });
+            if delta > 0.0 {
+                let velocity = (position - motion.position) / delta;
+                motion.velocity = motion.velocity.lerp(velocity, VELOCITY_SMOOTHING);
+            }
+            motion.position = position;
+
+            let lead = (motion.velocity * streaming.prefetch_seconds)
+                .clamp_length_max(streaming.max_prefetch_distance);
+            AnchorPath::new(position, position + lead, &streaming, &anchor.profile)
+        })
+        .collect();
+    if paths.is_empty() {
+        return;
+    }
+
+    let kept = |coords: IVec3| {
+        paths
+            .iter()
+            .any(|path| path.distance(coords) <= path.unload_radius)
+    };
+    let wanted = |coords: IVec3| {
+        paths
+            .iter()
+            .any(|path| path.distance(coords) <= path.load_radius)
+    };
+    let anchor_distance = |coords: IVec3| {
+        paths
+            .iter()
+            .map(|path| (coords.as_vec3() + Vec3::splat(0.5)).distance(path.start))
+            .fold(f32::INFINITY, f32::min)
+    };
+
+    for (entity, key, cells, aux) in chunks.iter() {
+        if kept(key.coords) {
+            continue;
+        }
+
+        if streaming.unload_mode == ChunkUnloadMode::Hibernate {
+            hibernated
+                .chunks
+                .insert(key.coords, (cells.clone(), aux.cloned()));
+        }
+        commands.entity(entity).despawn_recursive();
+        index.remove(key.coords);
+        snapshots.remove(key.coords);
+    }
+    let mut pending_coords = HashSet::new();
+    for (entity, chunk) in pending.iter() {
+        if kept(chunk.coords) {
+            pending_coords.insert(chunk.coords);
+        } else {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    let mut missing = HashMap::new();
+    for path in &paths {
+        let radius = (path.load_radius + path.start.distance(path.end)).ceil() as i32;
+        let center = path.start.floor().as_ivec3();
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                for z in -radius..=radius {
+                    let coords = center + IVec3::new(x, y, z);
+                    if wanted(coords)
+                        && index.entity(coords).is_none()
+                        && !pending_coords.contains(&coords)
+                    {
+                        missing.insert(coords, anchor_distance(coords));
+                    }
+                }
+            }
+        }
+    }
+    // Chunks around the anchors come first, the ones ahead of them fill in the remaining loads.
+    let mut missing: Vec<_> = missing.into_iter().collect();
+    missing.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let mut generated = pending_coords;
+    let mut loads = 0;
+    for (coords, _) in missing {
+        if loads == streaming.max_loads_per_frame {
+            break;
+        }
+        // Chunks spawned this frame are not visible yet, keep their neighbors for later.
+        let restored = hibernated.contains(coords);
+        if streaming.context_generator.is_some()
+            && !restored
+            && (0..27).any(|offset| {
+                let offset = IVec3::new(offset / 9, offset / 3 % 3, offset % 3) - IVec3::ONE;
+                generated.contains(&(coords + offset))
+            })
+        {
+            continue;
+        }
+        loads += 1;
+
+        let entity = match hibernated.chunks.remove(&coords) {
+            Some((cells, aux)) => {
+                let mut chunk = commands.spawn((
+                    ChunkBundle {
+                        cells,
+                        ..ChunkBundle::new(coords)
+                    },
+                    ChunkSource::Restored,
+                ));
+                if let Some(aux) = aux {
+                    chunk.insert(aux);
+                }
+                chunk.id()
+            }
+            None if streaming.async_generation
+                && (streaming.generator.is_some() || streaming.context_generator.is_some()) =>
+            {
+                let task = match streaming.context_generator.clone() {
+                    Some(generator) => {
+                        let context =
+                            generation_context(coords, &index, &chunks, &hibernated).into_owned();
+                        generated.insert(coords);
+                        AsyncComputeTaskPool::get().spawn(async move {
+                            ChunkCells::from_generator(|local| {
+                                generator(&context, coords * CHUNK_EDGE + local)
+                            })
+                        })
+                    }
+                    None => {
+                        let generator = streaming.generator.clone().unwrap();
+                        AsyncComputeTaskPool::get().spawn(async move {
+                            ChunkCells::from_generator(|local| {
+                                generator(coords * CHUNK_EDGE + local)
+                            })
+                        })
+                    }
+                };
+                commands.spawn(PendingChunk { coords, task });
+                continue;
+            }
+            None if streaming.context_generator.is_some() => {
+                let generator = streaming.context_generator.as_ref().unwrap();
+                let context = generation_context(coords, &index, &chunks, &hibernated);
+                generated.insert(coords);
+                commands
+                    .spawn((
+                        ChunkBundle::from_generator(coords, |local| {
+                            generator(&context, coords * CHUNK_EDGE + local)
+                        }),
+                        ChunkSource::Generated,
+                    ))
+                    .id()
+            }
+            None => match &streaming.generator {
+                Some(generator) => commands
+                    .spawn((
+                        ChunkBundle::from_generator(coords, |local| {
+                            generator(coords * CHUNK_EDGE + local)
+                        }),
+                        ChunkSource::Generated,
+                    ))
+                    .id(),
+                None => commands
+                    .spawn((ChunkBundle::new(coords), ChunkSource::Generated))
+                    .id(),
+            },
+        };
+        index.insert(coords, entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CellularAutomataPlugin, SimulationControl, VoxelCommands};
+    use bevy::{ecs::system::SystemState, time::TimeUpdateStrategy};
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        time::Duration,
+    };
+
+    fn async_app(generator: ChunkGenerator) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins((CellularAutomataPlugin, ChunkStreamingPlugin))
+            .insert_resource(ChunkStreaming {
+                // Only the chunk the anchor stands in is loaded.
+                load_radius: 0.5,
+                unload_radius: 1.0,
+                prefetch_seconds: 0.0,
+                generator: Some(generator),
+                async_generation: true,
+                ..default()
+            });
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        app
+    }
+
+    fn anchor_at(chunk: IVec3) -> GlobalTransform {
+        GlobalTransform::from_translation((chunk.as_vec3() + 0.5) * CHUNK_EDGE as f32)
+    }
+
+    /// Updates the app until no [`PendingChunk`] is left.
+    fn finish_generation(app: &mut App) {
+        for _ in 0..1000 {
+            app.update();
+            let mut pending = app.world.query::<&PendingChunk>();
+            if pending.iter(&app.world).next().is_none() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("chunk generation did not finish");
+    }
+
+    #[test]
+    fn async_chunks_are_inserted_once_generated() {
+        let voxel = IVec3::new(3, 4, 5);
+        let mut app = async_app(Arc::new(move |position: IVec3| match position == voxel {
+            true => AutomataState::new(3, 0),
+            false => AutomataState::EMPTY,
+        }));
+        app.world
+            .spawn((SimulationAnchor::default(), anchor_at(IVec3::ZERO)));
+        finish_generation(&mut app);
+
+        let entity = app
+            .world
+            .resource::<ChunkIndex>()
+            .entity(IVec3::ZERO)
+            .unwrap();
+        let cells = app.world.get::<ChunkCells>(entity).unwrap();
+        assert_eq!(cells.get(voxel), AutomataState::new(3, 0));
+        assert_eq!(cells.get(IVec3::ZERO), AutomataState::EMPTY);
+        assert_eq!(
+            app.world.get::<ChunkSource>(entity),
+            Some(&ChunkSource::Generated)
+        );
+    }
+
+    #[test]
+    fn pending_chunks_streamed_out_are_dropped() {
+        // Generation waits for the anchor to move away first.
+        let released = Arc::new(AtomicBool::new(false));
+        let generating = released.clone();
+        let mut app = async_app(Arc::new(move |_: IVec3| {
+            while !generating.load(Ordering::Acquire) {
+                std::thread::yield_now();
+            }
+            AutomataState::EMPTY
+        }));
+        let anchor = app
+            .world
+            .spawn((SimulationAnchor::default(), anchor_at(IVec3::ZERO)))
+            .id();
+        app.update();
+        let mut pending = app.world.query::<(Entity, &PendingChunk)>();
+        let (entity, chunk) = pending.single(&app.world);
+        assert_eq!(chunk.coords, IVec3::ZERO);
+
+        let far = IVec3::splat(10);
+        *app.world.get_mut::<GlobalTransform>(anchor).unwrap() = anchor_at(far);
+        app.update();
+        assert!(app.world.get_entity(entity).is_none());
+
+        released.store(true, Ordering::Release);
+        finish_generation(&mut app);
+        let index = app.world.resource::<ChunkIndex>();
+        assert!(index.entity(IVec3::ZERO).is_none());
+        assert!(index.entity(far).is_some());
+        let mut keys = app.world.query::<&ChunkKey>();
+        assert!(keys.iter(&app.world).all(|key| key.coords == far));
+    }
+
+    #[test]
+    fn edits_during_generation_keep_their_chunk() {
+        let released = Arc::new(AtomicBool::new(false));
+        let generating = released.clone();
+        let mut app = async_app(Arc::new(move |_: IVec3| {
+            while !generating.load(Ordering::Acquire) {
+                std::thread::yield_now();
+            }
+            AutomataState::new(2, 0)
+        }));
+        app.world
+            .spawn((SimulationAnchor::default(), anchor_at(IVec3::ZERO)));
+        app.update();
+
+        let voxel = IVec3::new(3, 4, 5);
+        let mut voxels = SystemState::<VoxelCommands>::new(&mut app.world);
+        voxels
+            .get_mut(&mut app.world)
+            .set_voxel(voxel.as_vec3() + 0.5, AutomataState::new(3, 0));
+        voxels.apply(&mut app.world);
+        let edited = app.world.resource::<ChunkIndex>().entity(IVec3::ZERO);
+        assert!(edited.is_some());
+
+        released.store(true, Ordering::Release);
+        finish_generation(&mut app);
+        assert_eq!(
+            app.world.resource::<ChunkIndex>().entity(IVec3::ZERO),
+            edited
+        );
+        let cells = app.world.get::<ChunkCells>(edited.unwrap()).unwrap();
+        assert_eq!(cells.get(voxel), AutomataState::new(3, 0));
+        let mut keys = app.world.query::<&ChunkKey>();
+        assert_eq!(keys.iter(&app.world).count(), 1);
+    }
+
+    #[test]
+    fn chunks_ahead_of_moving_anchors_are_prefetched() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins((CellularAutomataPlugin, ChunkStreamingPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                100,
+            )))
+            .insert_resource(ChunkStreaming {
+                load_radius: 0.5,
+                unload_radius: 1.0,
+                prefetch_seconds: 1.0,
+                max_prefetch_distance: 4.0,
+                ..default()
+            });
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        let anchor = app
+            .world
+            .spawn((SimulationAnchor::default(), anchor_at(IVec3::ZERO)))
+            .id();
+        app.update();
+        app.update();
+        assert!(app
+            .world
+            .resource::<ChunkIndex>()
+            .entity(IVec3::X)
+            .is_none());
+
+        // A chunk in a tenth of a second, a fifth of which is taken into the smoothed velocity,
+        // leads the anchor by two chunks.
+        *app.world.get_mut::<GlobalTransform>(anchor).unwrap() = anchor_at(IVec3::X);
+        app.update();
+        let index = app.world.resource::<ChunkIndex>();
+        assert!(index.entity(IVec3::new(3, 0, 0)).is_some());
+        assert!(index.entity(IVec3::new(4, 0, 0)).is_none());
+        assert!(index.entity(IVec3::new(-1, 0, 0)).is_none());
+    }
+
+    #[test]
+    fn context_generators_see_the_neighbors_generated_before() {
+        let seed = AutomataState::new(1, 0);
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins((CellularAutomataPlugin, ChunkStreamingPlugin))
+            .insert_resource(ChunkStreaming {
+                // The anchor chunk and its six face neighbors.
+                load_radius: 1.0,
+                unload_radius: 2.0,
+                prefetch_seconds: 0.0,
+                context_generator: Some(Arc::new(
+                    move |context: &GenerationContext, voxel: IVec3| {
+                        match context.coords() == IVec3::ZERO {
+                            true if voxel == IVec3::ZERO => seed,
+                            true => AutomataState::EMPTY,
+                            // Neighbors copy the seed of the anchor chunk.
+                            false => context.get(IVec3::ZERO).unwrap_or(AutomataState::EMPTY),
+                        }
+                    },
+                )),
+                ..default()
+            });
+        app.world.resource_mut::<SimulationControl>().paused = true;
+        app.world
+            .spawn((SimulationAnchor::default(), anchor_at(IVec3::ZERO)));
+
+        // Neighbors of the chunk generated in a frame wait for the next one.
+        app.update();
+        let index = app.world.resource::<ChunkIndex>();
+        assert!(index.entity(IVec3::ZERO).is_some());
+        assert!(index.entity(IVec3::X).is_none());
+
+        for _ in 0..6 {
+            app.update();
+        }
+        let index = app.world.resource::<ChunkIndex>();
+        for offset in [
+            IVec3::X,
+            IVec3::NEG_X,
+            IVec3::Y,
+            IVec3::NEG_Y,
+            IVec3::Z,
+            IVec3::NEG_Z,
+        ] {
+            let entity = index.entity(offset).unwrap();
+            let cells = app.world.get::<ChunkCells>(entity).unwrap();
+            assert_eq!(cells.get(IVec3::new(5, 6, 7)), seed);
+        }
+    }
+
+    #[test]
+    fn frozen_radius_caps_the_streaming_radii() {
+        let streaming = ChunkStreaming::default();
+        let radii = |frozen_radius| {
+            let profile = SimulationProfile {
+                frozen_radius,
+                ..default()
+            };
+            let path = AnchorPath::new(Vec3::ZERO, Vec3::ZERO, &streaming, &profile);
+            (path.load_radius, path.unload_radius)
+        };
+        assert_eq!(radii(12.0), (6.0, 8.0));
+        // Nothing is kept past the frozen radius, and chunks still load a margin closer.
+        assert_eq!(radii(7.0), (5.0, 7.0));
+        assert_eq!(radii(1.0), (0.0, 1.0));
+    }
+}
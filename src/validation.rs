@@ -0,0 +1,361 @@
+#[cfg(feature = "rapier")]
+use crate::{
+    colliders::{boxes_hash, chunk_boxes, ChunkColliderHash},
+    MaterialRegistry, VoxelColliderSettings,
+};
+use crate::{
+    voxel_to_chunk, ChunkCells, ChunkDistanceField, ChunkIndex, ChunkKey, EngineEvent,
+    OccupancyMask,
+};
+use bevy::{prelude::*, utils::HashMap};
+use std::time::Instant;
+#[cfg(feature = "render")]
+use {
+    crate::{
+        greedy_mesh, meshing::in_chunk, AutomataState, ChunkLayerMesh, ChunkMeshEvicted,
+        ChunkMeshSettings, ChunkMeshed, ChunkNeedsMesh, CHUNK_EDGE,
+    },
+    bevy::{ecs::system::SystemParam, render::mesh::VertexAttributeValues},
+};
+
+/// Sweeps the loaded chunks a few at a time checking that the data derived from their cells
+/// is still in sync, for long running servers where a missed update would otherwise go
+/// unnoticed until it shows.
+///
+/// Each frame checks chunks in [`Last`] until [`WorldValidation::budget_ms`] is spent, picking
+/// up where the previous frame stopped. Every broken [`ChunkInvariant`] is reported as an
+/// [`EngineEvent::ChunkInvariantBroken`], and repaired when [`WorldValidation::repair`] is set.
+pub struct WorldValidationPlugin;
+
+impl Plugin for WorldValidationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldValidation>()
+            .add_event::<EngineEvent>()
+            .add_systems(Last, validate_chunks.run_if(validation_enabled));
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct WorldValidation {
+    pub enabled: bool,
+    /// Milliseconds spent checking chunks per frame, at least one chunk is checked.
+    pub budget_ms: f32,
+    pub repair: bool,
+    /// Completed sweeps over every loaded chunk.
+    pub sweeps: u64,
+}
+
+impl Default for WorldValidation {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            budget_ms: 0.25,
+            repair: true,
+            sweeps: 0,
+        }
+    }
+}
+
+/// Data of a chunk checked by the [`WorldValidationPlugin`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChunkInvariant {
+    /// The [`ChunkIndex`] maps the chunk's coordinates to its entity and nothing else. Two
+    /// chunks sharing coordinates cannot be repaired.
+    Index,
+    /// The [`OccupancyMask`] matches the cells.
+    Occupancy,
+    /// The [`ChunkDistanceField`] matches the cells of the chunk and its neighbors.
+    DistanceField,
+    /// The vertex positions of the chunk meshes hash the same as a fresh greedy mesh. Skipped
+    /// while `ChunkMeshSettings::activity` splits faces by activity, and without the `render`
+    /// feature.
+    Mesh,
+    /// The collider of the chunk was built from its current solid voxels. Only checked with the
+    /// `rapier` feature once the `VoxelColliderPlugin` is added.
+    Collider,
+}
+
+/// Meshes of the chunks, for checking [`ChunkInvariant::Mesh`].
+#[cfg(feature = "render")]
+#[derive(SystemParam)]
+struct ChunkMeshes<'w, 's> {
+    settings: Option<Res<'w, ChunkMeshSettings>>,
+    meshes: Option<Res<'w, Assets<Mesh>>>,
+    chunks: Query<
+        'w,
+        's,
+        (
+            Option<&'static Handle<Mesh>>,
+            Option<&'static Children>,
+            Has<ChunkMeshed>,
+            Has<ChunkNeedsMesh>,
+            Has<ChunkMeshEvicted>,
+        ),
+    >,
+    layer_meshes: Query<'w, 's, &'static Handle<Mesh>, With<ChunkLayerMesh>>,
+}
+
+#[cfg(feature = "render")]
+impl ChunkMeshes<'_, '_> {
+    /// Hash of the vertex positions of the meshes of a chunk, see [`positions_hash`]. `None`
+    /// while they are not expected to be current.
+    fn positions_hash(&self, entity: Entity) -> Option<u64> {
+        let (mesh, children, meshed, needs_mesh, evicted) = self.chunks.get(entity).ok()?;
+        let activity = self
+            .settings
+            .as_ref()
+            .is_some_and(|settings| settings.activity);
+        if !meshed || needs_mesh || evicted || activity {
+            return None;
+        }
+        let meshes = self.meshes.as_ref()?;
+        let layered = children
+            .into_iter()
+            .flat_map(|children| children.iter())
+            .filter_map(|child| self.layer_meshes.get(*child).ok());
+        let positions = mesh
+            .into_iter()
+            .chain(layered)
+            .filter_map(|handle| meshes.get(handle))
+            .filter_map(|mesh| match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+                Some(VertexAttributeValues::Float32x3(positions)) => Some(positions),
+                _ => None,
+            })
+            .flatten();
+        Some(positions_hash(positions))
+    }
+}
+
+/// Hash of a set of vertex positions that does not depend on their order, since faces are
+/// split between the chunk mesh and its layer meshes.
+#[cfg(feature = "render")]
+fn positions_hash<'a>(positions: impl Iterator<Item = &'a [f32; 3]>) -> u64 {
+    use std::hash::{Hash, Hasher};
+    positions
+        .map(|position| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            position.map(f32::to_bits).hash(&mut hasher);
+            hasher.finish()
+        })
+        .fold(0, u64::wrapping_add)
+}
+
+/// Colliders of the chunks, for checking [`ChunkInvariant::Collider`].
+#[cfg(feature = "rapier")]
+#[derive(SystemParam)]
+struct ChunkColliders<'w, 's> {
+    settings: Option<Res<'w, VoxelColliderSettings>>,
+    registry: Option<Res<'w, MaterialRegistry>>,
+    hashes: Query<'w, 's, Option<&'static ChunkColliderHash>>,
+}
+
+#[cfg(feature = "rapier")]
+impl ChunkColliders<'_, '_> {
+    /// Whether the collider of a chunk no longer matches its cells, `None` without colliders.
+    fn stale(&self, entity: Entity, cells: &ChunkCells) -> Option<bool> {
+        let settings = self.settings.as_ref()?;
+        let boxes = chunk_boxes(cells, self.registry.as_ref()?, settings.shape);
+        let built = self.hashes.get(entity).ok()?.map(|hash| hash.0);
+        let expected = (!boxes.is_empty()).then(|| boxes_hash(&boxes));
+        Some(built != expected)
+    }
+}
+
+fn validation_enabled(validation: Res<WorldValidation>) -> bool {
+    validation.enabled
+}
+
+#[allow(clippy::too_many_arguments)]
+fn validate_chunks(
+    mut commands: Commands,
+    mut queue: Local<Vec<Entity>>,
+    mut validation: ResMut<WorldValidation>,
+    mut index: ResMut<ChunkIndex>,
+    mut events: EventWriter<EngineEvent>,
+    #[cfg(feature = "render")] meshes: ChunkMeshes,
+    #[cfg(feature = "rapier")] colliders: ChunkColliders,
+    mut chunks: Query<(
+        Entity,
+        &ChunkKey,
+        &mut ChunkCells,
+        Option<&ChunkDistanceField>,
+    )>,
+) {
+    let repair = validation.repair;
+    let mut report = |coords: IVec3, invariant: ChunkInvariant, repaired: bool| {
+        EngineEvent::ChunkInvariantBroken {
+            coords,
+            invariant,
+            repaired,
+        }
+        .report(&mut events);
+    };
+
+    if queue.is_empty() {
+        // Entries left behind by chunks that were despawned or moved.
+        let stale: Vec<IVec3> = index
+            .iter()
+            .filter(|(coords, entity)| {
+                chunks
+                    .get(*entity)
+                    .map_or(true, |(_, key, ..)| key.coords != *coords)
+            })
+            .map(|(coords, _)| coords)
+            .collect();
+        for coords in stale {
+            if repair {
+                index.remove(coords);
+            }
+            report(coords, ChunkInvariant::Index, repair);
+        }
+        queue.extend(chunks.iter().map(|(entity, ..)| entity));
+        // Popped from the back, so the sweep runs in query order.
+        queue.reverse();
+    }
+
+    let by_coords: HashMap<IVec3, Entity> = chunks
+        .iter()
+        .map(|(entity, key, ..)| (key.coords, entity))
+        .collect();
+    let start = Instant::now();
+    let mut checked = 0;
+    while let Some(entity) = queue.pop() {
+        if checked > 0 && start.elapsed().as_secs_f32() * 1000.0 >= validation.budget_ms {
+            queue.push(entity);
+            break;
+        }
+        checked += 1;
+        let Ok((_, key, cells, ..)) = chunks.get(entity) else {
+            continue;
+        };
+        let coords = key.coords;
+
+        match index.entity(coords) {
+            Some(indexed) if indexed == entity => {}
+            // Another chunk with the same coordinates owns the entry.
+            Some(indexed) if chunks.get(indexed).is_ok() => {
+                report(coords, ChunkInvariant::Index, false);
+            }
+            _ => {
+                if repair {
+                    index.insert(coords, entity);
+                }
+                report(coords, ChunkInvariant::Index, repair);
+            }
+        }
+
+        if cells.occupancy() != &OccupancyMask::from_storage(cells.storage()) {
+            if repair {
+                let (_, _, mut cells, ..) = chunks.get_mut(entity).unwrap();
+                // Marked changed so the snapshots, which keep the mask of unchanged chunks,
+                // pick up the repaired one.
+                cells.rebuild_occupancy();
+            }
+            report(coords, ChunkInvariant::Occupancy, repair);
+        }
+
+        let (_, _, _, field) = chunks.get(entity).unwrap();
+        let cell = |voxel: IVec3| {
+            let (chunk, local) = voxel_to_chunk(voxel);
+            by_coords
+                .get(&chunk)
+                .and_then(|entity| chunks.get(*entity).ok())
+                .map(|(_, _, cells, ..)| (cells, local))
+        };
+
+        if let Some(field) = field {
+            let solid = |voxel| cell(voxel).is_some_and(|(cells, local)| cells.is_alive(local));
+            let expected = ChunkDistanceField::compute(coords, solid);
+            if field.as_slice() != expected.as_slice() {
+                if repair {
+                    commands.entity(entity).insert(expected);
+                }
+                report(coords, ChunkInvariant::DistanceField, repair);
+            }
+        }
+
+        #[cfg(feature = "render")]
+        if let Some(actual) = meshes.positions_hash(entity) {
+            let (_, _, cells, _) = chunks.get(entity).unwrap();
+            let origin = coords * CHUNK_EDGE;
+            let expected = match cells.occupancy().is_empty() {
+                true => positions_hash(std::iter::empty()),
+                false => {
+                    let mesh = greedy_mesh(|local| match in_chunk(local) {
+                        true => cells.get(local),
+                        false => cell(origin + local)
+                            .map_or(AutomataState::EMPTY, |(cells, local)| cells.get(local)),
+                    });
+                    positions_hash(mesh.positions.iter())
+                }
+            };
+            if actual != expected {
+                if repair {
+                    commands.entity(entity).insert(ChunkNeedsMesh);
+                }
+                report(coords, ChunkInvariant::Mesh, repair);
+            }
+        }
+
+        #[cfg(feature = "rapier")]
+        if colliders.stale(entity, chunks.get(entity).unwrap().2) == Some(true) {
+            if repair {
+                // Rebuilt with the chunks whose cells changed.
+                chunks.get_mut(entity).unwrap().2.set_changed();
+            }
+            report(coords, ChunkInvariant::Collider, repair);
+        }
+    }
+
+    if queue.is_empty() && checked > 0 {
+        validation.sweeps += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn mesh_hashes_see_moved_vertices_but_not_their_order() {
+        let positions = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]];
+        let hash = positions_hash(positions.iter());
+
+        assert_eq!(positions_hash(positions.iter().rev()), hash);
+        // Same vertex count, one vertex moved.
+        let moved = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 2.0, 0.0]];
+        assert_ne!(positions_hash(moved.iter()), hash);
+    }
+
+    #[test]
+    fn stale_index_entries_are_repaired() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(crate::CellularAutomataPlugin)
+            .add_plugins(WorldValidationPlugin);
+        // No step rebuilds the index.
+        app.world.resource_mut::<crate::SimulationControl>().paused = true;
+        let despawned = app.world.spawn_empty().id();
+        app.world
+            .resource_mut::<ChunkIndex>()
+            .insert(IVec3::ONE, despawned);
+        app.world.despawn(despawned);
+        app.update();
+
+        assert!(app
+            .world
+            .resource::<ChunkIndex>()
+            .entity(IVec3::ONE)
+            .is_none());
+        let events = app.world.resource::<Events<EngineEvent>>();
+        assert!(events.iter_current_update_events().any(|event| {
+            *event
+                == EngineEvent::ChunkInvariantBroken {
+                    coords: IVec3::ONE,
+                    invariant: ChunkInvariant::Index,
+                    repaired: true,
+                }
+        }));
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{
+    voxel_to_chunk, AutomataState, ChunkBundle, ChunkCells, ChunkIndex, ChunkSource, Flags,
+};
+use bevy::{prelude::*, utils::HashMap};
+use std::{fmt, path::Path};
+
+#[derive(Debug)]
+pub enum VoxLoadError {
+    Io(std::io::Error),
+    Parse(String),
+    /// The file does not contain any model.
+    Empty,
+    /// A voxel uses the palette slot past index 255, which has no material to map to.
+    PaletteIndex(u8),
+}
+
+impl fmt::Display for VoxLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VoxLoadError::Io(error) => write!(f, "failed to read .vox file: {}", error),
+            VoxLoadError::Parse(error) => write!(f, "failed to parse .vox file: {}", error),
+            VoxLoadError::Empty => write!(f, ".vox file contains no model"),
+            VoxLoadError::PaletteIndex(index) => {
+                write!(
+                    f,
+                    ".vox voxel uses palette index {} past the last material",
+                    index
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for VoxLoadError {}
+
+impl From<std::io::Error> for VoxLoadError {
+    fn from(error: std::io::Error) -> Self {
+        VoxLoadError::Io(error)
+    }
+}
+
+/// Cells of a .vox model grouped by chunk.
+pub struct VoxChunks {
+    pub chunks: HashMap<IVec3, Vec<(IVec3, AutomataState)>>,
+}
+
+impl VoxChunks {
+    /// Converts the first model of a .vox file, placing its minimum corner at `origin`.
+    ///
+    /// MagicaVoxel palette index `n` (`1..=255`) becomes material `n`, so index 0 stays free
+    /// for empty cells. Models using the 256th palette slot are rejected with
+    /// [`VoxLoadError::PaletteIndex`]. Voxels get the collision flag like the ones of
+    /// [`LoadVoxelWorld`], and models are rotated from MagicaVoxel's Z-up to Y-up the same way.
+    ///
+    /// [`LoadVoxelWorld`]: crate::LoadVoxelWorld
+    pub fn from_bytes(bytes: &[u8], origin: IVec3) -> Result<Self, VoxLoadError> {
+        let vox = dot_vox::load_bytes(bytes).map_err(|error| VoxLoadError::Parse(error.into()))?;
+        let model = vox.models.first().ok_or(VoxLoadError::Empty)?;
+
+        let mut chunks: HashMap<IVec3, Vec<_>> = HashMap::new();
+        for voxel in &model.voxels {
+            let position = origin
+                + IVec3::new(
+                    model.size.x as i32 - 1 - voxel.x as i32,
+                    voxel.z as i32,
+                    voxel.y as i32,
+                );
+            let material = voxel
+                .i
+                .checked_add(1)
+                .ok_or(VoxLoadError::PaletteIndex(voxel.i))?;
+            let state = AutomataState::new(material, Flags::COLLISION_FLAG);
+            let (chunk, local) = voxel_to_chunk(position);
+            chunks.entry(chunk).or_default().push((local, state));
+        }
+
+        Ok(Self { chunks })
+    }
+}
+
+/// Loads a MagicaVoxel model into the automata world, see [`VoxChunks::from_bytes`].
+///
+/// The file is read and parsed right away. Chunks are spawned (or written into, when a chunk
+/// already exists at those coordinates) and registered in the [`ChunkIndex`] once the commands
+/// are applied.
+pub fn load_vox_into_world(
+    path: impl AsRef<Path>,
+    origin: IVec3,
+    commands: &mut Commands,
+) -> Result<(), VoxLoadError> {
+    let bytes = std::fs::read(path)?;
+    let vox = VoxChunks::from_bytes(&bytes, origin)?;
+
+    commands.add(move |world: &mut World| {
+        for (coords, cells) in vox.chunks {
+            let existing = world.resource::<ChunkIndex>().entity(coords);
+            match existing.and_then(|entity| world.get_mut::<ChunkCells>(entity)) {
+                Some(mut chunk) => {
+                    for (local, state) in cells {
+                        chunk.set(local, state);
+                    }
+                }
+                None => {
+                    let mut bundle = ChunkBundle::new(coords);
+                    for (local, state) in cells {
+                        bundle.cells.set(local, state);
+                    }
+                    let entity = world.spawn((bundle, ChunkSource::Loaded)).id();
+                    world.resource_mut::<ChunkIndex>().insert(coords, entity);
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,397 @@
+use crate::{
+    brick_origin, simulation::linear_index, ChunkCells, ChunkChanges, ChunkKey, SimulationSet,
+    VoxelWorldTransform, BRICKS_PER_AXIS, BRICK_EDGE, CHUNK_EDGE,
+};
+use bevy::{
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        primitives::{Aabb, Frustum},
+        render_resource::*,
+        renderer::{RenderDevice, RenderQueue},
+        Render, RenderApp, RenderSet,
+    },
+    utils::{HashMap, HashSet},
+};
+use std::sync::Arc;
+
+const BRICKS_PER_CHUNK: usize = (BRICKS_PER_AXIS * BRICKS_PER_AXIS * BRICKS_PER_AXIS) as usize;
+
+/// Keeps the chunks seen by the cameras resident in a fixed size 3D texture on the GPU, one
+/// `CHUNK_EDGE`³ slot per chunk, for renderers reading chunks anywhere in the world rather than
+/// around the origin like the voxel world texture.
+///
+/// The [`ChunkGpuAtlas`] lives in the render world. Changed bricks of resident chunks are
+/// written again every frame, and chunks entering a camera frustum are uploaded whole. Once the
+/// slots are full, the slot of the chunk that was seen the longest ago is reused.
+pub struct ChunkGpuAtlasPlugin {
+    /// Slots along each axis of the atlas texture, which holds the cube of this many chunks.
+    pub slots_per_axis: u32,
+}
+
+impl Default for ChunkGpuAtlasPlugin {
+    fn default() -> Self {
+        // 512 chunks in 32MB.
+        Self { slots_per_axis: 8 }
+    }
+}
+
+impl Plugin for ChunkGpuAtlasPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkGpuAtlasSource>()
+            .init_resource::<VoxelWorldTransform>()
+            .add_plugins(ExtractResourcePlugin::<ChunkGpuAtlasSource>::default())
+            .add_systems(PostUpdate, collect_atlas_chunks.after(SimulationSet::Run));
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+        let atlas = ChunkGpuAtlas::new(
+            render_app.world.resource::<RenderDevice>(),
+            self.slots_per_axis,
+        );
+        render_app
+            .insert_resource(atlas)
+            .add_systems(Render, update_chunk_gpu_atlas.in_set(RenderSet::Prepare));
+    }
+}
+
+/// Packed cells of a chunk, as read by the atlas.
+#[derive(Clone)]
+pub struct AtlasChunk {
+    /// Bumped every time the cells change.
+    pub version: u32,
+    /// Bricks changed since the previous version, as in [`ChunkChanges`].
+    pub bricks: u64,
+    /// Cells packed with `AutomataState::to_packed`, in chunk order.
+    pub cells: Arc<[u16]>,
+}
+
+/// Every loaded chunk and the ones inside a camera frustum this frame, extracted to the render
+/// world for the [`ChunkGpuAtlas`].
+#[derive(Resource, ExtractResource, Clone, Default)]
+pub struct ChunkGpuAtlasSource {
+    pub chunks: HashMap<IVec3, AtlasChunk>,
+    pub visible: HashSet<IVec3>,
+    /// Placement of the chunks in the world, for renderers reading the atlas.
+    pub world_transform: VoxelWorldTransform,
+}
+
+fn collect_atlas_chunks(
+    mut source: ResMut<ChunkGpuAtlasSource>,
+    world_transform: Res<VoxelWorldTransform>,
+    cameras: Query<(&Camera, &Frustum)>,
+    chunks: Query<(&ChunkKey, Ref<ChunkCells>, Option<&ChunkChanges>)>,
+) {
+    let source = source.as_mut();
+    let mut loaded = HashSet::with_capacity(source.chunks.len());
+    for (key, cells, changes) in chunks.iter() {
+        loaded.insert(key.coords);
+        if !cells.is_changed() && source.chunks.contains_key(&key.coords) {
+            continue;
+        }
+        let bricks = changes.map_or(u64::MAX, |changes| changes.bricks);
+        let cells: Arc<[u16]> = cells.to_packed_vec().into();
+        source
+            .chunks
+            .entry(key.coords)
+            .and_modify(|chunk| {
+                chunk.version = chunk.version.wrapping_add(1);
+                chunk.bricks = bricks;
+                chunk.cells = cells.clone();
+            })
+            .or_insert(AtlasChunk {
+                version: 0,
+                bricks: u64::MAX,
+                cells,
+            });
+    }
+    source.chunks.retain(|coords, _| loaded.contains(coords));
+
+    source.world_transform = *world_transform;
+    let affine = world_transform.affine();
+    source.visible.clear();
+    for coords in loaded {
+        let min = (coords * CHUNK_EDGE).as_vec3();
+        let aabb = Aabb::from_min_max(min, min + CHUNK_EDGE as f32);
+        let seen = cameras
+            .iter()
+            .filter(|(camera, _)| camera.is_active)
+            .any(|(_, frustum)| frustum.intersects_obb(&aabb, &affine, true, false));
+        if seen {
+            source.visible.insert(coords);
+        }
+    }
+}
+
+/// Slot of a resident chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Resident {
+    slot: u32,
+    version: u32,
+    last_visible: u32,
+}
+
+/// Slot bookkeeping of the atlas, separate from the texture.
+#[derive(Debug, Default)]
+struct AtlasSlots {
+    capacity: u32,
+    frame: u32,
+    resident: HashMap<IVec3, Resident>,
+    free: Vec<u32>,
+}
+
+impl AtlasSlots {
+    fn new(capacity: u32) -> Self {
+        Self {
+            capacity,
+            frame: 0,
+            resident: HashMap::new(),
+            free: (0..capacity).rev().collect(),
+        }
+    }
+
+    fn release(&mut self, coords: IVec3) {
+        if let Some(resident) = self.resident.remove(&coords) {
+            self.free.push(resident.slot);
+        }
+    }
+
+    /// Slot for a chunk seen this frame, evicting the chunk seen the longest ago when the atlas
+    /// is full. `None` when every slot holds a chunk seen this frame.
+    fn allocate(&mut self, coords: IVec3, version: u32) -> Option<u32> {
+        let slot = match self.free.pop() {
+            Some(slot) => slot,
+            None => {
+                let (&evicted, _) = self
+                    .resident
+                    .iter()
+                    .filter(|(_, resident)| resident.last_visible != self.frame)
+                    .min_by_key(|(_, resident)| resident.last_visible)?;
+                self.resident.remove(&evicted)?.slot
+            }
+        };
+        self.resident.insert(
+            coords,
+            Resident {
+                slot,
+                version,
+                last_visible: self.frame,
+            },
+        );
+        Some(slot)
+    }
+}
+
+/// 3D `R16Uint` texture holding the cells of the chunks seen by the cameras, see
+/// [`ChunkGpuAtlasPlugin`].
+///
+/// Slots are indexed like the voxel world texture with swizzled `zyx` coordinates, the texel
+/// of a cell being `slot_origin(coords) + local.zyx()`.
+#[derive(Resource)]
+pub struct ChunkGpuAtlas {
+    texture: Texture,
+    view: TextureView,
+    slots_per_axis: u32,
+    slots: AtlasSlots,
+}
+
+impl ChunkGpuAtlas {
+    fn new(render_device: &RenderDevice, slots_per_axis: u32) -> Self {
+        let slots_per_axis = slots_per_axis.max(1);
+        let edge = slots_per_axis * CHUNK_EDGE as u32;
+        let texture = render_device.create_texture(&TextureDescriptor {
+            label: Some("chunk gpu atlas"),
+            size: Extent3d {
+                width: edge,
+                height: edge,
+                depth_or_array_layers: edge,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D3,
+            format: TextureFormat::R16Uint,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::STORAGE_BINDING
+                | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        Self {
+            texture,
+            view,
+            slots_per_axis,
+            slots: AtlasSlots::new(slots_per_axis.pow(3)),
+        }
+    }
+
+    #[inline]
+    pub fn texture_view(&self) -> &TextureView {
+        &self.view
+    }
+
+    /// Number of chunks the atlas holds at once.
+    #[inline]
+    pub fn capacity(&self) -> u32 {
+        self.slots.capacity
+    }
+
+    pub fn resident_count(&self) -> usize {
+        self.slots.resident.len()
+    }
+
+    pub fn is_resident(&self, coords: IVec3) -> bool {
+        self.slots.resident.contains_key(&coords)
+    }
+
+    /// Coordinates of the chunks currently held by the atlas.
+    pub fn resident_chunks(&self) -> impl Iterator<Item = IVec3> + '_ {
+        self.slots.resident.keys().copied()
+    }
+
+    /// First texel of the slot holding a chunk, `None` when it is not resident.
+    pub fn slot_origin(&self, coords: IVec3) -> Option<UVec3> {
+        let slot = self.slots.resident.get(&coords)?.slot;
+        let axis = self.slots_per_axis;
+        let slot = UVec3::new(slot / (axis * axis), slot / axis % axis, slot % axis);
+        Some(slot.zyx() * CHUNK_EDGE as u32)
+    }
+
+    /// Writes the bricks in `bricks` of a resident chunk.
+    fn write_bricks(&self, queue: &RenderQueue, coords: IVec3, bricks: u64, cells: &[u16]) {
+        let Some(origin) = self.slot_origin(coords) else {
+            return;
+        };
+        if bricks == u64::MAX {
+            write_box(queue, &self.texture, origin, CHUNK_EDGE as u32, cells);
+            return;
+        }
+        for index in (0..BRICKS_PER_CHUNK).filter(|index| bricks & (1 << index) != 0) {
+            let local = brick_origin(index);
+            let mut data = Vec::with_capacity((BRICK_EDGE * BRICK_EDGE * BRICK_EDGE) as usize);
+            for x in 0..BRICK_EDGE {
+                for y in 0..BRICK_EDGE {
+                    for z in 0..BRICK_EDGE {
+                        data.push(cells[linear_index(local + IVec3::new(x, y, z))]);
+                    }
+                }
+            }
+            let origin = origin + local.zyx().as_uvec3();
+            write_box(queue, &self.texture, origin, BRICK_EDGE as u32, &data);
+        }
+    }
+}
+
+/// Writes a cube of `edge` cells in chunk order at `origin` of the swizzled texture.
+fn write_box(queue: &RenderQueue, texture: &Texture, origin: UVec3, edge: u32, data: &[u16]) {
+    queue.write_texture(
+        ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: Origin3d {
+                x: origin.x,
+                y: origin.y,
+                z: origin.z,
+            },
+            aspect: TextureAspect::All,
+        },
+        bytemuck::cast_slice(data),
+        ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(edge * 2),
+            rows_per_image: Some(edge),
+        },
+        Extent3d {
+            width: edge,
+            height: edge,
+            depth_or_array_layers: edge,
+        },
+    );
+}
+
+pub(super) fn update_chunk_gpu_atlas(
+    source: Res<ChunkGpuAtlasSource>,
+    mut atlas: ResMut<ChunkGpuAtlas>,
+    render_queue: Res<RenderQueue>,
+) {
+    let atlas = atlas.as_mut();
+    atlas.slots.frame = atlas.slots.frame.wrapping_add(1);
+    let frame = atlas.slots.frame;
+
+    let unloaded: Vec<_> = atlas
+        .slots
+        .resident
+        .keys()
+        .filter(|coords| !source.chunks.contains_key(*coords))
+        .copied()
+        .collect();
+    for coords in unloaded {
+        atlas.slots.release(coords);
+    }
+
+    // Keep the resident chunks up to date, visible or not, so they can be drawn again as soon
+    // as they are seen without waiting for an upload.
+    let mut uploads = Vec::new();
+    for (coords, resident) in atlas.slots.resident.iter_mut() {
+        let Some(chunk) = source.chunks.get(coords) else {
+            continue;
+        };
+        if source.visible.contains(coords) {
+            resident.last_visible = frame;
+        }
+        if resident.version != chunk.version {
+            let bricks = if resident.version.wrapping_add(1) == chunk.version {
+                chunk.bricks
+            } else {
+                u64::MAX
+            };
+            resident.version = chunk.version;
+            uploads.push((*coords, bricks));
+        }
+    }
+
+    for coords in source.visible.iter() {
+        if atlas.slots.resident.contains_key(coords) {
+            continue;
+        }
+        let Some(chunk) = source.chunks.get(coords) else {
+            continue;
+        };
+        if atlas.slots.allocate(*coords, chunk.version).is_some() {
+            uploads.push((*coords, u64::MAX));
+        }
+    }
+
+    for (coords, bricks) in uploads {
+        if let Some(chunk) = source.chunks.get(&coords) {
+            atlas.write_bricks(&render_queue, coords, bricks, &chunk.cells);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_atlas_evicts_the_chunk_seen_longest_ago() {
+        let mut slots = AtlasSlots::new(2);
+        slots.frame = 1;
+        let first = slots.allocate(IVec3::X, 0).unwrap();
+        slots.frame = 2;
+        let second = slots.allocate(IVec3::Y, 0).unwrap();
+        assert_ne!(first, second);
+
+        // Both slots hold a chunk seen this frame, nothing can be evicted.
+        slots.resident.get_mut(&IVec3::X).unwrap().last_visible = 2;
+        assert_eq!(slots.allocate(IVec3::Z, 0), None);
+
+        slots.frame = 3;
+        slots.resident.get_mut(&IVec3::Y).unwrap().last_visible = 3;
+        assert_eq!(slots.allocate(IVec3::Z, 0), Some(first));
+        assert!(!slots.resident.contains_key(&IVec3::X));
+
+        slots.release(IVec3::Y);
+        assert_eq!(slots.allocate(IVec3::NEG_X, 0), Some(second));
+    }
+}
@@ -0,0 +1,325 @@
+use super::voxel_world::VoxelUniforms;
+use crate::{ChunkAux, ChunkKey, SimulationSet, CHUNK_EDGE, CHUNK_VOLUME};
+use bevy::{
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_resource::*,
+        renderer::{RenderDevice, RenderQueue},
+        Render, RenderApp, RenderSet,
+    },
+    utils::HashMap,
+};
+
+/// Uploads the [`ChunkAux`] values of chunks into extra 3D textures, next to the `R16Uint`
+/// voxel world texture and with the same size and `zyx` indexing.
+///
+/// Nothing is allocated until channels are added to the [`AuxTextureLayout`]. Shaders pick the
+/// channels they read through [`AuxTextures::layout_entries`] and [`AuxTextures::bind_entries`].
+pub struct AuxTexturePlugin;
+
+impl Plugin for AuxTexturePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AuxTextureLayout>()
+            .init_resource::<AuxUploads>()
+            .add_plugins(ExtractResourcePlugin::<AuxTextureLayout>::default())
+            .add_plugins(ExtractResourcePlugin::<AuxUploads>::default())
+            .add_systems(PostUpdate, collect_aux_uploads.after(SimulationSet::Run));
+    }
+
+    fn finish(&self, app: &mut App) {
+        app.sub_app_mut(RenderApp)
+            .init_resource::<AuxTextures>()
+            .add_systems(
+                Render,
+                (prepare_aux_textures, write_aux_uploads)
+                    .chain()
+                    .in_set(RenderSet::Prepare)
+                    .after(super::voxel_world::load_voxel_world_prepare),
+            );
+    }
+}
+
+/// Texture format of an aux channel and the bits of the [`ChunkAux`] value it stores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuxFormat {
+    /// The whole value.
+    R16Uint,
+    /// The 8 bits starting at `shift`, so two channels can share a value.
+    R8Uint { shift: u8 },
+}
+
+impl AuxFormat {
+    pub fn texture_format(&self) -> TextureFormat {
+        match self {
+            AuxFormat::R16Uint => TextureFormat::R16Uint,
+            AuxFormat::R8Uint { .. } => TextureFormat::R8Uint,
+        }
+    }
+
+    fn bytes_per_texel(&self) -> u32 {
+        match self {
+            AuxFormat::R16Uint => 2,
+            AuxFormat::R8Uint { .. } => 1,
+        }
+    }
+
+    fn encode(&self, values: &[u16]) -> Vec<u8> {
+        match *self {
+            AuxFormat::R16Uint => bytemuck::cast_slice::<u16, u8>(values).to_vec(),
+            AuxFormat::R8Uint { shift } => values
+                .iter()
+                .map(|value| (value >> shift.min(15)) as u8)
+                .collect(),
+        }
+    }
+}
+
+/// A named texture built from the [`ChunkAux`] values, e.g. temperature, light or fluid level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuxChannel {
+    pub name: &'static str,
+    pub format: AuxFormat,
+}
+
+/// Aux channels uploaded to the GPU, in binding order.
+#[derive(Resource, ExtractResource, Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuxTextureLayout {
+    pub channels: Vec<AuxChannel>,
+}
+
+impl AuxTextureLayout {
+    pub fn with_channel(mut self, name: &'static str, format: AuxFormat) -> Self {
+        self.channels.push(AuxChannel { name, format });
+        self
+    }
+
+    pub fn channel(&self, name: &str) -> Option<&AuxChannel> {
+        self.channels.iter().find(|channel| channel.name == name)
+    }
+}
+
+/// Aux values of a whole chunk waiting to be written to the GPU.
+#[derive(Clone)]
+pub struct AuxUpload {
+    /// Voxel position of the first cell of the chunk.
+    pub origin: IVec3,
+    /// Values in chunk order (x, then y, then z innermost).
+    pub data: Vec<u16>,
+}
+
+/// Chunks whose aux values changed this frame, extracted to the render world.
+#[derive(Resource, ExtractResource, Clone, Default)]
+pub struct AuxUploads {
+    pub chunks: Vec<AuxUpload>,
+}
+
+fn collect_aux_uploads(
+    layout: Res<AuxTextureLayout>,
+    mut uploads: ResMut<AuxUploads>,
+    mut uploaded: Local<HashMap<Entity, IVec3>>,
+    mut removed: RemovedComponents<ChunkAux>,
+    chunks: Query<(Entity, &ChunkKey, Ref<ChunkAux>)>,
+) {
+    if !uploads.chunks.is_empty() {
+        uploads.chunks.clear();
+    }
+    if layout.channels.is_empty() {
+        return;
+    }
+
+    // Textures are recreated when the layout changes, so everything is uploaded again.
+    let everything = layout.is_changed();
+    for (entity, key, aux) in chunks.iter() {
+        if everything || aux.is_changed() {
+            uploaded.insert(entity, key.coords);
+            uploads.chunks.push(AuxUpload {
+                origin: key.coords * CHUNK_EDGE,
+                data: aux.as_slice().to_vec(),
+            });
+        }
+    }
+
+    for entity in removed.read() {
+        if let Some(coords) = uploaded.remove(&entity) {
+            uploads.chunks.push(AuxUpload {
+                origin: coords * CHUNK_EDGE,
+                data: vec![0; CHUNK_VOLUME],
+            });
+        }
+    }
+}
+
+/// GPU textures of the channels in the [`AuxTextureLayout`].
+#[derive(Resource, Default)]
+pub struct AuxTextures {
+    layout: AuxTextureLayout,
+    size: u32,
+    textures: Vec<(Texture, TextureView)>,
+    /// Latest values uploaded per chunk origin, written again into recreated textures.
+    chunks: HashMap<IVec3, Vec<u16>>,
+    /// Whether the textures were recreated since the chunks were last written.
+    recreated: bool,
+}
+
+impl AuxTextures {
+    pub fn view(&self, name: &str) -> Option<&TextureView> {
+        self.layout
+            .channels
+            .iter()
+            .position(|channel| channel.name == name)
+            .map(|index| &self.textures[index].1)
+    }
+
+    /// Layout entries for the named channels, bound from `first_binding` onwards.
+    ///
+    /// Channels missing from the layout are skipped, check [`AuxTextureLayout::channel`]
+    /// first when a shader requires them.
+    pub fn layout_entries(
+        &self,
+        channels: &[&str],
+        first_binding: u32,
+        visibility: ShaderStages,
+    ) -> Vec<BindGroupLayoutEntry> {
+        channels
+            .iter()
+            .filter(|name| self.layout.channel(name).is_some())
+            .enumerate()
+            .map(|(i, _)| BindGroupLayoutEntry {
+                binding: first_binding + i as u32,
+                visibility,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Uint,
+                    view_dimension: TextureViewDimension::D3,
+                    multisampled: false,
+                },
+                count: None,
+            })
+            .collect()
+    }
+
+    /// Bind group entries matching [`Self::layout_entries`].
+    pub fn bind_entries(&self, channels: &[&str], first_binding: u32) -> Vec<BindGroupEntry> {
+        channels
+            .iter()
+            .filter_map(|name| self.view(name))
+            .enumerate()
+            .map(|(i, view)| BindGroupEntry {
+                binding: first_binding + i as u32,
+                resource: BindingResource::TextureView(view),
+            })
+            .collect()
+    }
+}
+
+fn prepare_aux_textures(
+    layout: Res<AuxTextureLayout>,
+    voxel_uniforms: Res<VoxelUniforms>,
+    mut aux_textures: ResMut<AuxTextures>,
+    render_device: Res<RenderDevice>,
+) {
+    let size = voxel_uniforms.texture_size;
+    if aux_textures.layout == *layout && aux_textures.size == size {
+        return;
+    }
+
+    let textures = layout
+        .channels
+        .iter()
+        .map(|channel| {
+            let texture = render_device.create_texture(&TextureDescriptor {
+                label: Some(channel.name),
+                size: Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: size,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D3,
+                format: channel.format.texture_format(),
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&TextureViewDescriptor::default());
+            (texture, view)
+        })
+        .collect();
+
+    // The new textures start out empty, even when only the voxel world texture was resized.
+    let chunks = std::mem::take(&mut aux_textures.chunks);
+    *aux_textures = AuxTextures {
+        layout: layout.clone(),
+        size,
+        textures,
+        chunks,
+        recreated: true,
+    };
+}
+
+fn write_aux_uploads(
+    uploads: Res<AuxUploads>,
+    mut aux_textures: ResMut<AuxTextures>,
+    render_queue: Res<RenderQueue>,
+) {
+    if !uploads.is_changed() && !aux_textures.recreated {
+        return;
+    }
+
+    let aux_textures = aux_textures.as_mut();
+    for chunk in uploads.chunks.iter() {
+        // Unloaded chunks upload zeros, which the textures start out with.
+        if chunk.data.iter().all(|value| *value == 0) {
+            aux_textures.chunks.remove(&chunk.origin);
+        } else {
+            aux_textures.chunks.insert(chunk.origin, chunk.data.clone());
+        }
+    }
+
+    if std::mem::take(&mut aux_textures.recreated) {
+        for (origin, data) in aux_textures.chunks.iter() {
+            aux_textures.write_chunk(&render_queue, *origin, data);
+        }
+    } else {
+        for chunk in uploads.chunks.iter() {
+            aux_textures.write_chunk(&render_queue, chunk.origin, &chunk.data);
+        }
+    }
+}
+
+impl AuxTextures {
+    fn write_chunk(&self, render_queue: &RenderQueue, origin: IVec3, data: &[u16]) {
+        let size = self.size as i32;
+        let texel = origin + IVec3::splat(size / 2);
+        if texel.min_element() < 0 || texel.max_element() + CHUNK_EDGE > size {
+            return;
+        }
+
+        for (channel, (texture, _)) in self.layout.channels.iter().zip(&self.textures) {
+            // Indexed with swizzled (z, y, x) coordinates like the voxel world texture.
+            render_queue.write_texture(
+                ImageCopyTexture {
+                    texture,
+                    mip_level: 0,
+                    origin: Origin3d {
+                        x: texel.z as u32,
+                        y: texel.y as u32,
+                        z: texel.x as u32,
+                    },
+                    aspect: TextureAspect::All,
+                },
+                &channel.format.encode(data),
+                ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(CHUNK_EDGE as u32 * channel.format.bytes_per_texel()),
+                    rows_per_image: Some(CHUNK_EDGE as u32),
+                },
+                Extent3d {
+                    width: CHUNK_EDGE as u32,
+                    height: CHUNK_EDGE as u32,
+                    depth_or_array_layers: CHUNK_EDGE as u32,
+                },
+            );
+        }
+    }
+}
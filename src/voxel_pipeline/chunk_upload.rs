@@ -0,0 +1,154 @@
+use super::voxel_world::{VoxelData, VoxelUniforms};
+use crate::{
+    brick_origin, ChunkCells, ChunkChanges, ChunkKey, SimulationSet, BRICKS_PER_AXIS, BRICK_EDGE,
+    CHUNK_EDGE,
+};
+use bevy::{
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_resource::*,
+        renderer::RenderQueue,
+        Render, RenderApp, RenderSet,
+    },
+    utils::HashMap,
+};
+
+const BRICK_VOLUME: usize = (BRICK_EDGE * BRICK_EDGE * BRICK_EDGE) as usize;
+const BRICKS_PER_CHUNK: usize = (BRICKS_PER_AXIS * BRICKS_PER_AXIS * BRICKS_PER_AXIS) as usize;
+
+/// Copies the bricks of automata chunks that changed into the voxel world texture.
+///
+/// Chunk voxel `(0, 0, 0)` maps onto the center of the texture. Each changed brick is written
+/// with its own `write_texture` call, so a chunk with a single active region costs a 1KB upload
+/// instead of the full chunk.
+pub struct ChunkUploadPlugin;
+
+impl Plugin for ChunkUploadPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkUploads>()
+            .add_plugins(ExtractResourcePlugin::<ChunkUploads>::default())
+            .add_systems(PostUpdate, collect_chunk_uploads.after(SimulationSet::Run));
+    }
+
+    fn finish(&self, app: &mut App) {
+        app.sub_app_mut(RenderApp).add_systems(
+            Render,
+            write_chunk_uploads
+                .in_set(RenderSet::Prepare)
+                .after(super::voxel_world::load_voxel_world_prepare),
+        );
+    }
+}
+
+/// A brick of packed cells waiting to be written to the GPU.
+#[derive(Clone)]
+pub struct BrickUpload {
+    /// Voxel position of the first cell of the brick.
+    pub origin: IVec3,
+    /// Packed cells in chunk order (x, then y, then z innermost).
+    pub data: Vec<u16>,
+}
+
+/// Bricks collected this frame, extracted to the render world.
+#[derive(Resource, ExtractResource, Clone, Default)]
+pub struct ChunkUploads {
+    pub bricks: Vec<BrickUpload>,
+}
+
+fn collect_chunk_uploads(
+    mut uploads: ResMut<ChunkUploads>,
+    mut uploaded: Local<HashMap<Entity, IVec3>>,
+    mut removed: RemovedComponents<ChunkKey>,
+    chunks: Query<(Entity, &ChunkKey, &ChunkCells, Ref<ChunkChanges>)>,
+) {
+    if !uploads.bricks.is_empty() {
+        uploads.bricks.clear();
+    }
+
+    for (entity, key, cells, changes) in chunks.iter() {
+        if !changes.is_changed() {
+            continue;
+        }
+
+        let mask = if changes.is_added() {
+            u64::MAX
+        } else {
+            changes.bricks
+        };
+        uploaded.insert(entity, key.coords);
+
+        for index in (0..BRICKS_PER_CHUNK).filter(|index| mask & (1 << index) != 0) {
+            let local = brick_origin(index);
+            let mut data = Vec::with_capacity(BRICK_VOLUME);
+            for x in 0..BRICK_EDGE {
+                for y in 0..BRICK_EDGE {
+                    for z in 0..BRICK_EDGE {
+                        data.push(cells.get(local + IVec3::new(x, y, z)).to_packed());
+                    }
+                }
+            }
+
+            uploads.bricks.push(BrickUpload {
+                origin: key.coords * CHUNK_EDGE + local,
+                data,
+            });
+        }
+    }
+
+    // Clear the voxels of chunks that were despawned.
+    for entity in removed.read() {
+        if let Some(coords) = uploaded.remove(&entity) {
+            for index in 0..BRICKS_PER_CHUNK {
+                uploads.bricks.push(BrickUpload {
+                    origin: coords * CHUNK_EDGE + brick_origin(index),
+                    data: vec![0; BRICK_VOLUME],
+                });
+            }
+        }
+    }
+}
+
+pub(super) fn write_chunk_uploads(
+    uploads: Res<ChunkUploads>,
+    voxel_data: Res<VoxelData>,
+    voxel_uniforms: Res<VoxelUniforms>,
+    render_queue: Res<RenderQueue>,
+) {
+    if !uploads.is_changed() {
+        return;
+    }
+
+    let size = voxel_uniforms.texture_size as i32;
+    for brick in uploads.bricks.iter() {
+        let texel = brick.origin + IVec3::splat(size / 2);
+        if texel.min_element() < 0 || texel.max_element() + BRICK_EDGE > size {
+            continue;
+        }
+
+        // The voxel world texture is indexed with swizzled (z, y, x) coordinates.
+        render_queue.write_texture(
+            ImageCopyTexture {
+                texture: &voxel_data.voxel_world_texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: texel.z as u32,
+                    y: texel.y as u32,
+                    z: texel.x as u32,
+                },
+                aspect: TextureAspect::All,
+            },
+            bytemuck::cast_slice(&brick.data),
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(BRICK_EDGE as u32 * 2),
+                rows_per_image: Some(BRICK_EDGE as u32),
+            },
+            Extent3d {
+                width: BRICK_EDGE as u32,
+                height: BRICK_EDGE as u32,
+                depth_or_array_layers: BRICK_EDGE as u32,
+            },
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use self::{
     attachments::{AttachmentsNode, AttachmentsPlugin},
+    aux_textures::AuxTexturePlugin,
+    chunk_upload::ChunkUploadPlugin,
     compute::{
         animation::AnimationNode, automata::AutomataNode, clear::ClearNode, physics::PhysicsNode,
         rebuild::RebuildNode, ComputeResourcesPlugin,
@@ -8,6 +10,7 @@ use self::{
     voxel_world::VoxelWorldPlugin,
     voxelization::VoxelizationPlugin,
 };
+use crate::PaletteChanged;
 use bevy::{
     core_pipeline::{fxaa::FxaaNode, tonemapping::TonemappingNode, upscaling::UpscalingNode},
     prelude::*,
@@ -20,8 +23,13 @@ use bevy::{
     ui::UiPassNode,
 };
 
+pub mod atlas;
 pub mod attachments;
+pub mod aux_textures;
+pub mod chunk_upload;
 pub mod compute;
+pub mod raymarch;
+pub mod render_chunks;
 pub mod trace;
 pub mod voxel_world;
 pub mod voxelization;
@@ -31,9 +39,12 @@ pub struct RenderPlugin;
 impl Plugin for RenderPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(RenderGraphSettings::default())
+            .add_event::<PaletteChanged>()
             .add_plugins(ExtractResourcePlugin::<RenderGraphSettings>::default())
             .add_plugins(AttachmentsPlugin)
             .add_plugins(VoxelWorldPlugin)
+            .add_plugins(ChunkUploadPlugin)
+            .add_plugins(AuxTexturePlugin)
             .add_plugins(TracePlugin)
             .add_plugins(VoxelizationPlugin)
             .add_plugins(ComputeResourcesPlugin);
@@ -105,6 +116,7 @@ pub struct RenderGraphSettings {
     pub rebuild: bool,
     pub physics: bool,
     pub trace: bool,
+    pub raymarch: bool,
 }
 
 impl Default for RenderGraphSettings {
@@ -117,6 +129,7 @@ impl Default for RenderGraphSettings {
             rebuild: true,
             physics: true,
             trace: true,
+            raymarch: true,
         }
     }
 }
@@ -0,0 +1,251 @@
+use super::{
+    atlas::{update_chunk_gpu_atlas, ChunkGpuAtlas, ChunkGpuAtlasPlugin, ChunkGpuAtlasSource},
+    voxel_world::VoxelData,
+};
+use crate::{ChunkMeshPlugin, CHUNK_EDGE};
+use bevy::{
+    asset::embedded_asset,
+    core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+    prelude::*,
+    render::{
+        render_graph::RenderGraph,
+        render_resource::*,
+        renderer::{RenderDevice, RenderQueue},
+        view::{ExtractedView, ViewTarget},
+        Render, RenderApp, RenderSet,
+    },
+};
+pub use node::RaymarchNode;
+
+mod node;
+
+/// Most chunks along each axis of the lookup table the raymarch pass walks.
+const MAX_TABLE_EDGE: i32 = 64;
+
+/// Renders the chunks held by the [`ChunkGpuAtlas`] with a fullscreen raymarching pass, so dense
+/// worlds can be drawn anywhere without meshing them or fitting them in the voxel world
+/// texture.
+///
+/// Rays skip the chunks that are not resident and march the cells of the resident ones, and
+/// hits are shaded with the palette colors of the `VoxelPalette`. Add it after the
+/// `BevyVoxelEnginePlugin`, it adds the [`ChunkGpuAtlasPlugin`] when missing.
+///
+/// The pass has no depth test: hits are drawn over whatever the trace pass and chunk meshes
+/// wrote, nearer or not, and only pixels without a hit keep their color. Use it as the only
+/// renderer of the chunks, leaving out the `ChunkMeshPlugin` and setting
+/// [`RenderGraphSettings::trace`](super::RenderGraphSettings) to `false`.
+///
+/// The pass walks the box of visible resident chunks, clamped to 64 chunks per axis.
+pub struct ChunkRaymarchPlugin;
+
+impl Plugin for ChunkRaymarchPlugin {
+    fn build(&self, app: &mut App) {
+        #[cfg(any(not(target_family = "windows"), target_env = "gnu"))]
+        {
+            embedded_asset!(app, "src/", "raymarch.wgsl");
+        }
+        #[cfg(all(target_family = "windows", not(target_env = "gnu")))]
+        {
+            embedded_asset!(app, "src\\", "raymarch.wgsl");
+        }
+
+        if !app.is_plugin_added::<ChunkGpuAtlasPlugin>() {
+            app.add_plugins(ChunkGpuAtlasPlugin::default());
+        }
+    }
+
+    fn finish(&self, app: &mut App) {
+        if app.is_plugin_added::<ChunkMeshPlugin>() {
+            warn!("Chunk meshes are drawn under the raymarch pass, which has no depth test");
+        }
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .init_resource::<RaymarchPipelineData>()
+            .init_resource::<RaymarchChunkTable>()
+            .add_systems(
+                Render,
+                prepare_raymarch
+                    .in_set(RenderSet::Prepare)
+                    .after(update_chunk_gpu_atlas),
+            );
+
+        let node = RaymarchNode::new(&mut render_app.world);
+        let mut graph = render_app.world.resource_mut::<RenderGraph>();
+        if let Some(voxel_graph) = graph.get_sub_graph_mut("voxel") {
+            voxel_graph.add_node("raymarch", node);
+            voxel_graph.add_node_edge("trace", "raymarch");
+            voxel_graph.add_node_edge("raymarch", "tonemapping");
+        }
+    }
+}
+
+#[derive(Clone, ShaderType)]
+pub struct RaymarchUniforms {
+    pub camera_inverse: Mat4,
+    pub world_to_voxel: Mat4,
+    pub voxel_to_world: Mat4,
+    /// First chunk of the lookup table.
+    pub table_min: IVec3,
+    pub max_steps: u32,
+    /// Chunks along each axis of the lookup table, zero when nothing is resident.
+    pub table_size: UVec3,
+}
+
+#[derive(Component, Deref, DerefMut)]
+struct ViewRaymarchUniformBuffer(UniformBuffer<RaymarchUniforms>);
+
+/// Slot of every chunk in the box walked by the rays, `0` for chunks that are not resident and
+/// `1 << 24 | slot.x | slot.y << 8 | slot.z << 16` otherwise, `slot` being the texel origin of
+/// the chunk in the atlas divided by `CHUNK_EDGE`.
+#[derive(Resource, Default)]
+struct RaymarchChunkTable {
+    min: IVec3,
+    size: UVec3,
+    buffer: StorageBuffer<Vec<u32>>,
+}
+
+fn prepare_raymarch(
+    mut commands: Commands,
+    views: Query<(Entity, &ExtractedView)>,
+    atlas: Res<ChunkGpuAtlas>,
+    source: Res<ChunkGpuAtlasSource>,
+    mut table: ResMut<RaymarchChunkTable>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    let visible = || {
+        atlas
+            .resident_chunks()
+            .filter(|coords| source.visible.contains(coords))
+    };
+    let (min, max) = visible().fold((IVec3::MAX, IVec3::MIN), |(min, max), coords| {
+        (min.min(coords), max.max(coords))
+    });
+    let (min, size) = match min.cmple(max).all() {
+        true => (min, (max - min + 1).min(IVec3::splat(MAX_TABLE_EDGE))),
+        false => (IVec3::ZERO, IVec3::ZERO),
+    };
+
+    let entries = table.buffer.get_mut();
+    entries.clear();
+    // Storage buffers cannot be empty.
+    entries.resize((size.x * size.y * size.z).max(1) as usize, 0);
+    for coords in visible() {
+        let offset = coords - min;
+        if offset.cmpge(size).any() {
+            continue;
+        }
+        let Some(origin) = atlas.slot_origin(coords) else {
+            continue;
+        };
+        let slot = origin / CHUNK_EDGE as u32;
+        let index = (offset.x * size.y * size.z + offset.y * size.z + offset.z) as usize;
+        entries[index] = 1 << 24 | slot.x | slot.y << 8 | slot.z << 16;
+    }
+    table.min = min;
+    table.size = size.as_uvec3();
+    table.buffer.write_buffer(&render_device, &render_queue);
+
+    let voxel_to_world = Mat4::from(source.world_transform.affine());
+    let world_to_voxel = voxel_to_world.inverse();
+    for (entity, view) in views.iter() {
+        let camera_inverse = view.transform.compute_matrix() * view.projection.inverse();
+        let mut uniform_buffer = UniformBuffer::from(RaymarchUniforms {
+            camera_inverse,
+            world_to_voxel,
+            voxel_to_world,
+            table_min: table.min,
+            max_steps: 512,
+            table_size: table.size,
+        });
+        uniform_buffer.write_buffer(&render_device, &render_queue);
+        commands
+            .entity(entity)
+            .insert(ViewRaymarchUniformBuffer(uniform_buffer));
+    }
+}
+
+#[derive(Resource)]
+struct RaymarchPipelineData {
+    pipeline_id: CachedRenderPipelineId,
+    bind_group_layout: BindGroupLayout,
+}
+
+impl FromWorld for RaymarchPipelineData {
+    fn from_world(render_world: &mut World) -> Self {
+        let voxel_bind_group_layout = render_world
+            .resource::<VoxelData>()
+            .bind_group_layout
+            .clone();
+        let asset_server = render_world.resource::<AssetServer>();
+
+        let bind_group_layout = render_world
+            .resource::<RenderDevice>()
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("raymarch bind group layout"),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: BufferSize::new(RaymarchUniforms::SHADER_SIZE.into()),
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Uint,
+                            view_dimension: TextureViewDimension::D3,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let shader =
+            asset_server.load("embedded://bevy_voxel_engine/voxel_pipeline/raymarch/raymarch.wgsl");
+        let descriptor = RenderPipelineDescriptor {
+            label: Some("raymarch pipeline".into()),
+            layout: vec![voxel_bind_group_layout, bind_group_layout.clone()],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader,
+                shader_defs: Vec::new(),
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format: ViewTarget::TEXTURE_FORMAT_HDR,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            push_constant_ranges: vec![],
+        };
+        let pipeline_id = render_world
+            .resource::<PipelineCache>()
+            .queue_render_pipeline(descriptor);
+
+        RaymarchPipelineData {
+            pipeline_id,
+            bind_group_layout,
+        }
+    }
+}
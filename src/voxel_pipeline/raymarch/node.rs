@@ -0,0 +1,98 @@
+use super::{RaymarchChunkTable, RaymarchPipelineData, ViewRaymarchUniformBuffer};
+use crate::voxel_pipeline::{atlas::ChunkGpuAtlas, voxel_world::VoxelData, RenderGraphSettings};
+use bevy::{
+    prelude::*,
+    render::{
+        render_graph,
+        render_resource::*,
+        view::{ExtractedView, ViewTarget},
+    },
+};
+
+pub struct RaymarchNode {
+    query:
+        QueryState<(&'static ViewTarget, &'static ViewRaymarchUniformBuffer), With<ExtractedView>>,
+}
+
+impl RaymarchNode {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            query: world.query_filtered(),
+        }
+    }
+}
+
+impl render_graph::Node for RaymarchNode {
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut bevy::render::renderer::RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        if !world.resource::<RenderGraphSettings>().raymarch {
+            return Ok(());
+        }
+        let Ok((target, uniform_buffer)) = self.query.get_manual(world, graph.view_entity()) else {
+            return Ok(());
+        };
+        let pipeline_data = world.resource::<RaymarchPipelineData>();
+        let Some(pipeline) = world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(pipeline_data.pipeline_id)
+        else {
+            return Ok(());
+        };
+        let (Some(uniforms), Some(table)) = (
+            uniform_buffer.binding(),
+            world.resource::<RaymarchChunkTable>().buffer.binding(),
+        ) else {
+            return Ok(());
+        };
+
+        let bind_group = render_context.render_device().create_bind_group(
+            "raymarch bind group",
+            &pipeline_data.bind_group_layout,
+            &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniforms,
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        world.resource::<ChunkGpuAtlas>().texture_view(),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: table,
+                },
+            ],
+        );
+
+        // Drawn over the trace pass output without a depth test, pixels without a hit are
+        // discarded.
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("raymarch pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target.main_texture_view(),
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_bind_group(0, &world.resource::<VoxelData>().bind_group, &[]);
+        render_pass.set_bind_group(1, &bind_group, &[]);
+        render_pass.set_render_pipeline(pipeline);
+        render_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
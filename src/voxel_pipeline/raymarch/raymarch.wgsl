@@ -0,0 +1,162 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import bevy_voxel_engine::bindings::voxel_uniforms
+
+struct RaymarchUniforms {
+    camera_inverse: mat4x4<f32>,
+    world_to_voxel: mat4x4<f32>,
+    voxel_to_world: mat4x4<f32>,
+    table_min: vec3<i32>,
+    max_steps: u32,
+    table_size: vec3<u32>,
+};
+
+@group(1) @binding(0)
+var<uniform> uniforms: RaymarchUniforms;
+@group(1) @binding(1)
+var atlas: texture_3d<u32>;
+@group(1) @binding(2)
+var<storage, read> chunk_table: array<u32>;
+
+const CHUNK_EDGE: i32 = 32;
+const SUN_DIR: vec3<f32> = vec3(0.4, -0.6, 0.8);
+
+struct Hit {
+    hit: bool,
+    material: u32,
+    normal: vec3<f32>,
+};
+
+// Table entry of a chunk, 0 when it is not resident.
+fn chunk_entry(chunk: vec3<i32>) -> u32 {
+    let offset = chunk - uniforms.table_min;
+    let size = vec3<i32>(uniforms.table_size);
+    if any(offset < vec3(0)) || any(offset >= size) {
+        return 0u;
+    }
+    return chunk_table[offset.x * size.y * size.z + offset.y * size.z + offset.z];
+}
+
+// Material of a cell of a resident chunk, its texel being the slot origin plus `local.zyx`.
+fn cell_material(entry: u32, local: vec3<i32>) -> u32 {
+    let slot = vec3<i32>(vec3(entry, entry >> 8u, entry >> 16u) & vec3(0xffu));
+    let cell = textureLoad(atlas, slot * CHUNK_EDGE + local.zyx, 0).r;
+    return cell & 0xffu;
+}
+
+// Entry and exit distances of the ray in an axis aligned box.
+fn box_distances(origin: vec3<f32>, inverse_dir: vec3<f32>, box_min: vec3<f32>, box_max: vec3<f32>) -> vec2<f32> {
+    let t0 = (box_min - origin) * inverse_dir;
+    let t1 = (box_max - origin) * inverse_dir;
+    let near = min(t0, t1);
+    let far = max(t0, t1);
+    return vec2(max(max(near.x, near.y), near.z), min(min(far.x, far.y), far.z));
+}
+
+// Normal of the face of `voxel` the point entered through.
+fn entry_normal(pos: vec3<f32>, dir: vec3<f32>, voxel: vec3<i32>) -> vec3<f32> {
+    let inside = pos - vec3<f32>(voxel);
+    let distance = select(inside, 1.0 - inside, dir < vec3(0.0));
+    if distance.x <= distance.y && distance.x <= distance.z {
+        return vec3(-sign(dir.x), 0.0, 0.0);
+    }
+    if distance.y <= distance.z {
+        return vec3(0.0, -sign(dir.y), 0.0);
+    }
+    return vec3(0.0, 0.0, -sign(dir.z));
+}
+
+// Walks the table chunk by chunk, skipping chunks that are not resident, and the cells of the
+// resident ones with a DDA.
+fn raymarch(origin: vec3<f32>, ray_dir: vec3<f32>) -> Hit {
+    let miss = Hit(false, 0u, vec3(0.0));
+    if any(uniforms.table_size == vec3(0u)) {
+        return miss;
+    }
+
+    // Keep the inverse finite on axis aligned rays.
+    let dir = select(ray_dir, vec3(1e-6), abs(ray_dir) < vec3(1e-6));
+    let inverse_dir = 1.0 / dir;
+    let table_min = vec3<f32>(uniforms.table_min * CHUNK_EDGE);
+    let table_max = table_min + vec3<f32>(uniforms.table_size) * f32(CHUNK_EDGE);
+    let bounds = box_distances(origin, inverse_dir, table_min, table_max);
+    if bounds.x >= bounds.y || bounds.y <= 0.0 {
+        return miss;
+    }
+
+    let step = vec3<i32>(sign(dir));
+    let delta = abs(inverse_dir);
+    var t = max(bounds.x, 0.0) + 1e-4;
+    var steps = 0u;
+    while t < bounds.y && steps < uniforms.max_steps {
+        let pos = origin + dir * t;
+        var voxel = vec3<i32>(floor(pos));
+        let chunk = vec3<i32>(floor(vec3<f32>(voxel) / f32(CHUNK_EDGE)));
+        let chunk_min = vec3<f32>(chunk * CHUNK_EDGE);
+        let chunk_exit = box_distances(origin, inverse_dir, chunk_min, chunk_min + f32(CHUNK_EDGE)).y;
+        let entry = chunk_entry(chunk);
+        steps += 1u;
+        if entry == 0u {
+            t = chunk_exit + 1e-4;
+            continue;
+        }
+
+        var normal = entry_normal(pos, dir, voxel);
+        var next = (vec3<f32>(voxel) + select(vec3(0.0), vec3(1.0), dir > vec3(0.0)) - origin) * inverse_dir;
+        loop {
+            let local = voxel - chunk * CHUNK_EDGE;
+            if any(local < vec3(0)) || any(local >= vec3(CHUNK_EDGE)) {
+                break;
+            }
+            let material = cell_material(entry, local);
+            if material != 0u {
+                return Hit(true, material, normal);
+            }
+            steps += 1u;
+            if steps >= uniforms.max_steps {
+                return miss;
+            }
+            if next.x <= next.y && next.x <= next.z {
+                voxel.x += step.x;
+                t = next.x;
+                next.x += delta.x;
+                normal = vec3(-f32(step.x), 0.0, 0.0);
+            } else if next.y <= next.z {
+                voxel.y += step.y;
+                t = next.y;
+                next.y += delta.y;
+                normal = vec3(0.0, -f32(step.y), 0.0);
+            } else {
+                voxel.z += step.z;
+                t = next.z;
+                next.z += delta.z;
+                normal = vec3(0.0, 0.0, -f32(step.z));
+            }
+        }
+        t = max(t, chunk_exit) + 1e-4;
+    }
+    return miss;
+}
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let clip_space = vec2(1.0, -1.0) * (in.uv * 2.0 - 1.0);
+    let near = uniforms.camera_inverse * vec4(clip_space, 1.0, 1.0);
+    let far = uniforms.camera_inverse * vec4(clip_space, 0.01, 1.0);
+    let world_origin = near.xyz / near.w;
+    let world_dir = far.xyz / far.w - world_origin;
+
+    let origin = (uniforms.world_to_voxel * vec4(world_origin, 1.0)).xyz;
+    let dir = normalize((uniforms.world_to_voxel * vec4(world_dir, 0.0)).xyz);
+    let hit = raymarch(origin, dir);
+    if !hit.hit {
+        discard;
+    }
+
+    let material = voxel_uniforms.materials[hit.material];
+    let normal = normalize((uniforms.voxel_to_world * vec4(hit.normal, 0.0)).xyz);
+    var color = material.rgb * (0.3 + 0.7 * max(dot(normal, -normalize(SUN_DIR)), 0.0));
+    if material.a != 0.0 {
+        color = material.rgb;
+    }
+    return vec4(color, 1.0);
+}
@@ -0,0 +1,97 @@
+use crate::{ChunkCells, ChunkKey, CHUNK_VOLUME};
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::*,
+        renderer::{RenderDevice, RenderQueue},
+        Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+    },
+    utils::{HashMap, HashSet},
+};
+
+/// Mirrors the cells of every chunk into a GPU storage buffer, and spawns a [`RenderChunk`] in
+/// the render world for every visible chunk, so custom materials and shaders can bind them.
+///
+/// Changed chunks are copied during extraction and written to their buffers in
+/// [`RenderSet::PrepareResources`], so systems from [`RenderSet::PrepareBindGroups`] onwards
+/// see this frame's cells. Chunks without a [`ViewVisibility`] count as visible.
+pub struct RenderChunkPlugin;
+
+impl Plugin for RenderChunkPlugin {
+    fn build(&self, _app: &mut App) {}
+
+    fn finish(&self, app: &mut App) {
+        app.sub_app_mut(RenderApp)
+            .init_resource::<RenderChunkBuffers>()
+            .add_systems(ExtractSchedule, extract_render_chunks)
+            .add_systems(
+                Render,
+                prepare_render_chunks.in_set(RenderSet::PrepareResources),
+            );
+    }
+}
+
+/// Render world entity of a visible chunk, with the same [`Entity`] as the chunk in the main
+/// world.
+///
+/// `buffer` holds [`CHUNK_VOLUME`] cells packed with `AutomataState::to_packed`, two per `u32`
+/// with the first one in the low bits, in chunk order. In WGSL, bound as
+/// `array<u32, CHUNK_VOLUME / 2>`, the cell at `local` is
+/// `(cells[i / 2u] >> (16u * (i % 2u))) & 0xffffu` with
+/// `i = (local.x * 32 + local.y) * 32 + local.z`.
+#[derive(Component, Clone)]
+pub struct RenderChunk {
+    pub coords: IVec3,
+    pub buffer: Buffer,
+}
+
+/// Storage buffers of the chunks, kept from frame to frame in the render world.
+#[derive(Resource, Default)]
+struct RenderChunkBuffers {
+    buffers: HashMap<Entity, Buffer>,
+    /// Packed cells of the chunks that changed since the last frame.
+    changed: Vec<(Entity, Vec<u16>)>,
+}
+
+fn extract_render_chunks(
+    mut commands: Commands,
+    mut buffers: ResMut<RenderChunkBuffers>,
+    render_device: Res<RenderDevice>,
+    chunks: Extract<Query<(Entity, &ChunkKey, Ref<ChunkCells>, Option<&ViewVisibility>)>>,
+) {
+    let buffers = buffers.as_mut();
+    buffers.changed.clear();
+    let mut loaded = HashSet::with_capacity(buffers.buffers.len());
+    for (entity, key, cells, visibility) in chunks.iter() {
+        loaded.insert(entity);
+        let added = !buffers.buffers.contains_key(&entity);
+        if added {
+            let buffer = render_device.create_buffer(&BufferDescriptor {
+                label: Some("render chunk cells"),
+                size: (CHUNK_VOLUME * 2) as u64,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            buffers.buffers.insert(entity, buffer);
+        }
+        if added || cells.is_changed() {
+            buffers.changed.push((entity, cells.to_packed_vec()));
+        }
+
+        if visibility.map_or(true, |visibility| visibility.get()) {
+            commands.get_or_spawn(entity).insert(RenderChunk {
+                coords: key.coords,
+                buffer: buffers.buffers[&entity].clone(),
+            });
+        }
+    }
+    buffers.buffers.retain(|entity, _| loaded.contains(entity));
+}
+
+fn prepare_render_chunks(buffers: Res<RenderChunkBuffers>, render_queue: Res<RenderQueue>) {
+    for (entity, cells) in buffers.changed.iter() {
+        if let Some(buffer) = buffers.buffers.get(entity) {
+            render_queue.write_buffer(buffer, 0, bytemuck::cast_slice(cells));
+        }
+    }
+}
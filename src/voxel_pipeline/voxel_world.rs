@@ -65,6 +65,7 @@ impl Plugin for VoxelWorldPlugin {
             },
             &gh.texture_data.clone(),
         );
+        let voxel_world_texture = voxel_world.clone();
         let voxel_world = voxel_world.create_view(&TextureViewDescriptor::default());
 
         // Storage
@@ -193,6 +194,7 @@ impl Plugin for VoxelWorldPlugin {
             .insert_resource(VoxelData {
                 uniform_buffer,
                 voxel_world,
+                voxel_world_texture,
                 grid_hierarchy,
                 mip_texture,
                 texture_sampler,
@@ -209,6 +211,7 @@ impl Plugin for VoxelWorldPlugin {
 pub struct VoxelData {
     pub uniform_buffer: UniformBuffer<VoxelUniforms>,
     pub voxel_world: TextureView,
+    pub voxel_world_texture: Texture,
     pub grid_hierarchy: Buffer,
     pub mip_texture: Texture,
     pub texture_sampler: Sampler,
@@ -274,10 +277,17 @@ fn load_voxel_world(
         LoadVoxelWorld::Empty(_) | LoadVoxelWorld::File(_) => {
             let gh = match load_voxel_world.as_ref() {
                 LoadVoxelWorld::Empty(size) => GH::empty(*size),
+                #[cfg(feature = "dot_vox")]
                 LoadVoxelWorld::File(path) => {
                     let file = std::fs::read(path).unwrap();
                     GH::from_vox(&file).unwrap()
                 }
+                #[cfg(not(feature = "dot_vox"))]
+                LoadVoxelWorld::File(path) => {
+                    error!("Loading {} requires the dot_vox feature", path);
+                    *load_voxel_world = LoadVoxelWorld::None;
+                    return;
+                }
                 LoadVoxelWorld::None => unreachable!(),
             };
 
@@ -299,7 +309,7 @@ fn load_voxel_world(
     }
 }
 
-fn load_voxel_world_prepare(
+pub(super) fn load_voxel_world_prepare(
     mut voxel_data: ResMut<VoxelData>,
     render_device: Res<RenderDevice>,
     render_queue: Res<RenderQueue>,
@@ -335,6 +345,7 @@ fn load_voxel_world_prepare(
             &gh.texture_data,
         );
         voxel_data.voxel_world = voxel_world.create_view(&TextureViewDescriptor::default());
+        voxel_data.voxel_world_texture = voxel_world;
 
         // mip texture
         let mip_count = gh.texture_size.trailing_zeros();
@@ -0,0 +1,218 @@
+use crate::{
+    persistence::read_saved_chunks, simulation::brick_index_of, AutomataState, ChunkCells,
+    ChunkKey, CHUNK_EDGE,
+};
+use bevy::{prelude::*, utils::HashMap};
+use std::{fmt, io, path::Path};
+
+/// Cell that differs between two versions of a world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoxelDiff {
+    pub voxel: IVec3,
+    pub before: AutomataState,
+    pub after: AutomataState,
+}
+
+/// How a chunk differs between two versions of a world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkDiffKind {
+    /// Only present in the second version, compared against an empty chunk.
+    Added,
+    /// Only present in the first version, compared against an empty chunk.
+    Removed,
+    Changed,
+}
+
+/// Summary of the differences within a single chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkDiff {
+    pub coords: IVec3,
+    pub kind: ChunkDiffKind,
+    pub changed_voxels: usize,
+    /// Bricks containing a changed voxel, laid out like [`ChunkChanges`](crate::ChunkChanges).
+    pub bricks: u64,
+    /// First changed voxel in chunk order.
+    pub first: Option<VoxelDiff>,
+}
+
+/// Differences between two versions of a world, such as two saves or a save and the live world.
+///
+/// Chunks are listed in coordinate order. Added or removed chunks that are entirely empty do
+/// not count as a difference.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorldDiff {
+    pub chunks: Vec<ChunkDiff>,
+}
+
+impl WorldDiff {
+    pub fn between(
+        before: &HashMap<IVec3, ChunkCells>,
+        after: &HashMap<IVec3, ChunkCells>,
+    ) -> Self {
+        let empty = ChunkCells::default();
+        let mut chunks = Vec::new();
+
+        for (&coords, before_cells) in before {
+            let (kind, after_cells) = match after.get(&coords) {
+                Some(cells) => (ChunkDiffKind::Changed, cells),
+                None => (ChunkDiffKind::Removed, &empty),
+            };
+            chunks.extend(diff_chunk(coords, kind, before_cells, after_cells));
+        }
+        for (&coords, after_cells) in after {
+            if !before.contains_key(&coords) {
+                chunks.extend(diff_chunk(
+                    coords,
+                    ChunkDiffKind::Added,
+                    &empty,
+                    after_cells,
+                ));
+            }
+        }
+
+        chunks.sort_by_key(|chunk| chunk.coords.to_array());
+        Self { chunks }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    pub fn changed_voxels(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.changed_voxels).sum()
+    }
+
+    pub fn chunk(&self, coords: IVec3) -> Option<&ChunkDiff> {
+        self.chunks.iter().find(|chunk| chunk.coords == coords)
+    }
+}
+
+impl fmt::Display for WorldDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} voxels changed in {} chunks",
+            self.changed_voxels(),
+            self.chunks.len()
+        )?;
+        for chunk in &self.chunks {
+            write!(
+                f,
+                "- {:?} {:?}: {} voxels in {} bricks",
+                chunk.coords,
+                chunk.kind,
+                chunk.changed_voxels,
+                chunk.bricks.count_ones()
+            )?;
+            if let Some(first) = chunk.first {
+                write!(
+                    f,
+                    ", first at {:?} ({:?} -> {:?})",
+                    first.voxel, first.before, first.after
+                )?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn diff_chunk(
+    coords: IVec3,
+    kind: ChunkDiffKind,
+    before: &ChunkCells,
+    after: &ChunkCells,
+) -> Option<ChunkDiff> {
+    let mut diff = ChunkDiff {
+        coords,
+        kind,
+        changed_voxels: 0,
+        bricks: 0,
+        first: None,
+    };
+
+    let edge = CHUNK_EDGE as usize;
+    for (index, (a, b)) in before
+        .storage()
+        .iter()
+        .zip(after.storage().iter())
+        .enumerate()
+    {
+        if a == b {
+            continue;
+        }
+
+        let local = IVec3::new(
+            (index / (edge * edge)) as i32,
+            ((index / edge) % edge) as i32,
+            (index % edge) as i32,
+        );
+        diff.changed_voxels += 1;
+        diff.bricks |= 1 << brick_index_of(local);
+        diff.first.get_or_insert(VoxelDiff {
+            voxel: coords * CHUNK_EDGE + local,
+            before: a,
+            after: b,
+        });
+    }
+
+    (diff.changed_voxels > 0).then_some(diff)
+}
+
+/// Cells of every loaded chunk of `world`, keyed by chunk coordinates.
+pub fn world_chunks(world: &mut World) -> HashMap<IVec3, ChunkCells> {
+    let mut query = world.query::<(&ChunkKey, &ChunkCells)>();
+    query
+        .iter(world)
+        .map(|(key, cells)| (key.coords, cells.clone()))
+        .collect()
+}
+
+/// Compares the saves written by [`save_world`](crate::save_world) in two directories.
+pub fn diff_saves(before: &Path, after: &Path) -> io::Result<WorldDiff> {
+    let before = read_saved_chunks(before)?.into_iter().collect();
+    let after = read_saved_chunks(after)?.into_iter().collect();
+    Ok(WorldDiff::between(&before, &after))
+}
+
+/// Compares the save in `directory` with the chunks currently loaded in `world`.
+pub fn diff_save_with_world(directory: &Path, world: &mut World) -> io::Result<WorldDiff> {
+    let saved = read_saved_chunks(directory)?.into_iter().collect();
+    Ok(WorldDiff::between(&saved, &world_chunks(world)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CHUNK_VOLUME;
+
+    #[test]
+    fn diff_reports_changed_added_and_removed_chunks() {
+        let solid = AutomataState::new(1, 0);
+        let mut before = HashMap::default();
+        before.insert(IVec3::ZERO, ChunkCells::default());
+        before.insert(IVec3::X, ChunkCells::filled(solid));
+        before.insert(IVec3::Y, ChunkCells::default());
+
+        let mut after = before.clone();
+        after.remove(&IVec3::X);
+        after.remove(&IVec3::Y);
+        after
+            .get_mut(&IVec3::ZERO)
+            .unwrap()
+            .set(IVec3::new(1, 2, 3), solid);
+        after.insert(IVec3::Z, ChunkCells::default());
+
+        let diff = WorldDiff::between(&before, &after);
+        assert_eq!(diff.chunks.len(), 2);
+        assert_eq!(diff.changed_voxels(), 1 + CHUNK_VOLUME);
+
+        let changed = diff.chunk(IVec3::ZERO).unwrap();
+        assert_eq!(changed.kind, ChunkDiffKind::Changed);
+        assert_eq!(changed.first.unwrap().voxel, IVec3::new(1, 2, 3));
+        assert_eq!(changed.bricks.count_ones(), 1);
+        assert_eq!(diff.chunk(IVec3::X).unwrap().kind, ChunkDiffKind::Removed);
+        assert!(WorldDiff::between(&before, &before).is_empty());
+    }
+}
@@ -0,0 +1,258 @@
+use crate::{AutomataState, ChunkStreaming, SimulationSet};
+use bevy::prelude::*;
+use std::sync::Arc;
+
+/// Fills the chunks streamed in by the [`ChunkStreamingPlugin`](crate::ChunkStreamingPlugin)
+/// with terrain from a [`TerrainGenerator`], [`NoiseTerrain`] unless another one is given.
+///
+/// The generator is kept in the [`WorldGen`] resource and installed as
+/// [`ChunkStreaming::generator`] whenever it changes, so only chunks generated afterwards see a
+/// new one.
+pub struct WorldGenPlugin {
+    pub generator: Arc<dyn TerrainGenerator>,
+}
+
+impl WorldGenPlugin {
+    pub fn new(generator: impl TerrainGenerator) -> Self {
+        Self {
+            generator: Arc::new(generator),
+        }
+    }
+}
+
+impl Default for WorldGenPlugin {
+    fn default() -> Self {
+        Self::new(NoiseTerrain::default())
+    }
+}
+
+impl Plugin for WorldGenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkStreaming>()
+            .insert_resource(WorldGen {
+                generator: self.generator.clone(),
+            })
+            .add_systems(First, install_terrain_generator.before(SimulationSet::Tick));
+    }
+}
+
+/// Decides the state of every voxel of newly generated chunks.
+///
+/// Called from the streaming system with voxel positions, one chunk at a time, so it has to be
+/// deterministic for chunks to line up when they are generated again.
+pub trait TerrainGenerator: Send + Sync + 'static {
+    fn generate(&self, voxel: IVec3) -> AutomataState;
+}
+
+impl<F> TerrainGenerator for F
+where
+    F: Fn(IVec3) -> AutomataState + Send + Sync + 'static,
+{
+    fn generate(&self, voxel: IVec3) -> AutomataState {
+        self(voxel)
+    }
+}
+
+/// [`TerrainGenerator`] used by the [`WorldGenPlugin`], replaced by setting `generator`.
+#[derive(Resource, Clone)]
+pub struct WorldGen {
+    pub generator: Arc<dyn TerrainGenerator>,
+}
+
+fn install_terrain_generator(world_gen: Res<WorldGen>, mut streaming: ResMut<ChunkStreaming>) {
+    if !world_gen.is_changed() {
+        return;
+    }
+    let generator = world_gen.generator.clone();
+    streaming.generator = Some(Arc::new(move |voxel| generator.generate(voxel)));
+}
+
+/// Rolling terrain of stone under a layer of dirt, shaped by fractal simplex noise, with caves
+/// carved where a second noise is high.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseTerrain {
+    pub seed: u32,
+    /// Average surface height in voxels.
+    pub surface_height: f32,
+    /// Largest distance of the surface from its average, in voxels.
+    pub amplitude: f32,
+    /// Surface noise frequency of the first octave, in cycles per voxel.
+    pub frequency: f32,
+    pub octaves: u32,
+    /// Frequency multiplier from one octave to the next.
+    pub lacunarity: f32,
+    /// Amplitude multiplier from one octave to the next.
+    pub gain: f32,
+    /// Voxels of dirt between the surface and the stone.
+    pub dirt_depth: f32,
+    pub cave_frequency: f32,
+    /// Cave noise above which voxels are carved out, in `-1.0..=1.0`. Caves are disabled at
+    /// `1.0`.
+    pub cave_threshold: f32,
+    pub stone: AutomataState,
+    pub dirt: AutomataState,
+}
+
+impl Default for NoiseTerrain {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            surface_height: 0.0,
+            amplitude: 24.0,
+            frequency: 1.0 / 128.0,
+            octaves: 4,
+            lacunarity: 2.0,
+            gain: 0.5,
+            dirt_depth: 3.0,
+            cave_frequency: 1.0 / 48.0,
+            cave_threshold: 0.6,
+            stone: AutomataState::new(1, 0),
+            dirt: AutomataState::new(2, 0),
+        }
+    }
+}
+
+impl NoiseTerrain {
+    /// Height of the surface above the column at `x`, `z`.
+    pub fn surface(&self, x: i32, z: i32) -> f32 {
+        let position = Vec3::new(x as f32, 0.0, z as f32) * self.frequency;
+        self.surface_height + self.amplitude * fbm(self.seed, position, self)
+    }
+
+    fn is_cave(&self, voxel: IVec3) -> bool {
+        self.cave_threshold < 1.0
+            && simplex(
+                self.seed.wrapping_add(1),
+                voxel.as_vec3() * self.cave_frequency,
+            ) > self.cave_threshold
+    }
+}
+
+impl TerrainGenerator for NoiseTerrain {
+    fn generate(&self, voxel: IVec3) -> AutomataState {
+        let depth = self.surface(voxel.x, voxel.z) - voxel.y as f32;
+        if depth < 0.0 || self.is_cave(voxel) {
+            AutomataState::EMPTY
+        } else if depth < self.dirt_depth {
+            self.dirt
+        } else {
+            self.stone
+        }
+    }
+}
+
+/// Sum of `octaves` simplex octaves, normalized back to about `-1.0..=1.0`.
+fn fbm(seed: u32, position: Vec3, terrain: &NoiseTerrain) -> f32 {
+    let mut sum = 0.0;
+    let mut total = 0.0;
+    let mut amplitude = 1.0;
+    let mut position = position;
+    for octave in 0..terrain.octaves.max(1) {
+        sum += amplitude * simplex(seed.wrapping_add(octave.wrapping_mul(0x9e37)), position);
+        total += amplitude;
+        amplitude *= terrain.gain;
+        position *= terrain.lacunarity;
+    }
+    sum / total
+}
+
+const GRADIENTS: [Vec3; 12] = [
+    Vec3::new(1.0, 1.0, 0.0),
+    Vec3::new(-1.0, 1.0, 0.0),
+    Vec3::new(1.0, -1.0, 0.0),
+    Vec3::new(-1.0, -1.0, 0.0),
+    Vec3::new(1.0, 0.0, 1.0),
+    Vec3::new(-1.0, 0.0, 1.0),
+    Vec3::new(1.0, 0.0, -1.0),
+    Vec3::new(-1.0, 0.0, -1.0),
+    Vec3::new(0.0, 1.0, 1.0),
+    Vec3::new(0.0, -1.0, 1.0),
+    Vec3::new(0.0, 1.0, -1.0),
+    Vec3::new(0.0, -1.0, -1.0),
+];
+
+fn lattice_hash(seed: u32, corner: IVec3) -> u32 {
+    let mut hash = (corner.x as u32).wrapping_mul(73_856_093)
+        ^ (corner.y as u32).wrapping_mul(19_349_663)
+        ^ (corner.z as u32).wrapping_mul(83_492_791)
+        ^ seed.wrapping_mul(0x9e37_79b9);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x7feb_352d);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x846c_a68b);
+    hash ^ (hash >> 16)
+}
+
+/// 3D simplex noise in `-1.0..=1.0`, with gradients picked by hashing the lattice corners
+/// with `seed`.
+fn simplex(seed: u32, position: Vec3) -> f32 {
+    const SKEW: f32 = 1.0 / 3.0;
+    const UNSKEW: f32 = 1.0 / 6.0;
+
+    let sum = |v: Vec3| v.x + v.y + v.z;
+    let cell = (position + sum(position) * SKEW).floor();
+    let origin = cell - sum(cell) * UNSKEW;
+    let offset = position - origin;
+
+    // The two middle corners of the tetrahedron holding the position.
+    let (first, second) = match (
+        offset.x >= offset.y,
+        offset.y >= offset.z,
+        offset.x >= offset.z,
+    ) {
+        (true, true, _) => (IVec3::X, IVec3::new(1, 1, 0)),
+        (true, false, true) => (IVec3::X, IVec3::new(1, 0, 1)),
+        (true, false, false) => (IVec3::Z, IVec3::new(1, 0, 1)),
+        (false, false, _) => (IVec3::Z, IVec3::new(0, 1, 1)),
+        (false, true, false) => (IVec3::Y, IVec3::new(0, 1, 1)),
+        (false, true, true) => (IVec3::Y, IVec3::new(1, 1, 0)),
+    };
+
+    let cell = cell.as_ivec3();
+    let noise: f32 = [IVec3::ZERO, first, second, IVec3::ONE]
+        .into_iter()
+        .enumerate()
+        .map(|(i, corner)| {
+            let distance = offset - corner.as_vec3() + i as f32 * UNSKEW;
+            let falloff = 0.6 - distance.length_squared();
+            if falloff <= 0.0 {
+                return 0.0;
+            }
+            let gradient = GRADIENTS[(lattice_hash(seed, cell + corner) % 12) as usize];
+            falloff.powi(4) * gradient.dot(distance)
+        })
+        .sum();
+    (noise * 32.0).clamp(-1.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_terrain_layers_stone_dirt_and_air() {
+        let terrain = NoiseTerrain {
+            cave_threshold: 1.0,
+            ..default()
+        };
+        for (x, z) in [(0, 0), (17, -40), (-300, 123)] {
+            let surface = terrain.surface(x, z);
+            assert!(surface.abs() <= terrain.amplitude);
+
+            let y = surface.floor() as i32;
+            assert_eq!(
+                terrain.generate(IVec3::new(x, y + 1, z)),
+                AutomataState::EMPTY
+            );
+            assert_eq!(terrain.generate(IVec3::new(x, y, z)), terrain.dirt);
+            assert_eq!(terrain.generate(IVec3::new(x, y - 10, z)), terrain.stone);
+        }
+
+        // Deterministic and continuous.
+        let position = Vec3::new(10.3, -4.2, 7.7);
+        assert_eq!(simplex(3, position), simplex(3, position));
+        let step = simplex(3, position) - simplex(3, position + Vec3::splat(0.01));
+        assert!(step.abs() < 0.1);
+        assert_ne!(simplex(3, position), simplex(4, position));
+    }
+}